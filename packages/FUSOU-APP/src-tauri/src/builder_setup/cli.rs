@@ -1,6 +1,9 @@
 use clap::{ArgAction, Parser, Subcommand};
+use fusou_auth::{AuthManager, FileStorage};
+use fusou_upload::{PendingStore, UploadRetryService};
 use serde::Serialize;
 use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
 use tauri::{Error as TauriError, Manager};
 
 #[derive(Debug, Clone, Default)]
@@ -8,6 +11,8 @@ pub struct CliInvocation {
     pub show_version: bool,
     pub enable_terminal_logs: bool,
     pub app_info: Option<AppInfoRequest>,
+    pub device: Option<DeviceRequest>,
+    pub uploads: Option<UploadsRequest>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -15,6 +20,31 @@ pub struct AppInfoRequest {
     pub as_json: bool,
 }
 
+#[derive(Debug, Clone)]
+pub enum DeviceRequest {
+    List {
+        include_revoked: bool,
+        as_json: bool,
+    },
+    Rotate,
+    Revoke {
+        device_id: String,
+        reason: Option<String>,
+    },
+    RevokeOthers {
+        reason: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum UploadsRequest {
+    Metrics { as_prometheus: bool },
+    History { limit: usize, as_json: bool },
+    DeadLetterList { as_json: bool },
+    DeadLetterRequeue { id: String },
+    DeadLetterDelete { id: String },
+}
+
 #[derive(Parser, Debug)]
 #[command(
     name = "fusou",
@@ -51,19 +81,153 @@ enum CliCommand {
         )]
         json: bool,
     },
+    /// Manage devices registered for anonymous sync
+    Device {
+        #[command(subcommand)]
+        action: DeviceAction,
+    },
+    /// Inspect the pending upload queue
+    Uploads {
+        #[command(subcommand)]
+        action: UploadsAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DeviceAction {
+    /// List devices registered under the same account
+    List {
+        #[arg(
+            long = "include-revoked",
+            action = ArgAction::SetTrue,
+            help = "Include devices that were already revoked"
+        )]
+        include_revoked: bool,
+        #[arg(
+            long = "json",
+            action = ArgAction::SetTrue,
+            help = "Emit the device list as JSON"
+        )]
+        json: bool,
+    },
+    /// Replace this device's key with a newly generated one
+    Rotate,
+    /// Revoke another device by its device id
+    Revoke {
+        device_id: String,
+        #[arg(long = "reason", help = "Reason recorded on the server")]
+        reason: Option<String>,
+    },
+    /// Revoke every device except this one
+    RevokeOthers {
+        #[arg(long = "reason", help = "Reason recorded on the server")]
+        reason: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum UploadsAction {
+    /// Print queue depth and upload counters
+    Metrics {
+        #[arg(
+            long = "prometheus",
+            action = ArgAction::SetTrue,
+            help = "Emit Prometheus text format instead of JSON"
+        )]
+        prometheus: bool,
+    },
+    /// Print recently finished pending uploads, newest first
+    History {
+        #[arg(
+            long = "limit",
+            default_value_t = 20,
+            help = "Number of entries to show"
+        )]
+        limit: usize,
+        #[arg(
+            long = "json",
+            action = ArgAction::SetTrue,
+            help = "Emit the history as JSON"
+        )]
+        json: bool,
+    },
+    /// Manage uploads that were given up on
+    DeadLetter {
+        #[command(subcommand)]
+        action: DeadLetterAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DeadLetterAction {
+    /// List dead-lettered uploads
+    List {
+        #[arg(
+            long = "json",
+            action = ArgAction::SetTrue,
+            help = "Emit the dead-letter list as JSON"
+        )]
+        json: bool,
+    },
+    /// Move a dead-lettered upload back into the pending queue
+    Requeue { id: String },
+    /// Permanently delete a dead-lettered upload
+    Delete { id: String },
 }
 
 pub fn parse_invocation() -> CliInvocation {
     let cli = CliArgs::parse();
-    let app_info = match cli.command {
-        Some(CliCommand::Info { json }) => Some(AppInfoRequest { as_json: json }),
-        None => None,
-    };
-
-    CliInvocation {
+    let mut invocation = CliInvocation {
         show_version: cli.version,
         enable_terminal_logs: cli.logs,
-        app_info,
+        ..Default::default()
+    };
+    match cli.command {
+        Some(CliCommand::Info { json }) => {
+            invocation.app_info = Some(AppInfoRequest { as_json: json });
+        }
+        Some(CliCommand::Device { action }) => invocation.device = Some(action.into()),
+        Some(CliCommand::Uploads { action }) => invocation.uploads = Some(action.into()),
+        None => {}
+    }
+    invocation
+}
+
+impl From<DeviceAction> for DeviceRequest {
+    fn from(action: DeviceAction) -> Self {
+        match action {
+            DeviceAction::List {
+                include_revoked,
+                json,
+            } => DeviceRequest::List {
+                include_revoked,
+                as_json: json,
+            },
+            DeviceAction::Rotate => DeviceRequest::Rotate,
+            DeviceAction::Revoke { device_id, reason } => {
+                DeviceRequest::Revoke { device_id, reason }
+            }
+            DeviceAction::RevokeOthers { reason } => DeviceRequest::RevokeOthers { reason },
+        }
+    }
+}
+
+impl From<UploadsAction> for UploadsRequest {
+    fn from(action: UploadsAction) -> Self {
+        match action {
+            UploadsAction::Metrics { prometheus } => UploadsRequest::Metrics {
+                as_prometheus: prometheus,
+            },
+            UploadsAction::History { limit, json } => UploadsRequest::History {
+                limit,
+                as_json: json,
+            },
+            UploadsAction::DeadLetter { action } => match action {
+                DeadLetterAction::List { json } => UploadsRequest::DeadLetterList { as_json: json },
+                DeadLetterAction::Requeue { id } => UploadsRequest::DeadLetterRequeue { id },
+                DeadLetterAction::Delete { id } => UploadsRequest::DeadLetterDelete { id },
+            },
+        }
     }
 }
 
@@ -71,6 +235,8 @@ pub fn prepare_terminal_logs(invocation: &CliInvocation) {
     if invocation.enable_terminal_logs
         || invocation.show_version
         || invocation.app_info.is_some()
+        || invocation.device.is_some()
+        || invocation.uploads.is_some()
     {
         attach_to_terminal();
     }
@@ -93,6 +259,191 @@ pub fn handle_metadata_commands(
     Ok(())
 }
 
+/// Runs `fusou device ...` and exits. Must be called after configs are loaded,
+/// since the anonymous-sync endpoints are resolved from configs.toml.
+pub fn handle_device_commands(
+    app: &tauri::App,
+    invocation: &CliInvocation,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(request) = invocation.device.clone() else {
+        return Ok(());
+    };
+
+    let manager = app
+        .state::<Arc<Mutex<AuthManager<FileStorage>>>>()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+
+    let result = tauri::async_runtime::block_on(run_device_command(manager, request));
+    match result {
+        Ok(()) => std::process::exit(0),
+        Err(err) => {
+            let _ = writeln!(io::stderr(), "device command failed: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn run_device_command(
+    manager: AuthManager<FileStorage>,
+    request: DeviceRequest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut device_key = crate::util::load_or_create_device_key().await?;
+    if device_key.device_id().is_none() {
+        return Err("this device is not registered yet; start FUSOU and open the game once".into());
+    }
+
+    let mut stdout = io::stdout();
+    match request {
+        DeviceRequest::List {
+            include_revoked,
+            as_json,
+        } => {
+            let devices = manager
+                .list_devices_v2(&device_key, include_revoked)
+                .await?;
+            if as_json {
+                serde_json::to_writer_pretty(&mut stdout, &devices)?;
+                stdout.write_all(b"\n")?;
+            } else {
+                for device in &devices {
+                    let marker = if device.is_current { "*" } else { " " };
+                    let status = match device.revoked_at {
+                        Some(at) => format!("revoked {}", at.to_rfc3339()),
+                        None => "active".to_string(),
+                    };
+                    let last_seen = device
+                        .last_seen_at
+                        .map(|at| at.to_rfc3339())
+                        .unwrap_or_else(|| "-".to_string());
+                    writeln!(
+                        stdout,
+                        "{marker} {}  created {}  last seen {last_seen}  {status}",
+                        device.device_id,
+                        device.created_at.to_rfc3339(),
+                    )?;
+                }
+            }
+        }
+        DeviceRequest::Rotate => {
+            manager.rotate_device_key_v2(&mut device_key).await?;
+            writeln!(stdout, "Device key rotated")?;
+        }
+        DeviceRequest::Revoke { device_id, reason } => {
+            if device_key.device_id() == Some(device_id.as_str()) {
+                return Err("refusing to revoke the current device".into());
+            }
+            manager
+                .revoke_device_v2(&device_id, &device_key, reason.as_deref())
+                .await?;
+            writeln!(stdout, "Revoked {device_id}")?;
+        }
+        DeviceRequest::RevokeOthers { reason } => {
+            let revoked = manager
+                .revoke_other_devices_v2(&device_key, reason.as_deref())
+                .await?;
+            if revoked.is_empty() {
+                writeln!(stdout, "No other active devices")?;
+            }
+            for device_id in revoked {
+                writeln!(stdout, "Revoked {device_id}")?;
+            }
+        }
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Runs `fusou uploads ...` and exits. Reads the same pending directory the
+/// retry service uses, so it works while another instance is running.
+pub fn handle_upload_commands(
+    app: &tauri::App,
+    invocation: &CliInvocation,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(request) = invocation.uploads.clone() else {
+        return Ok(());
+    };
+
+    let store = app.state::<Arc<PendingStore>>().inner().clone();
+    let retry_service = app.state::<Arc<UploadRetryService>>().inner().clone();
+    match run_upload_command(&store, &retry_service, request) {
+        Ok(()) => std::process::exit(0),
+        Err(err) => {
+            let _ = writeln!(io::stderr(), "uploads command failed: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_upload_command(
+    store: &PendingStore,
+    retry_service: &UploadRetryService,
+    request: UploadsRequest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stdout = io::stdout();
+    match request {
+        UploadsRequest::Metrics { as_prometheus } => {
+            let snapshot = retry_service.metrics().snapshot(store);
+            if as_prometheus {
+                write!(stdout, "{}", snapshot.to_prometheus())?;
+            } else {
+                serde_json::to_writer_pretty(&mut stdout, &snapshot)?;
+                writeln!(stdout)?;
+            }
+        }
+        UploadsRequest::History { limit, as_json } => {
+            let entries = retry_service.history().recent(limit);
+            if as_json {
+                serde_json::to_writer_pretty(&mut stdout, &entries)?;
+                writeln!(stdout)?;
+            } else {
+                for entry in &entries {
+                    writeln!(
+                        stdout,
+                        "{}  {:<13} {:<16} attempts={} {}",
+                        entry.finished_at,
+                        format!("{:?}", entry.outcome).to_lowercase(),
+                        entry.context_kind,
+                        entry.attempts,
+                        entry.error_class.map(|class| class.as_str()).unwrap_or("-"),
+                    )?;
+                }
+            }
+        }
+        UploadsRequest::DeadLetterList { as_json } => {
+            let items = store.list_dead_letter();
+            if as_json {
+                serde_json::to_writer_pretty(&mut stdout, &items)?;
+                writeln!(stdout)?;
+            } else if items.is_empty() {
+                writeln!(stdout, "Dead-letter folder is empty")?;
+            } else {
+                for item in &items {
+                    writeln!(
+                        stdout,
+                        "{}  {:?}  {}  attempts={}  {}",
+                        item.pending.id,
+                        item.reason,
+                        item.pending.pending_type(),
+                        item.pending.attempt_count,
+                        item.pending.last_error.as_deref().unwrap_or("-"),
+                    )?;
+                }
+            }
+        }
+        UploadsRequest::DeadLetterRequeue { id } => {
+            store.requeue_dead_letter(&id)?;
+            writeln!(stdout, "Requeued {id}")?;
+        }
+        UploadsRequest::DeadLetterDelete { id } => {
+            store.delete_dead_letter(&id)?;
+            writeln!(stdout, "Deleted {id}")?;
+        }
+    }
+    Ok(())
+}
+
 fn print_version(app: &tauri::App) -> io::Result<()> {
     let package_info = app.package_info();
     let version = package_info.version.to_string();
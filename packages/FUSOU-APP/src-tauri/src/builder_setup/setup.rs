@@ -24,7 +24,7 @@ use crate::{
         cli, logger,
     },
     cmd::{native_cmd, tauri_cmd},
-    integration::discord,
+    integration::{discord, event_stream},
     notify, scheduler,
     storage::integrate,
     util::{get_RESOURCES_DIR, get_ROAMING_DIR, try_anonymous_auth},
@@ -898,6 +898,8 @@ pub fn setup_init(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>
     setup_updater(app)?;
     setup_deep_link(app)?;
     setup_configs()?;
+    cli::handle_device_commands(app, &cli_invocation)?;
+    cli::handle_upload_commands(app, &cli_invocation)?;
     let autostart_allowed = configs::get_user_configs_for_app()
         .autostart
         .get_enable_autostart();
@@ -911,6 +913,9 @@ pub fn setup_init(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>
     {
         setup_discord()?;
     }
+    tauri::async_runtime::spawn(async {
+        event_stream::start();
+    });
     notify_startup(app);
 
     let pending_store = app.state::<Arc<PendingStore>>().inner().clone();
@@ -950,6 +955,7 @@ pub fn setup_init(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>
         );
 
         discord::close();
+        event_stream::close();
 
         tokio::time::sleep(time::Duration::from_millis(2000)).await;
         app_handle.cleanup_before_exit();
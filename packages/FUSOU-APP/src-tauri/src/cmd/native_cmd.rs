@@ -2,7 +2,7 @@
 // use std::process::Command;
 use tauri_plugin_shell::ShellExt;
 use tracing_unwrap::{OptionExt, ResultExt};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::notify;
 
@@ -255,6 +255,26 @@ where
             return false;
         }
     };
+    add_cert_to_store(app, ca_path_buf).await
+}
+
+/// Installs the generated CA staged in `next_dir` (see
+/// `CaManager::next_dir`) ahead of a rotation.
+pub async fn add_staged_ca_to_store_sync<R>(app: &tauri::AppHandle<R>, next_dir: &Path) -> bool
+where
+    R: tauri::Runtime,
+{
+    #[cfg(target_os = "windows")]
+    let cert_path = next_dir.join(CA_CERT_NAME_DER);
+    #[cfg(target_os = "linux")]
+    let cert_path = next_dir.join(CA_CERT_NAME_CRT);
+    add_cert_to_store(app, cert_path).await
+}
+
+async fn add_cert_to_store<R>(app: &tauri::AppHandle<R>, ca_path_buf: PathBuf) -> bool
+where
+    R: tauri::Runtime,
+{
     let ca_path = ca_path_buf.to_string_lossy().to_string();
 
     #[cfg(target_os = "windows")]
@@ -5,6 +5,7 @@ use std::sync::{Arc, Mutex};
 use std::fs;
 
 use proxy_https::bidirectional_channel;
+use proxy_https::game_server::GameServer;
 use serde::Serialize;
 use tauri::{AppHandle, Emitter, Manager};
 
@@ -14,6 +15,9 @@ use crate::builder_setup::logger::MessageVisitor;
 #[cfg(feature = "gdrive")]
 use crate::storage::providers::gdrive;
 use crate::storage::cloud_provider_trait::{CloudProviderFactory, GOOGLE_PROVIDER_KEY};
+use crate::interface::growth_plan::{GrowthPlan, NodeExp, ShipGoal};
+use crate::interface::improvement::{ImprovementAttempt, ImprovementSummary};
+use crate::interface::inventory::{Inventory, InventoryQuery, InventoryResults, InventoryTarget};
 use crate::interface::mst_equip_exslot_ship::MstEquipExslotShips;
 use crate::interface::mst_equip_ship::MstEquipShips;
 use crate::interface::mst_ship::MstShips;
@@ -21,10 +25,14 @@ use crate::interface::mst_slot_item::MstSlotItems;
 use crate::interface::mst_slot_item_equip_type::MstSlotItemEquipTypes;
 use crate::interface::mst_stype::MstStypes;
 use crate::interface::mst_use_item::MstUseItems;
+use crate::interface::ship_remodel::{RemodelPlan, RemodelPlanner};
 use crate::interface::slot_item::SlotItems;
 
 use crate::sequence;
-use fusou_upload::{PendingStore, UploadContext, UploadRetryService};
+use fusou_upload::{
+    DeadLetterMeta, PendingStore, UploadHistoryEntry, UploadMetricsSnapshot, UploadOutcome,
+    UploadRetryService, UploadScheduleStatus, UploadScheduler,
+};
 
 // use tauri_plugin_notification::NotificationExt; // replaced by notify wrapper where needed
 
@@ -259,14 +267,14 @@ pub async fn read_emit_file(window: tauri::Window, path: &str) -> Result<(), Str
         s if s.ends_with("S") => {
             if let Ok(emit_data_list) = struct_selector_response(formated_path, content) {
                 for emit_data_element in emit_data_list {
-                    emit_data(window.app_handle(), emit_data_element);
+                    emit_data(window.app_handle(), emit_data_element, &GameServer::default());
                 }
             }
         }
         s if s.ends_with("Q") => {
             if let Ok(emit_data_list) = struct_selector_resquest(formated_path, content) {
                 for emit_data_element in emit_data_list {
-                    emit_data(window.app_handle(), emit_data_element);
+                    emit_data(window.app_handle(), emit_data_element, &GameServer::default());
                 }
             }
         }
@@ -389,6 +397,9 @@ pub async fn retry_pending_upload_item_now(
     Ok(format!("Pending upload retry triggered for {trimmed_id}"))
 }
 
+/// Pending / dead-letter edits are refused while a retry batch owns the queue.
+const RETRY_RUNNING_MESSAGE: &str = "retry process is running; wait until it finishes";
+
 #[tauri::command(rename_all = "snake_case")]
 pub async fn delete_pending_upload_item(
     id: String,
@@ -400,22 +411,31 @@ pub async fn delete_pending_upload_item(
         return Err("id is required".to_string());
     }
 
-    if retry_service.is_running() {
-        return Err("retry process is running; wait until it finishes".to_string());
-    }
-
-    let exists = pending_store
-        .list_pending()
-        .into_iter()
-        .any(|item| item.id == trimmed_id);
-    if !exists {
-        return Err(format!("pending item not found: {trimmed_id}"));
-    }
-
-    pending_store
-        .delete_pending(trimmed_id)
-        .map_err(|e| format!("failed to delete pending item: {e}"))?;
-    Ok(format!("Pending upload deleted: {trimmed_id}"))
+    retry_service
+        .run_exclusive(|| {
+            let Some(meta) = pending_store
+                .list_pending()
+                .into_iter()
+                .find(|item| item.id == trimmed_id)
+            else {
+                return Err(format!("pending item not found: {trimmed_id}"));
+            };
+
+            pending_store
+                .delete_pending(trimmed_id)
+                .map_err(|e| format!("failed to delete pending item: {e}"))?;
+            let entry = UploadHistoryEntry::from_meta(
+                &meta,
+                UploadOutcome::Discarded,
+                meta.attempt_count,
+                0,
+            );
+            if let Err(e) = retry_service.history().record(&entry) {
+                tracing::warn!("failed to record upload history for {trimmed_id}: {e}");
+            }
+            Ok(format!("Pending upload deleted: {trimmed_id}"))
+        })
+        .unwrap_or_else(|| Err(RETRY_RUNNING_MESSAGE.to_string()))
 }
 
 #[derive(Debug, Serialize)]
@@ -428,6 +448,8 @@ pub struct PendingRetryItemStatus {
     pub next_due_at: u64,
     pub seconds_until_next_due: u64,
     pub expires_at: u64,
+    pub last_error: Option<String>,
+    pub last_error_class: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -478,14 +500,13 @@ pub async fn get_pending_upload_retry_status(
                 due_now_count += 1;
             }
 
-            let pending_type = classify_pending_type(
-                &meta.target_url,
-                meta.context.as_deref(),
-            );
-
             PendingRetryItemStatus {
+                pending_type: meta.pending_type(),
+                last_error_class: meta
+                    .last_error_class
+                    .map(|class| class.as_str().to_string()),
+                last_error: meta.last_error,
                 id: meta.id,
-                pending_type,
                 attempt_count: meta.attempt_count,
                 created_at: meta.created_at,
                 last_attempt_at: meta.last_attempt_at,
@@ -511,58 +532,82 @@ pub async fn get_pending_upload_retry_status(
     })
 }
 
-fn classify_pending_type(target_url: &str, context_raw: Option<&str>) -> String {
-    let Some(raw) = context_raw else {
-        return classify_by_target(target_url);
-    };
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_upload_queue_metrics(
+    pending_store: tauri::State<'_, Arc<PendingStore>>,
+    retry_service: tauri::State<'_, Arc<UploadRetryService>>,
+) -> Result<UploadMetricsSnapshot, String> {
+    Ok(retry_service.metrics().snapshot(&pending_store))
+}
 
-    let Ok(context) = serde_json::from_str::<UploadContext>(raw) else {
-        return classify_by_target(target_url);
-    };
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_upload_schedule_status() -> Result<UploadScheduleStatus, String> {
+    Ok(UploadScheduler::global().status())
+}
 
-    match context {
-        UploadContext::Asset { .. } => "asset_upload".to_string(),
-        UploadContext::Snapshot { .. } => "snapshot_upload".to_string(),
-        UploadContext::Custom(value) => {
-            let provider = value
-                .get("provider")
-                .and_then(|v| v.as_str())
-                .filter(|v| !v.is_empty());
-            let operation = value
-                .get("operation")
-                .and_then(|v| v.as_str())
-                .filter(|v| !v.is_empty());
-
-            match (provider, operation) {
-                (Some(provider), Some(operation)) => format!("{}_{}", provider, operation),
-                (Some(provider), None) => format!("{}_custom", provider),
-                (None, Some(operation)) => {
-                    if operation == "upload" {
-                        let target = target_url.trim();
-                        if !target.is_empty() && target != "localfs" {
-                            return format!("cloud_upload:{}", target);
-                        }
-                    }
-                    operation.to_string()
-                }
-                (None, None) => classify_by_target(target_url),
-            }
-        }
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_upload_offline_mode(
+    enabled: bool,
+    retry_service: tauri::State<'_, Arc<UploadRetryService>>,
+) -> Result<UploadScheduleStatus, String> {
+    let scheduler = UploadScheduler::global();
+    scheduler.set_paused(enabled);
+    if !scheduler.is_paused() {
+        // Flush whatever piled up while offline.
+        retry_service.trigger_retry_force().await;
     }
+    Ok(scheduler.status())
 }
 
-fn classify_by_target(target_url: &str) -> String {
-    let target = target_url.trim();
-    if target.is_empty() {
-        return "unknown".to_string();
-    }
-    if target == "localfs" {
-        return "localfs_write".to_string();
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_upload_history(
+    limit: Option<usize>,
+    retry_service: tauri::State<'_, Arc<UploadRetryService>>,
+) -> Result<Vec<UploadHistoryEntry>, String> {
+    Ok(retry_service.history().recent(limit.unwrap_or(100)))
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_dead_letter_uploads(
+    pending_store: tauri::State<'_, Arc<PendingStore>>,
+) -> Result<Vec<DeadLetterMeta>, String> {
+    Ok(pending_store.list_dead_letter())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn requeue_dead_letter_upload(
+    id: String,
+    pending_store: tauri::State<'_, Arc<PendingStore>>,
+    retry_service: tauri::State<'_, Arc<UploadRetryService>>,
+) -> Result<String, String> {
+    let trimmed_id = id.trim();
+    if trimmed_id.is_empty() {
+        return Err("id is required".to_string());
     }
-    if target.starts_with("http://") || target.starts_with("https://") {
-        return "http_upload".to_string();
+
+    retry_service
+        .run_exclusive(|| pending_store.requeue_dead_letter(trimmed_id))
+        .ok_or_else(|| RETRY_RUNNING_MESSAGE.to_string())?
+        .map_err(|e| format!("failed to requeue dead-letter item: {e}"))?;
+    Ok(format!("Dead-letter upload requeued: {trimmed_id}"))
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn delete_dead_letter_upload(
+    id: String,
+    pending_store: tauri::State<'_, Arc<PendingStore>>,
+    retry_service: tauri::State<'_, Arc<UploadRetryService>>,
+) -> Result<String, String> {
+    let trimmed_id = id.trim();
+    if trimmed_id.is_empty() {
+        return Err("id is required".to_string());
     }
-    format!("cloud_upload:{}", target)
+
+    retry_service
+        .run_exclusive(|| pending_store.delete_dead_letter(trimmed_id))
+        .ok_or_else(|| RETRY_RUNNING_MESSAGE.to_string())?
+        .map_err(|e| format!("failed to delete dead-letter item: {e}"))?;
+    Ok(format!("Dead-letter upload deleted: {trimmed_id}"))
 }
 
 #[tauri::command(rename_all = "snake_case")]
@@ -642,6 +687,136 @@ pub async fn get_remodel_suppression_status(
     }))
 }
 
+/// Latency and error statistics of the running proxy; `None` before it has
+/// started or when `[proxy.telemetry]` is disabled.
+#[tauri::command]
+pub fn get_proxy_telemetry() -> Option<proxy_https::telemetry::TelemetrySnapshot> {
+    proxy_https::telemetry::telemetry_snapshot()
+}
+
+/// Game API fields seen in live responses that differ from the DTOs.
+#[tauri::command]
+pub fn get_api_drift_report() -> kc_api::parser::drift::DriftReport {
+    kc_api::parser::drift::report()
+}
+
+/// API messages that failed to parse, counted per endpoint and class.
+#[tauri::command]
+pub fn get_parse_error_report() -> kc_api::parser::error::ParseErrorReport {
+    kc_api::parser::error::report()
+}
+
+/// Ships or equipment matching `query`; the syntax is described in
+/// `kc_api::interface::inventory`.
+#[tauri::command]
+pub fn search_inventory(
+    target: InventoryTarget,
+    query: String,
+) -> Result<InventoryResults, String> {
+    let query = InventoryQuery::parse(&query).map_err(|e| e.to_string())?;
+    Inventory::load()
+        .search(target, &query)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_inventory_saved_queries() -> Vec<configs::ConfigsAppInventorySavedQuery> {
+    configs::get_user_configs_for_app()
+        .inventory
+        .get_saved_queries()
+}
+
+/// Run the query saved as `name` in `[app.inventory]`.
+#[tauri::command]
+pub fn search_inventory_saved(name: String) -> Result<InventoryResults, String> {
+    let saved = configs::get_user_configs_for_app()
+        .inventory
+        .get_saved_query(&name)
+        .ok_or_else(|| format!("no saved query named {name}"))?;
+    let target = match saved.target.as_deref() {
+        None | Some("ships") => InventoryTarget::Ships,
+        Some("slot_items") => InventoryTarget::SlotItems,
+        Some(other) => return Err(format!("{name}: unknown target {other}")),
+    };
+    search_inventory(target, saved.query).map_err(|e| format!("{name}: {e}"))
+}
+
+/// Remodel chain of the ship `id` with the level and items of each step.
+#[tauri::command]
+pub fn get_ship_remodel_plan(id: i64) -> Option<RemodelPlan> {
+    RemodelPlanner::load().plan(id)
+}
+
+/// Ships whose next remodel can be done now.
+#[tauri::command]
+pub fn get_ready_ship_remodels() -> Vec<RemodelPlan> {
+    RemodelPlanner::load().ready()
+}
+
+/// Modernization left and levelling estimate of the ship `id`.
+#[tauri::command]
+pub fn get_ship_growth_plan(id: i64) -> Option<GrowthPlan> {
+    crate::storage::growth_plan::plan(id)
+}
+
+/// Plans of the ships with a levelling goal.
+#[tauri::command]
+pub fn get_ship_growth_plans() -> Vec<GrowthPlan> {
+    crate::storage::growth_plan::plans()
+}
+
+/// Set the levelling goal of the ship `id`, or clear it with `None`.
+#[tauri::command]
+pub fn set_ship_growth_goal(id: i64, goal: Option<ShipGoal>) -> Option<GrowthPlan> {
+    crate::storage::growth_plan::set_goal(id, goal);
+    crate::storage::growth_plan::plan(id)
+}
+
+/// Nodes with observed base exp, for choosing a farming node.
+#[tauri::command]
+pub fn get_farming_nodes() -> Vec<NodeExp> {
+    crate::storage::growth_plan::nodes()
+}
+
+/// Logged improvement attempts on `slotitem_master_id`, or on every item,
+/// newest first.
+#[tauri::command]
+pub fn get_improvement_attempts(slotitem_master_id: Option<i64>) -> Vec<ImprovementAttempt> {
+    crate::storage::improvement_log::attempts(slotitem_master_id)
+}
+
+/// Success rates and consumption by star level, and the helper/weekday
+/// combinations seen for the item.
+#[tauri::command]
+pub fn get_improvement_summary(slotitem_master_id: i64) -> ImprovementSummary {
+    crate::storage::improvement_log::summary(slotitem_master_id)
+}
+
+#[tauri::command]
+pub fn get_improvement_summaries() -> Vec<ImprovementSummary> {
+    crate::storage::improvement_log::summaries()
+}
+
+/// Write a zip of a capture folder or HAR file without account data, for
+/// attaching to bug reports.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_anonymized_captures(
+    input_path: String,
+    output_path: String,
+) -> Result<proxy_https::anonymize::AnonymizeSummary, String> {
+    let input = std::path::PathBuf::from(&input_path);
+    let output = std::path::PathBuf::from(output_path);
+    tokio::task::spawn_blocking(move || proxy_https::anonymize::anonymize_to_zip(&input, &output))
+        .await
+        .map_err(|e| format!("anonymize task failed: {e}"))?
+        .map_err(|e| format!("failed to anonymize {input_path}: {e}"))
+}
+
+#[tauri::command]
+pub fn get_event_stream_info() -> Option<crate::integration::event_stream::EventStreamInfo> {
+    crate::integration::event_stream::info()
+}
+
 // Removed: use notify::show via internal callers when needed.
 
 /// Tauri command to get all stored logs
@@ -0,0 +1,155 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use kc_api::interface::interface::EmitData;
+use proxy_https::event_stream::{serve_event_stream, EventBroadcaster};
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+struct RunningServer {
+    broadcaster: Arc<EventBroadcaster>,
+    addr: SocketAddr,
+    token: String,
+    stop: Option<oneshot::Sender<()>>,
+}
+
+static EVENT_STREAM: LazyLock<Mutex<Option<RunningServer>>> = LazyLock::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventStreamInfo {
+    pub ws_url: String,
+    pub sse_url: String,
+    pub token: String,
+}
+
+fn running_broadcaster() -> Option<Arc<EventBroadcaster>> {
+    EVENT_STREAM
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|server| server.broadcaster.clone())
+}
+
+/// Start the local event stream server when `[app.event_stream]` is enabled.
+/// Must be called from within the async runtime.
+pub fn start() {
+    let configs = configs::get_user_configs_for_app().event_stream;
+    if !configs.get_enable() {
+        return;
+    }
+
+    let mut guard = EVENT_STREAM.lock().unwrap_or_else(|e| e.into_inner());
+    if guard.is_some() {
+        return;
+    }
+
+    let broadcaster = Arc::new(EventBroadcaster::new(configs.get_replay_buffer_size()));
+    let token = configs
+        .get_token()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+    let (stop, stopped) = oneshot::channel::<()>();
+    match serve_event_stream(
+        broadcaster.clone(),
+        configs.get_port(),
+        token.clone(),
+        async move {
+            let _ = stopped.await;
+        },
+    ) {
+        Ok(addr) => {
+            tracing::info!("Event stream server listening on {}", addr);
+            *guard = Some(RunningServer {
+                broadcaster,
+                addr,
+                token,
+                stop: Some(stop),
+            });
+        }
+        Err(e) => tracing::error!("Failed to start event stream server: {}", e),
+    }
+}
+
+pub fn close() {
+    let mut guard = EVENT_STREAM.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(mut server) = guard.take() {
+        if let Some(stop) = server.stop.take() {
+            let _ = stop.send(());
+        }
+    }
+}
+
+pub fn info() -> Option<EventStreamInfo> {
+    let guard = EVENT_STREAM.lock().unwrap_or_else(|e| e.into_inner());
+    guard.as_ref().map(|server| EventStreamInfo {
+        ws_url: format!("ws://{}/events/ws", server.addr),
+        sse_url: format!("http://{}/events/sse", server.addr),
+        token: server.token.clone(),
+    })
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// Publish parsed data as `emit.<kind>.<variant>`, e.g. `emit.set.mst_ships`.
+pub fn publish_emit_data(emit_data: &EmitData) {
+    let Some(broadcaster) = running_broadcaster() else {
+        return;
+    };
+
+    // EmitData is externally tagged: {"Set": {"Ships": {...}}}.
+    let value = match serde_json::to_value(emit_data) {
+        Ok(value) => value,
+        Err(e) => {
+            tracing::error!("Failed to serialize emit data for event stream: {}", e);
+            return;
+        }
+    };
+    let Some((kind, inner)) = value.as_object().and_then(|object| object.iter().next()) else {
+        return;
+    };
+    let (variant, data) = match inner {
+        serde_json::Value::Object(object) if object.len() == 1 => {
+            let (variant, data) = object.iter().next().unwrap();
+            (variant.clone(), data.clone())
+        }
+        _ => return,
+    };
+    broadcaster.publish(
+        format!("emit.{}.{}", to_snake_case(kind), to_snake_case(&variant)),
+        data,
+    );
+}
+
+/// Publish a raw API message as `api.request` / `api.response`. Responses are
+/// forwarded as JSON with the `svdata=` prefix removed; anything that does not
+/// parse is sent as a string.
+pub fn publish_api_message(kind: &str, path: &str, content_type: &str, content: &str) {
+    let Some(broadcaster) = running_broadcaster() else {
+        return;
+    };
+
+    let body = content.trim_start_matches('\u{feff}');
+    let body = body.strip_prefix("svdata=").unwrap_or(body);
+    let content = serde_json::from_str(body)
+        .unwrap_or_else(|_| serde_json::Value::String(content.to_string()));
+    broadcaster.publish(
+        format!("api.{kind}"),
+        serde_json::json!({
+            "path": path,
+            "content_type": content_type,
+            "content": content,
+        }),
+    );
+}
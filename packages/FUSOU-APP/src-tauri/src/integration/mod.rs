@@ -1 +1,2 @@
 pub mod discord;
+pub mod event_stream;
@@ -1,21 +1,31 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
 use kc_api::interface::cells::Cells;
 use kc_api::interface::ship::Ships;
 use proxy_https::bidirectional_channel;
-use std::error::Error;
+use proxy_https::game_server::GameServer;
 use tauri::Emitter;
 
 #[cfg(dev)]
 use regex::Regex;
 
+use crate::integration::event_stream;
 use crate::storage::submit_data;
 
 use kc_api::interface::air_base::AirBases;
+use kc_api::interface::api_error::{ApiError, ApiErrorClass};
 use kc_api::interface::deck_port::DeckPorts;
 use kc_api::interface::interface::{Add, EmitData, Identifier, Set};
 
+use kc_api::parser::drift;
+use kc_api::parser::error::ParseError;
 use kc_api::parser::parser::{request_parser, response_parser};
+use kc_api::parser::quarantine::EndpointQuarantine;
 
-pub fn emit_data(handle: &tauri::AppHandle, emit_data: EmitData) {
+/// `server` is the game server the parsed message came from.
+pub fn emit_data(handle: &tauri::AppHandle, emit_data: EmitData, server: &GameServer) {
+    event_stream::publish_emit_data(&emit_data);
     match emit_data {
         EmitData::Add(data) => match data {
             Add::Materials(data) => {
@@ -28,7 +38,15 @@ pub fn emit_data(handle: &tauri::AppHandle, emit_data: EmitData) {
             Add::Battle(data) => {
                 let cell_id = data.cell_id;
                 data.add_or();
-                let merged_battle = Cells::load()
+                let cells = Cells::load();
+                if data.battle_result.is_some() {
+                    crate::storage::growth_plan::record_battle(
+                        cells.maparea_id,
+                        cells.mapinfo_no,
+                        &data,
+                    );
+                }
+                let merged_battle = cells
                     .battles
                     .get(&cell_id)
                     .cloned()
@@ -70,6 +88,7 @@ pub fn emit_data(handle: &tauri::AppHandle, emit_data: EmitData) {
             }
             Set::Ships(data) => {
                 data.restore();
+                crate::storage::growth_plan::learn_exp_table(&data);
                 let _ = handle.emit_to("main", "set-kcs-ships", data);
             }
             Set::SlotItems(data) => {
@@ -148,18 +167,23 @@ pub fn emit_data(handle: &tauri::AppHandle, emit_data: EmitData) {
                 crate::senders::soku_speed_sender::enqueue_snapshot(data);
             }
             Set::RemodelSlotList(data) => {
+                crate::storage::improvement_log::record_slot_list(&data);
                 crate::senders::remodel_sender::enqueue_slotlist(data);
             }
             Set::RemodelDetail(data) => {
                 crate::senders::remodel_sender::enqueue_detail(data);
             }
+            Set::ImprovementAttempt(data) => {
+                crate::storage::improvement_log::record_attempt(data.clone());
+                let _ = handle.emit_to("main", "add-kcs-improvement-attempt", data);
+            }
             Set::Dammy(_) => {
                 let _ = handle.emit_to("main", "set-kcs-dammy", ());
             }
         },
         EmitData::Identifier(data) => match data {
             Identifier::Port(_) => {
-                submit_data::submit_port_table();
+                submit_data::submit_port_table(server);
             }
             Identifier::RequireInfo(_) => {}
             Identifier::GetData(_) => {
@@ -171,20 +195,62 @@ pub fn emit_data(handle: &tauri::AppHandle, emit_data: EmitData) {
                 let _ = handle.emit_to("main", "set-kcs-deck-battles", DeckPorts::load());
                 Ships::cashe_restore();
             }
+            Identifier::ApiError(data) => {
+                tracing::warn!(
+                    "game API error {} at {}: {}",
+                    data.api_result,
+                    data.endpoint,
+                    data.api_result_msg
+                );
+                data.interrupt_sortie();
+                notify_api_error(handle, &data);
+                let _ = handle.emit_to("main", "set-kcs-api-error", data);
+            }
         },
     }
 }
 
+/// Last notified API error class, so a burst of failing calls (the game
+/// retries after the cat) shows one notification.
+static LAST_API_ERROR_NOTIFICATION: Mutex<Option<(ApiErrorClass, Instant)>> = Mutex::new(None);
+const API_ERROR_NOTIFICATION_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+fn notify_api_error(handle: &tauri::AppHandle, error: &ApiError) {
+    {
+        let mut last = LAST_API_ERROR_NOTIFICATION.lock().unwrap();
+        if let Some((class, at)) = *last {
+            if class == error.class && at.elapsed() < API_ERROR_NOTIFICATION_INTERVAL {
+                return;
+            }
+        }
+        *last = Some((error.class, Instant::now()));
+    }
+    let title = match error.class {
+        ApiErrorClass::SessionExpired => "Game session expired",
+        ApiErrorClass::Maintenance => "Game under maintenance",
+        ApiErrorClass::InvalidRequest => "Game rejected a request",
+        ApiErrorClass::Other => "Game API error",
+    };
+    crate::notify::show(
+        handle,
+        title,
+        &format!(
+            "{} returned {} {}. Data of the current sortie will not be submitted.",
+            error.endpoint, error.api_result, error.api_result_msg
+        ),
+    );
+}
+
 pub fn struct_selector_response(
     name: String,
     data: String,
-) -> Result<Vec<EmitData>, Box<dyn Error>> {
+) -> Result<Vec<EmitData>, ParseError> {
     let data_removed_bom: String = data.replace("\u{feff}", "");
     let data_removed_svdata: String = data_removed_bom.replace("svdata=", "");
 
     #[cfg(dev)]
     {
-        let re_metadata = Regex::new(r"---\r?\n.*\r?\n.*\r?\n.*\r?\n.*\s*---\r?\n").unwrap();
+        let re_metadata = Regex::new(r"---\r?\n(?:.*\r?\n)*?---\r?\n").unwrap();
         let data_removed_metadata: String =
             re_metadata.replace(&data_removed_svdata, "").to_string();
         return response_parser(name, data_removed_metadata);
@@ -197,14 +263,14 @@ pub fn struct_selector_response(
 pub fn struct_selector_resquest(
     name: String,
     data: String,
-) -> Result<Vec<EmitData>, Box<dyn Error>> {
+) -> Result<Vec<EmitData>, ParseError> {
     let data_removed_bom: String = data.replace("\u{feff}", "");
 
     #[cfg(dev)]
     {
         let re_svdata = Regex::new(r"svdata=").unwrap();
         let data_removed_svdata: String = re_svdata.replace(&data_removed_bom, "").to_string();
-        let re_metadata = Regex::new(r"---\r?\n.*\r?\n.*\r?\n.*\r?\n.*\s*---\r?\n").unwrap();
+        let re_metadata = Regex::new(r"---\r?\n(?:.*\r?\n)*?---\r?\n").unwrap();
         let data_removed_metadata: String =
             re_metadata.replace(&data_removed_svdata, "").to_string();
         let repalced_data = data_removed_metadata
@@ -217,11 +283,34 @@ pub fn struct_selector_resquest(
     return request_parser(name, data_removed_bom);
 }
 
+/// Rewrite the API drift report and notify the UI when the game API changed
+/// since the last check.
+fn report_api_drift(handle: &tauri::AppHandle) {
+    let changes = drift::take_new_changes();
+    if changes.is_empty() {
+        return;
+    }
+    let dir = crate::util::get_ROAMING_DIR().join("api_drift");
+    match drift::write_report(&dir) {
+        Ok(path) => tracing::warn!(
+            changes = changes.len(),
+            "game API changed, see {}",
+            path.display()
+        ),
+        Err(e) => tracing::warn!("failed to write API drift report: {}", e),
+    }
+    let _ = handle.emit_to("main", "api-drift-detected", changes);
+}
+
 async fn parser_server(
     handle: &tauri::AppHandle,
     mut slave: bidirectional_channel::Slave<bidirectional_channel::StatusInfo>,
     mut proxy_log_slave: bidirectional_channel::Slave<bidirectional_channel::StatusInfo>,
 ) {
+    // Calls to endpoints without a DTO, recorded here so requests and
+    // responses keep the order they arrived in.
+    let quarantine = EndpointQuarantine::new(crate::util::get_ROAMING_DIR().join("quarantine"));
+    let mut last_server = None;
     loop {
         tokio::select! {
             recv_log = proxy_log_slave.recv() => {
@@ -229,22 +318,29 @@ async fn parser_server(
                     None => {
                         tracing::warn!("Received None message");
                     },
-                    Some(bidirectional_channel::StatusInfo::RESPONSE { path, content_type: _, content }) => {
+                    Some(bidirectional_channel::StatusInfo::RESPONSE { path, content_type, content, server }) => {
+                        server.track_switch(&mut last_server);
+                        quarantine.record_response(&path, &content);
                         let handle_clone = handle.clone();
                         tokio::task::spawn(async move {
+                            event_stream::publish_api_message("response", &path, &content_type, &content);
                             if let Ok(emit_data_list) = struct_selector_response(path, content) {
                                 for emit_data_element in emit_data_list {
-                                    emit_data(&handle_clone, emit_data_element);
+                                    emit_data(&handle_clone, emit_data_element, &server);
                                 }
                             };
+                            report_api_drift(&handle_clone);
                         });
                     },
-                    Some(bidirectional_channel::StatusInfo::REQUEST { path, content_type: _, content }) => {
+                    Some(bidirectional_channel::StatusInfo::REQUEST { path, content_type, content, server }) => {
+                        server.track_switch(&mut last_server);
+                        quarantine.record_request(&path, &content);
                         let handle_clone = handle.clone();
                         tokio::task::spawn(async move {
+                            event_stream::publish_api_message("request", &path, &content_type, &content);
                             if let Ok(emit_data_list) = struct_selector_resquest(path, content) {
                                 for emit_data_element in emit_data_list {
-                                    emit_data(&handle_clone, emit_data_element);
+                                    emit_data(&handle_clone, emit_data_element, &server);
                                 }
                             };
                         });
@@ -182,9 +182,33 @@ pub async fn run() {
             cmd::tauri_cmd::retry_pending_upload_item_now,
             cmd::tauri_cmd::delete_pending_upload_item,
             cmd::tauri_cmd::get_pending_upload_retry_status,
+            cmd::tauri_cmd::get_upload_queue_metrics,
+            cmd::tauri_cmd::get_upload_schedule_status,
+            cmd::tauri_cmd::set_upload_offline_mode,
+            cmd::tauri_cmd::get_upload_history,
+            cmd::tauri_cmd::list_dead_letter_uploads,
+            cmd::tauri_cmd::requeue_dead_letter_upload,
+            cmd::tauri_cmd::delete_dead_letter_upload,
             cmd::tauri_cmd::get_ship_growth_suppression_status,
             cmd::tauri_cmd::get_quest_tree_suppression_status,
             cmd::tauri_cmd::get_remodel_suppression_status,
+            cmd::tauri_cmd::get_event_stream_info,
+            cmd::tauri_cmd::get_proxy_telemetry,
+            cmd::tauri_cmd::get_api_drift_report,
+            cmd::tauri_cmd::get_parse_error_report,
+            cmd::tauri_cmd::export_anonymized_captures,
+            cmd::tauri_cmd::search_inventory,
+            cmd::tauri_cmd::get_inventory_saved_queries,
+            cmd::tauri_cmd::search_inventory_saved,
+            cmd::tauri_cmd::get_ship_remodel_plan,
+            cmd::tauri_cmd::get_ready_ship_remodels,
+            cmd::tauri_cmd::get_ship_growth_plan,
+            cmd::tauri_cmd::get_ship_growth_plans,
+            cmd::tauri_cmd::set_ship_growth_goal,
+            cmd::tauri_cmd::get_farming_nodes,
+            cmd::tauri_cmd::get_improvement_attempts,
+            cmd::tauri_cmd::get_improvement_summary,
+            cmd::tauri_cmd::get_improvement_summaries,
             cmd::tauri_cmd::get_all_logs,
             #[cfg(dev)]
             cmd::tauri_cmd::open_debug_window,
@@ -237,7 +261,7 @@ pub async fn run() {
 
             // Register app-level custom retry handler so pending items are retried and deleted on success
             let retry_handler =
-                Arc::new(crate::storage::retry_handler::AppUploadRetryHandler::new(
+                Arc::new(crate::storage::retry_handler::StorageRetryHandler::new(
                     auth_manager_for_retry.clone(),
                 ));
             let retry_service = Arc::new(UploadRetryService::new(
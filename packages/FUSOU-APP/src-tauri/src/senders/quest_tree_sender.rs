@@ -208,7 +208,7 @@ impl QuestTreeSender {
         let request = UploadRequest {
             endpoint: &self.ingest_endpoint,
             handshake_body,
-            data,
+            data: data.into(),
             headers: {
                 let mut h = std::collections::HashMap::new();
                 // Reuse retry-service duplicate suppression by content-hash key.
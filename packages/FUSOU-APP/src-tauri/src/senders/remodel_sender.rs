@@ -211,7 +211,7 @@ impl RemodelSender {
         let request = UploadRequest {
             endpoint: &self.ingest_endpoint,
             handshake_body,
-            data,
+            data: data.into(),
             headers: {
                 let mut h = std::collections::HashMap::new();
                 h.insert("content-hash".to_string(), content_hash.clone());
@@ -177,7 +177,7 @@ impl SokuSpeedSender {
         let request = UploadRequest {
             endpoint: &self.ingest_endpoint,
             handshake_body,
-            data,
+            data: data.into(),
             headers: {
                 let mut h = std::collections::HashMap::new();
                 h.insert("content-hash".to_string(), content_hash);
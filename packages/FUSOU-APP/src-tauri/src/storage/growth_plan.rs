@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use kc_api::interface::battle::Battle;
+use kc_api::interface::growth_plan::{GrowthPlan, GrowthPlanStore, NodeExp, ShipGoal};
+use kc_api::interface::mst_ship::MstShips;
+use kc_api::interface::ship::Ships;
+use once_cell::sync::Lazy;
+
+use crate::util::get_ROAMING_DIR;
+
+/// Ship goals, base exp observed per node and the learned exp table, kept in
+/// the roaming directory.
+static STORE: Lazy<Mutex<GrowthPlanStore>> = Lazy::new(|| {
+    let store = GrowthPlanStore::load(&store_path()).unwrap_or_else(|e| {
+        tracing::warn!("Failed to read ship growth plans: {}", e);
+        GrowthPlanStore::default()
+    });
+    Mutex::new(store)
+});
+
+fn store_path() -> PathBuf {
+    get_ROAMING_DIR().join("ship_growth_plans.json")
+}
+
+fn update(f: impl FnOnce(&mut GrowthPlanStore) -> bool) {
+    let mut store = STORE.lock().unwrap();
+    if f(&mut store) {
+        if let Err(e) = store.save(&store_path()) {
+            tracing::error!("Failed to write ship growth plans: {}", e);
+        }
+    }
+}
+
+/// Count the base exp of a finished battle towards its node.
+pub fn record_battle(maparea_id: i64, mapinfo_no: i64, battle: &Battle) {
+    update(|store| store.record_battle(maparea_id, mapinfo_no, battle));
+}
+
+pub fn learn_exp_table(ships: &Ships) {
+    update(|store| store.learn_exp_table(ships));
+}
+
+pub fn set_goal(id: i64, goal: Option<ShipGoal>) {
+    update(|store| {
+        store.set_goal(id, goal);
+        true
+    });
+}
+
+pub fn plan(id: i64) -> Option<GrowthPlan> {
+    STORE
+        .lock()
+        .unwrap()
+        .plan(id, &Ships::load(), &MstShips::load())
+}
+
+pub fn plans() -> Vec<GrowthPlan> {
+    STORE
+        .lock()
+        .unwrap()
+        .plans(&Ships::load(), &MstShips::load())
+}
+
+pub fn nodes() -> Vec<NodeExp> {
+    STORE.lock().unwrap().nodes.clone()
+}
@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use kc_api::interface::improvement::{ImprovementAttempt, ImprovementLog, ImprovementSummary};
+use kc_api::interface::remodel::RemodelSlotList;
+use once_cell::sync::Lazy;
+
+use crate::util::get_ROAMING_DIR;
+
+/// Equipment improvement attempts and the helper/weekday catalogue, kept in
+/// the roaming directory.
+static LOG: Lazy<Mutex<ImprovementLog>> = Lazy::new(|| {
+    let log = ImprovementLog::load(&log_path()).unwrap_or_else(|e| {
+        tracing::warn!("Failed to read improvement log: {}", e);
+        ImprovementLog::default()
+    });
+    Mutex::new(log)
+});
+
+fn log_path() -> PathBuf {
+    get_ROAMING_DIR().join("improvement_log.json")
+}
+
+fn update(f: impl FnOnce(&mut ImprovementLog) -> bool) {
+    let mut log = LOG.lock().unwrap();
+    if f(&mut log) {
+        if let Err(e) = log.save(&log_path()) {
+            tracing::error!("Failed to write improvement log: {}", e);
+        }
+    }
+}
+
+pub fn record_attempt(attempt: ImprovementAttempt) {
+    update(|log| {
+        log.record_attempt(attempt);
+        true
+    });
+}
+
+pub fn record_slot_list(slot_list: &RemodelSlotList) {
+    update(|log| log.record_slot_list(slot_list));
+}
+
+pub fn attempts(slotitem_master_id: Option<i64>) -> Vec<ImprovementAttempt> {
+    LOG.lock().unwrap().attempts(slotitem_master_id)
+}
+
+pub fn summary(slotitem_master_id: i64) -> ImprovementSummary {
+    LOG.lock().unwrap().summary(slotitem_master_id)
+}
+
+pub fn summaries() -> Vec<ImprovementSummary> {
+    LOG.lock().unwrap().summaries()
+}
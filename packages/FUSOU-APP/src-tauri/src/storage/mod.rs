@@ -17,6 +17,10 @@ pub mod root_validator {
     pub use fusou_storage::root_validator::*;
 }
 
+pub mod retry_handler {
+    pub use fusou_storage::retry_handler::*;
+}
+
 pub mod service {
     pub use fusou_storage::service::*;
 }
@@ -25,8 +29,9 @@ pub use fusou_storage::{set_auth_page_launcher, set_dataset_id_resolver};
 
 pub mod snapshot;
 
+pub mod growth_plan;
+pub mod improvement_log;
 pub mod integrate;
-pub mod retry_handler;
 pub mod submit_data;
 
 pub use fusou_storage::CloudProviderFactory;
@@ -97,7 +97,7 @@ pub async fn perform_snapshot_sync_app(
     let request = UploadRequest {
         endpoint: &snapshot_url,
         handshake_body,
-        data: payload_bytes,
+        data: payload_bytes.into(),
         headers,
         context: UploadContext::Snapshot { is_snapshot: true },
     };
@@ -124,6 +124,15 @@ pub async fn perform_snapshot_sync_app(
             notify::show(app, "Snapshot sync", "Snapshot already up-to-date");
             Ok(json!({ "ok": true, "skipped": true, "tag": "latest" }))
         }
+        Ok(UploadResult::Queued) => {
+            tracing::info!("Snapshot queued by upload scheduler");
+            notify::show(
+                app,
+                "Snapshot sync",
+                "Uploads are paused; snapshot queued for later",
+            );
+            Ok(json!({ "ok": true, "queued": true, "tag": "latest" }))
+        }
         Err(e) => {
             // Check for authentication errors
             if e.contains("Authentication error") {
@@ -1,6 +1,6 @@
 use kc_api::{
     database::table::{GetDataTable, PortTable, PortTableEncode},
-    interface::cells::Cells,
+    interface::{api_error::ApiError, cells::Cells},
 };
 
 use crate::{
@@ -10,6 +10,7 @@ use crate::{
 };
 
 use fusou_upload::{PendingStore, UploadRetryService};
+use proxy_https::game_server::GameServer;
 use once_cell::sync::Lazy;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -59,9 +60,20 @@ pub fn submit_get_data_table() {
     });
 }
 
-pub fn submit_port_table() {
+/// Upload the accumulated cells, tagged with the server of the `port`
+/// response that ended the sortie.
+pub fn submit_port_table(server: &GameServer) {
+    if let Some(error) = ApiError::take_sortie_interruption() {
+        tracing::warn!(
+            "submit_port_table: skipped (sortie interrupted by API error {} at {})",
+            error.api_result,
+            error.endpoint
+        );
+        return;
+    }
     if !Cells::reset_flag() {
         let cells = Cells::load();
+        let server_index = server.index;
         let maparea_id = cells.maparea_id;
         let mapinfo_no = cells.mapinfo_no;
         tracing::info!(
@@ -102,7 +114,7 @@ pub fn submit_port_table() {
                 user_env
             };
             let timestamp = chrono::Utc::now().timestamp();
-            let port_table = PortTable::new(cells, user_env, timestamp);
+            let port_table = PortTable::new(cells, user_env, server_index, timestamp);
 
             match port_table.encode_non_empty_tables() {
                 Ok(tables) => {
@@ -135,7 +135,7 @@ pub async fn get_local_fallback_id() -> String {
     }
 }
 
-async fn load_or_create_device_key() -> Result<DeviceKey, String> {
+pub(crate) async fn load_or_create_device_key() -> Result<DeviceKey, String> {
     let path = get_ROAMING_DIR().join("fusou-auth-device-key.json");
     DeviceKey::load_or_create(path)
         .await
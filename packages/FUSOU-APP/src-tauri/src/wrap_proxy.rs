@@ -1,7 +1,9 @@
 // use proxy::bidirectional_channel::{Master, Slave, StatusInfo};
 use proxy_https::{
     bidirectional_channel::{Master, Slave, StatusInfo},
+    ca_manager::{CaEnsureOutcome, CaManager, CaPolicy},
     edit_pac::edit_pac,
+    pac_script::{PacHandle, PacRules},
 };
 use tauri::Url;
 
@@ -10,16 +12,19 @@ use crate::{
         get_pac_bidirectional_channel, get_proxy_bidirectional_channel,
         get_proxy_log_bidirectional_channel,
     },
-    cmd::native_cmd::{self, add_store_sync},
+    cmd::native_cmd::{self, add_staged_ca_to_store_sync, add_store_sync},
 };
 
 use fusou_auth::{AuthManager, FileStorage};
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 
 #[cfg(target_os = "linux")]
 use crate::cmd::native_cmd::check_ca_installed;
 
+/// Rules of the generated PAC script, kept across proxy restarts.
+static PAC_HANDLE: OnceLock<PacHandle> = OnceLock::new();
+
 pub async fn check_ca_and_install<R>(app: &tauri::AppHandle<R>) -> bool
 where
     R: tauri::Runtime,
@@ -106,16 +111,45 @@ where
     let use_generated_certs = proxy_configs.certificates.get_use_generated_certs();
 
     if use_generated_certs {
-        let ca_check_result = proxy_https::proxy_server_https::check_ca(ca_path.clone());
+        let ca_manager = CaManager::new(
+            ca_path.clone(),
+            CaPolicy::from_configs(&proxy_configs.certificates),
+        );
+        let ca_outcome = ca_manager
+            .ensure(chrono::Utc::now())
+            .map_err(|e| format!("Failed to prepare generated CA certificate: {e}"))?;
 
-        if ca_check_result {
+        if let CaEnsureOutcome::Staged { next } = &ca_outcome {
+            if !check_ca_and_install(app).await {
+                return Err("Failed to install or verify generated CA certificate".into());
+            }
+            // Switch to the staged CA only once it is trusted; otherwise keep
+            // signing with the current CA and try again on the next start.
+            tracing::info!(
+                activate_after = %next.activate_after,
+                "Next generated CA certificate is staged, installing..."
+            );
+            if add_staged_ca_to_store_sync(app, &ca_manager.next_dir()).await {
+                ca_manager
+                    .activate_next(chrono::Utc::now())
+                    .map_err(|e| format!("Failed to activate staged CA certificate: {e}"))?;
+                #[cfg(target_os = "linux")]
+                if !check_ca_installed(app).await {
+                    return Err("Generated CA certificate verification failed after rotation".into());
+                }
+            } else {
+                tracing::warn!("Failed to install staged CA certificate, keeping the current one");
+            }
+        } else if !ca_outcome.needs_install() {
             tracing::info!("Generated CA certificate already exists");
             if !check_ca_and_install(app).await {
                 return Err("Failed to install or verify generated CA certificate".into());
             }
         } else {
-            tracing::info!("Generated CA certificate does not exist, creating...");
-            proxy_https::proxy_server_https::create_ca(ca_path.clone());
+            tracing::info!(
+                outcome = ?ca_outcome,
+                "Generated CA certificate was created or rotated, installing..."
+            );
             if !add_store_sync(app).await {
                 return Err("Failed to install regenerated CA certificate".into());
             }
@@ -153,35 +187,63 @@ where
         ca_path,
         file_prefix.unwrap_or("".to_string()),
         auth_manager_for_proxy,
+        proxy_https::proxy_hook::ProxyHooks::new(),
     );
 
     if proxy_addr.is_err() {
         return Err("Failed to start proxy server".into());
     }
 
-    // start pac server
-    // let pac_addr = proxy::pac_server::serve_pac_file(pac_path.clone(), 0, pac_bidirectional_channel_slave);
-    let pac_addr = proxy_https::pac_server::serve_pac_file(
-        pac_path.clone(),
-        0,
-        pac_bidirectional_channel_slave,
-    );
-
-    if pac_addr.is_err() {
-        return Err("Failed to start pac server".into());
-    }
-
-    // edit_pac(pac_path.as_str(), proxy_addr.unwrap().to_string().as_str());
+    let proxy_addr_string = match proxy_addr {
+        // The PAC file is read on this machine, so point it at loopback when
+        // the proxy listens on all interfaces.
+        Ok(addr) if addr.ip().is_unspecified() => {
+            std::net::SocketAddr::from(([127, 0, 0, 1], addr.port())).to_string()
+        }
+        Ok(addr) => addr.to_string(),
+        Err(_) => return Err("Failed to start proxy server".into()),
+    };
     let host = if proxy_target.is_empty() {
         None
     } else {
         Some(proxy_target.as_str())
     };
-    let proxy_addr_string = match proxy_addr {
-        Ok(addr) => addr.to_string(),
-        Err(_) => return Err("Failed to start proxy server".into()),
+
+    // start pac server
+    let pac_addr = if proxy_configs.pac.get_generate() && !proxy_configs.pac.get_use_custom_pac()
+    {
+        let rules = PacRules::from_configs(
+            &proxy_configs.pac,
+            &configs::get_user_configs_for_app().connect_kc_server,
+            host,
+            &proxy_addr_string,
+        );
+        let pac_handle = PAC_HANDLE
+            .get_or_init(|| PacHandle::new(PacRules::default()).with_output_file(&pac_path))
+            .clone();
+        // Restarting the proxy keeps the handle, so only changed rules
+        // (e.g. a new proxy port) regenerate the script.
+        pac_handle.update(|current| *current = rules);
+        proxy_https::pac_server::serve_generated_pac(
+            pac_handle,
+            0,
+            pac_bidirectional_channel_slave,
+        )
+    } else {
+        // let pac_addr = proxy::pac_server::serve_pac_file(pac_path.clone(), 0, pac_bidirectional_channel_slave);
+        let pac_addr = proxy_https::pac_server::serve_pac_file(
+            pac_path.clone(),
+            0,
+            pac_bidirectional_channel_slave,
+        );
+        // edit_pac(pac_path.as_str(), proxy_addr.unwrap().to_string().as_str());
+        edit_pac(pac_path.as_str(), proxy_addr_string.clone().as_str(), host);
+        pac_addr
     };
-    edit_pac(pac_path.as_str(), proxy_addr_string.clone().as_str(), host);
+
+    if pac_addr.is_err() {
+        return Err("Failed to start pac server".into());
+    }
 
     if let Ok(pac_socket) = pac_addr {
         native_cmd::add_pac(
@@ -11,6 +11,8 @@ type PendingRetryItemStatus = {
   next_due_at: number;
   seconds_until_next_due: number;
   expires_at: number;
+  last_error?: string | null;
+  last_error_class?: string | null;
 };
 
 type PendingRetryStatus = {
@@ -0,0 +1,491 @@
+//! Removal of account data from captures, for attaching them to bug reports.
+//!
+//! Takes a capture folder (the `kcsapi/<time>Q@...`/`<time>S@...` layout) or
+//! a HAR file and writes a zip with the same data minus the API token, member
+//! ids, nicknames, comments, fleet names, other admirals in exercises and
+//! rankings, cookies and login parameters. Masked values keep their JSON type
+//! (see [`kc_api_dto::mask`]), so the files still parse and
+//! replay like the originals.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use kc_api_dto::mask::{mask_query_string, mask_value};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::har::{capture_files, read_captures, Har, HarEntry, HarNameValue, KcsapiCapture};
+
+/// Keys masked in every API call, matched against dotted key paths such as
+/// `res.api_data.api_basic.api_nickname` or `req.api_cmt`.
+pub const MASK_PATTERNS: &[&str] = &[
+    r"\.api_token$",
+    r"\.api_member_id$",
+    r"\.api_nickname(_id)?$",
+    r"\.api_comment(_id)?$",
+    r"\.api_cmt(_id)?$",
+    // Fleet and preset names.
+    r"\.api_(deck|deck_port|deck_data|data_deck)(\.\d+)?\.api_name(_id)?$",
+    r"\.api_deckname(_id)?$",
+    r"\.api_deck_name$",
+    // Exercise opponents.
+    r"\.api_enemy_(id|name|name_id|comment|comment_id)$",
+    r"\.api_user_name$",
+];
+
+/// Additional keys for endpoints whose top-level `api_name` is a fleet name,
+/// and for the ranking, whose keys are obfuscated.
+const ENDPOINT_MASK_PATTERNS: &[(&str, &[&str])] = &[
+    (
+        "/kcsapi/api_get_member/deck",
+        &[r"^res\.api_data\.api_name(_id)?$"],
+    ),
+    (
+        "/kcsapi/api_req_hensei/preset_register",
+        &[r"^(req|res\.api_data)\.api_name(_id)?$"],
+    ),
+    (
+        "/kcsapi/api_req_hensei/preset_select",
+        &[r"^res\.api_data\.api_name(_id)?$"],
+    ),
+    (
+        "/kcsapi/api_req_member/updatedeckname",
+        &[r"^req\.api_name(_id)?$"],
+    ),
+    (
+        "/kcsapi/api_req_ranking/",
+        // Nickname and comment.
+        &[r"^res\.api_data\.api_list\.api_(mtjmdcwtvhdr|itbrdpdbkynm)$"],
+    ),
+];
+
+/// Query parameters of page and gadget URLs that identify the login.
+const URL_MASK_PATTERNS: &[&str] = &[r"^req\.(api_starttime|owner|viewer|st|sesid)$"];
+
+/// Headers dropped from HAR entries.
+const DROPPED_HEADERS: &[&str] = &[
+    "cookie",
+    "set-cookie",
+    "authorization",
+    "proxy-authorization",
+];
+
+/// Headers holding URLs whose query is masked.
+const URL_HEADERS: &[&str] = &["referer", "location"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AnonymizeSummary {
+    /// API calls (or HAR entries) in the archive.
+    pub api_calls: usize,
+    /// Files in the archive.
+    pub files: usize,
+}
+
+fn mask_patterns(path: &str) -> Vec<&'static str> {
+    let mut patterns = MASK_PATTERNS.to_vec();
+    for (prefix, extra) in ENDPOINT_MASK_PATTERNS {
+        if path.starts_with(prefix) {
+            patterns.extend_from_slice(extra);
+        }
+    }
+    patterns
+}
+
+/// Mask a form-encoded request body of the API call `path`.
+pub fn anonymize_request(path: &str, body: &str) -> String {
+    mask_query_string(body, &mask_patterns(path))
+}
+
+/// Mask a response body (`svdata=` + JSON) of the API call `path`. Bodies
+/// that are not JSON are dropped, since they cannot be checked.
+pub fn anonymize_response(path: &str, body: &str) -> Option<String> {
+    let json = body.trim_start_matches('\u{feff}');
+    let json = json.strip_prefix("svdata=").unwrap_or(json);
+    let value: Value = serde_json::from_str(json).ok()?;
+    Some(format!(
+        "svdata={}",
+        mask_value("res", value, &mask_patterns(path))
+    ))
+}
+
+pub fn anonymize_capture(capture: &KcsapiCapture) -> KcsapiCapture {
+    KcsapiCapture {
+        request: capture
+            .request
+            .as_deref()
+            .map(|body| anonymize_request(&capture.path, body)),
+        response: capture
+            .response
+            .as_deref()
+            .and_then(|body| anonymize_response(&capture.path, body)),
+        // Identifies the installation.
+        env_id: String::new(),
+        ..capture.clone()
+    }
+}
+
+/// Mask a HAR archive. `/kcsapi` bodies are masked like captures; bodies of
+/// other entries (pages, gadgets, assets) are removed, since the login
+/// gadget response carries the API token.
+pub fn anonymize_har(har: &Har) -> Har {
+    let mut har = har.clone();
+    for entry in &mut har.log.entries {
+        anonymize_entry(entry);
+    }
+    har
+}
+
+fn anonymize_entry(entry: &mut HarEntry) {
+    let path = entry
+        .request
+        .url
+        .parse::<http::Uri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_default();
+    let is_api = path.starts_with("/kcsapi/");
+
+    entry.request.url = mask_url(&entry.request.url);
+    entry.request.cookies.clear();
+    entry.request.headers = anonymize_headers(&entry.request.headers);
+    for param in &mut entry.request.query_string {
+        param.value = mask_query_param(&param.name, &param.value);
+    }
+    if let Some(post_data) = &mut entry.request.post_data {
+        post_data.text = if is_api {
+            anonymize_request(&path, &post_data.text)
+        } else {
+            String::new()
+        };
+    }
+
+    entry.response.cookies.clear();
+    entry.response.headers = anonymize_headers(&entry.response.headers);
+    entry.response.redirect_url = mask_url(&entry.response.redirect_url);
+    let content = &mut entry.response.content;
+    content.text = if is_api {
+        content
+            .decoded_text()
+            .and_then(|body| anonymize_response(&path, &body))
+    } else {
+        None
+    };
+    content.encoding = None;
+    content.size = content.text.as_ref().map_or(0, |text| text.len() as i64);
+}
+
+fn anonymize_headers(headers: &[HarNameValue]) -> Vec<HarNameValue> {
+    headers
+        .iter()
+        .filter(|header| {
+            !DROPPED_HEADERS
+                .iter()
+                .any(|name| header.name.eq_ignore_ascii_case(name))
+        })
+        .map(|header| {
+            let is_url = URL_HEADERS
+                .iter()
+                .any(|name| header.name.eq_ignore_ascii_case(name));
+            HarNameValue {
+                name: header.name.clone(),
+                value: if is_url {
+                    mask_url(&header.value)
+                } else {
+                    header.value.clone()
+                },
+            }
+        })
+        .collect()
+}
+
+fn url_patterns() -> Vec<&'static str> {
+    [MASK_PATTERNS, URL_MASK_PATTERNS].concat()
+}
+
+fn mask_url(url: &str) -> String {
+    match url.split_once('?') {
+        Some((base, query)) => format!("{base}?{}", mask_query_string(query, &url_patterns())),
+        None => url.to_string(),
+    }
+}
+
+fn mask_query_param(name: &str, value: &str) -> String {
+    let masked = mask_query_string(&format!("{name}={value}"), &url_patterns());
+    masked
+        .split_once('=')
+        .map_or(masked.clone(), |(_, value)| value.to_string())
+}
+
+/// Write an anonymized copy of `input`, a capture folder or a HAR file, to
+/// the zip file `output`.
+pub fn anonymize_to_zip(input: &Path, output: &Path) -> io::Result<AnonymizeSummary> {
+    let mut zip = ZipWriter::default();
+    let api_calls = if input.is_dir() {
+        let captures: Vec<KcsapiCapture> = read_captures(input)?
+            .iter()
+            .map(anonymize_capture)
+            .collect();
+        for (name, content) in capture_files(&captures) {
+            zip.add(&name, &content)?;
+        }
+        captures.len()
+    } else {
+        let har = anonymize_har(&Har::read(input)?);
+        let name = input
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "capture.har".to_string());
+        zip.add(&name, &serde_json::to_vec_pretty(&har)?)?;
+        har.log.entries.len()
+    };
+    let files = zip.len();
+
+    let tmp = output.with_extension("zip.tmp");
+    std::fs::write(&tmp, zip.finish()?)?;
+    std::fs::rename(tmp, output)?;
+    Ok(AnonymizeSummary { api_calls, files })
+}
+
+/// 1980-01-01, the earliest DOS date, so the archive does not carry the time
+/// it was made.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Minimal deflate zip writer (no ZIP64, so below 4 GiB and 65535 files).
+#[derive(Default)]
+struct ZipWriter {
+    data: Vec<u8>,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    fn len(&self) -> usize {
+        self.entries as usize
+    }
+
+    fn add(&mut self, name: &str, content: &[u8]) -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content)?;
+        let compressed = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(content);
+
+        let offset = to_u32(self.data.len())?;
+        let mut fields = Vec::new();
+        fields.extend(20u16.to_le_bytes()); // version needed to extract
+        fields.extend(0x0800u16.to_le_bytes()); // UTF-8 names
+        fields.extend(8u16.to_le_bytes()); // deflate
+        fields.extend(0u16.to_le_bytes()); // time
+        fields.extend(DOS_DATE.to_le_bytes());
+        fields.extend(crc.sum().to_le_bytes());
+        fields.extend(to_u32(compressed.len())?.to_le_bytes());
+        fields.extend(to_u32(content.len())?.to_le_bytes());
+        fields.extend(
+            u16::try_from(name.len())
+                .map_err(|_| too_large())?
+                .to_le_bytes(),
+        );
+        fields.extend(0u16.to_le_bytes()); // extra field length
+
+        self.data.extend(0x04034b50u32.to_le_bytes());
+        self.data.extend(&fields);
+        self.data.extend(name.as_bytes());
+        self.data.extend(compressed);
+
+        self.central_directory.extend(0x02014b50u32.to_le_bytes());
+        self.central_directory.extend(20u16.to_le_bytes()); // version made by
+        self.central_directory.extend(&fields);
+        self.central_directory.extend(0u16.to_le_bytes()); // comment length
+        self.central_directory.extend(0u16.to_le_bytes()); // disk number
+        self.central_directory.extend(0u16.to_le_bytes()); // internal attributes
+        self.central_directory.extend(0u32.to_le_bytes()); // external attributes
+        self.central_directory.extend(offset.to_le_bytes());
+        self.central_directory.extend(name.as_bytes());
+
+        self.entries = self.entries.checked_add(1).ok_or_else(too_large)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<Vec<u8>> {
+        let offset = to_u32(self.data.len())?;
+        let size = to_u32(self.central_directory.len())?;
+        self.data.append(&mut self.central_directory);
+        self.data.extend(0x06054b50u32.to_le_bytes());
+        self.data.extend(0u16.to_le_bytes()); // disk number
+        self.data.extend(0u16.to_le_bytes()); // disk with the central directory
+        self.data.extend(self.entries.to_le_bytes());
+        self.data.extend(self.entries.to_le_bytes());
+        self.data.extend(size.to_le_bytes());
+        self.data.extend(offset.to_le_bytes());
+        self.data.extend(0u16.to_le_bytes()); // comment length
+        Ok(self.data)
+    }
+}
+
+fn to_u32(len: usize) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| too_large())
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "archive too large for zip")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::path::PathBuf;
+
+    use chrono::TimeZone;
+    use chrono_tz::Asia::Tokyo;
+
+    use crate::game_server::GameServer;
+    use crate::har::{captures_to_har, write_captures};
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("fusou-anonymize-test-{}", uuid::Uuid::new_v4()))
+    }
+
+    /// Files of a zip written by [`ZipWriter`].
+    fn read_zip(data: &[u8]) -> Vec<(String, String)> {
+        let u16_at = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+        let u32_at = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap()) as usize;
+        let end = data.len() - 22;
+        assert_eq!(u32_at(end), 0x06054b50);
+        let mut files = Vec::new();
+        let mut at = 0;
+        for _ in 0..u16_at(end + 10) {
+            assert_eq!(u32_at(at), 0x04034b50);
+            let compressed = u32_at(at + 18);
+            let name_len = u16_at(at + 26);
+            let name = String::from_utf8(data[at + 30..at + 30 + name_len].to_vec()).unwrap();
+            let start = at + 30 + name_len;
+            let mut content = String::new();
+            flate2::read::DeflateDecoder::new(&data[start..start + compressed])
+                .read_to_string(&mut content)
+                .unwrap();
+            files.push((name, content));
+            at = start + compressed;
+        }
+        assert_eq!(at, u32_at(end + 16));
+        files
+    }
+
+    fn port_capture() -> KcsapiCapture {
+        let requested_at = Tokyo.with_ymd_and_hms(2025, 6, 27, 12, 0, 0).unwrap();
+        KcsapiCapture {
+            path: "/kcsapi/api_port/port".to_string(),
+            requested_at,
+            responded_at: requested_at + chrono::Duration::milliseconds(250),
+            request: Some("api_verno=1&api_token=0123abcd&api_port=42".to_string()),
+            response: Some(format!(
+                "svdata={}",
+                serde_json::json!({
+                    "api_result": 1,
+                    "api_data": {
+                        "api_basic": {
+                            "api_member_id": "12345678",
+                            "api_nickname": "admiral",
+                            "api_comment": "hello",
+                            "api_level": 120
+                        },
+                        "api_deck_port": [
+                            {"api_member_id": 12345678, "api_id": 1, "api_name": "第1艦隊", "api_ship": [1, 2]}
+                        ]
+                    }
+                })
+            )),
+            env_id: "env".to_string(),
+            server: GameServer::default(),
+        }
+    }
+
+    #[test]
+    fn zips_masked_capture_folder() {
+        let dir = temp_dir();
+        write_captures(&dir, &[port_capture()]).unwrap();
+
+        let output = dir.join("report.zip");
+        let summary = anonymize_to_zip(&dir, &output).unwrap();
+        assert_eq!(
+            summary,
+            AnonymizeSummary {
+                api_calls: 1,
+                files: 2
+            }
+        );
+
+        let files = read_zip(&std::fs::read(&output).unwrap());
+        assert_eq!(files[0].0, "kcsapi/20250627_120000000Q@api_port@port");
+        assert!(files[0]
+            .1
+            .ends_with("---\napi_verno=1&api_token=__MASKED_API_TOKEN__&api_port=42"));
+        assert!(files[0].1.contains("EnvId: \n"));
+        assert_eq!(files[1].0, "kcsapi/20250627_120000250S@api_port@port");
+        let body = files[1].1.split_once("\n---\n").unwrap().1;
+        let response: Value = serde_json::from_str(body.strip_prefix("svdata=").unwrap()).unwrap();
+        let data = &response["api_data"];
+        assert_eq!(
+            data["api_basic"]["api_member_id"],
+            "__MASKED_API_MEMBER_ID__"
+        );
+        assert_eq!(data["api_basic"]["api_nickname"], "__MASKED_API_NICKNAME__");
+        assert_eq!(data["api_basic"]["api_comment"], "__MASKED_API_COMMENT__");
+        assert_eq!(data["api_basic"]["api_level"], 120);
+        assert_eq!(data["api_deck_port"][0]["api_member_id"], i64::MIN);
+        assert_eq!(data["api_deck_port"][0]["api_name"], "__MASKED_API_NAME__");
+        assert_eq!(
+            data["api_deck_port"][0]["api_ship"],
+            serde_json::json!([1, 2])
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn masks_har_entries() {
+        let mut har = captures_to_har(&[port_capture()]);
+        let mut page = har.log.entries[0].clone();
+        page.request.url =
+            "https://w01.test/kcs2/index.php?api_root=/kcsapi&api_token=0123abcd&api_starttime=1750993200000"
+                .to_string();
+        page.response.content.text = Some("svdata={\"api_token\":\"0123abcd\"}".to_string());
+        har.log.entries.push(page);
+        for entry in &mut har.log.entries {
+            entry.request.headers.push(HarNameValue {
+                name: "Cookie".to_string(),
+                value: "session=secret".to_string(),
+            });
+            entry.request.headers.push(HarNameValue {
+                name: "Referer".to_string(),
+                value: "https://w01.test/kcs2/index.php?api_token=0123abcd".to_string(),
+            });
+        }
+
+        let har = anonymize_har(&har);
+        let json = serde_json::to_string(&har).unwrap();
+        assert!(!json.contains("0123abcd"));
+        assert!(!json.contains("secret"));
+        assert!(!json.contains("admiral"));
+        assert!(json.contains("__MASKED_API_TOKEN__"));
+
+        let api = &har.log.entries[0];
+        assert!(api
+            .response
+            .content
+            .text
+            .as_ref()
+            .unwrap()
+            .contains("\"api_level\":120"));
+        let page = &har.log.entries[1];
+        assert_eq!(
+            page.request.url,
+            format!(
+                "https://w01.test/kcs2/index.php?api_root=/kcsapi&api_token=__MASKED_API_TOKEN__&api_starttime={}",
+                i64::MIN
+            )
+        );
+        assert_eq!(page.response.content.text, None);
+    }
+}
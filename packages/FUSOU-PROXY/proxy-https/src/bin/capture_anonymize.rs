@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use proxy_https::anonymize::anonymize_to_zip;
+
+const USAGE: &str = "\
+Usage:
+  capture_anonymize <CAPTURE_DIR | INPUT.har> <OUTPUT.zip>
+      Write a zip of the captures (or the HAR file) without the API token,
+      member ids, nicknames, comments, fleet names and other account data";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [input, output] = args.as_slice() else {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+    match anonymize_to_zip(&PathBuf::from(input), &PathBuf::from(output)) {
+        Ok(summary) => println!(
+            "wrote {} files for {} API calls to {output}",
+            summary.files, summary.api_calls
+        ),
+        Err(e) => {
+            eprintln!("capture_anonymize: {e}");
+            std::process::exit(1);
+        }
+    }
+}
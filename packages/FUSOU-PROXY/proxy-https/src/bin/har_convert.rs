@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use proxy_https::game_server::GameServerResolver;
+use proxy_https::har::{captures_to_har, har_to_captures, read_captures, write_captures, Har};
+
+const USAGE: &str = "\
+Usage:
+  har_convert to-kcsapi <INPUT.har> <OUTPUT_DIR> [--env-id <ID>]
+      Write the /kcsapi entries of a HAR file as capture files in OUTPUT_DIR/kcsapi
+  har_convert to-har <CAPTURE_DIR> <OUTPUT.har>
+      Build a HAR file from the capture files in CAPTURE_DIR (or CAPTURE_DIR/kcsapi)";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["to-kcsapi", input, output] => to_kcsapi(input, output, ""),
+        ["to-kcsapi", input, output, "--env-id", env_id] => to_kcsapi(input, output, env_id),
+        ["to-har", input, output] => to_har(input, output),
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("har_convert: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn to_kcsapi(input: &str, output: &str, env_id: &str) -> std::io::Result<()> {
+    let har = Har::read(&PathBuf::from(input))?;
+    let servers =
+        GameServerResolver::from_configs(&configs::get_user_configs_for_app().connect_kc_server);
+    let captures = har_to_captures(&har, env_id, &servers);
+    let written = write_captures(&PathBuf::from(output), &captures)?;
+    println!(
+        "wrote {written} files for {} API calls ({} HAR entries)",
+        captures.len(),
+        har.log.entries.len()
+    );
+    Ok(())
+}
+
+fn to_har(input: &str, output: &str) -> std::io::Result<()> {
+    let captures = read_captures(&PathBuf::from(input))?;
+    captures_to_har(&captures).write(&PathBuf::from(output))?;
+    println!("wrote {} entries to {output}", captures.len());
+    Ok(())
+}
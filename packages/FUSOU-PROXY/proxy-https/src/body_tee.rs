@@ -0,0 +1,272 @@
+//! Streaming bodies through the proxy while keeping a copy for hooks.
+//!
+//! [`tee`] wraps a body so that frames are forwarded to the client as soon
+//! as they arrive. Each data chunk is also handed to a background task that
+//! keeps it in memory up to [`SpoolConfig::memory_limit`] and spills larger
+//! bodies to a temporary file, so the proxy never holds a large resource
+//! twice while it is still being transferred. Once the body has been fully
+//! forwarded, the spooled copy is passed to the completion callback; a
+//! spilled body stays in its file rather than being read back. Bodies that
+//! fail or are abandoned by the client are discarded without calling it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use http_body_util::combinators::BoxBody;
+use hudsucker::hyper::body::{Body as HttpBody, Bytes, Frame, SizeHint};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::proxy_hook::HookBody;
+
+/// Where a tee keeps its copy of the body.
+#[derive(Debug, Clone)]
+pub struct SpoolConfig {
+    /// Bodies up to this many bytes are kept in memory.
+    pub memory_limit: usize,
+    /// Directory for bodies larger than `memory_limit`.
+    pub dir: PathBuf,
+}
+
+impl SpoolConfig {
+    pub fn from_configs(streaming: &configs::ConfigsProxyStreaming) -> Self {
+        Self {
+            memory_limit: streaming.get_memory_spool_limit(),
+            dir: streaming.get_spool_dir().unwrap_or_else(std::env::temp_dir),
+        }
+    }
+}
+
+impl Default for SpoolConfig {
+    fn default() -> Self {
+        Self {
+            memory_limit: 4_000_000,
+            dir: std::env::temp_dir(),
+        }
+    }
+}
+
+enum TeeChunk {
+    Data(Bytes),
+    End,
+}
+
+struct TeeBody {
+    inner: hudsucker::Body,
+    tx: Option<mpsc::UnboundedSender<TeeChunk>>,
+}
+
+impl HttpBody for TeeBody {
+    type Data = Bytes;
+    type Error = hudsucker::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let polled = Pin::new(&mut self.inner).poll_frame(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let (Some(data), Some(tx)) = (frame.data_ref(), &self.tx) {
+                    // Bytes clones share the buffer; the copy happens in the spool task.
+                    let _ = tx.send(TeeChunk::Data(data.clone()));
+                }
+            }
+            Poll::Ready(Some(Err(_))) => {
+                // Dropping the sender without `End` discards the spool.
+                self.tx = None;
+            }
+            Poll::Ready(None) => {
+                if let Some(tx) = self.tx.take() {
+                    let _ = tx.send(TeeChunk::End);
+                }
+            }
+            Poll::Pending => {}
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Forward `body` unchanged and call `on_complete` with a copy of it once it
+/// has been read to the end.
+pub fn tee<F>(body: hudsucker::Body, spool: SpoolConfig, on_complete: F) -> hudsucker::Body
+where
+    F: FnOnce(HookBody) + Send + 'static,
+{
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        match spool_body(rx, &spool).await {
+            Ok(Some(body)) => on_complete(body),
+            Ok(None) => tracing::debug!("body was not read to the end, dropping spooled copy"),
+            Err(e) => tracing::warn!("failed to spool body for inspection: {}", e),
+        }
+    });
+    hudsucker::Body::from(BoxBody::new(TeeBody {
+        inner: body,
+        tx: Some(tx),
+    }))
+}
+
+/// Temporary spool file, removed when dropped.
+#[derive(Debug)]
+pub(crate) struct SpooledFile {
+    path: PathBuf,
+    len: u64,
+}
+
+impl SpooledFile {
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!("failed to remove spool file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+async fn spool_body(
+    mut rx: mpsc::UnboundedReceiver<TeeChunk>,
+    spool: &SpoolConfig,
+) -> io::Result<Option<HookBody>> {
+    let mut memory: Vec<u8> = Vec::new();
+    let mut spool_file: Option<(SpooledFile, tokio::fs::File)> = None;
+
+    while let Some(chunk) = rx.recv().await {
+        match chunk {
+            TeeChunk::Data(data) => {
+                if spool_file.is_none() && memory.len() + data.len() > spool.memory_limit {
+                    tokio::fs::create_dir_all(&spool.dir).await?;
+                    let path = spool
+                        .dir
+                        .join(format!("fusou-spool-{}", uuid::Uuid::new_v4()));
+                    let file = tokio::fs::File::create(&path).await?;
+                    let (spooled, file) = spool_file.insert((SpooledFile { path, len: 0 }, file));
+                    file.write_all(&memory).await?;
+                    spooled.len = memory.len() as u64;
+                    memory = Vec::new();
+                }
+                match spool_file.as_mut() {
+                    Some((spooled, file)) => {
+                        file.write_all(&data).await?;
+                        spooled.len += data.len() as u64;
+                    }
+                    None => memory.extend_from_slice(&data),
+                }
+            }
+            TeeChunk::End => {
+                return match spool_file {
+                    Some((spooled, mut file)) => {
+                        file.flush().await?;
+                        // Close the file before hooks open it.
+                        drop(file);
+                        Ok(Some(HookBody::spooled(spooled)))
+                    }
+                    None => Ok(Some(HookBody::from(Bytes::from(memory)))),
+                };
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+    use std::io::Read;
+    use tokio::sync::oneshot;
+
+    fn chunked_body(chunks: &[&'static [u8]]) -> hudsucker::Body {
+        let chunks: Vec<Result<Bytes, io::Error>> = chunks
+            .iter()
+            .map(|chunk| Ok(Bytes::from_static(chunk)))
+            .collect();
+        hudsucker::Body::from_stream(futures_util::stream::iter(chunks))
+    }
+
+    fn spool_in(dir: &std::path::Path, memory_limit: usize) -> SpoolConfig {
+        SpoolConfig {
+            memory_limit,
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn spool_files(dir: &std::path::Path) -> usize {
+        std::fs::read_dir(dir)
+            .map(|entries| entries.count())
+            .unwrap_or(0)
+    }
+
+    #[tokio::test]
+    async fn forwards_body_and_hands_over_complete_copy() {
+        let dir = std::env::temp_dir().join(format!("fusou-tee-test-{}", uuid::Uuid::new_v4()));
+        for memory_limit in [1024, 4] {
+            let (done_tx, done_rx) = oneshot::channel();
+            let body = tee(
+                chunked_body(&[b"svdata=", b"{\"api_result\":1}"]),
+                spool_in(&dir, memory_limit),
+                move |copy| {
+                    let _ = done_tx.send(copy);
+                },
+            );
+
+            let forwarded = body.collect().await.unwrap().to_bytes();
+            assert_eq!(&forwarded[..], b"svdata={\"api_result\":1}");
+            let copy = done_rx.await.unwrap();
+            assert_eq!(copy.len(), forwarded.len() as u64);
+            assert_eq!(copy.to_bytes().unwrap(), forwarded);
+            // The small limit spills to a file, which hooks get instead of a
+            // copy in memory.
+            assert_eq!(copy.path().is_some(), memory_limit == 4);
+            assert_eq!(copy.as_bytes().is_none(), memory_limit == 4);
+            let mut read = Vec::new();
+            copy.reader().unwrap().read_to_end(&mut read).unwrap();
+            assert_eq!(read, forwarded);
+        }
+        // The spool file is removed with the last clone of the body.
+        assert_eq!(spool_files(&dir), 0);
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[tokio::test]
+    async fn abandoned_body_is_discarded() {
+        let dir = std::env::temp_dir().join(format!("fusou-tee-test-{}", uuid::Uuid::new_v4()));
+        let (done_tx, done_rx) = oneshot::channel::<HookBody>();
+        let mut body = tee(
+            chunked_body(&[b"first chunk", b"second chunk"]),
+            spool_in(&dir, 4),
+            move |copy| {
+                let _ = done_tx.send(copy);
+            },
+        );
+
+        let first = body.frame().await.unwrap().unwrap();
+        assert_eq!(
+            first.data_ref().unwrap(),
+            &Bytes::from_static(b"first chunk")
+        );
+        drop(body);
+
+        assert!(done_rx.await.is_err());
+        assert_eq!(spool_files(&dir), 0);
+        let _ = std::fs::remove_dir(&dir);
+    }
+}
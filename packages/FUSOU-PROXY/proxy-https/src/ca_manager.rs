@@ -0,0 +1,781 @@
+//! Lifecycle of the generated CA certificate.
+//!
+//! The CA lives in the CA directory under the fixed file names read by
+//! [`check_ca`] and `serve_proxy` and installed by the desktop app.
+//! [`CaManager`] adds a manifest (`ca_manifest.json`) recording when each CA
+//! was created and when it expires. Shortly before expiry it stages the next
+//! CA under `next/` while the proxy keeps signing with the current one, so
+//! clients can install the new CA before it is used. The staged CA replaces
+//! the current one once the grace period is over (never later than the
+//! current CA's expiry) or as soon as [`CaManager::activate_next`] is called
+//! after installing it. The replaced CA is kept under `retired/` for the same
+//! grace period; removing it from client trust stores is left to the user. It
+//! also exports the CA for installing on other devices.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use hudsucker::rcgen::{self, Certificate, CertificateParams, DnType, DnValue, KeyPair};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::proxy_server_https::{
+    ca_params, check_ca, write_ca_files, CA_CERT_NAME_CRT, CA_CERT_NAME_DER, CA_CERT_NAME_PEM,
+    CA_KEY_NAME_PEM,
+};
+
+pub const CA_MANIFEST_NAME: &str = "ca_manifest.json";
+const RETIRED_DIR_NAME: &str = "retired";
+const NEXT_DIR_NAME: &str = "next";
+const CA_COMMON_NAME_PREFIX: &str = "FUSOU CA";
+const CA_FILE_NAMES: [&str; 4] = [
+    CA_CERT_NAME_PEM,
+    CA_CERT_NAME_CRT,
+    CA_CERT_NAME_DER,
+    CA_KEY_NAME_PEM,
+];
+
+/// Validity and rotation settings, see `[proxy.certificates]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaPolicy {
+    pub validity_days: i64,
+    pub expiry_warning_days: i64,
+    pub rotate_before_days: i64,
+    pub rotation_grace_days: i64,
+}
+
+impl CaPolicy {
+    pub fn from_configs(certificates: &configs::ConfigsProxyCertificates) -> Self {
+        Self {
+            validity_days: certificates.get_ca_validity_days(),
+            expiry_warning_days: certificates.get_ca_expiry_warning_days(),
+            rotate_before_days: certificates.get_ca_rotate_before_days(),
+            rotation_grace_days: certificates.get_ca_rotation_grace_days(),
+        }
+    }
+}
+
+impl Default for CaPolicy {
+    fn default() -> Self {
+        Self {
+            validity_days: 3650,
+            expiry_warning_days: 30,
+            rotate_before_days: 7,
+            rotation_grace_days: 14,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaRecord {
+    /// SHA-256 of the DER certificate, lowercase hex.
+    pub fingerprint: String,
+    pub common_name: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A rotated-out CA kept in `retired/<dir_name>` until `remove_after`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetiredCa {
+    #[serde(flatten)]
+    pub record: CaRecord,
+    pub retired_at: DateTime<Utc>,
+    pub remove_after: DateTime<Utc>,
+    pub dir_name: String,
+}
+
+/// The CA that replaces the current one, kept in `next/` until it is
+/// activated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StagedCa {
+    #[serde(flatten)]
+    pub record: CaRecord,
+    pub staged_at: DateTime<Utc>,
+    /// `ensure` switches to this CA from then on.
+    pub activate_after: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaManifest {
+    /// Random per install; part of the common name of generated CAs so that
+    /// two installs never share a CA.
+    pub install_id: String,
+    pub current: CaRecord,
+    #[serde(default)]
+    pub next: Option<StagedCa>,
+    #[serde(default)]
+    pub retired: Vec<RetiredCa>,
+}
+
+#[derive(Debug)]
+pub enum CaError {
+    Io(io::Error),
+    Certificate(String),
+    Manifest(serde_json::Error),
+}
+
+impl fmt::Display for CaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaError::Io(e) => write!(f, "CA file error: {e}"),
+            CaError::Certificate(e) => write!(f, "CA certificate error: {e}"),
+            CaError::Manifest(e) => write!(f, "CA manifest error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CaError {}
+
+impl From<io::Error> for CaError {
+    fn from(e: io::Error) -> Self {
+        CaError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for CaError {
+    fn from(e: serde_json::Error) -> Self {
+        CaError::Manifest(e)
+    }
+}
+
+impl From<rcgen::Error> for CaError {
+    fn from(e: rcgen::Error) -> Self {
+        CaError::Certificate(e.to_string())
+    }
+}
+
+#[cfg(feature = "pkcs12")]
+impl From<openssl::error::ErrorStack> for CaError {
+    fn from(e: openssl::error::ErrorStack) -> Self {
+        CaError::Certificate(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaEnsureOutcome {
+    /// The current CA is valid and was kept.
+    Existing,
+    /// No usable CA existed; a new one was generated.
+    Created,
+    /// The CA is about to expire and its replacement is staged in `next/`.
+    /// The current CA is still used; install the staged one on clients.
+    Staged { next: StagedCa },
+    /// The staged CA has replaced the current one.
+    Rotated { previous: CaRecord },
+}
+
+impl CaEnsureOutcome {
+    /// Whether clients have to install a new certificate.
+    pub fn needs_install(&self) -> bool {
+        !matches!(self, CaEnsureOutcome::Existing)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CaStatus {
+    pub install_id: String,
+    pub current: CaRecord,
+    pub days_remaining: i64,
+    pub expiring_soon: bool,
+    pub next: Option<StagedCa>,
+    pub retired: Vec<RetiredCa>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaExportFormat {
+    /// Certificate only, PEM.
+    Pem,
+    /// Certificate only, DER (`.cer`/`.der` for Windows and Android).
+    Der,
+    /// Certificate and private key, password protected. Only for moving the
+    /// CA to another FUSOU install; never install this on a client.
+    #[cfg(feature = "pkcs12")]
+    Pkcs12,
+}
+
+pub struct CaManager {
+    dir: PathBuf,
+    policy: CaPolicy,
+}
+
+impl CaManager {
+    pub fn new(dir: impl Into<PathBuf>, policy: CaPolicy) -> Self {
+        Self {
+            dir: dir.into(),
+            policy,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn retired_dir(&self, retired: &RetiredCa) -> PathBuf {
+        self.dir.join(RETIRED_DIR_NAME).join(&retired.dir_name)
+    }
+
+    /// Directory holding the staged CA files, under the same file names as
+    /// the current CA.
+    pub fn next_dir(&self) -> PathBuf {
+        self.dir.join(NEXT_DIR_NAME)
+    }
+
+    /// `Ok(None)` when there is no manifest yet.
+    pub fn load_manifest(&self) -> Result<Option<CaManifest>, CaError> {
+        match fs::read(self.dir.join(CA_MANIFEST_NAME)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save_manifest(&self, manifest: &CaManifest) -> Result<(), CaError> {
+        let path = self.dir.join(CA_MANIFEST_NAME);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(manifest)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Make sure a usable CA exists: create it when missing or broken, stage
+    /// its replacement when it expires within `rotate_before_days`, switch to
+    /// the staged CA once its `activate_after` has passed, and remove retired
+    /// CAs whose grace period is over. Call before starting the proxy.
+    pub fn ensure(&self, now: DateTime<Utc>) -> Result<CaEnsureOutcome, CaError> {
+        if !check_ca(self.dir.to_string_lossy().to_string()) {
+            let previous = self.load_manifest().unwrap_or_else(|e| {
+                tracing::warn!("ignoring unreadable CA manifest: {}", e);
+                None
+            });
+            let install_id = previous
+                .as_ref()
+                .map(|manifest| manifest.install_id.clone())
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            let (cert, key, record) = self.build_ca(&install_id, now)?;
+            fs::create_dir_all(&self.dir)?;
+            write_ca_files(&self.dir, &cert, &key)?;
+            tracing::info!(fingerprint = %record.fingerprint, expires_at = %record.expires_at, "generated CA certificate");
+            // A staged CA was meant to follow the broken one; start over.
+            remove_dir_if_exists(&self.next_dir())?;
+            self.save_manifest(&CaManifest {
+                install_id,
+                current: record,
+                next: None,
+                retired: previous
+                    .map(|manifest| manifest.retired)
+                    .unwrap_or_default(),
+            })?;
+            self.prune_retired(now)?;
+            return Ok(CaEnsureOutcome::Created);
+        }
+
+        self.prune_retired(now)?;
+        let status = self.status(now)?;
+        let next = match status.next {
+            Some(next) => next,
+            None if status.days_remaining <= self.policy.rotate_before_days => {
+                tracing::warn!(
+                    days_remaining = status.days_remaining,
+                    "CA certificate is about to expire, staging its replacement"
+                );
+                self.stage_next(now)?
+            }
+            None => return Ok(CaEnsureOutcome::Existing),
+        };
+        if next.activate_after <= now {
+            if let Some(previous) = self.activate_next(now)? {
+                return Ok(CaEnsureOutcome::Rotated { previous });
+            }
+        }
+        Ok(CaEnsureOutcome::Staged { next })
+    }
+
+    /// Current state, adopting CA files that have no manifest entry yet.
+    /// Logs a warning when the CA expires within `expiry_warning_days`.
+    pub fn status(&self, now: DateTime<Utc>) -> Result<CaStatus, CaError> {
+        let manifest = self.current_manifest()?;
+        let days_remaining = (manifest.current.expires_at - now).num_days();
+        let expiring_soon = days_remaining <= self.policy.expiry_warning_days;
+        if expiring_soon {
+            tracing::warn!(
+                expires_at = %manifest.current.expires_at,
+                days_remaining,
+                "CA certificate expires soon"
+            );
+        }
+        Ok(CaStatus {
+            install_id: manifest.install_id,
+            current: manifest.current,
+            days_remaining,
+            expiring_soon,
+            next: manifest.next,
+            retired: manifest.retired,
+        })
+    }
+
+    /// Generate the next CA into `next/` and return it, or return the one
+    /// already staged. The current CA stays in use; the staged one becomes
+    /// due after `rotation_grace_days`, but no later than the current CA
+    /// expires.
+    pub fn stage_next(&self, now: DateTime<Utc>) -> Result<StagedCa, CaError> {
+        let mut manifest = self.current_manifest()?;
+        let next_dir = self.next_dir();
+        if let Some(next) = &manifest.next {
+            if check_ca(next_dir.to_string_lossy().to_string()) {
+                return Ok(next.clone());
+            }
+            tracing::warn!(fingerprint = %next.record.fingerprint, "staged CA files are missing, staging a new CA");
+        }
+
+        let (cert, key, record) = self.build_ca(&manifest.install_id, now)?;
+        remove_dir_if_exists(&next_dir)?;
+        fs::create_dir_all(&next_dir)?;
+        write_ca_files(&next_dir, &cert, &key)?;
+        let next = StagedCa {
+            record,
+            staged_at: now,
+            activate_after: (now + Duration::days(self.policy.rotation_grace_days))
+                .min(manifest.current.expires_at),
+        };
+        tracing::info!(
+            fingerprint = %next.record.fingerprint,
+            activate_after = %next.activate_after,
+            "staged next CA certificate"
+        );
+        manifest.next = Some(next.clone());
+        self.save_manifest(&manifest)?;
+        Ok(next)
+    }
+
+    /// Switch to the staged CA, e.g. once it is installed on every client.
+    /// The previous CA moves to `retired/` and is removed after
+    /// `rotation_grace_days`. Returns the previous CA, or `None` when
+    /// nothing is staged.
+    pub fn activate_next(&self, now: DateTime<Utc>) -> Result<Option<CaRecord>, CaError> {
+        let mut manifest = self.current_manifest()?;
+        let Some(next) = manifest.next.take() else {
+            return Ok(None);
+        };
+        let next_dir = self.next_dir();
+        if !check_ca(next_dir.to_string_lossy().to_string()) {
+            self.save_manifest(&manifest)?;
+            return Err(CaError::Certificate(format!(
+                "staged CA in {} is missing or invalid",
+                next_dir.display()
+            )));
+        }
+
+        let previous = manifest.current.clone();
+        let retired = RetiredCa {
+            dir_name: format!(
+                "{}_{}",
+                previous.created_at.format("%Y%m%d%H%M%S"),
+                &previous.fingerprint[..previous.fingerprint.len().min(12)]
+            ),
+            record: previous.clone(),
+            retired_at: now,
+            remove_after: now + Duration::days(self.policy.rotation_grace_days),
+        };
+        let retired_dir = self.retired_dir(&retired);
+        fs::create_dir_all(&retired_dir)?;
+        for name in CA_FILE_NAMES {
+            fs::rename(self.dir.join(name), retired_dir.join(name))?;
+        }
+        for name in CA_FILE_NAMES {
+            fs::rename(next_dir.join(name), self.dir.join(name))?;
+        }
+        remove_dir_if_exists(&next_dir)?;
+        tracing::info!(
+            fingerprint = %next.record.fingerprint,
+            previous = %previous.fingerprint,
+            remove_after = %retired.remove_after,
+            "rotated CA certificate"
+        );
+
+        manifest.current = next.record;
+        manifest.retired.push(retired);
+        self.save_manifest(&manifest)?;
+        Ok(Some(previous))
+    }
+
+    /// Remove retired CAs whose grace period is over and return them. Copies
+    /// installed in client trust stores are not touched.
+    pub fn prune_retired(&self, now: DateTime<Utc>) -> Result<Vec<RetiredCa>, CaError> {
+        let Some(mut manifest) = self.load_manifest()? else {
+            return Ok(Vec::new());
+        };
+        let (expired, kept): (Vec<_>, Vec<_>) = manifest
+            .retired
+            .into_iter()
+            .partition(|retired| retired.remove_after <= now);
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+        for retired in &expired {
+            remove_dir_if_exists(&self.retired_dir(retired))?;
+            tracing::info!(fingerprint = %retired.record.fingerprint, "removed retired CA certificate");
+        }
+        manifest.retired = kept;
+        self.save_manifest(&manifest)?;
+        Ok(expired)
+    }
+
+    /// Write the current CA to `dest`. `password` is only used for PKCS#12.
+    #[cfg_attr(not(feature = "pkcs12"), allow(unused_variables))]
+    pub fn export(
+        &self,
+        format: CaExportFormat,
+        dest: &Path,
+        password: &str,
+    ) -> Result<(), CaError> {
+        let bytes = match format {
+            CaExportFormat::Pem => fs::read(self.dir.join(CA_CERT_NAME_PEM))?,
+            CaExportFormat::Der => fs::read(self.dir.join(CA_CERT_NAME_DER))?,
+            #[cfg(feature = "pkcs12")]
+            CaExportFormat::Pkcs12 => {
+                let cert =
+                    openssl::x509::X509::from_der(&fs::read(self.dir.join(CA_CERT_NAME_DER))?)?;
+                let key = openssl::pkey::PKey::private_key_from_pem(&fs::read(
+                    self.dir.join(CA_KEY_NAME_PEM),
+                )?)?;
+                openssl::pkcs12::Pkcs12::builder()
+                    .name(CA_COMMON_NAME_PREFIX)
+                    .pkey(&key)
+                    .cert(&cert)
+                    .build2(password)?
+                    .to_der()?
+            }
+        };
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(dest, bytes)?;
+        Ok(())
+    }
+
+    /// Load the manifest, recording the CA files as the current CA when the
+    /// manifest is missing or describes a different certificate (e.g. the
+    /// files were replaced by hand or copied from another install).
+    fn current_manifest(&self) -> Result<CaManifest, CaError> {
+        let on_disk = self.read_current_record()?;
+        let manifest = match self.load_manifest() {
+            Ok(Some(manifest)) if manifest.current.fingerprint == on_disk.fingerprint => {
+                return Ok(manifest);
+            }
+            Ok(Some(mut manifest)) => {
+                tracing::warn!(
+                    expected = %manifest.current.fingerprint,
+                    found = %on_disk.fingerprint,
+                    "CA certificate does not match the manifest, adopting the certificate on disk"
+                );
+                manifest.current = on_disk;
+                manifest
+            }
+            Ok(None) => CaManifest {
+                install_id: uuid::Uuid::new_v4().to_string(),
+                current: on_disk,
+                next: None,
+                retired: Vec::new(),
+            },
+            Err(e) => {
+                tracing::warn!("replacing unreadable CA manifest: {}", e);
+                CaManifest {
+                    install_id: uuid::Uuid::new_v4().to_string(),
+                    current: on_disk,
+                    next: None,
+                    retired: Vec::new(),
+                }
+            }
+        };
+        self.save_manifest(&manifest)?;
+        Ok(manifest)
+    }
+
+    fn read_current_record(&self) -> Result<CaRecord, CaError> {
+        let pem_path = self.dir.join(CA_CERT_NAME_PEM);
+        let params = CertificateParams::from_ca_cert_pem(&fs::read_to_string(&pem_path)?)?;
+        let der = fs::read(self.dir.join(CA_CERT_NAME_DER))?;
+        let common_name = match params.distinguished_name.get(&DnType::CommonName) {
+            Some(DnValue::Utf8String(name)) => name.clone(),
+            Some(DnValue::PrintableString(name)) => name.as_str().to_string(),
+            _ => String::new(),
+        };
+        // Certificates from `create_ca` use rcgen's default validity start,
+        // so the file time is the better creation date.
+        let created_at = fs::metadata(&pem_path)
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        let expires_at = Utc
+            .timestamp_opt(params.not_after.unix_timestamp(), 0)
+            .single()
+            .ok_or_else(|| CaError::Certificate("invalid notAfter".to_string()))?;
+        Ok(CaRecord {
+            fingerprint: fingerprint(&der),
+            common_name,
+            created_at,
+            expires_at,
+        })
+    }
+
+    fn build_ca(
+        &self,
+        install_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(Certificate, KeyPair, CaRecord), CaError> {
+        let key = KeyPair::generate()?;
+        let common_name = format!(
+            "{CA_COMMON_NAME_PREFIX} {}",
+            &install_id[..install_id.len().min(8)]
+        );
+        let not_before = now - Duration::days(1);
+        let not_after = now + Duration::days(self.policy.validity_days);
+
+        let mut params = ca_params();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name.as_str());
+        params.not_before = rcgen::date_time_ymd(
+            not_before.year(),
+            not_before.month() as u8,
+            not_before.day() as u8,
+        );
+        params.not_after = rcgen::date_time_ymd(
+            not_after.year(),
+            not_after.month() as u8,
+            not_after.day() as u8,
+        );
+        let cert = params.self_signed(&key)?;
+
+        let record = CaRecord {
+            fingerprint: fingerprint(cert.der()),
+            common_name,
+            created_at: now,
+            // rcgen only sets whole days.
+            expires_at: Utc
+                .with_ymd_and_hms(
+                    not_after.year(),
+                    not_after.month(),
+                    not_after.day(),
+                    0,
+                    0,
+                    0,
+                )
+                .single()
+                .unwrap_or(not_after),
+        };
+        Ok((cert, key, record))
+    }
+}
+
+fn remove_dir_if_exists(dir: &Path) -> Result<(), CaError> {
+    match fs::remove_dir_all(dir) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_ca_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("fusou-ca-test-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn days(n: i64) -> Duration {
+        Duration::days(n)
+    }
+
+    #[test]
+    fn ensure_creates_then_keeps_ca() {
+        let dir = temp_ca_dir();
+        let manager = CaManager::new(&dir, CaPolicy::default());
+        let now = Utc::now();
+
+        assert_eq!(manager.ensure(now).unwrap(), CaEnsureOutcome::Created);
+        assert!(check_ca(dir.to_string_lossy().to_string()));
+        let status = manager.status(now).unwrap();
+        assert!(status.current.common_name.starts_with("FUSOU CA "));
+        assert!((3648..=3650).contains(&status.days_remaining));
+        assert!(!status.expiring_soon);
+
+        assert_eq!(
+            manager.ensure(now + days(1)).unwrap(),
+            CaEnsureOutcome::Existing
+        );
+        assert_eq!(
+            manager.load_manifest().unwrap().unwrap().current,
+            status.current
+        );
+
+        // Another install gets a different CA.
+        let other_dir = temp_ca_dir();
+        let other = CaManager::new(&other_dir, CaPolicy::default());
+        other.ensure(now).unwrap();
+        let other_status = other.status(now).unwrap();
+        assert_ne!(other_status.install_id, status.install_id);
+        assert_ne!(other_status.current.fingerprint, status.current.fingerprint);
+
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_dir_all(other_dir).unwrap();
+    }
+
+    #[test]
+    fn rotation_stages_next_ca_before_switching() {
+        let dir = temp_ca_dir();
+        let policy = CaPolicy {
+            validity_days: 40,
+            expiry_warning_days: 20,
+            rotate_before_days: 10,
+            rotation_grace_days: 5,
+        };
+        let manager = CaManager::new(&dir, policy);
+        let created = Utc::now();
+        manager.ensure(created).unwrap();
+        let first = manager.status(created).unwrap().current;
+
+        assert!(manager.status(created + days(25)).unwrap().expiring_soon);
+        assert_eq!(
+            manager.ensure(created + days(25)).unwrap(),
+            CaEnsureOutcome::Existing
+        );
+
+        // The replacement is staged but the current CA keeps signing.
+        let staged_at = created + days(31);
+        let CaEnsureOutcome::Staged { next } = manager.ensure(staged_at).unwrap() else {
+            panic!("expected a staged CA");
+        };
+        assert_ne!(next.record.fingerprint, first.fingerprint);
+        assert_eq!(next.activate_after, staged_at + days(5));
+        assert!(check_ca(manager.next_dir().to_string_lossy().to_string()));
+        assert_eq!(manager.status(staged_at).unwrap().current, first);
+        assert_eq!(
+            manager.ensure(staged_at + days(4)).unwrap(),
+            CaEnsureOutcome::Staged { next: next.clone() }
+        );
+
+        let rotated_at = staged_at + days(5);
+        assert_eq!(
+            manager.ensure(rotated_at).unwrap(),
+            CaEnsureOutcome::Rotated {
+                previous: first.clone()
+            }
+        );
+        let status = manager.status(rotated_at).unwrap();
+        assert_eq!(status.current, next.record);
+        assert!(status.next.is_none());
+        assert!(!manager.next_dir().exists());
+        assert_eq!(status.retired.len(), 1);
+        let retired = &status.retired[0];
+        assert_eq!(retired.record, first);
+        assert!(manager.retired_dir(retired).join(CA_CERT_NAME_PEM).exists());
+
+        assert!(manager
+            .prune_retired(rotated_at + days(4))
+            .unwrap()
+            .is_empty());
+        let pruned = manager.prune_retired(rotated_at + days(5)).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert!(!manager.retired_dir(&pruned[0]).exists());
+        assert!(manager.status(rotated_at).unwrap().retired.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn confirmed_install_activates_staged_ca_early() {
+        let dir = temp_ca_dir();
+        let manager = CaManager::new(&dir, CaPolicy::default());
+        let now = Utc::now();
+        manager.ensure(now).unwrap();
+        let first = manager.status(now).unwrap().current;
+        assert_eq!(manager.activate_next(now).unwrap(), None);
+
+        let next = manager.stage_next(now).unwrap();
+        assert_eq!(manager.stage_next(now).unwrap(), next);
+        assert_eq!(manager.activate_next(now).unwrap(), Some(first));
+        assert_eq!(manager.status(now).unwrap().current, next.record);
+        assert_eq!(manager.ensure(now).unwrap(), CaEnsureOutcome::Existing);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn adopts_ca_without_manifest_and_detects_replacement() {
+        let dir = temp_ca_dir();
+        crate::proxy_server_https::create_ca(dir.to_string_lossy().to_string());
+        let manager = CaManager::new(&dir, CaPolicy::default());
+        let now = Utc::now();
+
+        assert_eq!(manager.ensure(now).unwrap(), CaEnsureOutcome::Existing);
+        let adopted = manager.load_manifest().unwrap().unwrap();
+
+        crate::proxy_server_https::create_ca(dir.to_string_lossy().to_string());
+        let status = manager.status(now).unwrap();
+        assert_eq!(status.install_id, adopted.install_id);
+        assert_ne!(status.current.fingerprint, adopted.current.fingerprint);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn exports_pem_and_der() {
+        let dir = temp_ca_dir();
+        let manager = CaManager::new(&dir, CaPolicy::default());
+        manager.ensure(Utc::now()).unwrap();
+        let export_dir = dir.join("export");
+
+        manager
+            .export(CaExportFormat::Pem, &export_dir.join("ca.pem"), "")
+            .unwrap();
+        manager
+            .export(CaExportFormat::Der, &export_dir.join("ca.der"), "")
+            .unwrap();
+
+        let pem = fs::read_to_string(export_dir.join("ca.pem")).unwrap();
+        assert!(pem.starts_with("-----BEGIN CERTIFICATE-----"));
+        let der = fs::read(export_dir.join("ca.der")).unwrap();
+        assert_eq!(
+            fingerprint(&der),
+            manager.status(Utc::now()).unwrap().current.fingerprint
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "pkcs12")]
+    fn exports_pkcs12() {
+        let dir = temp_ca_dir();
+        let manager = CaManager::new(&dir, CaPolicy::default());
+        manager.ensure(Utc::now()).unwrap();
+        let dest = dir.join("export").join("ca.p12");
+
+        manager
+            .export(CaExportFormat::Pkcs12, &dest, "secret")
+            .unwrap();
+
+        let p12 = openssl::pkcs12::Pkcs12::from_der(&fs::read(dest).unwrap())
+            .unwrap()
+            .parse2("secret")
+            .unwrap();
+        let der = fs::read(dir.join(CA_CERT_NAME_DER)).unwrap();
+        assert_eq!(p12.cert.unwrap().to_der().unwrap(), der);
+        assert!(p12.pkey.is_some());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
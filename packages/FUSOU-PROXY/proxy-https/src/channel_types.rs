@@ -1,3 +1,5 @@
+use crate::game_server::GameServer;
+
 #[derive(Debug, Clone)]
 pub enum StatusInfo {
     HEALTH {
@@ -12,10 +14,12 @@ pub enum StatusInfo {
         path: String,
         content_type: String,
         content: String,
+        server: GameServer,
     },
     REQUEST {
         path: String,
         content_type: String,
         content: String,
+        server: GameServer,
     },
 }
@@ -0,0 +1,117 @@
+//! Which clients may use the proxy when it listens on a non-loopback address.
+//!
+//! Entries come from `[proxy.network].proxy_allowed_clients` as IP addresses
+//! or CIDR ranges. Loopback clients are always allowed; an empty list allows
+//! every client, which is only safe while the proxy binds to loopback.
+
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientAllowlist {
+    /// Network address and prefix length.
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl ClientAllowlist {
+    /// Invalid entries are logged and skipped.
+    pub fn from_configs(entries: &[String]) -> Self {
+        let ranges = entries
+            .iter()
+            .filter_map(|entry| match parse_range(entry) {
+                Some(range) => Some(range),
+                None => {
+                    tracing::warn!("invalid proxy_allowed_clients entry: {}", entry);
+                    None
+                }
+            })
+            .collect();
+        Self { ranges }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn allows(&self, client: IpAddr) -> bool {
+        let client = canonical(client);
+        client.is_loopback()
+            || self.ranges.is_empty()
+            || self
+                .ranges
+                .iter()
+                .any(|(network, prefix)| in_range(client, *network, *prefix))
+    }
+}
+
+fn parse_range(entry: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match entry.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix.parse::<u8>().ok()?)),
+        None => (entry, None),
+    };
+    let address = canonical(address.trim().parse().ok()?);
+    let max = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max);
+    (prefix <= max).then_some((address, prefix))
+}
+
+/// IPv4-mapped IPv6 clients (dual-stack listeners) compare as IPv4.
+fn canonical(address: IpAddr) -> IpAddr {
+    match address {
+        IpAddr::V6(v6) => v6
+            .to_ipv4_mapped()
+            .map(IpAddr::V4)
+            .unwrap_or(IpAddr::V6(v6)),
+        v4 => v4,
+    }
+}
+
+fn in_range(client: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (client, network) {
+        (IpAddr::V4(client), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(client) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(client), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(client) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn allows_listed_ranges_and_loopback() {
+        let allowlist = ClientAllowlist::from_configs(&[
+            "192.168.1.0/24".to_string(),
+            "10.0.0.5".to_string(),
+            "fd00::/8".to_string(),
+            "not an address".to_string(),
+            "10.0.0.0/33".to_string(),
+        ]);
+        assert!(allowlist.allows(ip("192.168.1.20")));
+        assert!(allowlist.allows(ip("::ffff:192.168.1.20")));
+        assert!(allowlist.allows(ip("10.0.0.5")));
+        assert!(allowlist.allows(ip("fd12::1")));
+        assert!(allowlist.allows(ip("127.0.0.1")));
+        assert!(allowlist.allows(ip("::1")));
+        assert!(!allowlist.allows(ip("192.168.2.20")));
+        assert!(!allowlist.allows(ip("10.0.0.6")));
+        assert!(!allowlist.allows(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn empty_allows_everyone() {
+        let allowlist = ClientAllowlist::from_configs(&[]);
+        assert!(allowlist.is_empty());
+        assert!(allowlist.allows(ip("203.0.113.7")));
+        assert!(ClientAllowlist::from_configs(&["0.0.0.0/0".to_string()]).allows(ip("203.0.113.7")));
+    }
+}
@@ -0,0 +1,533 @@
+//! Local broadcast of game events for third-party tools (overlays, stream
+//! widgets, bots).
+//!
+//! Events are published to an [`EventBroadcaster`] and served as JSON over
+//! WebSocket (`/events/ws`) and Server-Sent Events (`/events/sse`) on
+//! 127.0.0.1 only. Every client must present the shared token either as
+//! `Authorization: Bearer <token>` or as `?token=<token>`.
+//!
+//! Query parameters common to both endpoints:
+//! - `topics`: comma separated topic filter. `emit` matches `emit.set.ships`
+//!   and every other topic below `emit`; empty or missing means all topics.
+//! - `since`: replay buffered events with an offset greater than this before
+//!   switching to live events. SSE clients may send `Last-Event-ID` instead,
+//!   which browsers do automatically when reconnecting.
+//!
+//! Offsets start at 1 and increase by one per published event. Only the last
+//! `capacity` events are buffered; older ones cannot be replayed.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+use warp::http::StatusCode;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::Filter;
+
+/// Capacity of the live channel; slower clients catch up from the replay
+/// buffer.
+const LIVE_CHANNEL_CAPACITY: usize = 256;
+
+/// A single published event as sent to clients.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StreamEvent {
+    pub offset: u64,
+    pub topic: String,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: i64,
+    pub data: serde_json::Value,
+}
+
+struct ReplayBuffer {
+    next_offset: u64,
+    capacity: usize,
+    events: VecDeque<Arc<StreamEvent>>,
+}
+
+/// Fan-out of published events with a bounded replay buffer.
+pub struct EventBroadcaster {
+    buffer: Mutex<ReplayBuffer>,
+    sender: broadcast::Sender<Arc<StreamEvent>>,
+}
+
+impl EventBroadcaster {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(LIVE_CHANNEL_CAPACITY);
+        Self {
+            buffer: Mutex::new(ReplayBuffer {
+                next_offset: 1,
+                capacity: capacity.max(1),
+                events: VecDeque::new(),
+            }),
+            sender,
+        }
+    }
+
+    /// Publish `data` under `topic` and return its offset.
+    pub fn publish(&self, topic: impl Into<String>, data: serde_json::Value) -> u64 {
+        let mut buffer = self.buffer.lock().unwrap();
+        let event = Arc::new(StreamEvent {
+            offset: buffer.next_offset,
+            topic: topic.into(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            data,
+        });
+        buffer.next_offset += 1;
+        if buffer.events.len() == buffer.capacity {
+            buffer.events.pop_front();
+        }
+        buffer.events.push_back(event.clone());
+        // Sent under the lock so live delivery order matches offsets.
+        let _ = self.sender.send(event.clone());
+        event.offset
+    }
+
+    /// Buffered events with an offset greater than `offset`.
+    pub fn replay_since(&self, offset: u64) -> Vec<Arc<StreamEvent>> {
+        let buffer = self.buffer.lock().unwrap();
+        buffer
+            .events
+            .iter()
+            .filter(|event| event.offset > offset)
+            .cloned()
+            .collect()
+    }
+
+    /// Offset of the most recently published event (0 when none).
+    pub fn last_offset(&self) -> u64 {
+        self.buffer.lock().unwrap().next_offset - 1
+    }
+
+    /// Start receiving events. With `since`, buffered events after that
+    /// offset are delivered first; otherwise only events published from now
+    /// on.
+    pub fn subscribe(self: &Arc<Self>, since: Option<u64>, filter: TopicFilter) -> Subscription {
+        let buffer = self.buffer.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let last_offset = buffer.next_offset - 1;
+        let pending = match since {
+            Some(since) => buffer
+                .events
+                .iter()
+                .filter(|event| event.offset > since)
+                .cloned()
+                .collect(),
+            None => VecDeque::new(),
+        };
+        Subscription {
+            broadcaster: self.clone(),
+            receiver,
+            pending,
+            last_offset: since.map_or(last_offset, |since| since.min(last_offset)),
+            filter,
+        }
+    }
+}
+
+/// Topic filter parsed from a comma separated list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopicFilter {
+    topics: Vec<String>,
+}
+
+impl TopicFilter {
+    pub fn parse(topics: &str) -> Self {
+        Self {
+            topics: topics
+                .split(',')
+                .map(str::trim)
+                .filter(|topic| !topic.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    pub fn matches(&self, topic: &str) -> bool {
+        self.topics.is_empty()
+            || self.topics.iter().any(|wanted| {
+                topic == wanted
+                    || (topic.starts_with(wanted.as_str())
+                        && topic[wanted.len()..].starts_with('.'))
+            })
+    }
+}
+
+/// Events for one client, in offset order and without duplicates.
+pub struct Subscription {
+    broadcaster: Arc<EventBroadcaster>,
+    receiver: broadcast::Receiver<Arc<StreamEvent>>,
+    pending: VecDeque<Arc<StreamEvent>>,
+    last_offset: u64,
+    filter: TopicFilter,
+}
+
+impl Subscription {
+    /// Next matching event.
+    pub async fn next(&mut self) -> Option<Arc<StreamEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                // Live events already delivered through a replay.
+                if event.offset <= self.last_offset {
+                    continue;
+                }
+                self.last_offset = event.offset;
+                if self.filter.matches(&event.topic) {
+                    return Some(event);
+                }
+                continue;
+            }
+            match self.receiver.recv().await {
+                Ok(event) => self.pending.push_back(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::debug!(skipped, "event stream client lagged, replaying from buffer");
+                    self.pending
+                        .extend(self.broadcaster.replay_since(self.last_offset));
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StreamQuery {
+    token: Option<String>,
+    topics: Option<String>,
+    since: Option<u64>,
+}
+
+fn authorized(expected: &str, query: &StreamQuery, authorization: Option<&str>) -> bool {
+    let presented = authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(query.token.as_deref());
+    match presented {
+        Some(presented) => {
+            presented.len() == expected.len()
+                && presented
+                    .bytes()
+                    .zip(expected.bytes())
+                    .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+        }
+        None => false,
+    }
+}
+
+fn unauthorized() -> Box<dyn warp::Reply> {
+    Box::new(warp::reply::with_status(
+        "unauthorized",
+        StatusCode::UNAUTHORIZED,
+    ))
+}
+
+#[derive(Clone)]
+struct ServerState {
+    broadcaster: Arc<EventBroadcaster>,
+    token: Arc<String>,
+    /// Becomes `true` on shutdown so long-lived streams end and let the
+    /// server stop.
+    closed: watch::Receiver<bool>,
+}
+
+async fn next_until_closed(
+    subscription: &mut Subscription,
+    closed: &mut watch::Receiver<bool>,
+) -> Option<Arc<StreamEvent>> {
+    if *closed.borrow() {
+        return None;
+    }
+    tokio::select! {
+        event = subscription.next() => event,
+        _ = closed.changed() => None,
+    }
+}
+
+async fn forward_to_websocket(
+    socket: WebSocket,
+    mut subscription: Subscription,
+    mut closed: watch::Receiver<bool>,
+) {
+    let (mut sink, mut incoming) = socket.split();
+    loop {
+        tokio::select! {
+            event = next_until_closed(&mut subscription, &mut closed) => {
+                let Some(event) = event else { break };
+                let text = match serde_json::to_string(event.as_ref()) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        tracing::error!("failed to serialize stream event: {}", e);
+                        continue;
+                    }
+                };
+                if sink.send(Message::text(text)).await.is_err() {
+                    break;
+                }
+            }
+            message = incoming.next() => {
+                match message {
+                    Some(Ok(message)) if !message.is_close() => {}
+                    _ => break,
+                }
+            }
+        }
+    }
+    let _ = sink.close().await;
+}
+
+fn sse_event(event: &StreamEvent) -> warp::sse::Event {
+    let sse_event = || {
+        warp::sse::Event::default()
+            .id(event.offset.to_string())
+            .event(event.topic.clone())
+    };
+    match sse_event().json_data(event) {
+        Ok(sse_event) => sse_event,
+        Err(e) => {
+            tracing::error!("failed to serialize stream event: {}", e);
+            sse_event().data("null")
+        }
+    }
+}
+
+/// Serve `broadcaster` on `127.0.0.1:port` (0 picks an unused port) until
+/// `shutdown` resolves.
+pub fn serve_event_stream(
+    broadcaster: Arc<EventBroadcaster>,
+    port: u16,
+    token: String,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<SocketAddr, Box<dyn std::error::Error>> {
+    let (close_sender, closed) = watch::channel(false);
+    let state = ServerState {
+        broadcaster,
+        token: Arc::new(token),
+        closed,
+    };
+    let with_state = warp::any().map(move || state.clone());
+
+    let websocket = warp::path!("events" / "ws")
+        .and(warp::get())
+        .and(warp::ws())
+        .and(warp::query::<StreamQuery>())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(with_state.clone())
+        .map(
+            |ws: Ws,
+             query: StreamQuery,
+             authorization: Option<String>,
+             state: ServerState|
+             -> Box<dyn warp::Reply> {
+                if !authorized(&state.token, &query, authorization.as_deref()) {
+                    return unauthorized();
+                }
+                let filter = TopicFilter::parse(query.topics.as_deref().unwrap_or_default());
+                let subscription = state.broadcaster.subscribe(query.since, filter);
+                Box::new(ws.on_upgrade(move |socket| {
+                    forward_to_websocket(socket, subscription, state.closed)
+                }))
+            },
+        );
+
+    let sse = warp::path!("events" / "sse")
+        .and(warp::get())
+        .and(warp::query::<StreamQuery>())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<u64>("last-event-id"))
+        .and(with_state)
+        .map(
+            |query: StreamQuery,
+             authorization: Option<String>,
+             last_event_id: Option<u64>,
+             state: ServerState|
+             -> Box<dyn warp::Reply> {
+                if !authorized(&state.token, &query, authorization.as_deref()) {
+                    return unauthorized();
+                }
+                let filter = TopicFilter::parse(query.topics.as_deref().unwrap_or_default());
+                let subscription = state
+                    .broadcaster
+                    .subscribe(last_event_id.or(query.since), filter);
+                let events = futures_util::stream::unfold(
+                    (subscription, state.closed),
+                    |(mut subscription, mut closed)| async move {
+                        let event = next_until_closed(&mut subscription, &mut closed).await?;
+                        Some((
+                            Ok::<_, Infallible>(sse_event(&event)),
+                            (subscription, closed),
+                        ))
+                    },
+                );
+                Box::new(warp::sse::reply(warp::sse::keep_alive().stream(events)))
+            },
+        );
+
+    let (addr, server) = warp::serve(websocket.or(sse)).try_bind_with_graceful_shutdown(
+        ([127, 0, 0, 1], port),
+        async move {
+            shutdown.await;
+            let _ = close_sender.send(true);
+            tracing::info!("Shutting down event stream server");
+        },
+    )?;
+    tracing::info!("Event stream server addr: {}", addr);
+
+    tokio::task::spawn(server);
+
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hudsucker::tokio_tungstenite::{connect_async, tungstenite};
+    use serde_json::json;
+    use std::time::Duration;
+
+    const TOKEN: &str = "test-token";
+
+    fn start(
+        broadcaster: &Arc<EventBroadcaster>,
+    ) -> (SocketAddr, tokio::sync::oneshot::Sender<()>) {
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let addr = serve_event_stream(broadcaster.clone(), 0, TOKEN.to_string(), async move {
+            let _ = stopped.await;
+        })
+        .unwrap();
+        (addr, stop)
+    }
+
+    async fn next_event<S>(socket: &mut S) -> StreamEvent
+    where
+        S: futures_util::Stream<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        serde_json::from_str(message.to_text().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn subscription_replays_buffer_then_follows_live_events() {
+        let broadcaster = Arc::new(EventBroadcaster::new(2));
+        for n in 1..=3 {
+            broadcaster.publish(format!("emit.set.n{n}"), json!(n));
+        }
+        assert_eq!(broadcaster.last_offset(), 3);
+        // Offset 1 fell out of the buffer.
+        let offsets: Vec<u64> = broadcaster
+            .replay_since(0)
+            .iter()
+            .map(|e| e.offset)
+            .collect();
+        assert_eq!(offsets, vec![2, 3]);
+
+        let mut replaying = broadcaster.subscribe(Some(1), TopicFilter::parse("emit"));
+        let mut live = broadcaster.subscribe(None, TopicFilter::parse("api.response"));
+        broadcaster.publish("api.response", json!({"path": "/kcsapi/api_port/port"}));
+        broadcaster.publish("emitter", json!(null));
+        broadcaster.publish("emit.add.ship", json!(5));
+
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            seen.push(replaying.next().await.unwrap().offset);
+        }
+        assert_eq!(seen, vec![2, 3, 6]);
+        let event = live.next().await.unwrap();
+        assert_eq!((event.offset, event.topic.as_str()), (4, "api.response"));
+    }
+
+    #[test]
+    fn topic_filter_matches_dotted_prefixes() {
+        let filter = TopicFilter::parse(" emit.set , api.request,");
+        assert!(filter.matches("emit.set"));
+        assert!(filter.matches("emit.set.ships"));
+        assert!(filter.matches("api.request"));
+        assert!(!filter.matches("emit.settings"));
+        assert!(!filter.matches("api.response"));
+        assert!(TopicFilter::parse("").matches("anything"));
+    }
+
+    #[tokio::test]
+    async fn websocket_requires_token_and_streams_filtered_events() {
+        let broadcaster = Arc::new(EventBroadcaster::new(16));
+        broadcaster.publish("api.request", json!("skipped"));
+        broadcaster.publish("emit.set.ships", json!([1, 2]));
+        let (addr, _stop) = start(&broadcaster);
+
+        let denied = connect_async(format!("ws://{addr}/events/ws?token=wrong")).await;
+        match denied {
+            Err(tungstenite::Error::Http(response)) => {
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED.as_u16())
+            }
+            other => panic!("expected 401, got {other:?}"),
+        }
+
+        let (mut socket, _) = connect_async(format!(
+            "ws://{addr}/events/ws?token={TOKEN}&topics=emit&since=0"
+        ))
+        .await
+        .unwrap();
+        let replayed = next_event(&mut socket).await;
+        assert_eq!((replayed.offset, replayed.data), (2, json!([1, 2])));
+
+        broadcaster.publish("api.response", json!("skipped"));
+        broadcaster.publish("emit.add.ship", json!(7));
+        let live = next_event(&mut socket).await;
+        assert_eq!((live.offset, live.topic.as_str()), (4, "emit.add.ship"));
+    }
+
+    #[tokio::test]
+    async fn sse_resumes_from_last_event_id() {
+        let broadcaster = Arc::new(EventBroadcaster::new(16));
+        for n in 1..=3 {
+            broadcaster.publish("emit.set.n", json!(n));
+        }
+        let (addr, stop) = start(&broadcaster);
+        let client = reqwest::Client::new();
+        let url = format!("http://{addr}/events/sse");
+
+        let denied = client.get(&url).send().await.unwrap();
+        assert_eq!(denied.status(), StatusCode::UNAUTHORIZED.as_u16());
+
+        let mut response = client
+            .get(&url)
+            .bearer_auth(TOKEN)
+            .header("Last-Event-ID", "2")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        broadcaster.publish("emit.set.n", json!(4));
+
+        let mut body = String::new();
+        while !body.contains("id:4") {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), response.chunk())
+                .await
+                .unwrap()
+                .unwrap()
+                .expect("stream ended early");
+            body.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        assert!(!body.contains("id:2\n"));
+        assert!(body.contains("event:emit.set.n\n"));
+        assert!(body.contains("id:3\n"));
+        let data_line = body.lines().find(|line| line.starts_with("data:")).unwrap();
+        let event: StreamEvent = serde_json::from_str(&data_line["data:".len()..]).unwrap();
+        assert_eq!((event.offset, event.data), (3, json!(3)));
+
+        // Shutdown ends open streams.
+        stop.send(()).unwrap();
+        let rest = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Ok(Some(_)) = response.chunk().await {}
+        })
+        .await;
+        assert!(rest.is_ok());
+    }
+}
@@ -0,0 +1,158 @@
+//! Which game server (world) an API call went to.
+//!
+//! The proxy resolves the request host against
+//! `[app.connect_kc_server.server_list]` and tags every message sent to the
+//! parser and every capture file with the result, so data from accounts on
+//! different worlds stays separate.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Names of the worlds, indexed by server number - 1.
+const WORLD_NAMES: [&str; 20] = [
+    "横須賀鎮守府",
+    "新呉鎮守府",
+    "佐世保鎮守府",
+    "舞鶴鎮守府",
+    "大湊警備府",
+    "トラック泊地",
+    "リンガ泊地",
+    "ラバウル基地",
+    "ショートランド泊地",
+    "ブイン基地",
+    "タウイタウイ泊地",
+    "パラオ泊地",
+    "ブルネイ泊地",
+    "単冠湾泊地",
+    "幌筵泊地",
+    "宿毛湾泊地",
+    "鹿屋基地",
+    "岩川基地",
+    "佐伯湾泊地",
+    "柱島泊地",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameServer {
+    /// Server number from the server list; `None` for hosts not in it.
+    pub index: Option<i32>,
+    pub host: String,
+    /// World name, or the host when the server is unknown.
+    pub name: String,
+}
+
+impl GameServer {
+    pub fn unknown(host: &str) -> Self {
+        Self {
+            index: None,
+            host: host.to_string(),
+            name: host.to_string(),
+        }
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Log when this message came from a different server than `last`, the
+    /// server of the previous message, and remember it there. Parsers keep
+    /// `last` per channel; the server itself travels with each message.
+    pub fn track_switch(&self, last: &mut Option<GameServer>) {
+        if last.as_ref() != Some(self) {
+            tracing::info!(index = ?self.index, host = %self.host, name = %self.name, "game server changed");
+            *last = Some(self.clone());
+        }
+    }
+}
+
+/// Maps request hosts to [`GameServer`]s.
+#[derive(Debug, Clone, Default)]
+pub struct GameServerResolver {
+    by_host: HashMap<String, i32>,
+}
+
+impl GameServerResolver {
+    pub fn new(servers: &HashMap<i32, String>) -> Self {
+        Self {
+            by_host: servers
+                .iter()
+                .map(|(index, host)| (host.trim().to_ascii_lowercase(), *index))
+                .collect(),
+        }
+    }
+
+    pub fn from_configs(servers: &configs::ConfigsAppConnectKcServer) -> Self {
+        Self::new(&servers.get_all_servers())
+    }
+
+    /// `host` may include a port.
+    pub fn resolve(&self, host: &str) -> GameServer {
+        let host = host
+            .rsplit_once(':')
+            .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+            .map_or(host, |(host, _)| host)
+            .to_ascii_lowercase();
+        match self.by_host.get(&host) {
+            Some(index) => GameServer {
+                index: Some(*index),
+                name: usize::try_from(*index - 1)
+                    .ok()
+                    .and_then(|i| WORLD_NAMES.get(i))
+                    .map_or_else(|| host.clone(), |name| name.to_string()),
+                host,
+            },
+            None => GameServer::unknown(&host),
+        }
+    }
+
+    /// Server of a request URI, falling back to its `Host` header.
+    pub fn resolve_request(&self, uri: &http::Uri, headers: &http::HeaderMap) -> GameServer {
+        let host = uri
+            .authority()
+            .map(|authority| authority.as_str().to_string())
+            .or_else(|| {
+                headers
+                    .get(http::header::HOST)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+            })
+            .unwrap_or_default();
+        self.resolve(&host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_listed_hosts_and_keeps_unknown_ones() {
+        let resolver = GameServerResolver::new(&HashMap::from([
+            (1, "w01y.kancolle-server.com".to_string()),
+            (16, "w16s.kancolle-server.com".to_string()),
+        ]));
+
+        assert_eq!(
+            resolver.resolve("W16S.kancolle-server.com:443"),
+            GameServer {
+                index: Some(16),
+                host: "w16s.kancolle-server.com".to_string(),
+                name: "宿毛湾泊地".to_string(),
+            }
+        );
+        let uri: http::Uri = "https://w01y.kancolle-server.com/kcsapi/api_port/port"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            resolver
+                .resolve_request(&uri, &http::HeaderMap::new())
+                .index,
+            Some(1)
+        );
+
+        let unknown = resolver.resolve("203.0.113.1");
+        assert!(!unknown.is_known());
+        assert_eq!(unknown.name, "203.0.113.1");
+    }
+}
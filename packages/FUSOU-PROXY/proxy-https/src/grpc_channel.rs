@@ -1,6 +1,7 @@
 #![cfg(feature = "grpc")]
 
 use crate::channel_types::StatusInfo;
+use crate::game_server::GameServer;
 use once_cell::sync::OnceCell;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
@@ -51,22 +52,26 @@ impl GrpcPayload for StatusInfo {
                 path,
                 content_type,
                 content,
+                server,
             } => ProtoStatusInfo {
                 kind: Some(proto::status_info::Kind::Response(proto::Response {
                     path: path.clone(),
                     content_type: content_type.clone(),
                     content: content.clone(),
+                    server: Some(game_server_to_proto(server)),
                 })),
             },
             StatusInfo::REQUEST {
                 path,
                 content_type,
                 content,
+                server,
             } => ProtoStatusInfo {
                 kind: Some(proto::status_info::Kind::Request(proto::Request {
                     path: path.clone(),
                     content_type: content_type.clone(),
                     content: content.clone(),
+                    server: Some(game_server_to_proto(server)),
                 })),
             },
         }
@@ -86,17 +91,36 @@ impl GrpcPayload for StatusInfo {
                 path: value.path,
                 content_type: value.content_type,
                 content: value.content,
+                server: game_server_from_proto(value.server),
             }),
             Some(proto::status_info::Kind::Request(value)) => Ok(StatusInfo::REQUEST {
                 path: value.path,
                 content_type: value.content_type,
                 content: value.content,
+                server: game_server_from_proto(value.server),
             }),
             None => Err(Status::invalid_argument("missing StatusInfo.kind")),
         }
     }
 }
 
+fn game_server_to_proto(server: &GameServer) -> proto::GameServer {
+    proto::GameServer {
+        index: server.index.unwrap_or_default(),
+        host: server.host.clone(),
+        name: server.name.clone(),
+    }
+}
+
+fn game_server_from_proto(server: Option<proto::GameServer>) -> GameServer {
+    let server = server.unwrap_or_default();
+    GameServer {
+        index: Some(server.index).filter(|index| *index != 0),
+        host: server.host,
+        name: server.name,
+    }
+}
+
 fn endpoint_uri() -> &'static str {
     static ENDPOINT: OnceCell<String> = OnceCell::new();
     ENDPOINT.get_or_init(|| {
@@ -0,0 +1,987 @@
+//! HAR 1.2 (HTTP Archive) recording and conversion.
+//!
+//! [`HarRecorder`] is a proxy hook that writes sessions as `.har` files with
+//! headers, status codes, timing and decoded bodies, so they can be opened in
+//! browser devtools and shared. The converters translate between HAR and the
+//! `kcsapi/<time>Q@...`/`<time>S@...` capture layout written by the proxy
+//! (`allow_save_api_requests`/`allow_save_api_responses`), which the parser
+//! tests read.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::{Asia::Tokyo, Tz};
+use http::{request, HeaderMap};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::game_server::{GameServer, GameServerResolver};
+use crate::proxy_hook::{header_str, HookBody, HookFilter, ProxyHook, ProxyRequest, ProxyResponse};
+use crate::proxy_server_https::{decode_response_body, parse_content_encodings};
+
+pub const HAR_VERSION: &str = "1.2";
+const CREATOR_NAME: &str = "FUSOU";
+
+/// Origin used for URLs of entries converted from capture files that do
+/// not record the game server host.
+pub const IMPORTED_ORIGIN: &str = "https://kcsapi.invalid";
+const IMPORTED_HOST: &str = "kcsapi.invalid";
+
+/// Keeps requests whose response never arrives from piling up.
+const MAX_PENDING_PER_URL: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    #[serde(default)]
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    /// ISO 8601 time the request started.
+    pub started_date_time: String,
+    /// Total time in milliseconds.
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    #[serde(default)]
+    pub cache: serde_json::Map<String, serde_json::Value>,
+    pub timings: HarTimings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+    #[serde(default)]
+    pub headers: Vec<HarNameValue>,
+    #[serde(default)]
+    pub query_string: Vec<HarNameValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+    #[serde(default)]
+    pub headers: Vec<HarNameValue>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarNameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    pub mime_type: String,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    /// Length of the decoded body.
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// `"base64"` when `text` holds a binary body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+impl HarContent {
+    fn from_body(mime_type: String, body: &[u8]) -> Self {
+        let (text, encoding) = match std::str::from_utf8(body) {
+            Ok(text) => (text.to_string(), None),
+            Err(_) => (
+                base64::engine::general_purpose::STANDARD.encode(body),
+                Some("base64".to_string()),
+            ),
+        };
+        Self {
+            size: body.len() as i64,
+            mime_type,
+            text: Some(text),
+            encoding,
+        }
+    }
+
+    /// Decoded body as UTF-8 text, if present.
+    pub fn decoded_text(&self) -> Option<String> {
+        let text = self.text.as_ref()?;
+        match self.encoding.as_deref() {
+            Some("base64") => base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .ok()
+                .and_then(|body| String::from_utf8(body).ok()),
+            _ => Some(text.clone()),
+        }
+    }
+}
+
+/// The proxy only measures the total time, which is reported as `wait`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarTimings {
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+impl HarTimings {
+    fn total(time: f64) -> Self {
+        Self {
+            send: 0.0,
+            wait: time,
+            receive: 0.0,
+        }
+    }
+}
+
+impl Har {
+    pub fn new(entries: Vec<HarEntry>) -> Self {
+        Self {
+            log: HarLog {
+                version: HAR_VERSION.to_string(),
+                creator: HarCreator {
+                    name: CREATOR_NAME.to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                entries,
+            },
+        }
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        serde_json::from_reader(io::BufReader::new(file)).map_err(io::Error::from)
+    }
+
+    /// Write via a temporary file so readers never see a partial archive.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let tmp = path.with_extension("har.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+fn header_list(headers: &HeaderMap) -> Vec<HarNameValue> {
+    headers
+        .iter()
+        .map(|(name, value)| HarNameValue {
+            name: name.to_string(),
+            value: String::from_utf8_lossy(value.as_bytes()).to_string(),
+        })
+        .collect()
+}
+
+fn query_list(query: Option<&str>) -> Vec<HarNameValue> {
+    query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            HarNameValue {
+                name: name.to_string(),
+                value: value.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Requests inside the TLS tunnel may carry only a path; rebuild the
+/// absolute URL from the `Host` header in that case.
+fn absolute_url(parts: &request::Parts) -> String {
+    if parts.uri.authority().is_some() {
+        return parts.uri.to_string();
+    }
+    let host = parts
+        .headers
+        .get(http::header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("localhost");
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    format!("https://{host}{path}")
+}
+
+fn format_started(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+struct PendingRequest {
+    started: DateTime<Utc>,
+    instant: Instant,
+    request: HarRequest,
+}
+
+/// An entry whose response body is still content-encoded; the writer
+/// decodes it off the proxy's response path.
+struct RecordedEntry {
+    entry: HarEntry,
+    body: HookBody,
+    content_encodings: Vec<String>,
+}
+
+impl RecordedEntry {
+    fn into_entry(self) -> HarEntry {
+        let mut entry = self.entry;
+        let body = match self.body.to_bytes() {
+            Ok(body) => decode_response_body(body.to_vec(), &self.content_encodings, true),
+            Err(e) => {
+                tracing::warn!(url = %entry.request.url, "HAR: failed to read response body: {}", e);
+                Vec::new()
+            }
+        };
+        let mime_type = std::mem::take(&mut entry.response.content.mime_type);
+        entry.response.content = HarContent::from_body(mime_type, &body);
+        entry
+    }
+}
+
+/// Proxy hook writing traffic to HAR files in `dir`.
+///
+/// A new file `session_<JST time>.har` is started with the first entry and
+/// after every `max_entries_per_file` entries. Entries are appended to the
+/// open file, so a session interrupted by a crash still leaves a valid
+/// archive.
+pub struct HarRecorder {
+    include_resources: bool,
+    pending: Mutex<HashMap<String, VecDeque<PendingRequest>>>,
+    tx: mpsc::UnboundedSender<RecordedEntry>,
+}
+
+impl HarRecorder {
+    /// Must be called inside a Tokio runtime; the writer runs on a blocking task.
+    pub fn new(dir: PathBuf, include_resources: bool, max_entries_per_file: usize) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::task::spawn_blocking(move || write_sessions(rx, dir, max_entries_per_file.max(1)));
+        Self {
+            include_resources,
+            pending: Mutex::new(HashMap::new()),
+            tx,
+        }
+    }
+
+    pub fn from_configs(har: &configs::ConfigsProxyHar, save_path: &Path) -> Self {
+        Self::new(
+            har.get_output_dir()
+                .unwrap_or_else(|| save_path.join("har")),
+            har.get_include_resources(),
+            har.get_max_entries_per_file(),
+        )
+    }
+}
+
+impl ProxyHook for HarRecorder {
+    fn name(&self) -> &str {
+        "har"
+    }
+
+    fn filter(&self) -> HookFilter {
+        if self.include_resources {
+            HookFilter::any()
+        } else {
+            HookFilter::any().path_prefix("/kcsapi")
+        }
+    }
+
+    fn on_request(&self, request: &ProxyRequest<'_>) {
+        let url = absolute_url(request.parts);
+        let key = request.parts.uri.to_string();
+        let post_data = (!request.body.is_empty()).then(|| HarPostData {
+            mime_type: request.content_type.to_string(),
+            text: String::from_utf8_lossy(request.body).to_string(),
+        });
+        let pending = PendingRequest {
+            started: Utc::now(),
+            instant: Instant::now(),
+            request: HarRequest {
+                method: request.parts.method.to_string(),
+                url,
+                http_version: format!("{:?}", request.parts.version),
+                cookies: Vec::new(),
+                headers: header_list(&request.parts.headers),
+                query_string: query_list(request.parts.uri.query()),
+                post_data,
+                headers_size: -1,
+                body_size: request.body.len() as i64,
+            },
+        };
+        let mut pending_by_url = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let queue = pending_by_url.entry(key).or_default();
+        if queue.len() >= MAX_PENDING_PER_URL {
+            queue.pop_front();
+        }
+        queue.push_back(pending);
+    }
+
+    fn on_response(&self, response: &ProxyResponse<'_>) {
+        let key = response.request_uri.to_string();
+        let pending = {
+            let mut pending_by_url = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            let pending = pending_by_url.get_mut(&key).and_then(VecDeque::pop_front);
+            if pending_by_url.get(&key).is_some_and(VecDeque::is_empty) {
+                pending_by_url.remove(&key);
+            }
+            pending
+        };
+        let Some(pending) = pending else {
+            tracing::debug!(uri = %key, "HAR: response without a recorded request");
+            return;
+        };
+
+        let time = pending.instant.elapsed().as_secs_f64() * 1000.0;
+        let entry = HarEntry {
+            started_date_time: format_started(pending.started),
+            time,
+            request: pending.request,
+            response: HarResponse {
+                status: response.parts.status.as_u16(),
+                status_text: response
+                    .parts
+                    .status
+                    .canonical_reason()
+                    .unwrap_or_default()
+                    .to_string(),
+                http_version: format!("{:?}", response.parts.version),
+                cookies: Vec::new(),
+                headers: header_list(&response.parts.headers),
+                // Filled in by the writer once the body is decoded.
+                content: HarContent {
+                    size: 0,
+                    mime_type: response.content_type.to_string(),
+                    text: None,
+                    encoding: None,
+                },
+                redirect_url: response
+                    .parts
+                    .headers
+                    .get(http::header::LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string(),
+                headers_size: -1,
+                body_size: response.body.len() as i64,
+            },
+            cache: serde_json::Map::new(),
+            timings: HarTimings::total(time),
+        };
+        let _ = self.tx.send(RecordedEntry {
+            entry,
+            body: response.body.clone(),
+            content_encodings: parse_content_encodings(&header_str(
+                &response.parts.headers,
+                http::header::CONTENT_ENCODING,
+            )),
+        });
+    }
+}
+
+fn session_file_name() -> String {
+    let jst = Tokyo.from_utc_datetime(&Utc::now().naive_utc());
+    format!("session_{}.har", jst.format("%Y%m%d_%H%M%S%3f"))
+}
+
+/// End of a serialized [`Har`] after its last entry.
+const ENTRIES_END: &[u8] = b"]}}";
+
+/// A session file being written. After every append the file holds a
+/// complete archive; the next append overwrites its closing [`ENTRIES_END`].
+struct HarFile {
+    file: std::fs::File,
+    entries: usize,
+}
+
+impl HarFile {
+    fn create(path: &Path) -> io::Result<Self> {
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(&serde_json::to_vec(&Har::new(Vec::new()))?)?;
+        Ok(Self { file, entries: 0 })
+    }
+
+    fn append(&mut self, entries: &[HarEntry]) -> io::Result<()> {
+        let mut buf = Vec::new();
+        for entry in entries {
+            if self.entries > 0 {
+                buf.push(b',');
+            }
+            serde_json::to_writer(&mut buf, entry)?;
+            self.entries += 1;
+        }
+        buf.extend_from_slice(ENTRIES_END);
+        self.file.seek(SeekFrom::End(-(ENTRIES_END.len() as i64)))?;
+        self.file.write_all(&buf)
+    }
+}
+
+fn write_sessions(
+    mut rx: mpsc::UnboundedReceiver<RecordedEntry>,
+    dir: PathBuf,
+    max_entries_per_file: usize,
+) {
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        tracing::error!("failed to create HAR directory {}: {}", dir.display(), e);
+        return;
+    }
+    let mut session: Option<(PathBuf, HarFile)> = None;
+
+    while let Some(recorded) = rx.blocking_recv() {
+        let (path, mut file) = match session.take() {
+            Some((path, file)) if file.entries < max_entries_per_file => (path, file),
+            _ => {
+                let path = dir.join(session_file_name());
+                match HarFile::create(&path) {
+                    Ok(file) => {
+                        tracing::info!("recording HAR session to {}", path.display());
+                        (path, file)
+                    }
+                    Err(e) => {
+                        tracing::error!("failed to create HAR file {}: {}", path.display(), e);
+                        continue;
+                    }
+                }
+            }
+        };
+        let mut entries = vec![recorded.into_entry()];
+        // Write once per burst instead of once per entry.
+        while file.entries + entries.len() < max_entries_per_file {
+            match rx.try_recv() {
+                Ok(recorded) => entries.push(recorded.into_entry()),
+                Err(_) => break,
+            }
+        }
+
+        if let Err(e) = file.append(&entries) {
+            tracing::error!("failed to write HAR file {}: {}", path.display(), e);
+        }
+        session = Some((path, file));
+    }
+}
+
+/// Name of a capture file relative to the save directory, e.g.
+/// `kcsapi/20250101_120000123S@api_port@port`.
+pub(crate) fn capture_file_name(time: &DateTime<Tz>, kind: CaptureKind, path: &str) -> String {
+    format!(
+        "kcsapi/{}{}{}",
+        time.format("%Y%m%d_%H%M%S%3f"),
+        kind.marker(),
+        path.replace("/kcsapi", "").replace('/', "@")
+    )
+}
+
+/// Header written in front of every capture file.
+pub(crate) fn capture_metadata(time: &DateTime<Tz>, env_id: &str, server: &GameServer) -> String {
+    format!(
+        "---\nProxyApp: {}\nTimestamp: {}\nEnvId: {}\nServerIndex: {}\nServerHost: {}\nServerName: {}\n---\n",
+        "FUSOU",
+        time.timestamp(),
+        env_id,
+        server.index.map(|index| index.to_string()).unwrap_or_default(),
+        server.host,
+        server.name
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CaptureKind {
+    Request,
+    Response,
+}
+
+impl CaptureKind {
+    fn marker(self) -> char {
+        match self {
+            CaptureKind::Request => 'Q',
+            CaptureKind::Response => 'S',
+        }
+    }
+}
+
+/// One API call in the kcsapi capture layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KcsapiCapture {
+    /// API path, e.g. `/kcsapi/api_port/port`.
+    pub path: String,
+    pub requested_at: DateTime<Tz>,
+    pub responded_at: DateTime<Tz>,
+    /// Form-encoded request body.
+    pub request: Option<String>,
+    /// Decoded response body, including the `svdata=` prefix.
+    pub response: Option<String>,
+    pub env_id: String,
+    /// Default when the capture does not record the server.
+    pub server: GameServer,
+}
+
+/// Extract the API calls of a HAR archive. Entries outside `/kcsapi` and
+/// entries with unreadable timestamps are skipped.
+pub fn har_to_captures(
+    har: &Har,
+    env_id: &str,
+    servers: &GameServerResolver,
+) -> Vec<KcsapiCapture> {
+    har.log
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let uri = entry.request.url.parse::<http::Uri>().ok()?;
+            let path = uri.path().to_string();
+            let server = match uri.host() {
+                None | Some(IMPORTED_HOST) => GameServer::default(),
+                Some(host) => servers.resolve(host),
+            };
+            if !path.starts_with("/kcsapi/") {
+                return None;
+            }
+            let started = DateTime::parse_from_rfc3339(&entry.started_date_time).ok()?;
+            let requested_at = started.with_timezone(&Tokyo);
+            let responded_at = requested_at
+                + chrono::Duration::microseconds((entry.time.max(0.0) * 1000.0) as i64);
+            Some(KcsapiCapture {
+                path,
+                requested_at,
+                responded_at,
+                request: entry
+                    .request
+                    .post_data
+                    .as_ref()
+                    .map(|post_data| post_data.text.clone()),
+                response: entry.response.content.decoded_text(),
+                env_id: env_id.to_string(),
+                server,
+            })
+        })
+        .collect()
+}
+
+/// Build a HAR archive from captures. Headers are not part of the capture
+/// layout, so only `Content-Type` is filled in.
+pub fn captures_to_har(captures: &[KcsapiCapture]) -> Har {
+    let entries = captures
+        .iter()
+        .map(|capture| {
+            let time = (capture.responded_at - capture.requested_at)
+                .num_microseconds()
+                .unwrap_or_default()
+                .max(0) as f64
+                / 1000.0;
+            let request_body = capture.request.clone().unwrap_or_default();
+            let response_body = capture.response.clone().unwrap_or_default();
+            HarEntry {
+                started_date_time: format_started(capture.requested_at.with_timezone(&Utc)),
+                time,
+                request: HarRequest {
+                    method: "POST".to_string(),
+                    url: if capture.server.host.is_empty() {
+                        format!("{IMPORTED_ORIGIN}{}", capture.path)
+                    } else {
+                        format!("https://{}{}", capture.server.host, capture.path)
+                    },
+                    http_version: "HTTP/1.1".to_string(),
+                    cookies: Vec::new(),
+                    headers: vec![HarNameValue {
+                        name: "content-type".to_string(),
+                        value: "application/x-www-form-urlencoded".to_string(),
+                    }],
+                    query_string: Vec::new(),
+                    post_data: capture.request.as_ref().map(|text| HarPostData {
+                        mime_type: "application/x-www-form-urlencoded".to_string(),
+                        text: text.clone(),
+                    }),
+                    headers_size: -1,
+                    body_size: request_body.len() as i64,
+                },
+                response: HarResponse {
+                    status: 200,
+                    status_text: "OK".to_string(),
+                    http_version: "HTTP/1.1".to_string(),
+                    cookies: Vec::new(),
+                    headers: vec![HarNameValue {
+                        name: "content-type".to_string(),
+                        value: "text/plain".to_string(),
+                    }],
+                    content: HarContent::from_body(
+                        "text/plain".to_string(),
+                        response_body.as_bytes(),
+                    ),
+                    redirect_url: String::new(),
+                    headers_size: -1,
+                    body_size: response_body.len() as i64,
+                },
+                cache: serde_json::Map::new(),
+                timings: HarTimings::total(time),
+            }
+        })
+        .collect();
+    Har::new(entries)
+}
+
+/// Write captures below `dir` (creating `dir/kcsapi`). Returns the number of
+/// files written.
+pub fn write_captures(dir: &Path, captures: &[KcsapiCapture]) -> io::Result<usize> {
+    std::fs::create_dir_all(dir.join("kcsapi"))?;
+    let files = capture_files(captures);
+    for (name, content) in &files {
+        std::fs::write(dir.join(name), content)?;
+    }
+    Ok(files.len())
+}
+
+/// Relative file names and contents of the capture files for `captures`.
+pub(crate) fn capture_files(captures: &[KcsapiCapture]) -> Vec<(String, Vec<u8>)> {
+    let mut files = Vec::new();
+    for capture in captures {
+        let parts = [
+            (
+                CaptureKind::Request,
+                &capture.requested_at,
+                &capture.request,
+            ),
+            (
+                CaptureKind::Response,
+                &capture.responded_at,
+                &capture.response,
+            ),
+        ];
+        for (kind, time, body) in parts {
+            let Some(body) = body else { continue };
+            let content = [
+                capture_metadata(time, &capture.env_id, &capture.server).as_bytes(),
+                body.as_bytes(),
+            ]
+            .concat();
+            files.push((capture_file_name(time, kind, &capture.path), content));
+        }
+    }
+    files
+}
+
+struct CaptureFile {
+    kind: CaptureKind,
+    path: String,
+    time: DateTime<Tz>,
+    env_id: String,
+    server: GameServer,
+    body: String,
+}
+
+fn parse_capture_file(file_name: &str, content: &str) -> Option<CaptureFile> {
+    // <%Y%m%d_%H%M%S%3f><Q|S>@api_xxx@yyy
+    let stamp = file_name.get(..18)?;
+    let kind = match file_name.get(18..19)? {
+        "Q" => CaptureKind::Request,
+        "S" => CaptureKind::Response,
+        _ => return None,
+    };
+    let path = format!("/kcsapi{}", file_name.get(19..)?.replace('@', "/"));
+    let naive = chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%d_%H%M%S%3f").ok()?;
+    let time = Tokyo.from_local_datetime(&naive).single()?;
+
+    let (metadata, body) = content
+        .strip_prefix("---\n")
+        .and_then(|rest| rest.split_once("\n---\n"))
+        .unwrap_or(("", content));
+    let field = |name: &str| {
+        metadata
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let env_id = field("EnvId");
+    let server = GameServer {
+        index: field("ServerIndex").parse().ok(),
+        host: field("ServerHost"),
+        name: field("ServerName"),
+    };
+    Some(CaptureFile {
+        kind,
+        path,
+        time,
+        env_id,
+        server,
+        body: body.to_string(),
+    })
+}
+
+/// Read the capture files in `dir` (or `dir/kcsapi` when present) and pair
+/// each response with the latest unanswered request for the same path.
+/// Files that do not follow the capture layout are ignored.
+pub fn read_captures(dir: &Path) -> io::Result<Vec<KcsapiCapture>> {
+    let kcsapi_dir = dir.join("kcsapi");
+    let dir = if kcsapi_dir.is_dir() {
+        kcsapi_dir
+    } else {
+        dir.to_path_buf()
+    };
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let content = String::from_utf8_lossy(&std::fs::read(entry.path())?).to_string();
+        if let Some(file) = parse_capture_file(&file_name, &content) {
+            files.push((file_name, file));
+        }
+    }
+    // Timestamps come first in the name, and `Q` sorts before `S`.
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut captures: Vec<KcsapiCapture> = Vec::new();
+    let mut open_requests: HashMap<String, usize> = HashMap::new();
+    for (_, file) in files {
+        match file.kind {
+            CaptureKind::Request => {
+                open_requests.insert(file.path.clone(), captures.len());
+                captures.push(KcsapiCapture {
+                    path: file.path,
+                    requested_at: file.time,
+                    responded_at: file.time,
+                    request: Some(file.body),
+                    response: None,
+                    env_id: file.env_id,
+                    server: file.server,
+                });
+            }
+            CaptureKind::Response => match open_requests.remove(&file.path) {
+                Some(index) => {
+                    captures[index].responded_at = file.time;
+                    captures[index].response = Some(file.body);
+                }
+                None => captures.push(KcsapiCapture {
+                    path: file.path,
+                    requested_at: file.time,
+                    responded_at: file.time,
+                    request: None,
+                    response: Some(file.body),
+                    env_id: file.env_id,
+                    server: file.server,
+                }),
+            },
+        }
+    }
+    Ok(captures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hudsucker::hyper::body::Bytes;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("fusou-har-test-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn captures_round_trip_through_har() {
+        let requested_at = Tokyo.with_ymd_and_hms(2025, 6, 27, 12, 0, 0).unwrap();
+        let servers = GameServerResolver::new(&HashMap::from([(
+            16,
+            "w16s.kancolle-server.com".to_string(),
+        )]));
+        let mut captures = vec![KcsapiCapture {
+            path: "/kcsapi/api_port/port".to_string(),
+            requested_at,
+            responded_at: requested_at + chrono::Duration::milliseconds(250),
+            request: Some("api_verno=1&api_token=xxx".to_string()),
+            response: Some("svdata={\"api_result\":1}".to_string()),
+            env_id: "env".to_string(),
+            server: servers.resolve("w16s.kancolle-server.com"),
+        }];
+        let mut unknown_server = captures[0].clone();
+        unknown_server.path = "/kcsapi/api_get_member/material".to_string();
+        unknown_server.requested_at += chrono::Duration::seconds(1);
+        unknown_server.responded_at += chrono::Duration::seconds(1);
+        unknown_server.server = GameServer::default();
+        captures.push(unknown_server);
+
+        let har = captures_to_har(&captures);
+        let json = serde_json::to_string(&har).unwrap();
+        assert!(json.contains("\"startedDateTime\":\"2025-06-27T03:00:00.000Z\""));
+        assert!(json.contains("\"redirectURL\":\"\""));
+        let har: Har = serde_json::from_str(&json).unwrap();
+        assert_eq!(har.log.entries[0].time, 250.0);
+        assert_eq!(har_to_captures(&har, "env", &servers), captures);
+
+        let dir = temp_dir();
+        assert_eq!(write_captures(&dir, &captures).unwrap(), 4);
+        let mut names: Vec<String> = std::fs::read_dir(dir.join("kcsapi"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "20250627_120000000Q@api_port@port",
+                "20250627_120000250S@api_port@port",
+                "20250627_120001000Q@api_get_member@material",
+                "20250627_120001250S@api_get_member@material",
+            ]
+        );
+        assert_eq!(read_captures(&dir).unwrap(), captures);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn recorder_writes_paired_entries() {
+        let dir = temp_dir();
+        let recorder = HarRecorder::new(dir.clone(), false, 10);
+        assert!(!recorder
+            .filter()
+            .matches("/kcs2/img/title.png", "image/png"));
+
+        let (request_parts, _) = http::Request::post("https://w01.test/kcsapi/api_port/port")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(())
+            .unwrap()
+            .into_parts();
+        recorder.on_request(&ProxyRequest {
+            parts: &request_parts,
+            path: "/kcsapi/api_port/port",
+            content_type: "application/x-www-form-urlencoded",
+            body: &Bytes::from_static(b"api_verno=1"),
+        });
+        let (response_parts, _) = http::Response::builder()
+            .status(200)
+            .header("content-type", "text/plain")
+            .body(())
+            .unwrap()
+            .into_parts();
+        recorder.on_response(&ProxyResponse {
+            request_uri: &request_parts.uri,
+            parts: &response_parts,
+            path: "/kcsapi/api_port/port",
+            content_type: "text/plain",
+            body: &HookBody::from(Bytes::from_static(b"svdata={}")),
+        });
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"svdata={\"api_result\":1}").unwrap();
+        let (gzip_parts, _) = http::Response::builder()
+            .status(200)
+            .header("content-type", "text/plain")
+            .header("content-encoding", "gzip")
+            .body(())
+            .unwrap()
+            .into_parts();
+        recorder.on_request(&ProxyRequest {
+            parts: &request_parts,
+            path: "/kcsapi/api_port/port",
+            content_type: "application/x-www-form-urlencoded",
+            body: &Bytes::from_static(b"api_verno=2"),
+        });
+        recorder.on_response(&ProxyResponse {
+            request_uri: &request_parts.uri,
+            parts: &gzip_parts,
+            path: "/kcsapi/api_port/port",
+            content_type: "text/plain",
+            body: &HookBody::from(Bytes::from(encoder.finish().unwrap())),
+        });
+
+        let har = loop {
+            let file = std::fs::read_dir(&dir)
+                .ok()
+                .and_then(|mut entries| entries.next())
+                .and_then(|entry| entry.ok())
+                .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "har"));
+            if let Some(har) = file
+                .and_then(|entry| Har::read(&entry.path()).ok())
+                .filter(|har| har.log.entries.len() == 2)
+            {
+                break har;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        };
+        let entry = &har.log.entries[0];
+        assert_eq!(entry.request.url, "https://w01.test/kcsapi/api_port/port");
+        assert_eq!(
+            entry.request.post_data.as_ref().unwrap().text,
+            "api_verno=1"
+        );
+        assert_eq!(entry.response.status, 200);
+        assert_eq!(entry.response.content.text.as_deref(), Some("svdata={}"));
+        let content = &har.log.entries[1].response.content;
+        assert_eq!(content.text.as_deref(), Some("svdata={\"api_result\":1}"));
+        assert_eq!(content.size, 23);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn session_file_is_a_valid_archive_after_every_append() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.har");
+        let entries = captures_to_har(&[KcsapiCapture {
+            path: "/kcsapi/api_port/port".to_string(),
+            requested_at: Tokyo.with_ymd_and_hms(2025, 6, 27, 12, 0, 0).unwrap(),
+            responded_at: Tokyo.with_ymd_and_hms(2025, 6, 27, 12, 0, 1).unwrap(),
+            request: Some("api_verno=1".to_string()),
+            response: Some("svdata={}".to_string()),
+            env_id: "env".to_string(),
+            server: GameServer::default(),
+        }])
+        .log
+        .entries;
+
+        let mut file = HarFile::create(&path).unwrap();
+        assert_eq!(Har::read(&path).unwrap(), Har::new(Vec::new()));
+        file.append(&entries).unwrap();
+        assert_eq!(Har::read(&path).unwrap(), Har::new(entries.clone()));
+        file.append(&[entries[0].clone(), entries[0].clone()])
+            .unwrap();
+        assert_eq!(
+            Har::read(&path).unwrap(),
+            Har::new(vec![entries[0].clone(); 3])
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
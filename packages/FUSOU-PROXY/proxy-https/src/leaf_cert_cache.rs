@@ -0,0 +1,206 @@
+//! Cache of per-host leaf certificates signed by the proxy CA.
+//!
+//! Generating a leaf certificate costs a key signature for every new host, so
+//! [`CachingAuthority`] keeps the most recently used ones and counts hits,
+//! misses and evictions. The proxy registers its cache globally so the
+//! numbers can be read with [`leaf_cert_cache_stats`] (e.g. by the daemon's
+//! status endpoint).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use http::uri::Authority;
+use hudsucker::certificate_authority::CertificateAuthority;
+use hudsucker::rustls::ServerConfig;
+use serde::Serialize;
+
+/// Leaf certificates are valid for a year; regenerate well before that.
+const LEAF_CERT_MAX_AGE: Duration = Duration::from_secs(180 * 24 * 60 * 60);
+
+static ACTIVE_CACHE: OnceLock<Mutex<Option<Arc<LeafCertCache>>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LeafCacheStats {
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+struct CacheEntry {
+    config: Arc<ServerConfig>,
+    created: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<Authority, CacheEntry>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+/// Least-recently-used map from host to its TLS server config.
+pub struct LeafCertCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+impl LeafCertCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn stats(&self) -> LeafCacheStats {
+        let state = self.state.lock().unwrap();
+        LeafCacheStats {
+            capacity: self.capacity,
+            entries: state.entries.len(),
+            hits: state.hits,
+            misses: state.misses,
+            evictions: state.evictions,
+        }
+    }
+
+    fn get(&self, authority: &Authority) -> Option<Arc<ServerConfig>> {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        let found = match state.entries.get_mut(authority) {
+            Some(entry) if entry.created.elapsed() < LEAF_CERT_MAX_AGE => {
+                entry.last_used = tick;
+                Some(entry.config.clone())
+            }
+            Some(_) => {
+                state.entries.remove(authority);
+                None
+            }
+            None => None,
+        };
+        match found {
+            Some(_) => state.hits += 1,
+            None => state.misses += 1,
+        }
+        found
+    }
+
+    fn insert(&self, authority: Authority, config: Arc<ServerConfig>) {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        if !state.entries.contains_key(&authority) && state.entries.len() >= self.capacity {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(authority, _)| authority.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+                state.evictions += 1;
+            }
+        }
+        state.entries.insert(
+            authority,
+            CacheEntry {
+                config,
+                created: Instant::now(),
+                last_used: tick,
+            },
+        );
+    }
+}
+
+/// Wraps a [`CertificateAuthority`] with a [`LeafCertCache`]. The wrapped
+/// authority should not cache on its own.
+pub struct CachingAuthority<A> {
+    inner: A,
+    cache: Arc<LeafCertCache>,
+}
+
+impl<A: CertificateAuthority> CachingAuthority<A> {
+    pub fn new(inner: A, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: Arc::new(LeafCertCache::new(capacity)),
+        }
+    }
+
+    pub fn cache(&self) -> Arc<LeafCertCache> {
+        self.cache.clone()
+    }
+
+    /// Make this cache the one reported by [`leaf_cert_cache_stats`].
+    pub fn register_global(self) -> Self {
+        *ACTIVE_CACHE
+            .get_or_init(|| Mutex::new(None))
+            .lock()
+            .unwrap() = Some(self.cache.clone());
+        self
+    }
+}
+
+impl<A: CertificateAuthority> CertificateAuthority for CachingAuthority<A> {
+    async fn gen_server_config(&self, authority: &Authority) -> Arc<ServerConfig> {
+        if let Some(config) = self.cache.get(authority) {
+            return config;
+        }
+        let config = self.inner.gen_server_config(authority).await;
+        self.cache.insert(authority.clone(), config.clone());
+        config
+    }
+}
+
+/// Statistics of the cache used by the running proxy, if any.
+pub fn leaf_cert_cache_stats() -> Option<LeafCacheStats> {
+    ACTIVE_CACHE
+        .get()
+        .and_then(|active| active.lock().unwrap().as_ref().map(|cache| cache.stats()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hudsucker::certificate_authority::RcgenAuthority;
+    use hudsucker::rcgen::KeyPair;
+    use hudsucker::rustls::crypto::aws_lc_rs;
+
+    #[tokio::test]
+    async fn caches_recent_hosts_and_counts() {
+        let key_pair = KeyPair::generate().unwrap();
+        let ca_cert = crate::proxy_server_https::ca_params()
+            .self_signed(&key_pair)
+            .unwrap();
+        let authority = CachingAuthority::new(
+            RcgenAuthority::new(key_pair, ca_cert, 0, aws_lc_rs::default_provider()),
+            2,
+        );
+        let host = |name: &str| name.parse::<Authority>().unwrap();
+
+        let first = authority.gen_server_config(&host("w01.test:443")).await;
+        let again = authority.gen_server_config(&host("w01.test:443")).await;
+        assert!(Arc::ptr_eq(&first, &again));
+        authority.gen_server_config(&host("w02.test:443")).await;
+        authority.gen_server_config(&host("w01.test:443")).await;
+        // w02 is the least recently used one.
+        authority.gen_server_config(&host("w03.test:443")).await;
+        authority.gen_server_config(&host("w01.test:443")).await;
+
+        assert_eq!(
+            authority.cache().stats(),
+            LeafCacheStats {
+                capacity: 2,
+                entries: 2,
+                hits: 3,
+                misses: 3,
+                evictions: 1,
+            }
+        );
+    }
+}
@@ -6,6 +6,18 @@ pub mod mpsc_channel;
 #[cfg(feature = "grpc")]
 pub mod grpc_channel;
 
+pub mod anonymize;
+pub mod body_tee;
+pub mod ca_manager;
+pub mod client_allowlist;
 pub mod edit_pac;
+pub mod event_stream;
+pub mod game_server;
+pub mod har;
+pub mod leaf_cert_cache;
+pub mod pac_script;
 pub mod pac_server;
+pub mod proxy_hook;
 pub mod proxy_server_https;
+pub mod telemetry;
+pub mod upstream_proxy;
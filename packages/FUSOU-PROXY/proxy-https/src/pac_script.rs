@@ -0,0 +1,283 @@
+//! PAC script generated from configuration.
+//!
+//! Instead of patching the bundled `proxy_auto.pac` template (see
+//! [`crate::edit_pac`]), [`PacRules`] lists the host patterns routed to FUSOU
+//! (the game servers of `[app.connect_kc_server]` plus
+//! `[proxy.pac].extra_proxy_hosts`) and the fallbacks tried when FUSOU is
+//! unreachable; every other host is connected `DIRECT`. A [`PacHandle`] holds
+//! the current rules for the PAC server, which renders the script on every
+//! request. The app replaces the rules through [`PacHandle::update`] each time
+//! the proxy starts, so a restarted proxy is picked up without restarting the
+//! PAC server. The configuration is read once at startup; edits to the server
+//! list in `configs.toml` apply after restarting the app.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+const FALLBACK_KINDS: [&str; 6] = ["DIRECT", "PROXY", "HTTP", "HTTPS", "SOCKS", "SOCKS5"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PacRules {
+    /// `host:port` of the FUSOU proxy; `None` routes everything `DIRECT`.
+    pub proxy_addr: Option<String>,
+    /// Game servers routed to FUSOU.
+    pub servers: Vec<String>,
+    /// Other `shExpMatch` patterns routed to FUSOU.
+    pub extra_hosts: Vec<String>,
+    /// PAC entries appended after FUSOU, e.g. `PROXY 192.168.0.2:8080`.
+    pub fallbacks: Vec<String>,
+}
+
+impl PacRules {
+    /// Rules for `proxy_addr`. `target` is the game server picked at launch;
+    /// without one, every server of the server list is routed.
+    pub fn from_configs(
+        pac: &configs::ConfigsProxyPac,
+        servers: &configs::ConfigsAppConnectKcServer,
+        target: Option<&str>,
+        proxy_addr: &str,
+    ) -> Self {
+        let mut rules = Self {
+            proxy_addr: Some(proxy_addr.to_string()),
+            ..Self::default()
+        };
+        match target {
+            Some(target) => rules.servers = host_patterns([target.to_string()]),
+            None => rules.set_servers(&servers.get_all_servers()),
+        }
+        rules.set_extra_hosts(pac.get_extra_proxy_hosts());
+        rules.add_fallbacks(pac.get_fallbacks());
+        rules
+    }
+
+    /// Replace the routed game servers, ordered by server number.
+    pub fn set_servers(&mut self, servers: &std::collections::HashMap<i32, String>) {
+        let mut servers: Vec<_> = servers.iter().collect();
+        servers.sort_by_key(|(index, _)| **index);
+        self.servers = host_patterns(servers.into_iter().map(|(_, host)| host.clone()));
+    }
+
+    pub fn set_extra_hosts(&mut self, hosts: impl IntoIterator<Item = String>) {
+        self.extra_hosts = host_patterns(hosts);
+    }
+
+    /// Patterns routed to FUSOU, servers first, without duplicates.
+    pub fn proxy_hosts(&self) -> Vec<&str> {
+        let mut hosts: Vec<&str> = Vec::new();
+        for host in self.servers.iter().chain(&self.extra_hosts) {
+            if !hosts.contains(&host.as_str()) {
+                hosts.push(host);
+            }
+        }
+        hosts
+    }
+
+    pub fn add_fallbacks(&mut self, fallbacks: impl IntoIterator<Item = String>) {
+        for fallback in fallbacks {
+            let fallback = fallback.split_whitespace().collect::<Vec<_>>().join(" ");
+            if is_fallback(&fallback) {
+                self.fallbacks.push(fallback);
+            } else {
+                tracing::warn!(fallback, "ignoring invalid PAC fallback");
+            }
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut script = String::from("// Generated by FUSOU. Manual changes are overwritten.\n");
+        script.push_str("function FindProxyForURL(url, host) {\n");
+        let proxy_hosts = self.proxy_hosts();
+        if let (Some(proxy_addr), false) = (&self.proxy_addr, proxy_hosts.is_empty()) {
+            let conditions = proxy_hosts
+                .iter()
+                .map(|host| format!("shExpMatch(host, \"{host}\")"))
+                .collect::<Vec<_>>()
+                .join(" ||\n        ");
+            let route = std::iter::once(format!("PROXY {proxy_addr}"))
+                .chain(self.fallbacks.iter().cloned())
+                .collect::<Vec<_>>()
+                .join("; ");
+            script.push_str(&format!(
+                "    if ({conditions}) {{\n        return \"{route}\";\n    }}\n\n"
+            ));
+        }
+        script.push_str("    return \"DIRECT\";\n}\n");
+        script
+    }
+}
+
+/// Lowercased patterns, skipping anything that is not a plain host pattern.
+fn host_patterns(hosts: impl IntoIterator<Item = String>) -> Vec<String> {
+    hosts
+        .into_iter()
+        .map(|host| host.trim().to_ascii_lowercase())
+        .filter(|host| {
+            let valid = is_host_pattern(host);
+            if !valid {
+                tracing::warn!(host, "ignoring invalid PAC host pattern");
+            }
+            valid
+        })
+        .collect()
+}
+
+fn is_host_pattern(host: &str) -> bool {
+    !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '*' | '?' | ':'))
+}
+
+fn is_fallback(entry: &str) -> bool {
+    let mut parts = entry.split(' ');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("DIRECT"), None, None) => true,
+        (Some(kind), Some(addr), None) => {
+            kind != "DIRECT"
+                && FALLBACK_KINDS.contains(&kind)
+                && addr
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'))
+        }
+        _ => false,
+    }
+}
+
+/// Shared, updatable [`PacRules`]. Clones refer to the same rules.
+#[derive(Clone)]
+pub struct PacHandle {
+    rules: Arc<watch::Sender<PacRules>>,
+    output_file: Option<Arc<PathBuf>>,
+}
+
+impl PacHandle {
+    pub fn new(rules: PacRules) -> Self {
+        Self {
+            rules: Arc::new(watch::Sender::new(rules)),
+            output_file: None,
+        }
+    }
+
+    /// Also write the script to `path` now and whenever the rules change, for
+    /// clients that read the PAC file from disk.
+    pub fn with_output_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output_file = Some(Arc::new(path.into()));
+        self.write_output_file();
+        self
+    }
+
+    pub fn rules(&self) -> PacRules {
+        self.rules.borrow().clone()
+    }
+
+    pub fn script(&self) -> String {
+        self.rules.borrow().render()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<PacRules> {
+        self.rules.subscribe()
+    }
+
+    /// Apply `change`; the script is regenerated only when the rules differ.
+    pub fn update(&self, change: impl FnOnce(&mut PacRules)) {
+        let changed = self.rules.send_if_modified(|rules| {
+            let before = rules.clone();
+            change(rules);
+            *rules != before
+        });
+        if changed {
+            tracing::info!(rules = ?*self.rules.borrow(), "PAC rules updated");
+            self.write_output_file();
+        }
+    }
+
+    fn write_output_file(&self) {
+        let Some(path) = &self.output_file else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(path.as_path(), self.script()));
+        if let Err(e) = result {
+            tracing::warn!("failed to write PAC file {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_hosts_fallbacks_and_direct() {
+        let mut rules = PacRules {
+            proxy_addr: Some("127.0.0.1:3000".to_string()),
+            ..PacRules::default()
+        };
+        rules.set_servers(
+            &[
+                (2, "w02k.kancolle-server.com".to_string()),
+                (1, "W01Y.kancolle-server.com".to_string()),
+            ]
+            .into(),
+        );
+        rules.set_extra_hosts([
+            "*.example.test".to_string(),
+            "w01y.kancolle-server.com".to_string(),
+            "bad\"host".to_string(),
+        ]);
+        rules.add_fallbacks([
+            "PROXY  192.168.0.2:8080".to_string(),
+            "DIRECT".to_string(),
+            "return 1".to_string(),
+        ]);
+
+        assert_eq!(
+            rules.render(),
+            "// Generated by FUSOU. Manual changes are overwritten.
+function FindProxyForURL(url, host) {
+    if (shExpMatch(host, \"w01y.kancolle-server.com\") ||
+        shExpMatch(host, \"w02k.kancolle-server.com\") ||
+        shExpMatch(host, \"*.example.test\")) {
+        return \"PROXY 127.0.0.1:3000; PROXY 192.168.0.2:8080; DIRECT\";
+    }
+
+    return \"DIRECT\";
+}
+"
+        );
+        assert!(!PacRules::default().render().contains("PROXY"));
+    }
+
+    #[test]
+    fn handle_regenerates_on_change() {
+        let path = std::env::temp_dir()
+            .join(format!("fusou-pac-test-{}", uuid::Uuid::new_v4()))
+            .join("proxy.pac");
+        let handle = PacHandle::new(PacRules::default()).with_output_file(&path);
+        let mut updates = handle.subscribe();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("PROXY"));
+
+        handle.update(|rules| rules.set_extra_hosts(["*.example.test".to_string()]));
+        handle.update(|rules| {
+            rules.set_servers(&[(1, "w01y.kancolle-server.com".to_string())].into());
+            rules.proxy_addr = Some("127.0.0.1:4000".to_string());
+        });
+        assert!(updates.has_changed().unwrap());
+        updates.mark_unchanged();
+        handle.update(|rules| rules.proxy_addr = Some("127.0.0.1:4000".to_string()));
+        assert!(!updates.has_changed().unwrap());
+
+        assert!(handle.script().contains("return \"PROXY 127.0.0.1:4000\";"));
+        assert_eq!(
+            handle.rules().proxy_hosts(),
+            vec!["w01y.kancolle-server.com", "*.example.test"]
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), handle.script());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
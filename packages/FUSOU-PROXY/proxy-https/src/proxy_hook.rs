@@ -0,0 +1,415 @@
+//! Hook pipeline for traffic passing through the HTTPS proxy.
+//!
+//! Bodies are only copied for traffic some hook's [`HookFilter`] matches; the
+//! complete body is then offered to each matching [`ProxyHook`]. Request hooks
+//! run before the request is forwarded. With streaming enabled
+//! (`[proxy.streaming]`), responses are forwarded to the client as they arrive
+//! and response hooks run once the whole body has been received; bodies too
+//! large to keep in memory are handed over as their spool file (see
+//! [`HookBody`]). Hooks only
+//! observe traffic and may run on the proxy's request path, so anything slow
+//! (disk or network I/O, decoding) should be moved onto a spawned task. A
+//! panicking hook is logged and skipped without affecting the other hooks or
+//! the proxied traffic.
+
+use std::io;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Arc;
+
+use http::{request, response, HeaderMap, Uri};
+use hudsucker::hyper::body::Bytes;
+
+use crate::body_tee::SpooledFile;
+use crate::proxy_server_https::{
+    decode_response_body, normalize_content_type, parse_content_encodings,
+};
+
+/// A request as seen by hooks.
+pub struct ProxyRequest<'a> {
+    pub parts: &'a request::Parts,
+    /// Request path, e.g. `/kcsapi/api_port/port`.
+    pub path: &'a str,
+    /// Raw `Content-Type` header value (empty when absent).
+    pub content_type: &'a str,
+    pub body: &'a Bytes,
+}
+
+/// A response as seen by hooks, together with the URI of its request.
+pub struct ProxyResponse<'a> {
+    pub request_uri: &'a Uri,
+    pub parts: &'a response::Parts,
+    /// Path of the request this response answers.
+    pub path: &'a str,
+    /// Raw `Content-Type` header value (empty when absent).
+    pub content_type: &'a str,
+    /// Body as received from the server, still content-encoded.
+    pub body: &'a HookBody,
+}
+
+impl ProxyResponse<'_> {
+    /// Body with `Content-Encoding` (gzip, deflate, br) removed. Decoding, and
+    /// reading a spooled body, block; call it from a blocking task for large
+    /// bodies.
+    pub fn decoded_body(&self) -> io::Result<Vec<u8>> {
+        let encodings = parse_content_encodings(&header_str(
+            &self.parts.headers,
+            http::header::CONTENT_ENCODING,
+        ));
+        Ok(decode_response_body(
+            self.body.to_bytes()?.to_vec(),
+            &encodings,
+            true,
+        ))
+    }
+}
+
+/// Complete response body handed to hooks. Clones are cheap and share the
+/// body; a spooled body's file is removed once the last clone is dropped, so
+/// hooks may keep a clone for a spawned task.
+#[derive(Debug, Clone)]
+pub struct HookBody(BodyRepr);
+
+#[derive(Debug, Clone)]
+enum BodyRepr {
+    Memory(Bytes),
+    Spooled(Arc<SpooledFile>),
+}
+
+impl HookBody {
+    pub(crate) fn spooled(file: SpooledFile) -> Self {
+        Self(BodyRepr::Spooled(Arc::new(file)))
+    }
+
+    pub fn len(&self) -> u64 {
+        match &self.0 {
+            BodyRepr::Memory(bytes) => bytes.len() as u64,
+            BodyRepr::Spooled(file) => file.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The body, when it is held in memory.
+    pub fn as_bytes(&self) -> Option<&Bytes> {
+        match &self.0 {
+            BodyRepr::Memory(bytes) => Some(bytes),
+            BodyRepr::Spooled(_) => None,
+        }
+    }
+
+    /// The spool file holding the body, when it did not fit in memory. The
+    /// file must not be modified and only lives as long as this body.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.0 {
+            BodyRepr::Memory(_) => None,
+            BodyRepr::Spooled(file) => Some(file.path()),
+        }
+    }
+
+    /// Read the body from the start.
+    pub fn reader(&self) -> io::Result<Box<dyn io::Read + Send>> {
+        match &self.0 {
+            BodyRepr::Memory(bytes) => Ok(Box::new(io::Cursor::new(bytes.clone()))),
+            BodyRepr::Spooled(file) => Ok(Box::new(io::BufReader::new(std::fs::File::open(
+                file.path(),
+            )?))),
+        }
+    }
+
+    /// Write the body to `path`; a spooled body is copied without reading it
+    /// into memory.
+    pub async fn write_to(&self, path: &Path) -> io::Result<()> {
+        match &self.0 {
+            BodyRepr::Memory(bytes) => tokio::fs::write(path, bytes).await,
+            BodyRepr::Spooled(file) => tokio::fs::copy(file.path(), path).await.map(|_| ()),
+        }
+    }
+
+    /// The whole body in memory. Reads a spooled body from disk, which blocks.
+    pub fn to_bytes(&self) -> io::Result<Bytes> {
+        match &self.0 {
+            BodyRepr::Memory(bytes) => Ok(bytes.clone()),
+            BodyRepr::Spooled(file) => std::fs::read(file.path()).map(Bytes::from),
+        }
+    }
+}
+
+impl From<Bytes> for HookBody {
+    fn from(bytes: Bytes) -> Self {
+        Self(BodyRepr::Memory(bytes))
+    }
+}
+
+pub(crate) fn header_str(headers: &HeaderMap, name: http::header::HeaderName) -> String {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Which traffic a hook wants to see. An empty list matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookFilter {
+    path_prefixes: Vec<String>,
+    content_types: Vec<String>,
+}
+
+impl HookFilter {
+    /// Match all traffic.
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    /// Only paths starting with `prefix` (may be called several times).
+    pub fn path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.path_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Only this MIME type, ignoring parameters such as `charset` (may be
+    /// called several times).
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_types
+            .push(normalize_content_type(content_type));
+        self
+    }
+
+    pub fn matches(&self, path: &str, content_type: &str) -> bool {
+        let path_ok = self.path_prefixes.is_empty()
+            || self
+                .path_prefixes
+                .iter()
+                .any(|prefix| path.starts_with(prefix.as_str()));
+        let content_type_ok = self.content_types.is_empty() || {
+            let content_type = normalize_content_type(content_type);
+            self.content_types.contains(&content_type)
+        };
+        path_ok && content_type_ok
+    }
+}
+
+/// A plugin observing proxied traffic.
+pub trait ProxyHook: Send + Sync {
+    /// Short identifier used in logs.
+    fn name(&self) -> &str;
+
+    /// Evaluated once when the hook is registered.
+    fn filter(&self) -> HookFilter {
+        HookFilter::any()
+    }
+
+    fn on_request(&self, _request: &ProxyRequest<'_>) {}
+
+    fn on_response(&self, _response: &ProxyResponse<'_>) {}
+}
+
+#[derive(Clone)]
+struct RegisteredHook {
+    hook: Arc<dyn ProxyHook>,
+    filter: HookFilter,
+}
+
+/// Ordered set of hooks; hooks run in registration order.
+#[derive(Clone, Default)]
+pub struct ProxyHooks {
+    hooks: Vec<RegisteredHook>,
+}
+
+impl ProxyHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, hook: impl ProxyHook + 'static) -> Self {
+        self.register(Arc::new(hook));
+        self
+    }
+
+    pub fn register(&mut self, hook: Arc<dyn ProxyHook>) {
+        let filter = hook.filter();
+        tracing::debug!(hook = hook.name(), ?filter, "proxy hook registered");
+        self.hooks.push(RegisteredHook { hook, filter });
+    }
+
+    /// Append all hooks of `other` after the ones already registered.
+    pub fn extend(&mut self, other: ProxyHooks) {
+        self.hooks.extend(other.hooks);
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.hooks
+            .iter()
+            .map(|registered| registered.hook.name().to_string())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Whether any hook wants to see traffic for this path and content type.
+    /// Bodies nobody inspects are passed through without a copy.
+    pub fn wants(&self, path: &str, content_type: &str) -> bool {
+        self.hooks
+            .iter()
+            .any(|registered| registered.filter.matches(path, content_type))
+    }
+
+    pub fn dispatch_request(&self, request: &ProxyRequest<'_>) {
+        for registered in &self.hooks {
+            if registered
+                .filter
+                .matches(request.path, request.content_type)
+            {
+                Self::guard(registered, "request", || {
+                    registered.hook.on_request(request)
+                });
+            }
+        }
+    }
+
+    pub fn dispatch_response(&self, response: &ProxyResponse<'_>) {
+        for registered in &self.hooks {
+            if registered
+                .filter
+                .matches(response.path, response.content_type)
+            {
+                Self::guard(registered, "response", || {
+                    registered.hook.on_response(response)
+                });
+            }
+        }
+    }
+
+    fn guard(registered: &RegisteredHook, stage: &str, run: impl FnOnce()) {
+        if catch_unwind(AssertUnwindSafe(run)).is_err() {
+            tracing::error!(hook = registered.hook.name(), stage, "proxy hook panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        name: &'static str,
+        filter: HookFilter,
+        seen: Mutex<Vec<String>>,
+    }
+
+    impl ProxyHook for Recorder {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn filter(&self) -> HookFilter {
+            self.filter.clone()
+        }
+
+        fn on_request(&self, request: &ProxyRequest<'_>) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("req {}", request.path));
+        }
+
+        fn on_response(&self, response: &ProxyResponse<'_>) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("res {}", response.path));
+        }
+    }
+
+    struct Panicking;
+
+    impl ProxyHook for Panicking {
+        fn name(&self) -> &str {
+            "panicking"
+        }
+
+        fn on_request(&self, _request: &ProxyRequest<'_>) {
+            panic!("broken plugin");
+        }
+    }
+
+    fn request(uri: &str, content_type: &str) -> request::Parts {
+        let (parts, _) = http::Request::post(uri)
+            .header("content-type", content_type)
+            .body(())
+            .unwrap()
+            .into_parts();
+        parts
+    }
+
+    #[test]
+    fn filter_matches_prefix_and_mime_type() {
+        let filter = HookFilter::any()
+            .path_prefix("/kcsapi/")
+            .content_type("text/plain");
+        assert!(filter.matches("/kcsapi/api_port/port", "text/plain; charset=UTF-8"));
+        assert!(!filter.matches("/kcs2/img/title.png", "text/plain"));
+        assert!(!filter.matches("/kcsapi/api_port/port", "application/json"));
+        assert!(HookFilter::any().matches("/anything", ""));
+    }
+
+    #[test]
+    fn wants_only_paths_some_hook_inspects() {
+        let hooks = ProxyHooks::new().with(Recorder {
+            name: "api",
+            filter: HookFilter::any().path_prefix("/kcsapi/"),
+            ..Recorder::default()
+        });
+        assert!(hooks.wants("/kcsapi/api_port/port", "text/plain"));
+        assert!(!hooks.wants("/kcs/sound/kc9999/1.mp3", "audio/mpeg"));
+        assert!(!ProxyHooks::new().wants("/kcsapi/api_port/port", "text/plain"));
+    }
+
+    #[test]
+    fn dispatch_runs_matching_hooks_in_order_and_survives_panics() {
+        let api = Arc::new(Recorder {
+            name: "api",
+            filter: HookFilter::any().path_prefix("/kcsapi/"),
+            ..Recorder::default()
+        });
+        let all = Arc::new(Recorder {
+            name: "all",
+            ..Recorder::default()
+        });
+        let mut hooks = ProxyHooks::new().with(Panicking);
+        hooks.register(api.clone());
+        hooks.register(all.clone());
+        assert_eq!(hooks.names(), vec!["panicking", "api", "all"]);
+
+        let body = Bytes::from_static(b"api_verno=1");
+        for uri in [
+            "http://w01.test/kcsapi/api_port/port",
+            "http://w01.test/kcs2/index.php",
+        ] {
+            let parts = request(uri, "application/x-www-form-urlencoded");
+            hooks.dispatch_request(&ProxyRequest {
+                parts: &parts,
+                path: parts.uri.path(),
+                content_type: "application/x-www-form-urlencoded",
+                body: &body,
+            });
+        }
+
+        assert_eq!(*api.seen.lock().unwrap(), vec!["req /kcsapi/api_port/port"]);
+        assert_eq!(
+            *all.seen.lock().unwrap(),
+            vec!["req /kcsapi/api_port/port", "req /kcs2/index.php"]
+        );
+    }
+}
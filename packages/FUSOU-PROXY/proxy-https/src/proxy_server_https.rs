@@ -18,24 +18,36 @@ use chrono_tz::Asia::Tokyo;
 use std::os::windows::fs::MetadataExt;
 
 use crate::bidirectional_channel;
+use crate::body_tee::{self, SpoolConfig};
+use crate::ca_manager::{CaManager, CaPolicy};
+use crate::client_allowlist::ClientAllowlist;
+use crate::game_server::{GameServer, GameServerResolver};
+use crate::har::{capture_file_name, capture_metadata, CaptureKind, HarRecorder};
+use crate::leaf_cert_cache::CachingAuthority;
+use crate::proxy_hook::{
+    header_str, HookBody, HookFilter, ProxyHook, ProxyHooks, ProxyRequest, ProxyResponse,
+};
+use crate::telemetry::{ApiTelemetry, Exchange, ExchangeOutcome, TelemetryConfig};
+use crate::upstream_proxy::{UpstreamConnector, UpstreamProxy};
 
 use configs;
 
 use fusou_auth::{AuthManager, FileStorage};
 use fusou_storage::asset_sync;
 use std::sync::Arc;
+use std::time::Instant;
 use tracing_unwrap::ResultExt;
 
 pub static CA_CERT_NAME: &str = "fusou_ca_cert";
 pub static CA_CERT_NAME_PEM: &str = "fusou_ca_cert.pem";
 pub static CA_CERT_NAME_CRT: &str = "fusou_ca_cert.crt";
 pub static CA_CERT_NAME_DER: &str = "fusou_ca_cert.der";
-static CA_KEY_NAME_PEM: &str = "fusou_ca_key.pem";
+pub(crate) static CA_KEY_NAME_PEM: &str = "fusou_ca_key.pem";
 
 static ORGANIZATION_NAME: &str = "FUSOU";
 static COUNTRY_NAME: &str = "JP";
 
-fn normalize_content_type(content_type: &str) -> String {
+pub(crate) fn normalize_content_type(content_type: &str) -> String {
     content_type
         .split(';')
         .next()
@@ -43,7 +55,7 @@ fn normalize_content_type(content_type: &str) -> String {
         .unwrap_or_default()
 }
 
-fn parse_content_encodings(content_encoding: &str) -> Vec<String> {
+pub(crate) fn parse_content_encodings(content_encoding: &str) -> Vec<String> {
     content_encoding
         .split(',')
         .map(|v| v.trim().to_ascii_lowercase())
@@ -87,7 +99,7 @@ fn decode_body_with_encoding(body: &[u8], encoding: &str) -> Result<Vec<u8>, Str
     }
 }
 
-fn decode_response_body(mut body: Vec<u8>, encodings: &[String], try_gzip_sniff: bool) -> Vec<u8> {
+pub(crate) fn decode_response_body(mut body: Vec<u8>, encodings: &[String], try_gzip_sniff: bool) -> Vec<u8> {
     if !encodings.is_empty() {
         for encoding in encodings.iter().rev() {
             match decode_body_with_encoding(&body, encoding) {
@@ -112,10 +124,17 @@ fn decode_response_body(mut body: Vec<u8>, encodings: &[String], try_gzip_sniff:
     body
 }
 
+/// Request an exchange belongs to: its URI and the game server it was
+/// resolved to.
+struct ExchangeSource {
+    uri: Uri,
+    server: GameServer,
+}
+
 fn log_response(
     parts: response::Parts,
-    body: Vec<u8>,
-    uri: Uri,
+    body: HookBody,
+    source: ExchangeSource,
     tx_proxy_log: bidirectional_channel::Master<bidirectional_channel::StatusInfo>,
     save_path: String,
     file_prefix: String,
@@ -123,6 +142,7 @@ fn log_response(
     allow_save_resources: bool,
     allow_save_main_js_local: bool,
 ) {
+    let ExchangeSource { uri, server } = source;
     let mut raw_content_type = String::new();
     let mut content_type = String::new();
     let mut content_encoding = String::new();
@@ -211,19 +231,7 @@ fn log_response(
         tokio::spawn(async move {
             // Phase 4: Decompress CPU-bound operations using spawn_blocking
             let buffer_for_text = if !pass && content_type.eq("text/plain") {
-                let body_clone = body.clone();
-                let content_encodings_clone = content_encodings.clone();
-                match tokio::task::spawn_blocking(move || {
-                    decode_response_body(body_clone, &content_encodings_clone, true)
-                })
-                .await
-                {
-                    Ok(buf) => buf,
-                    Err(e) => {
-                        tracing::error!("spawn_blocking decompression failed: {}", e);
-                        body.clone()
-                    }
-                }
+                decode_in_background(body.clone(), content_encodings.clone()).await
             } else {
                 Vec::new()
             };
@@ -234,6 +242,7 @@ fn log_response(
                         path: uri_path.clone(),
                         content_type: raw_content_type.clone(),
                         content: buffer_string,
+                        server: server.clone(),
                     };
                     let _ = tx_proxy_log.send(mes).await;
                 } else {
@@ -255,17 +264,9 @@ fn log_response(
                         }
                     }
 
-                    let time_formated = format!(
-                        "kcsapi/{}S{}",
-                        jst.format("%Y%m%d_%H%M%S%3f"),
-                        uri_path.as_str().replace("/kcsapi", "").replace("/", "@")
-                    );
-                    let metadata_string = format!(
-                        "---\nProxyApp: {}\nTimestamp: {}\nEnvId: {}\n---\n",
-                        "FUSOU",
-                        jst.timestamp(),
-                        file_prefix
-                    );
+                    let time_formated =
+                        capture_file_name(&jst, CaptureKind::Response, uri_path.as_str());
+                    let metadata_string = capture_metadata(&jst, &file_prefix, &server);
                     let metadata_buffer = metadata_string.as_bytes();
                     let combined_buffer = [metadata_buffer, buffer_for_text.as_slice()].concat();
                     // Phase 3: Use async file I/O (non-blocking)
@@ -293,48 +294,23 @@ fn log_response(
 
                     if content_type.eq("application/json") || is_json_by_path {
                         // Phase 4: Decompress JSON using spawn_blocking
-                        let body_clone = body.clone();
-                        let content_encodings_clone = content_encodings.clone();
-                        let json_buffer = match tokio::task::spawn_blocking(move || {
-                            decode_response_body(body_clone, &content_encodings_clone, true)
-                        })
-                        .await
-                        {
-                            Ok(buf) => buf,
-                            Err(e) => {
-                                tracing::error!("spawn_blocking JSON decompression failed: {}", e);
-                                body.clone()
-                            }
-                        };
+                        let json_buffer =
+                            decode_in_background(body.clone(), content_encodings.clone()).await;
 
                         // Phase 3: Use async file I/O (non-blocking)
                         if let Err(e) = tokio::fs::write(&file_log_path, json_buffer).await {
                             tracing::error!("Failed to write json file: {}", e);
                         }
                     } else if is_main_js && allow_save_main_js_local {
-                        let body_clone = body.clone();
-                        let content_encodings_clone = content_encodings.clone();
-                        let js_buffer = match tokio::task::spawn_blocking(move || {
-                            decode_response_body(body_clone, &content_encodings_clone, true)
-                        })
-                        .await
-                        {
-                            Ok(buf) => buf,
-                            Err(e) => {
-                                tracing::error!(
-                                    "spawn_blocking main.js decompression failed: {}",
-                                    e
-                                );
-                                body.clone()
-                            }
-                        };
+                        let js_buffer =
+                            decode_in_background(body.clone(), content_encodings.clone()).await;
 
                         if let Err(e) = tokio::fs::write(&file_log_path, js_buffer).await {
                             tracing::error!("Failed to write main.js file: {}", e);
                         }
                     } else {
                         // Phase 3: Use async file I/O (non-blocking)
-                        if let Err(e) = tokio::fs::write(&file_log_path, body.clone()).await {
+                        if let Err(e) = body.write_to(&file_log_path).await {
                             tracing::error!("Failed to write file: {}", e);
                         }
                     }
@@ -347,15 +323,37 @@ fn log_response(
     }
 }
 
+/// Decode a response body on a blocking task, reading it back first when it
+/// was spooled to disk.
+async fn decode_in_background(body: HookBody, content_encodings: Vec<String>) -> Vec<u8> {
+    match tokio::task::spawn_blocking(move || {
+        body.to_bytes()
+            .map(|bytes| decode_response_body(bytes.to_vec(), &content_encodings, true))
+    })
+    .await
+    {
+        Ok(Ok(buf)) => buf,
+        Ok(Err(e)) => {
+            tracing::error!("failed to read spooled response body: {}", e);
+            Vec::new()
+        }
+        Err(e) => {
+            tracing::error!("spawn_blocking decompression failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
 fn log_request(
     parts: request::Parts,
     body: Vec<u8>,
-    uri: Uri,
+    source: ExchangeSource,
     tx_proxy_log: bidirectional_channel::Master<bidirectional_channel::StatusInfo>,
     save_path: String,
     file_prefix: String,
     allow_save_api_requests: bool,
 ) {
+    let ExchangeSource { uri, server } = source;
     let mut content_type: String = String::new();
     let mut _content_length: i64 = -1;
 
@@ -410,6 +408,7 @@ fn log_request(
                         path: uri_path.clone(),
                         content_type: content_type.to_string(),
                         content: buffer_string,
+                        server: server.clone(),
                     };
                     let _ = tx_proxy_log.send(mes).await;
                 } else {
@@ -435,17 +434,9 @@ fn log_request(
                     //     uri_path.as_str().replace("/kcsapi", "").replace("/", "@")
                     // );
 
-                    let time_formated = format!(
-                        "kcsapi/{}Q{}",
-                        jst.format("%Y%m%d_%H%M%S%3f"),
-                        uri_path.as_str().replace("/kcsapi", "").replace("/", "@")
-                    );
-                    let metadata_string = format!(
-                        "---\nProxyApp: {}\nTimestamp: {}\nEnvId: {}\n---\n",
-                        "FUSOU",
-                        jst.timestamp(),
-                        file_prefix
-                    );
+                    let time_formated =
+                        capture_file_name(&jst, CaptureKind::Request, uri_path.as_str());
+                    let metadata_string = capture_metadata(&jst, &file_prefix, &server);
                     let metadata_buffer = metadata_string.as_bytes();
                     let combined_buffer = [metadata_buffer, buffer.as_slice()].concat();
                     fs::write(path_log.join(Path::new(&time_formated)), combined_buffer)
@@ -485,9 +476,9 @@ fn log_request(
     }
 }
 
-#[derive(Clone)]
-struct LogHandler {
-    request_uri: Uri,
+/// Built-in hook: forwards game API traffic to the app channel and saves
+/// traffic to disk according to the `allow_save_*` settings.
+struct LogHook {
     tx_proxy_log: bidirectional_channel::Master<bidirectional_channel::StatusInfo>,
     save_path: String,
     file_prefix: String,
@@ -495,18 +486,125 @@ struct LogHandler {
     allow_save_api_responses: bool,
     allow_save_resources: bool,
     allow_save_main_js_local: bool,
+    servers: GameServerResolver,
+}
+
+impl ProxyHook for LogHook {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    fn filter(&self) -> HookFilter {
+        // Resources are only inspected when they are saved, so large assets
+        // can be streamed to the client without a copy.
+        if self.allow_save_resources {
+            return HookFilter::any();
+        }
+        let filter = HookFilter::any().path_prefix("/kcsapi");
+        if self.allow_save_main_js_local {
+            filter.path_prefix("/kcs2/js/")
+        } else {
+            filter
+        }
+    }
+
+    fn on_request(&self, request: &ProxyRequest<'_>) {
+        log_request(
+            request.parts.clone(),
+            request.body.to_vec(),
+            ExchangeSource {
+                uri: request.parts.uri.clone(),
+                server: self
+                    .servers
+                    .resolve_request(&request.parts.uri, &request.parts.headers),
+            },
+            self.tx_proxy_log.clone(),
+            self.save_path.clone(),
+            self.file_prefix.clone(),
+            self.allow_save_api_requests,
+        );
+    }
+
+    fn on_response(&self, response: &ProxyResponse<'_>) {
+        log_response(
+            response.parts.clone(),
+            response.body.clone(),
+            ExchangeSource {
+                uri: response.request_uri.clone(),
+                server: self
+                    .servers
+                    .resolve_request(response.request_uri, &http::HeaderMap::new()),
+            },
+            self.tx_proxy_log.clone(),
+            self.save_path.clone(),
+            self.file_prefix.clone(),
+            self.allow_save_api_responses,
+            self.allow_save_resources,
+            self.allow_save_main_js_local,
+        );
+    }
+}
+
+#[derive(Clone)]
+struct LogHandler {
+    request_uri: Uri,
+    hooks: ProxyHooks,
+    /// `None` buffers every response before forwarding it.
+    streaming: Option<SpoolConfig>,
+    telemetry: Option<Arc<ApiTelemetry>>,
+    allowlist: Arc<ClientAllowlist>,
+    /// When the current request was received and forwarded.
+    received_at: Option<Instant>,
+    forwarded_at: Option<Instant>,
+}
+
+impl LogHandler {
+    fn record_exchange(&mut self, outcome: ExchangeOutcome) {
+        let (Some(telemetry), Some(received_at), Some(forwarded_at)) = (
+            &self.telemetry,
+            self.received_at.take(),
+            self.forwarded_at.take(),
+        ) else {
+            return;
+        };
+        telemetry.record(Exchange {
+            path: self.request_uri.path().to_string(),
+            outcome,
+            upstream: forwarded_at.elapsed(),
+            proxy_overhead: forwarded_at - received_at,
+        });
+    }
 }
 
 impl HttpHandler for LogHandler {
     async fn handle_request(
         &mut self,
-        _ctx: &HttpContext,
+        ctx: &HttpContext,
         req: Request<Body>,
     ) -> RequestOrResponse {
+        if !self.allowlist.allows(ctx.client_addr.ip()) {
+            tracing::warn!(client = %ctx.client_addr, "rejected client not in proxy_allowed_clients");
+            return Response::builder()
+                .status(http::StatusCode::FORBIDDEN)
+                .body(Body::empty())
+                .expect("Failed to build response")
+                .into();
+        }
         self.request_uri = req.uri().clone();
+        self.received_at = Some(Instant::now());
 
         let (part, body) = req.into_parts();
 
+        let content_type = header_str(&part.headers, http::header::CONTENT_TYPE);
+        // Inspected requests are small API form posts; they stay buffered so
+        // hooks see each request before its response.
+        if self.streaming.is_some()
+            && !self.hooks.wants(self.request_uri.path(), &content_type)
+        {
+            self.forwarded_at = Some(Instant::now());
+            return hudsucker::RequestOrResponse::Request(Request::from_parts(part, body));
+        }
+
         let body_vec = match body.collect().await {
             Ok(collected) => collected.to_bytes().to_vec(),
             Err(e) => {
@@ -517,26 +615,45 @@ impl HttpHandler for LogHandler {
         let body = hyper::body::Bytes::from(body_vec);
         let full_body = http_body_util::Full::from(body.clone());
 
-        let body_vec = body.to_vec();
-        log_request(
-            part.clone(),
-            body_vec,
-            self.request_uri.clone(),
-            self.tx_proxy_log.clone(),
-            self.save_path.clone(),
-            self.file_prefix.clone(),
-            self.allow_save_api_requests,
-        );
+        self.hooks.dispatch_request(&ProxyRequest {
+            parts: &part,
+            path: self.request_uri.path(),
+            content_type: &content_type,
+            body: &body,
+        });
 
         let reconstructed_body = hudsucker::Body::from(full_body);
         let reconstructed_resquest = Request::from_parts(part, reconstructed_body);
 
+        self.forwarded_at = Some(Instant::now());
         return hudsucker::RequestOrResponse::Request(reconstructed_resquest);
     }
 
     async fn handle_response(&mut self, _ctx: &HttpContext, res: Response<Body>) -> Response<Body> {
+        self.record_exchange(ExchangeOutcome::Status(res.status().as_u16()));
+
         let (part, body) = res.into_parts();
 
+        let content_type = header_str(&part.headers, http::header::CONTENT_TYPE);
+        if let Some(spool) = &self.streaming {
+            if !self.hooks.wants(self.request_uri.path(), &content_type) {
+                return Response::from_parts(part, body);
+            }
+            let hooks = self.hooks.clone();
+            let request_uri = self.request_uri.clone();
+            let hook_parts = part.clone();
+            let body = body_tee::tee(body, spool.clone(), move |body| {
+                hooks.dispatch_response(&ProxyResponse {
+                    request_uri: &request_uri,
+                    parts: &hook_parts,
+                    path: request_uri.path(),
+                    content_type: &content_type,
+                    body: &body,
+                });
+            });
+            return Response::from_parts(part, body);
+        }
+
         let body_vec = match body.collect().await {
             Ok(collected) => collected.to_bytes().to_vec(),
             Err(e) => {
@@ -547,30 +664,45 @@ impl HttpHandler for LogHandler {
         let body = hyper::body::Bytes::from(body_vec);
         let full_body = http_body_util::Full::from(body.clone());
 
-        let body_vec = body.to_vec();
-        log_response(
-            part.clone(),
-            body_vec,
-            self.request_uri.clone(),
-            self.tx_proxy_log.clone(),
-            self.save_path.clone(),
-            self.file_prefix.clone(),
-            self.allow_save_api_responses,
-            self.allow_save_resources,
-            self.allow_save_main_js_local,
-        );
+        self.hooks.dispatch_response(&ProxyResponse {
+            request_uri: &self.request_uri,
+            parts: &part,
+            path: self.request_uri.path(),
+            content_type: &content_type,
+            body: &HookBody::from(body),
+        });
 
         let reconstructed_body = hudsucker::Body::from(full_body);
         let reconstructed_response = Response::from_parts(part, reconstructed_body);
 
         return reconstructed_response;
     }
+
+    async fn handle_error(
+        &mut self,
+        _ctx: &HttpContext,
+        err: hyper_util::client::legacy::Error,
+    ) -> Response<Body> {
+        tracing::warn!(uri = %self.request_uri, "failed to forward request: {}", err);
+        self.record_exchange(ExchangeOutcome::UpstreamError);
+        Response::builder()
+            .status(http::StatusCode::BAD_GATEWAY)
+            .body(Body::empty())
+            .expect("Failed to build response")
+    }
 }
 
 pub fn create_ca(ca_save_path: String) {
     let ca_dir = Path::new(ca_save_path.as_str());
     let ca_key_pair = rcgen::KeyPair::generate().unwrap();
+    let ca_cert = ca_params().self_signed(&ca_key_pair).unwrap();
 
+    let _ = fs::create_dir_all(ca_dir);
+    let _ = write_ca_files(ca_dir, &ca_cert, &ca_key_pair);
+}
+
+/// Parameters shared by every generated CA.
+pub(crate) fn ca_params() -> rcgen::CertificateParams {
     let mut ca_param = rcgen::CertificateParams::default();
     ca_param.distinguished_name = rcgen::DistinguishedName::new();
     ca_param.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
@@ -588,14 +720,18 @@ pub fn create_ca(ca_save_path: String) {
     ca_param
         .distinguished_name
         .push(rcgen::DnType::OrganizationName, ORGANIZATION_NAME);
-    let ca_cert = ca_param.self_signed(&ca_key_pair).unwrap();
-
-    let _ = fs::create_dir_all(ca_dir);
+    ca_param
+}
 
-    let _ = fs::write(ca_dir.join(CA_CERT_NAME_PEM), ca_cert.pem());
-    let _ = fs::write(ca_dir.join(CA_CERT_NAME_CRT), ca_cert.pem());
-    let _ = fs::write(ca_dir.join(CA_CERT_NAME_DER), ca_cert.der());
-    let _ = fs::write(ca_dir.join(CA_KEY_NAME_PEM), ca_key_pair.serialize_pem());
+pub(crate) fn write_ca_files(
+    ca_dir: &Path,
+    ca_cert: &rcgen::Certificate,
+    ca_key_pair: &rcgen::KeyPair,
+) -> std::io::Result<()> {
+    fs::write(ca_dir.join(CA_CERT_NAME_PEM), ca_cert.pem())?;
+    fs::write(ca_dir.join(CA_CERT_NAME_CRT), ca_cert.pem())?;
+    fs::write(ca_dir.join(CA_CERT_NAME_DER), ca_cert.der())?;
+    fs::write(ca_dir.join(CA_KEY_NAME_PEM), ca_key_pair.serialize_pem())
 }
 
 pub fn check_ca(ca_save_path: String) -> bool {
@@ -681,6 +817,7 @@ pub fn serve_proxy(
     ca_save_path: String,
     file_prefix: String,
     _auth_manager: Arc<AuthManager<FileStorage>>,
+    hooks: ProxyHooks,
 ) -> Result<SocketAddr, Box<dyn std::error::Error>> {
     setup_default_crypto_provider();
 
@@ -735,7 +872,20 @@ pub fn serve_proxy(
         .self_signed(&key_pair)
         .map_err(|e| format!("failed to self-sign CA certificate {}: {}", ca_cert_path.display(), e))?;
 
-    let ca = RcgenAuthority::new(key_pair, ca_cert, 1_000, aws_lc_rs::default_provider());
+    if use_generated_certs {
+        let ca_manager = CaManager::new(ca_dir, CaPolicy::from_configs(&configs.certificates));
+        if let Err(e) = ca_manager.status(Utc::now()) {
+            tracing::warn!("failed to read CA manifest: {}", e);
+        }
+    }
+
+    // Leaf certificates are cached by `CachingAuthority` so its statistics
+    // can be reported.
+    let ca = CachingAuthority::new(
+        RcgenAuthority::new(key_pair, ca_cert, 0, aws_lc_rs::default_provider()),
+        configs.certificates.get_leaf_cert_cache_size(),
+    )
+    .register_global();
 
     let mut http = hyper_util::client::legacy::connect::HttpConnector::new();
 
@@ -756,6 +906,18 @@ pub fn serve_proxy(
     // http.set_send_buffer_size(Some(8_000_000_usize));
     http.set_send_buffer_size(configs.network.get_send_buffer_size());
 
+    let upstream = UpstreamProxy::from_configs(&configs.network.upstream)?;
+    if let Some(upstream) = &upstream {
+        tracing::info!(
+            kind = ?upstream.kind,
+            host = %upstream.host,
+            port = upstream.port,
+            bypass = ?upstream.bypass,
+            "forwarding outgoing connections through upstream proxy"
+        );
+    }
+    let connector = UpstreamConnector::new(http, upstream);
+
     let tls_root_store = {
         // use "rustls-native-certs" crate
         let mut roots = rustls::RootCertStore::empty();
@@ -774,15 +936,25 @@ pub fn serve_proxy(
         .with_tls_config(tls)
         .https_or_http()
         .enable_http1()
-        .wrap_connector(http);
+        .wrap_connector(connector);
 
     let client = hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
         .build(https);
+    let bind_address = configs.network.get_proxy_server_bind_address();
     let addr = match (port, configs.network.get_proxy_server_port()) {
-        (0, 0) => SocketAddr::from(([127, 0, 0, 1], available_port().unwrap())),
-        (0, port) => SocketAddr::from(([127, 0, 0, 1], port)),
-        (port, _) => SocketAddr::from(([127, 0, 0, 1], port)),
+        (0, 0) => SocketAddr::from((bind_address, available_port().unwrap())),
+        (0, port) => SocketAddr::from((bind_address, port)),
+        (port, _) => SocketAddr::from((bind_address, port)),
     };
+    let allowlist = Arc::new(ClientAllowlist::from_configs(
+        &configs.network.get_proxy_allowed_clients(),
+    ));
+    if !bind_address.is_loopback() && allowlist.is_empty() {
+        tracing::warn!(
+            "proxy listens on {} without proxy_allowed_clients; any client that can reach it can use it and read the decrypted game traffic",
+            bind_address
+        );
+    }
     let save_path = if let Some(save_path) = configs.get_save_file_location() {
         save_path
     } else {
@@ -824,20 +996,57 @@ pub fn serve_proxy(
         tracing::info!("asset sync disabled in configuration");
     }
 
+    // The built-in log hook runs first, then hooks supplied by the caller.
+    let har_save_path = std::path::PathBuf::from(&save_path);
+    let mut proxy_hooks = ProxyHooks::new().with(LogHook {
+        tx_proxy_log: tx_proxy_log.clone(),
+        save_path,
+        file_prefix: file_prefix.clone(),
+        allow_save_api_requests,
+        allow_save_api_responses,
+        allow_save_resources,
+        allow_save_main_js_local,
+        servers: GameServerResolver::from_configs(
+            &configs::get_user_configs_for_app().connect_kc_server,
+        ),
+    });
+    if configs.har.get_enable() {
+        proxy_hooks = proxy_hooks.with(HarRecorder::from_configs(&configs.har, &har_save_path));
+    }
+    proxy_hooks.extend(hooks);
+    tracing::info!(hooks = ?proxy_hooks.names(), "proxy hooks registered");
+
+    let telemetry = if configs.telemetry.get_enable() {
+        let telemetry = Arc::new(ApiTelemetry::new(TelemetryConfig::from_configs(
+            &configs.telemetry,
+            &har_save_path,
+        )));
+        Some(telemetry.register_global())
+    } else {
+        tracing::info!("proxy telemetry disabled in configuration");
+        None
+    };
+
+    let streaming = if configs.streaming.get_enable() {
+        Some(SpoolConfig::from_configs(&configs.streaming))
+    } else {
+        tracing::info!("response streaming disabled, bodies are buffered before forwarding");
+        None
+    };
+
     let server_proxy = Proxy::builder()
         .with_addr(addr)
         .with_ca(ca)
         .with_client(client)
         // .with_rustls_client(aws_lc_rs::default_provider())
         .with_http_handler(LogHandler {
-            tx_proxy_log: tx_proxy_log.clone(),
             request_uri: Uri::default(),
-            save_path,
-            file_prefix: file_prefix.clone(),
-            allow_save_api_requests,
-            allow_save_api_responses,
-            allow_save_resources,
-            allow_save_main_js_local,
+            hooks: proxy_hooks,
+            streaming,
+            telemetry: telemetry.clone(),
+            allowlist,
+            received_at: None,
+            forwarded_at: None,
         })
         .with_graceful_shutdown(async move {
             loop {
@@ -870,6 +1079,9 @@ pub fn serve_proxy(
                     },
                 }
             }
+            if let Some(telemetry) = telemetry {
+                telemetry.flush();
+            }
         })
         .build()
         .expect_or_log("Failed to create proxy");
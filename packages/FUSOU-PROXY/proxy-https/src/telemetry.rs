@@ -0,0 +1,603 @@
+//! Latency and error statistics of proxied requests.
+//!
+//! [`ApiTelemetry`] records one [`Exchange`] per proxied request: the time
+//! the upstream server took to answer (network and server), the time the
+//! proxy itself spent before forwarding the request, and the status code or
+//! upstream connection error. `/kcsapi` endpoints are tracked one by one,
+//! everything else is counted under [`OTHER_ENDPOINT`].
+//!
+//! Statistics of the last `[proxy.telemetry].window_minutes` minutes are kept
+//! in memory in one slot per minute. Totals of the current day (JST) are
+//! written to `<output_dir>/<YYYY-MM-DD>.json` every few minutes and when the
+//! day changes, by a blocking task so the proxy handlers never wait on disk.
+//! The proxy registers its telemetry globally so it can be read with
+//! [`telemetry_snapshot`] (e.g. by the daemon's status endpoint).
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use chrono_tz::Asia::Tokyo;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// Upper bounds (exclusive) of the latency histogram buckets. Slower
+/// responses are counted in one more bucket after the last.
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [25, 50, 100, 250, 500, 1000, 2500, 5000, 10000, 30000];
+
+/// Endpoint name for requests outside `/kcsapi`.
+pub const OTHER_ENDPOINT: &str = "(other)";
+
+/// How often the summary of the current day is rewritten.
+const SUMMARY_FLUSH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+static ACTIVE_TELEMETRY: OnceLock<Mutex<Option<Arc<ApiTelemetry>>>> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExchangeOutcome {
+    Status(u16),
+    /// The request could not be forwarded, e.g. connection refused or timed out.
+    UpstreamError,
+}
+
+impl ExchangeOutcome {
+    /// Failures that the game client is expected to retry.
+    fn is_failure(&self) -> bool {
+        match self {
+            ExchangeOutcome::Status(status) => *status >= 500,
+            ExchangeOutcome::UpstreamError => true,
+        }
+    }
+}
+
+/// One proxied request.
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub path: String,
+    pub outcome: ExchangeOutcome,
+    /// From forwarding the request until the response headers (or the error)
+    /// arrived.
+    pub upstream: Duration,
+    /// From receiving the request until forwarding it.
+    pub proxy_overhead: Duration,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointStats {
+    pub requests: u64,
+    /// Counts per [`LATENCY_BUCKETS_MS`] bucket of responses that arrived.
+    pub latency_buckets: Vec<u64>,
+    pub latency_total_ms: u64,
+    pub latency_max_ms: u64,
+    pub proxy_overhead_total_ms: u64,
+    pub status_counts: BTreeMap<u16, u64>,
+    pub upstream_errors: u64,
+    pub retries: u64,
+}
+
+impl EndpointStats {
+    fn record(&mut self, exchange: &Exchange, retry: bool) {
+        self.requests += 1;
+        self.proxy_overhead_total_ms += exchange.proxy_overhead.as_millis() as u64;
+        if retry {
+            self.retries += 1;
+        }
+        match exchange.outcome {
+            ExchangeOutcome::Status(status) => {
+                *self.status_counts.entry(status).or_default() += 1;
+                let latency_ms = exchange.upstream.as_millis() as u64;
+                self.latency_buckets.resize(LATENCY_BUCKETS_MS.len() + 1, 0);
+                let bucket = LATENCY_BUCKETS_MS
+                    .iter()
+                    .position(|bound| latency_ms < *bound)
+                    .unwrap_or(LATENCY_BUCKETS_MS.len());
+                self.latency_buckets[bucket] += 1;
+                self.latency_total_ms += latency_ms;
+                self.latency_max_ms = self.latency_max_ms.max(latency_ms);
+            }
+            ExchangeOutcome::UpstreamError => self.upstream_errors += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &EndpointStats) {
+        self.requests += other.requests;
+        if self.latency_buckets.len() < other.latency_buckets.len() {
+            self.latency_buckets.resize(other.latency_buckets.len(), 0);
+        }
+        for (count, other) in self.latency_buckets.iter_mut().zip(&other.latency_buckets) {
+            *count += other;
+        }
+        self.latency_total_ms += other.latency_total_ms;
+        self.latency_max_ms = self.latency_max_ms.max(other.latency_max_ms);
+        self.proxy_overhead_total_ms += other.proxy_overhead_total_ms;
+        for (status, count) in &other.status_counts {
+            *self.status_counts.entry(*status).or_default() += count;
+        }
+        self.upstream_errors += other.upstream_errors;
+        self.retries += other.retries;
+    }
+
+    fn responses(&self) -> u64 {
+        self.latency_buckets.iter().sum()
+    }
+
+    pub fn mean_latency_ms(&self) -> Option<u64> {
+        let responses = self.responses();
+        (responses > 0).then(|| self.latency_total_ms / responses)
+    }
+
+    pub fn mean_proxy_overhead_ms(&self) -> Option<u64> {
+        (self.requests > 0).then(|| self.proxy_overhead_total_ms / self.requests)
+    }
+
+    /// Upper bound of the bucket holding the `quantile` of response
+    /// latencies; the maximum for the overflow bucket.
+    pub fn latency_quantile_ms(&self, quantile: f64) -> Option<u64> {
+        let responses = self.responses();
+        if responses == 0 {
+            return None;
+        }
+        let rank = ((responses as f64 * quantile).ceil() as u64).clamp(1, responses);
+        let mut seen = 0;
+        for (bucket, count) in self.latency_buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(
+                    LATENCY_BUCKETS_MS
+                        .get(bucket)
+                        .copied()
+                        .unwrap_or(self.latency_max_ms)
+                        .min(self.latency_max_ms.max(1)),
+                );
+            }
+        }
+        Some(self.latency_max_ms)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointSummary {
+    pub stats: EndpointStats,
+    pub mean_latency_ms: Option<u64>,
+    pub p50_latency_ms: Option<u64>,
+    pub p95_latency_ms: Option<u64>,
+    pub mean_proxy_overhead_ms: Option<u64>,
+}
+
+impl From<EndpointStats> for EndpointSummary {
+    fn from(stats: EndpointStats) -> Self {
+        Self {
+            mean_latency_ms: stats.mean_latency_ms(),
+            p50_latency_ms: stats.latency_quantile_ms(0.5),
+            p95_latency_ms: stats.latency_quantile_ms(0.95),
+            mean_proxy_overhead_ms: stats.mean_proxy_overhead_ms(),
+            stats,
+        }
+    }
+}
+
+/// Statistics of the rolling window.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelemetrySnapshot {
+    pub window_minutes: usize,
+    /// Start of the oldest minute in the window.
+    pub since: Option<DateTime<Utc>>,
+    pub total: EndpointSummary,
+    pub endpoints: BTreeMap<String, EndpointSummary>,
+}
+
+/// Totals of one day (JST), as written to `<date>.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub updated_at: DateTime<Utc>,
+    pub total: EndpointSummary,
+    pub endpoints: BTreeMap<String, EndpointSummary>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub window_minutes: usize,
+    pub retry_window: Duration,
+    /// `None` keeps the statistics in memory only.
+    pub output_dir: Option<PathBuf>,
+}
+
+impl TelemetryConfig {
+    pub fn from_configs(configs: &configs::ConfigsProxyTelemetry, save_path: &Path) -> Self {
+        Self {
+            window_minutes: configs.get_window_minutes(),
+            retry_window: configs.get_retry_window(),
+            output_dir: Some(
+                configs
+                    .get_output_dir()
+                    .unwrap_or_else(|| save_path.join("telemetry")),
+            ),
+        }
+    }
+}
+
+struct TelemetryState {
+    /// `(minute since the epoch, statistics)`, oldest first.
+    minutes: VecDeque<(i64, HashMap<String, EndpointStats>)>,
+    /// Recorded by this process; the writer adds the totals already on disk.
+    day: Option<(NaiveDate, HashMap<String, EndpointStats>)>,
+    last_failure: HashMap<String, DateTime<Utc>>,
+    last_flush: Option<DateTime<Utc>>,
+}
+
+/// Totals of one day recorded by this process, for the summary writer.
+struct DayTotals {
+    date: NaiveDate,
+    stats: HashMap<String, EndpointStats>,
+    updated_at: DateTime<Utc>,
+}
+
+pub struct ApiTelemetry {
+    config: TelemetryConfig,
+    state: Mutex<TelemetryState>,
+    /// `None` when there is no output directory.
+    writer: Option<mpsc::UnboundedSender<DayTotals>>,
+}
+
+impl ApiTelemetry {
+    /// Must be called inside a Tokio runtime when `config.output_dir` is set;
+    /// the summaries are written on a blocking task.
+    pub fn new(config: TelemetryConfig) -> Self {
+        let writer = config.output_dir.clone().map(|dir| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::task::spawn_blocking(move || write_summaries(rx, dir));
+            tx
+        });
+        Self {
+            config,
+            writer,
+            state: Mutex::new(TelemetryState {
+                minutes: VecDeque::new(),
+                day: None,
+                last_failure: HashMap::new(),
+                last_flush: None,
+            }),
+        }
+    }
+
+    /// Make this telemetry the one reported by [`telemetry_snapshot`].
+    pub fn register_global(self: Arc<Self>) -> Arc<Self> {
+        *ACTIVE_TELEMETRY
+            .get_or_init(|| Mutex::new(None))
+            .lock()
+            .unwrap() = Some(self.clone());
+        self
+    }
+
+    pub fn record(&self, exchange: Exchange) {
+        self.record_at(Utc::now(), exchange);
+    }
+
+    /// Record an exchange that finished at `now`.
+    pub fn record_at(&self, now: DateTime<Utc>, exchange: Exchange) {
+        let endpoint = endpoint_name(&exchange.path);
+        let date = Tokyo.from_utc_datetime(&now.naive_utc()).date_naive();
+        let minute = now.timestamp().div_euclid(60);
+
+        let mut finished_day = None;
+        let mut flush_today = None;
+        {
+            let mut state = self.state.lock().unwrap();
+
+            let started = now
+                - chrono::Duration::from_std(exchange.upstream + exchange.proxy_overhead)
+                    .unwrap_or_default();
+            let retry = match state.last_failure.get(&endpoint) {
+                Some(failed) => {
+                    *failed <= started
+                        && (started - *failed).to_std().unwrap_or_default()
+                            <= self.config.retry_window
+                }
+                None => false,
+            };
+            if exchange.outcome.is_failure() {
+                state.last_failure.insert(endpoint.clone(), now);
+            } else {
+                state.last_failure.remove(&endpoint);
+            }
+
+            if state.minutes.back().map(|(m, _)| *m) != Some(minute) {
+                state.minutes.push_back((minute, HashMap::new()));
+            }
+            self.expire(&mut state, minute);
+            if let Some((_, stats)) = state.minutes.back_mut() {
+                stats
+                    .entry(endpoint.clone())
+                    .or_default()
+                    .record(&exchange, retry);
+            }
+
+            if state.day.as_ref().map(|(day, _)| *day) != Some(date) {
+                finished_day = state.day.take();
+                state.day = Some((date, HashMap::new()));
+                state.last_flush = Some(now);
+            }
+            if let Some((_, stats)) = state.day.as_mut() {
+                stats.entry(endpoint).or_default().record(&exchange, retry);
+            }
+            let due = state.last_flush.is_none_or(|last| {
+                (now - last).to_std().unwrap_or_default() >= SUMMARY_FLUSH_INTERVAL
+            });
+            if due {
+                state.last_flush = Some(now);
+                flush_today = state.day.clone();
+            }
+        }
+
+        for (date, stats) in finished_day.into_iter().chain(flush_today) {
+            self.write_day(date, stats, now);
+        }
+    }
+
+    /// Statistics of the rolling window ending at `now`.
+    pub fn snapshot_at(&self, now: DateTime<Utc>) -> TelemetrySnapshot {
+        let mut state = self.state.lock().unwrap();
+        self.expire(&mut state, now.timestamp().div_euclid(60));
+        let mut endpoints: HashMap<String, EndpointStats> = HashMap::new();
+        for (_, stats) in &state.minutes {
+            for (endpoint, stats) in stats {
+                endpoints.entry(endpoint.clone()).or_default().merge(stats);
+            }
+        }
+        let (total, endpoints) = summarize(endpoints);
+        TelemetrySnapshot {
+            window_minutes: self.config.window_minutes,
+            since: state
+                .minutes
+                .front()
+                .and_then(|(minute, _)| DateTime::from_timestamp(minute * 60, 0)),
+            total,
+            endpoints,
+        }
+    }
+
+    pub fn snapshot(&self) -> TelemetrySnapshot {
+        self.snapshot_at(Utc::now())
+    }
+
+    /// Write the summary of the current day now, e.g. before shutting down.
+    pub fn flush(&self) {
+        let today = {
+            let mut state = self.state.lock().unwrap();
+            state.last_flush = Some(Utc::now());
+            state.day.clone()
+        };
+        if let Some((date, stats)) = today {
+            self.write_day(date, stats, Utc::now());
+        }
+    }
+
+    fn expire(&self, state: &mut TelemetryState, minute: i64) {
+        let oldest = minute - self.config.window_minutes as i64 + 1;
+        while state.minutes.front().is_some_and(|(m, _)| *m < oldest) {
+            state.minutes.pop_front();
+        }
+    }
+
+    /// Hand the totals of `date` to the summary writer.
+    fn write_day(
+        &self,
+        date: NaiveDate,
+        stats: HashMap<String, EndpointStats>,
+        now: DateTime<Utc>,
+    ) {
+        if let Some(writer) = &self.writer {
+            let _ = writer.send(DayTotals {
+                date,
+                stats,
+                updated_at: now,
+            });
+        }
+    }
+}
+
+fn summary_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("{}.json", date.format("%Y-%m-%d")))
+}
+
+/// Totals already written for `date`, so restarting the proxy keeps them.
+fn load_day(dir: &Path, date: NaiveDate) -> HashMap<String, EndpointStats> {
+    let Ok(content) = std::fs::read_to_string(summary_path(dir, date)) else {
+        return HashMap::new();
+    };
+    match serde_json::from_str::<DailySummary>(&content) {
+        Ok(summary) => summary
+            .endpoints
+            .into_iter()
+            .map(|(endpoint, summary)| (endpoint, summary.stats))
+            .collect(),
+        Err(e) => {
+            tracing::warn!("ignoring unreadable telemetry summary for {}: {}", date, e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Writes the daily summaries sent by [`ApiTelemetry`], adding the totals
+/// each file held when this process first wrote it.
+fn write_summaries(mut rx: mpsc::UnboundedReceiver<DayTotals>, dir: PathBuf) {
+    let mut loaded: Option<(NaiveDate, HashMap<String, EndpointStats>)> = None;
+    while let Some(totals) = rx.blocking_recv() {
+        // Only the latest totals of each day matter; skip the ones behind.
+        let mut latest = BTreeMap::from([(totals.date, totals)]);
+        while let Ok(totals) = rx.try_recv() {
+            latest.insert(totals.date, totals);
+        }
+        for (date, totals) in latest {
+            if loaded.as_ref().map(|(day, _)| *day) != Some(date) {
+                loaded = Some((date, load_day(&dir, date)));
+            }
+            let mut stats = loaded
+                .as_ref()
+                .map(|(_, stats)| stats.clone())
+                .unwrap_or_default();
+            for (endpoint, day_stats) in &totals.stats {
+                stats.entry(endpoint.clone()).or_default().merge(day_stats);
+            }
+            write_day(&dir, date, stats, totals.updated_at);
+        }
+    }
+}
+
+fn write_day(
+    dir: &Path,
+    date: NaiveDate,
+    stats: HashMap<String, EndpointStats>,
+    now: DateTime<Utc>,
+) {
+    let path = summary_path(dir, date);
+    let (total, endpoints) = summarize(stats);
+    let summary = DailySummary {
+        date,
+        updated_at: now,
+        total,
+        endpoints,
+    };
+    let result = std::fs::create_dir_all(dir).and_then(|_| {
+        let json = serde_json::to_vec_pretty(&summary).map_err(std::io::Error::other)?;
+        std::fs::write(&path, json)
+    });
+    if let Err(e) = result {
+        tracing::warn!(
+            "failed to write telemetry summary {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+fn endpoint_name(path: &str) -> String {
+    if path.starts_with("/kcsapi/") {
+        path.to_string()
+    } else {
+        OTHER_ENDPOINT.to_string()
+    }
+}
+
+fn summarize(
+    stats: HashMap<String, EndpointStats>,
+) -> (EndpointSummary, BTreeMap<String, EndpointSummary>) {
+    let mut total = EndpointStats::default();
+    let endpoints = stats
+        .into_iter()
+        .map(|(endpoint, stats)| {
+            total.merge(&stats);
+            (endpoint, EndpointSummary::from(stats))
+        })
+        .collect();
+    (total.into(), endpoints)
+}
+
+/// Statistics of the telemetry used by the running proxy, if any.
+pub fn telemetry_snapshot() -> Option<TelemetrySnapshot> {
+    ACTIVE_TELEMETRY.get().and_then(|active| {
+        active
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|telemetry| telemetry.snapshot())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(path: &str, outcome: ExchangeOutcome, upstream_ms: u64) -> Exchange {
+        Exchange {
+            path: path.to_string(),
+            outcome,
+            upstream: Duration::from_millis(upstream_ms),
+            proxy_overhead: Duration::from_millis(2),
+        }
+    }
+
+    /// Wait for the summary writer to write `file` with `requests` in total.
+    async fn written_summary(dir: &Path, file: &str, requests: u64) -> DailySummary {
+        loop {
+            let written = std::fs::read_to_string(dir.join(file))
+                .ok()
+                .and_then(|content| serde_json::from_str::<DailySummary>(&content).ok())
+                .filter(|summary| summary.total.stats.requests == requests);
+            if let Some(written) = written {
+                return written;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn records_window_retries_and_daily_summary() {
+        let dir =
+            std::env::temp_dir().join(format!("fusou-telemetry-test-{}", uuid::Uuid::new_v4()));
+        let telemetry = ApiTelemetry::new(TelemetryConfig {
+            window_minutes: 10,
+            retry_window: Duration::from_secs(30),
+            output_dir: Some(dir.clone()),
+        });
+        let start = Utc.with_ymd_and_hms(2026, 5, 1, 3, 0, 0).unwrap();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+        let port = "/kcsapi/api_port/port";
+
+        telemetry.record_at(at(0), exchange(port, ExchangeOutcome::Status(200), 40));
+        telemetry.record_at(at(5), exchange(port, ExchangeOutcome::UpstreamError, 5000));
+        // The client tries again right away.
+        telemetry.record_at(at(10), exchange(port, ExchangeOutcome::Status(502), 300));
+        telemetry.record_at(at(20), exchange(port, ExchangeOutcome::Status(200), 3000));
+        telemetry.record_at(at(120), exchange(port, ExchangeOutcome::Status(200), 60));
+        telemetry.record_at(
+            at(130),
+            exchange("/kcs2/img/a.png", ExchangeOutcome::Status(200), 10),
+        );
+
+        let snapshot = telemetry.snapshot_at(at(130));
+        let port_stats = &snapshot.endpoints[port];
+        assert_eq!(port_stats.stats.requests, 5);
+        assert_eq!(port_stats.stats.retries, 2);
+        assert_eq!(port_stats.stats.upstream_errors, 1);
+        assert_eq!(
+            port_stats.stats.status_counts,
+            BTreeMap::from([(200, 3), (502, 1)])
+        );
+        assert_eq!(port_stats.mean_latency_ms, Some(850));
+        assert_eq!(port_stats.p50_latency_ms, Some(100));
+        assert_eq!(port_stats.p95_latency_ms, Some(3000));
+        assert_eq!(port_stats.stats.latency_max_ms, 3000);
+        assert_eq!(snapshot.endpoints[OTHER_ENDPOINT].stats.requests, 1);
+        assert_eq!(snapshot.total.stats.requests, 6);
+        assert_eq!(snapshot.since, Some(start));
+
+        // Minutes older than the window are dropped.
+        let later = telemetry.snapshot_at(at(10 * 60 + 30));
+        assert_eq!(later.total.stats.requests, 2);
+
+        // The day changes at midnight JST (15:00 UTC).
+        telemetry.record_at(
+            Utc.with_ymd_and_hms(2026, 5, 1, 15, 0, 0).unwrap(),
+            exchange(port, ExchangeOutcome::Status(200), 40),
+        );
+        let written = written_summary(&dir, "2026-05-01.json", 6).await;
+        assert_eq!(written.endpoints[port].stats.retries, 2);
+
+        // A restarted proxy continues the totals of the day.
+        telemetry.flush();
+        written_summary(&dir, "2026-05-02.json", 1).await;
+        let restarted = ApiTelemetry::new(telemetry.config.clone());
+        restarted.record_at(
+            Utc.with_ymd_and_hms(2026, 5, 1, 16, 0, 0).unwrap(),
+            exchange(port, ExchangeOutcome::Status(200), 40),
+        );
+        restarted.flush();
+        written_summary(&dir, "2026-05-02.json", 2).await;
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
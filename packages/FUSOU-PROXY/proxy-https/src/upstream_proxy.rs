@@ -0,0 +1,441 @@
+//! Upstream proxy chaining for the outgoing side of the proxy.
+//!
+//! When `[proxy.network.upstream]` is configured, connections to the game
+//! servers are opened through another proxy (HTTP CONNECT or SOCKS5) instead
+//! of directly, so FUSOU can sit in front of a corporate proxy, another viewer
+//! or a SOCKS tunnel. Hosts matching the bypass rules are still connected
+//! directly.
+
+use std::error::Error as StdError;
+use std::future::{poll_fn, Future};
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use base64::Engine;
+use configs::ConfigsProxyNetworkUpstream;
+use http::{HeaderValue, Uri};
+use hyper_util::client::legacy::connect::proxy::{SocksV5, Tunnel};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpStream;
+use tower_service::Service;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamProxyKind {
+    /// HTTP proxy; every destination is tunnelled with `CONNECT`.
+    HttpConnect,
+    Socks5,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamProxy {
+    pub kind: UpstreamProxyKind,
+    pub host: String,
+    pub port: u16,
+    pub credentials: Option<(String, String)>,
+    pub bypass: Vec<String>,
+    pub socks5_local_dns: bool,
+}
+
+impl UpstreamProxy {
+    /// `Ok(None)` when no upstream proxy is configured.
+    pub fn from_configs(upstream: &ConfigsProxyNetworkUpstream) -> Result<Option<Self>, String> {
+        let Some(kind) = upstream.get_kind() else {
+            return Ok(None);
+        };
+        let kind = match kind.as_str() {
+            "http" => UpstreamProxyKind::HttpConnect,
+            "socks5" => UpstreamProxyKind::Socks5,
+            other => return Err(format!("unsupported upstream proxy kind: {other}")),
+        };
+        let host = upstream
+            .get_host()
+            .ok_or("upstream proxy requires proxy.network.upstream.host")?;
+        let port = match upstream.get_port() {
+            0 => return Err("upstream proxy requires proxy.network.upstream.port".to_string()),
+            port => port,
+        };
+        let credentials = match (upstream.get_username(), upstream.get_password()) {
+            (None, None) => None,
+            (username, password) => {
+                Some((username.unwrap_or_default(), password.unwrap_or_default()))
+            }
+        };
+
+        Ok(Some(Self {
+            kind,
+            host,
+            port,
+            credentials,
+            bypass: upstream.get_bypass(),
+            socks5_local_dns: upstream.get_socks5_local_dns(),
+        }))
+    }
+
+    /// Whether `host` is connected directly. A domain rule also matches its
+    /// subdomains (`example.com`, `.example.com` and `*.example.com` are
+    /// equivalent) and `*` matches every host.
+    pub fn bypasses(&self, host: &str) -> bool {
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+        let host_ip = host.parse::<IpAddr>().ok();
+
+        self.bypass.iter().any(|rule| {
+            let rule = rule.trim().to_ascii_lowercase();
+            if rule == "*" {
+                return true;
+            }
+            let rule = rule.trim_start_matches('[').trim_end_matches(']');
+            if let Ok(rule_ip) = rule.parse::<IpAddr>() {
+                return host_ip == Some(rule_ip);
+            }
+            let domain = rule.trim_start_matches("*.").trim_start_matches('.');
+            !domain.is_empty()
+                && (host == domain
+                    || host
+                        .strip_suffix(domain)
+                        .is_some_and(|prefix| prefix.ends_with('.')))
+        })
+    }
+
+    fn proxy_uri(&self) -> Result<Uri, BoxError> {
+        let host = match self.host.parse::<IpAddr>() {
+            Ok(IpAddr::V6(ip)) => format!("[{ip}]"),
+            _ => self.host.clone(),
+        };
+        Ok(format!("http://{}:{}", host, self.port).parse()?)
+    }
+
+    fn basic_auth(&self) -> Option<HeaderValue> {
+        let (username, password) = self.credentials.as_ref()?;
+        let encoded =
+            base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+        HeaderValue::from_str(&format!("Basic {encoded}")).ok()
+    }
+}
+
+/// Connector handed to the hyper client: connects directly, or through the
+/// configured upstream proxy for hosts that are not bypassed.
+#[derive(Clone)]
+pub struct UpstreamConnector {
+    direct: HttpConnector,
+    upstream: Option<Arc<UpstreamProxy>>,
+}
+
+impl UpstreamConnector {
+    pub fn new(direct: HttpConnector, upstream: Option<UpstreamProxy>) -> Self {
+        Self {
+            direct,
+            upstream: upstream.map(Arc::new),
+        }
+    }
+
+    async fn connect_via(
+        mut direct: HttpConnector,
+        upstream: Arc<UpstreamProxy>,
+        dst: Uri,
+    ) -> Result<TokioIo<TcpStream>, BoxError> {
+        // The proxy address is always dialled as plain TCP.
+        direct.enforce_http(false);
+        let proxy_uri = upstream.proxy_uri()?;
+        // Both handshakes default to port 443 when the destination has none.
+        let dst = with_explicit_port(dst)?;
+
+        match upstream.kind {
+            UpstreamProxyKind::HttpConnect => {
+                let mut tunnel = Tunnel::new(proxy_uri, direct);
+                if let Some(auth) = upstream.basic_auth() {
+                    tunnel = tunnel.with_auth(auth);
+                }
+                poll_fn(|cx| tunnel.poll_ready(cx)).await?;
+                Ok(tunnel.call(dst).await?)
+            }
+            UpstreamProxyKind::Socks5 => {
+                let mut socks =
+                    SocksV5::new(proxy_uri, direct).local_dns(upstream.socks5_local_dns);
+                if let Some((username, password)) = upstream.credentials.clone() {
+                    socks = socks.with_auth(username, password);
+                }
+                poll_fn(|cx| socks.poll_ready(cx)).await?;
+                Ok(socks.call(dst).await?)
+            }
+        }
+    }
+}
+
+fn with_explicit_port(dst: Uri) -> Result<Uri, BoxError> {
+    if dst.port().is_some() {
+        return Ok(dst);
+    }
+    let host = dst.host().ok_or("destination has no host")?;
+    let port = match dst.scheme_str() {
+        Some("http") => 80,
+        _ => 443,
+    };
+    let mut parts = dst.clone().into_parts();
+    parts.authority = Some(format!("{host}:{port}").parse()?);
+    Ok(Uri::from_parts(parts)?)
+}
+
+impl Service<Uri> for UpstreamConnector {
+    type Response = TokioIo<TcpStream>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.direct.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let upstream = self
+            .upstream
+            .clone()
+            .filter(|upstream| !dst.host().is_some_and(|host| upstream.bypasses(host)));
+        match upstream {
+            Some(upstream) => {
+                tracing::debug!(dst = %dst, proxy = %upstream.host, "connecting via upstream proxy");
+                Box::pin(Self::connect_via(self.direct.clone(), upstream, dst))
+            }
+            None => {
+                let connecting = self.direct.call(dst);
+                Box::pin(async move { connecting.await.map_err(Into::into) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::{BodyExt, Empty};
+    use hyper::body::Bytes;
+    use hyper_util::client::legacy::Client;
+    use hyper_util::rt::TokioExecutor;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// Origin server answering every connection with a fixed body.
+    async fn spawn_origin() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\ncontent-length: 6\r\nconnection: close\r\n\r\norigin",
+                        )
+                        .await;
+                });
+            }
+        });
+        addr
+    }
+
+    /// Stand-in HTTP proxy: reports each CONNECT request head, then tunnels.
+    async fn spawn_connect_proxy() -> (SocketAddr, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut byte = [0u8; 1];
+                    while !head.ends_with(b"\r\n\r\n") {
+                        if client.read(&mut byte).await.unwrap_or(0) == 0 {
+                            return;
+                        }
+                        head.push(byte[0]);
+                    }
+                    let head = String::from_utf8_lossy(&head).to_string();
+                    let target = head
+                        .split_whitespace()
+                        .nth(1)
+                        .unwrap_or_default()
+                        .to_string();
+                    let _ = tx.send(head);
+                    let mut upstream = TcpStream::connect(target).await.unwrap();
+                    client
+                        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                        .await
+                        .unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+                });
+            }
+        });
+        (addr, rx)
+    }
+
+    /// Stand-in SOCKS5 proxy requiring username/password authentication.
+    /// Reports the credentials it was given, then tunnels.
+    async fn spawn_socks5_proxy() -> (SocketAddr, mpsc::UnboundedReceiver<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let mut greeting = [0u8; 2];
+                    client.read_exact(&mut greeting).await.unwrap();
+                    let mut methods = vec![0u8; greeting[1] as usize];
+                    client.read_exact(&mut methods).await.unwrap();
+                    assert!(methods.contains(&0x02), "client must offer user/pass auth");
+                    client.write_all(&[0x05, 0x02]).await.unwrap();
+
+                    let mut version_and_len = [0u8; 2];
+                    client.read_exact(&mut version_and_len).await.unwrap();
+                    let mut username = vec![0u8; version_and_len[1] as usize];
+                    client.read_exact(&mut username).await.unwrap();
+                    let mut password_len = [0u8; 1];
+                    client.read_exact(&mut password_len).await.unwrap();
+                    let mut password = vec![0u8; password_len[0] as usize];
+                    client.read_exact(&mut password).await.unwrap();
+                    let _ = tx.send((
+                        String::from_utf8_lossy(&username).to_string(),
+                        String::from_utf8_lossy(&password).to_string(),
+                    ));
+                    client.write_all(&[0x01, 0x00]).await.unwrap();
+
+                    let mut request = [0u8; 4];
+                    client.read_exact(&mut request).await.unwrap();
+                    assert_eq!(request[3], 0x01, "test destinations are IPv4 literals");
+                    let mut ip = [0u8; 4];
+                    client.read_exact(&mut ip).await.unwrap();
+                    let mut port = [0u8; 2];
+                    client.read_exact(&mut port).await.unwrap();
+                    let target = SocketAddr::from((ip, u16::from_be_bytes(port)));
+                    let mut upstream = TcpStream::connect(target).await.unwrap();
+                    client
+                        .write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                        .await
+                        .unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+                });
+            }
+        });
+        (addr, rx)
+    }
+
+    fn upstream(kind: UpstreamProxyKind, addr: SocketAddr, bypass: &[&str]) -> UpstreamProxy {
+        UpstreamProxy {
+            kind,
+            host: addr.ip().to_string(),
+            port: addr.port(),
+            credentials: Some(("fusou".to_string(), "secret".to_string())),
+            bypass: bypass.iter().map(|rule| rule.to_string()).collect(),
+            socks5_local_dns: false,
+        }
+    }
+
+    async fn get(connector: UpstreamConnector, origin: SocketAddr) -> String {
+        let client = Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(connector);
+        let response = client
+            .get(
+                format!("http://{origin}/kcsapi/api_port/port")
+                    .parse()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8_lossy(&body).to_string()
+    }
+
+    #[tokio::test]
+    async fn http_connect_upstream_tunnels_with_basic_auth() {
+        let origin = spawn_origin().await;
+        let (proxy, mut heads) = spawn_connect_proxy().await;
+        let connector = UpstreamConnector::new(
+            HttpConnector::new(),
+            Some(upstream(UpstreamProxyKind::HttpConnect, proxy, &[])),
+        );
+
+        assert_eq!(get(connector, origin).await, "origin");
+        let head = heads.recv().await.unwrap();
+        assert!(head.starts_with(&format!("CONNECT {origin} HTTP/1.1")));
+        // base64("fusou:secret")
+        assert!(head.to_ascii_lowercase().contains(
+            "proxy-authorization: basic ZnVzb3U6c2VjcmV0"
+                .to_ascii_lowercase()
+                .as_str()
+        ));
+    }
+
+    #[tokio::test]
+    async fn socks5_upstream_tunnels_with_credentials() {
+        let origin = spawn_origin().await;
+        let (proxy, mut credentials) = spawn_socks5_proxy().await;
+        let connector = UpstreamConnector::new(
+            HttpConnector::new(),
+            Some(upstream(UpstreamProxyKind::Socks5, proxy, &[])),
+        );
+
+        assert_eq!(get(connector, origin).await, "origin");
+        assert_eq!(
+            credentials.recv().await.unwrap(),
+            ("fusou".to_string(), "secret".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn bypassed_hosts_connect_directly() {
+        let origin = spawn_origin().await;
+        // Nothing listens here, so only a direct connection can succeed.
+        let dead_proxy = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let connector = UpstreamConnector::new(
+            HttpConnector::new(),
+            Some(upstream(
+                UpstreamProxyKind::HttpConnect,
+                dead_proxy,
+                &["127.0.0.1"],
+            )),
+        );
+
+        assert_eq!(get(connector, origin).await, "origin");
+    }
+
+    #[test]
+    fn bypass_rules_match_domains_and_ips() {
+        let proxy = UpstreamProxy {
+            kind: UpstreamProxyKind::Socks5,
+            host: "proxy.local".to_string(),
+            port: 1080,
+            credentials: None,
+            bypass: vec!["example.com".into(), "*.kancolle.test".into(), "::1".into()],
+            socks5_local_dns: false,
+        };
+        assert!(proxy.bypasses("example.com"));
+        assert!(proxy.bypasses("www.Example.com"));
+        assert!(!proxy.bypasses("notexample.com"));
+        assert!(proxy.bypasses("w01.kancolle.test"));
+        assert!(proxy.bypasses("[::1]"));
+        assert!(!proxy.bypasses("127.0.0.1"));
+    }
+
+    #[test]
+    fn default_configs_have_no_upstream() {
+        let configs = configs::get_user_configs_for_proxy();
+        assert_eq!(
+            UpstreamProxy::from_configs(&configs.network.upstream),
+            Ok(None)
+        );
+    }
+}
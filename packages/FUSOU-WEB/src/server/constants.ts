@@ -16,6 +16,13 @@ export const CORS_HEADERS: Record<string, string> = {
 };
 
 export const SIGNED_URL_TTL_SECONDS = 120;
+
+// Chunked (multipart) two-stage uploads: parts are staged in the target bucket
+// under MULTIPART_STAGING_PREFIX and assembled when the client sends "complete".
+export const MULTIPART_STAGING_PREFIX = "__multipart";
+export const MULTIPART_MIN_PART_BYTES = 256 * 1024;
+export const MULTIPART_MAX_PART_BYTES = 32 * 1024 * 1024;
+export const MULTIPART_MAX_PARTS = 1000;
 export const SNAPSHOT_TOKEN_TTL_SECONDS = 300;
 // Threshold for treating snapshot payload as empty and skipping upload
 export const SNAPSHOT_EMPTY_PAYLOAD_THRESHOLD_BYTES = 100;
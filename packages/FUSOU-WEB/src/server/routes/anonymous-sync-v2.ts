@@ -8,6 +8,7 @@
  *   3. 端末は Ed25519 keypair をローカル生成・保管し、`/v2/register` で公開鍵を登録する
  *   4. 以降の `/v2/refresh` は stateless challenge nonce に対する署名で本人性を担保
  *   5. `/v2/revoke` は別の自端末から既存端末を失効させる経路 (端末紛失時の自己復旧用)
+ *   6. `/v2/rotate` は現在の鍵の署名を条件に端末の公開鍵を差し替える
  *
  * 旧 `/anonymous-sync` (v1) は salt をクライアントが持って `member_id_hash` を計算する
  * 設計だったが、salt 漏洩で任意アカウントの dataset_token を取得できる弱点があった。
@@ -363,7 +364,7 @@ async function consumeDeviceNonce(options: {
   supabaseAdmin: any;
   deviceId: string;
   nonce: string;
-  context: "refresh" | "revoke";
+  context: "refresh" | "revoke" | "rotate";
 }): Promise<{ ok: true } | { ok: false; status: 401 | 500; error: string }> {
   const nowMs = Date.now();
   const nonceInsert = await options.supabaseAdmin
@@ -1907,6 +1908,178 @@ app.post("/anonymous-sync/v2/revoke", async (c) => {
   }
 });
 
+// ========================
+// POST /anonymous-sync/v2/rotate
+// ========================
+//
+// message = "rotate|" + device_id + "|" + new_device_pub + "|" + nonce を
+// sig (登録済みの鍵) と new_sig (新しい鍵) の両方で署名して送る。
+// 登録済みの鍵が既に new_device_pub なら差し替え済みとして 200 を返す。
+// 応答を受け取れなかったクライアントは新しい鍵で sig を作り直して再送すれば、
+// サーバーがどちらの鍵を持っているかを確認できる。
+
+app.post("/anonymous-sync/v2/rotate", async (c) => {
+  try {
+    const body = await c.req.json().catch(() => null);
+    if (!body || typeof body !== "object") {
+      return c.json({ error: "invalid_json" }, 400);
+    }
+
+    const deviceId = normalizeDeviceId((body as any).device_id);
+    if (!deviceId) {
+      return c.json({ error: "device_id must be a UUID" }, 400);
+    }
+
+    const newPubkey = normalizePubkey((body as any).new_device_pub);
+    if (!newPubkey) {
+      return c.json(
+        { error: "new_device_pub must be a base64 32-byte Ed25519 key" },
+        400,
+      );
+    }
+
+    const nonce =
+      typeof (body as any).nonce === "string"
+        ? (body as any).nonce.trim().toLowerCase()
+        : "";
+    if (!/^[a-f0-9]{64}$/.test(nonce)) {
+      return c.json({ error: "nonce malformed" }, 400);
+    }
+
+    const sig = (body as any).sig;
+    const newSig = (body as any).new_sig;
+    if (
+      typeof sig !== "string" ||
+      sig.length === 0 ||
+      typeof newSig !== "string" ||
+      newSig.length === 0
+    ) {
+      return c.json({ error: "sig and new_sig are required" }, 400);
+    }
+
+    const base = resolveBaseConfig(c);
+    if (!base.ok) {
+      console.error("[anonymous-sync-v2/rotate] config invalid:", base.reason);
+      return c.json({ error: "Server configuration error" }, 500);
+    }
+
+    const challengeResolved = resolveChallengeSecret(c);
+    if (!challengeResolved.ok) {
+      console.error(
+        "[anonymous-sync-v2/rotate] challenge secret invalid:",
+        challengeResolved.reason,
+      );
+      return c.json({ error: "Server configuration error" }, 500);
+    }
+
+    const nonceValid = await verifyChallengeNonce(
+      challengeResolved.secret,
+      deviceId,
+      nonce,
+    );
+    if (!nonceValid) {
+      return c.json({ error: "nonce_invalid_or_expired" }, 401);
+    }
+
+    const supabaseAdmin = createClient(
+      base.config.supabaseUrl,
+      base.config.serviceRoleKey,
+    );
+
+    const { data: device, error: deviceErr } = await supabaseAdmin
+      .from("user_devices")
+      .select("device_pubkey, revoked_at")
+      .eq("device_id", deviceId)
+      .maybeSingle<{ device_pubkey: string; revoked_at: string | null }>();
+    if (deviceErr) {
+      console.error("[anonymous-sync-v2/rotate] lookup failed:", deviceErr);
+      return c.json({ error: "Database error" }, 500);
+    }
+    if (!device || device.revoked_at) {
+      return c.json({ error: "device_unknown_or_revoked" }, 404);
+    }
+
+    const hexBody = device.device_pubkey.startsWith("\\x")
+      ? device.device_pubkey.slice(2)
+      : device.device_pubkey;
+    if (hexBody.length !== 64 || !/^[0-9a-fA-F]+$/.test(hexBody)) {
+      console.error(
+        "[anonymous-sync-v2/rotate] device_pubkey not a 32-byte hex value",
+      );
+      return c.json({ error: "Internal server error" }, 500);
+    }
+    const currentPubBytes = new Uint8Array(32);
+    for (let i = 0; i < 32; i++) {
+      currentPubBytes[i] = parseInt(hexBody.slice(i * 2, i * 2 + 2), 16);
+    }
+    const currentPubB64 = encodeBytesToBase64(currentPubBytes);
+
+    const message = `rotate|${deviceId}|${newPubkey.base64}|${nonce}`;
+    const sigValid = await verifyDeviceSig({
+      publicKeyB64: currentPubB64,
+      message,
+      signatureB64: sig,
+    });
+    if (!sigValid) {
+      return c.json({ error: "signature_invalid" }, 401);
+    }
+    const newSigValid = await verifyDeviceSig({
+      publicKeyB64: newPubkey.base64,
+      message,
+      signatureB64: newSig,
+    });
+    if (!newSigValid) {
+      return c.json({ error: "new_signature_invalid" }, 401);
+    }
+
+    const nonceConsume = await consumeDeviceNonce({
+      supabaseAdmin,
+      deviceId,
+      nonce,
+      context: "rotate",
+    });
+    if (!nonceConsume.ok) {
+      return c.json({ error: nonceConsume.error }, nonceConsume.status);
+    }
+
+    if (currentPubB64 === newPubkey.base64) {
+      return c.json({ device_id: deviceId, rotated: false });
+    }
+
+    const newPubkeyHex = `\\x${Array.from(newPubkey.raw)
+      .map((b) => b.toString(16).padStart(2, "0"))
+      .join("")}`;
+
+    // 読み取った鍵のままの行だけを更新し、並行する rotate との競合で鍵を取り違えない
+    const { data: updated, error: updateErr } = await supabaseAdmin
+      .from("user_devices")
+      .update({ device_pubkey: newPubkeyHex })
+      .eq("device_id", deviceId)
+      .eq("device_pubkey", device.device_pubkey)
+      .is("revoked_at", null)
+      .select("device_id");
+    if (updateErr) {
+      if ((updateErr as any).code === "23505") {
+        return c.json({ error: "device_pub_conflict" }, 409);
+      }
+      console.error(
+        "[anonymous-sync-v2/rotate] device_pubkey UPDATE failed:",
+        updateErr,
+      );
+      return c.json({ error: "Failed to rotate device key" }, 500);
+    }
+    if (!updated || updated.length === 0) {
+      return c.json({ error: "rotation_conflict" }, 409);
+    }
+
+    console.log(`[anonymous-sync-v2/rotate] ok device=${deviceId}`);
+    return c.json({ device_id: deviceId, rotated: true });
+  } catch (err) {
+    console.error("[anonymous-sync-v2/rotate] unexpected error:", err);
+    return c.json({ error: "Internal server error" }, 500);
+  }
+});
+
 app.delete("/anonymous-sync/v2/devices/:deviceId", async (c) => {
   try {
     const accessToken = extractAccessToken(c);
@@ -1,5 +1,12 @@
 import type { R2BucketBinding } from "../types";
-import { SIGNED_URL_TTL_SECONDS } from "../constants";
+import {
+  MAX_UPLOAD_BYTES,
+  MULTIPART_MAX_PART_BYTES,
+  MULTIPART_MAX_PARTS,
+  MULTIPART_MIN_PART_BYTES,
+  MULTIPART_STAGING_PREFIX,
+  SIGNED_URL_TTL_SECONDS,
+} from "../constants";
 
 /**
  * Common two-stage upload handler for secure, hash-verified uploads
@@ -64,6 +71,15 @@ function extractBearerToken(request: Request): string | null {
  *   - Verifies content hash (SHA-256)
  *   - Runs custom processing via executionProcessor
  *   - Uploads to R2 bucket
+ *
+ * Multipart variant (large payloads, resumable):
+ *   - Stage 1 body carries `multipart: { part_size, part_count, resume_upload_id? }`
+ *     and the response echoes `multipart: { uploadId, partSize, partCount }`.
+ *     Passing a previous uploadId as resume_upload_id keeps already staged parts.
+ *   - Stage 2 requests all carry X-Upload-Token and are one of:
+ *       X-Upload-Part: n (1-based) + X-Part-Sha256  -> stage part n
+ *       X-Upload-Action: status                     -> { parts: number[] }
+ *       X-Upload-Action: complete                   -> assemble, then executionProcessor
  */
 export async function handleTwoStageUpload(
   c: any,
@@ -164,7 +180,25 @@ async function handlePreparation(
     return validationResult; // Validation error
   }
 
-  const { tokenPayload = {}, fields } = validationResult;
+  const { fields } = validationResult;
+  let tokenPayload: Record<string, any> = validationResult.tokenPayload ?? {};
+
+  let multipart: MultipartPlan | null = null;
+  if (body?.multipart !== undefined && body?.multipart !== null) {
+    multipart = parseMultipartPlan(body.multipart);
+    if (!multipart) {
+      return c.json(
+        { error: "Invalid multipart parameters", code: "MULTIPART_INVALID" },
+        400,
+      );
+    }
+    tokenPayload = {
+      ...tokenPayload,
+      multipart_upload_id: multipart.uploadId,
+      multipart_part_size: multipart.partSize,
+      multipart_part_count: multipart.partCount,
+    };
+  }
 
   // [Issue #15] UPDATED: Dynamic TTL based on expected file size
   // Large files need more time to upload to R2 and process
@@ -219,9 +253,170 @@ async function handlePreparation(
     token: signedToken,
     expiresAt: new Date(Date.now() + effectiveTTL * 1000).toISOString(),
     ...(fields && { fields }),
+    ...(multipart && { multipart }),
   });
 }
 
+interface MultipartPlan {
+  uploadId: string;
+  partSize: number;
+  partCount: number;
+}
+
+const UPLOAD_ID_PATTERN =
+  /^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$/;
+
+function parseMultipartPlan(raw: any): MultipartPlan | null {
+  const partSize = Number(raw?.part_size);
+  const partCount = Number(raw?.part_count);
+  if (
+    !Number.isInteger(partSize) ||
+    partSize < MULTIPART_MIN_PART_BYTES ||
+    partSize > MULTIPART_MAX_PART_BYTES ||
+    !Number.isInteger(partCount) ||
+    partCount < 1 ||
+    partCount > MULTIPART_MAX_PARTS ||
+    partSize * partCount > MAX_UPLOAD_BYTES + partSize
+  ) {
+    return null;
+  }
+  const resumeId =
+    typeof raw?.resume_upload_id === "string"
+      ? raw.resume_upload_id.toLowerCase()
+      : null;
+  return {
+    uploadId:
+      resumeId && UPLOAD_ID_PATTERN.test(resumeId)
+        ? resumeId
+        : crypto.randomUUID(),
+    partSize,
+    partCount,
+  };
+}
+
+async function sha256Hex(data: Uint8Array): Promise<string> {
+  const digest = await crypto.subtle.digest("SHA-256", data);
+  return Array.from(new Uint8Array(digest))
+    .map((b) => b.toString(16).padStart(2, "0"))
+    .join("");
+}
+
+/**
+ * Stage 2 for multipart uploads. Parts are staged per acting user so an
+ * uploadId cannot be used to read or complete another user's upload.
+ */
+async function handleMultipartExecution(
+  c: any,
+  request: Request,
+  config: UploadConfig,
+  tokenPayload: Record<string, any>,
+  actingUser: { id: string; [key: string]: any },
+): Promise<Response> {
+  const { bucket, executionProcessor } = config;
+  const uploadId = String(tokenPayload.multipart_upload_id);
+  const partSize = Number(tokenPayload.multipart_part_size);
+  const partCount = Number(tokenPayload.multipart_part_count);
+  const prefix = `${MULTIPART_STAGING_PREFIX}/${actingUser.id}/${uploadId}/`;
+
+  const partHeader = request.headers.get("X-Upload-Part");
+  if (partHeader !== null) {
+    const partNumber = Number(partHeader);
+    if (
+      !Number.isInteger(partNumber) ||
+      partNumber < 1 ||
+      partNumber > partCount
+    ) {
+      return c.json(
+        { error: "Invalid part number", code: "PART_INVALID" },
+        400,
+      );
+    }
+    const data = new Uint8Array(await request.arrayBuffer());
+    if (data.byteLength === 0 || data.byteLength > partSize) {
+      return c.json({ error: "Invalid part size", code: "PART_INVALID" }, 400);
+    }
+    const expected = request.headers.get("X-Part-Sha256")?.toLowerCase();
+    const actual = await sha256Hex(data);
+    if (!expected || expected !== actual) {
+      return c.json(
+        { error: "Part hash mismatch", code: "PART_HASH_MISMATCH" },
+        400,
+      );
+    }
+    await bucket.put(`${prefix}${partNumber}`, data, {
+      customMetadata: { sha256: actual },
+    });
+    return c.json({ part: partNumber, sha256: actual });
+  }
+
+  const action = request.headers.get("X-Upload-Action");
+  if (action === "status") {
+    const listed = await bucket.list({ prefix });
+    const parts = listed.objects
+      .map((object) => Number(object.key.slice(prefix.length)))
+      .filter((n) => Number.isInteger(n) && n >= 1 && n <= partCount)
+      .sort((a, b) => a - b);
+    return c.json({ uploadId, parts });
+  }
+
+  if (action !== "complete") {
+    return c.json(
+      {
+        error: "Missing X-Upload-Part or X-Upload-Action",
+        code: "MULTIPART_ACTION_MISSING",
+      },
+      400,
+    );
+  }
+
+  const chunks: Uint8Array[] = [];
+  let total = 0;
+  for (let partNumber = 1; partNumber <= partCount; partNumber++) {
+    const object = await bucket.get(`${prefix}${partNumber}`);
+    if (!object) {
+      return c.json(
+        {
+          error: `Part ${partNumber} has not been uploaded`,
+          code: "PART_MISSING",
+        },
+        400,
+      );
+    }
+    const chunk = new Uint8Array(await object.arrayBuffer());
+    total += chunk.byteLength;
+    if (total > MAX_UPLOAD_BYTES) {
+      return c.json({ error: "Upload too large" }, 413);
+    }
+    chunks.push(chunk);
+  }
+  const data = new Uint8Array(total);
+  let offset = 0;
+  for (const chunk of chunks) {
+    data.set(chunk, offset);
+    offset += chunk.byteLength;
+  }
+
+  try {
+    const processingResult = await executionProcessor(
+      tokenPayload,
+      data,
+      actingUser,
+    );
+    if (processingResult instanceof Response) {
+      return processingResult;
+    }
+    if (bucket.delete) {
+      for (let partNumber = 1; partNumber <= partCount; partNumber++) {
+        await bucket.delete(`${prefix}${partNumber}`);
+      }
+    }
+    return c.json(processingResult.response);
+  } catch (error) {
+    console.error("[Upload] Multipart completion error:", error);
+    return c.json({ error: "Upload failed" }, 500);
+  }
+}
+
 async function handleExecution(
   c: any,
   request: Request,
@@ -288,6 +483,16 @@ async function handleExecution(
   // actingUser は upload token 内の user_id（dataset_token.sub）を使用する。
   const actingUser = { id: tokenUserId } as { id: string; [key: string]: any };
 
+  if (tokenPayload.multipart_upload_id) {
+    return await handleMultipartExecution(
+      c,
+      request,
+      config,
+      tokenPayload,
+      actingUser,
+    );
+  }
+
   // Read body
   const bodyStream = request.body;
   if (!bodyStream) {
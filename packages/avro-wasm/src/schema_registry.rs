@@ -16,6 +16,10 @@ static SCHEMA_V0_5_JSON: &str = include_str!("../../kc_api/generated-schemas/sch
 static SCHEMA_V0_5_1_JSON: &str =
     include_str!("../../kc_api/generated-schemas/schema_v0_5_1.json");
 
+#[cfg(feature = "schema_v0_5_2")]
+static SCHEMA_V0_5_2_JSON: &str =
+    include_str!("../../kc_api/generated-schemas/schema_v0_5_2.json");
+
 // #[cfg(feature = "schema_v0_6")]
 // static SCHEMA_V0_6_JSON: &str = include_str!("../../kc_api/generated-schemas/schema_v0_6.json");
 
@@ -81,6 +85,8 @@ fn get_schema_set(version: &str) -> Option<SchemaSet> {
         "v0_5" => Some(load_schema_set(SCHEMA_V0_5_JSON, "v0_5")),
         #[cfg(feature = "schema_v0_5_1")]
         "v0_5_1" => Some(load_schema_set(SCHEMA_V0_5_1_JSON, "v0_5_1")),
+        #[cfg(feature = "schema_v0_5_2")]
+        "v0_5_2" => Some(load_schema_set(SCHEMA_V0_5_2_JSON, "v0_5_2")),
         // #[cfg(feature = "schema_v0_6")]
         // "v0_6" => Some(load_schema_set(SCHEMA_V0_6_JSON, "v0_6")),
         _ => None,
@@ -101,6 +107,10 @@ fn get_all_schema_sets_internal() -> Vec<SchemaSet> {
     if let Some(s) = get_schema_set("v0_5_1") {
         sets.push(s);
     }
+    #[cfg(feature = "schema_v0_5_2")]
+    if let Some(s) = get_schema_set("v0_5_2") {
+        sets.push(s);
+    }
     // #[cfg(feature = "schema_v0_6")]
     // if let Some(s) = get_schema_set("v0_6") {
     //     sets.push(s);
@@ -309,6 +319,8 @@ pub fn get_available_versions() -> Vec<String> {
     versions.push("v0_5".to_string());
     #[cfg(feature = "schema_v0_5_1")]
     versions.push("v0_5_1".to_string());
+    #[cfg(feature = "schema_v0_5_2")]
+    versions.push("v0_5_2".to_string());
     // #[cfg(feature = "schema_v0_6")]
     // versions.push("v0_6".to_string());
     versions
@@ -364,6 +364,8 @@ mod tests {
         assert!(versions.contains(&"v0_5".to_string()));
         #[cfg(feature = "schema_v0_5_1")]
         assert!(versions.contains(&"v0_5_1".to_string()));
+        #[cfg(feature = "schema_v0_5_2")]
+        assert!(versions.contains(&"v0_5_2".to_string()));
         // #[cfg(feature = "schema_v0_6")]
         // assert!(versions.contains(&"v0_6".to_string()));
     }
@@ -389,6 +391,13 @@ mod tests {
         assert!(!schemas.is_empty());
     }
 
+    #[test]
+    #[cfg(feature = "schema_v0_5_2")]
+    fn test_get_available_schemas_v0_5_2() {
+        let schemas = schema_registry::get_available_schemas("v0_5_2");
+        assert!(!schemas.is_empty());
+    }
+
     // #[test]
     // #[cfg(feature = "schema_v0_6")]
     // fn test_get_available_schemas_v0_6() {
@@ -4,6 +4,7 @@ extern crate toml;
 use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
 use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
 
 use once_cell::sync::OnceCell;
@@ -15,6 +16,11 @@ pub struct ConfigsProxyCertificates {
     use_generated_certs: Option<bool>,
     cert_file: Option<String>,
     key_file: Option<String>,
+    ca_validity_days: Option<i64>,
+    ca_expiry_warning_days: Option<i64>,
+    ca_rotate_before_days: Option<i64>,
+    ca_rotation_grace_days: Option<i64>,
+    leaf_cert_cache_size: Option<i64>,
 }
 
 impl ConfigsProxyCertificates {
@@ -35,6 +41,66 @@ impl ConfigsProxyCertificates {
     pub fn get_key_file(&self) -> Option<PathBuf> {
         self.key_file.clone().map(PathBuf::from)
     }
+
+    pub fn get_ca_validity_days(&self) -> i64 {
+        self.ca_validity_days
+            .unwrap_or_else(|| {
+                get_default_configs()
+                    .proxy
+                    .certificates
+                    .ca_validity_days
+                    .unwrap()
+            })
+            .max(1)
+    }
+
+    pub fn get_ca_expiry_warning_days(&self) -> i64 {
+        self.ca_expiry_warning_days
+            .unwrap_or_else(|| {
+                get_default_configs()
+                    .proxy
+                    .certificates
+                    .ca_expiry_warning_days
+                    .unwrap()
+            })
+            .max(0)
+    }
+
+    pub fn get_ca_rotate_before_days(&self) -> i64 {
+        self.ca_rotate_before_days
+            .unwrap_or_else(|| {
+                get_default_configs()
+                    .proxy
+                    .certificates
+                    .ca_rotate_before_days
+                    .unwrap()
+            })
+            .max(0)
+    }
+
+    pub fn get_ca_rotation_grace_days(&self) -> i64 {
+        self.ca_rotation_grace_days
+            .unwrap_or_else(|| {
+                get_default_configs()
+                    .proxy
+                    .certificates
+                    .ca_rotation_grace_days
+                    .unwrap()
+            })
+            .max(0)
+    }
+
+    pub fn get_leaf_cert_cache_size(&self) -> usize {
+        self.leaf_cert_cache_size
+            .unwrap_or_else(|| {
+                get_default_configs()
+                    .proxy
+                    .certificates
+                    .leaf_cert_cache_size
+                    .unwrap()
+            })
+            .max(1) as usize
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -42,6 +108,9 @@ pub struct ConfigsProxyPac {
     use_custom_pac: Option<bool>,
     pac_script: Option<String>,
     pac_server_port: Option<i64>,
+    generate: Option<bool>,
+    extra_proxy_hosts: Option<Vec<String>>,
+    fallbacks: Option<Vec<String>>,
 }
 
 impl ConfigsProxyPac {
@@ -64,6 +133,38 @@ impl ConfigsProxyPac {
             .unwrap_or_else(|| get_default_configs().proxy.pac.pac_server_port.unwrap())
             as u16
     }
+
+    pub fn get_generate(&self) -> bool {
+        self.generate
+            .unwrap_or_else(|| get_default_configs().proxy.pac.generate.unwrap())
+    }
+
+    pub fn get_extra_proxy_hosts(&self) -> Vec<String> {
+        self.extra_proxy_hosts
+            .clone()
+            .unwrap_or_else(|| {
+                get_default_configs()
+                    .proxy
+                    .pac
+                    .extra_proxy_hosts
+                    .clone()
+                    .unwrap()
+            })
+            .into_iter()
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+            .collect()
+    }
+
+    pub fn get_fallbacks(&self) -> Vec<String> {
+        self.fallbacks
+            .clone()
+            .unwrap_or_else(|| get_default_configs().proxy.pac.fallbacks.clone().unwrap())
+            .into_iter()
+            .map(|entry| entry.trim().to_string())
+            .filter(|entry| !entry.is_empty())
+            .collect()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -105,6 +206,114 @@ impl ConfigsProxyChannel {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigsProxyStreaming {
+    enable: Option<bool>,
+    memory_spool_limit: Option<i64>,
+    spool_dir: Option<String>,
+}
+
+impl ConfigsProxyStreaming {
+    pub fn get_enable(&self) -> bool {
+        self.enable
+            .unwrap_or_else(|| get_default_configs().proxy.streaming.enable.unwrap())
+    }
+
+    pub fn get_memory_spool_limit(&self) -> usize {
+        self.memory_spool_limit
+            .unwrap_or_else(|| {
+                get_default_configs()
+                    .proxy
+                    .streaming
+                    .memory_spool_limit
+                    .unwrap()
+            })
+            .max(0) as usize
+    }
+
+    pub fn get_spool_dir(&self) -> Option<PathBuf> {
+        match self.spool_dir {
+            Some(ref v) if !v.trim().is_empty() => Some(PathBuf::from(v.trim())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigsProxyHar {
+    enable: Option<bool>,
+    include_resources: Option<bool>,
+    max_entries_per_file: Option<i64>,
+    output_dir: Option<String>,
+}
+
+impl ConfigsProxyHar {
+    pub fn get_enable(&self) -> bool {
+        self.enable
+            .unwrap_or_else(|| get_default_configs().proxy.har.enable.unwrap())
+    }
+
+    pub fn get_include_resources(&self) -> bool {
+        self.include_resources
+            .unwrap_or_else(|| get_default_configs().proxy.har.include_resources.unwrap())
+    }
+
+    pub fn get_max_entries_per_file(&self) -> usize {
+        self.max_entries_per_file
+            .unwrap_or_else(|| get_default_configs().proxy.har.max_entries_per_file.unwrap())
+            .max(1) as usize
+    }
+
+    pub fn get_output_dir(&self) -> Option<PathBuf> {
+        match self.output_dir {
+            Some(ref v) if !v.trim().is_empty() => Some(PathBuf::from(v.trim())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigsProxyTelemetry {
+    enable: Option<bool>,
+    window_minutes: Option<i64>,
+    retry_window_secs: Option<i64>,
+    output_dir: Option<String>,
+}
+
+impl ConfigsProxyTelemetry {
+    pub fn get_enable(&self) -> bool {
+        self.enable
+            .unwrap_or_else(|| get_default_configs().proxy.telemetry.enable.unwrap())
+    }
+
+    pub fn get_window_minutes(&self) -> usize {
+        self.window_minutes
+            .unwrap_or_else(|| get_default_configs().proxy.telemetry.window_minutes.unwrap())
+            .max(1) as usize
+    }
+
+    pub fn get_retry_window(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.retry_window_secs
+                .unwrap_or_else(|| {
+                    get_default_configs()
+                        .proxy
+                        .telemetry
+                        .retry_window_secs
+                        .unwrap()
+                })
+                .max(0) as u64,
+        )
+    }
+
+    pub fn get_output_dir(&self) -> Option<PathBuf> {
+        match self.output_dir {
+            Some(ref v) if !v.trim().is_empty() => Some(PathBuf::from(v.trim())),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ConfigsProxyNetwork {
     backend_crate: Option<String>,
@@ -115,6 +324,10 @@ pub struct ConfigsProxyNetwork {
     recv_buffer_size: Option<i64>,
     send_buffer_size: Option<i64>,
     proxy_server_port: Option<i64>,
+    proxy_server_bind_address: Option<String>,
+    proxy_allowed_clients: Option<Vec<String>>,
+    #[serde(default)]
+    pub upstream: ConfigsProxyNetworkUpstream,
 }
 
 impl ConfigsProxyNetwork {
@@ -186,6 +399,133 @@ impl ConfigsProxyNetwork {
                     .unwrap()
             }) as u16
     }
+
+    /// Falls back to the default when the value is not a valid IP address.
+    pub fn get_proxy_server_bind_address(&self) -> IpAddr {
+        let default = || {
+            get_default_configs()
+                .proxy
+                .network
+                .proxy_server_bind_address
+                .as_deref()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        };
+        match self.proxy_server_bind_address.as_deref().map(str::trim) {
+            Some(v) if !v.is_empty() => v.parse().unwrap_or_else(|_| {
+                tracing::warn!("invalid proxy_server_bind_address: {}", v);
+                default()
+            }),
+            _ => default(),
+        }
+    }
+
+    /// IP addresses or CIDR ranges of the clients allowed to use the proxy.
+    /// Empty allows any client that can reach the bind address.
+    pub fn get_proxy_allowed_clients(&self) -> Vec<String> {
+        self.proxy_allowed_clients
+            .clone()
+            .unwrap_or_else(|| {
+                get_default_configs()
+                    .proxy
+                    .network
+                    .proxy_allowed_clients
+                    .clone()
+                    .unwrap()
+            })
+            .into_iter()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigsProxyNetworkUpstream {
+    kind: Option<String>,
+    host: Option<String>,
+    port: Option<i64>,
+    username: Option<String>,
+    password: Option<String>,
+    bypass: Option<Vec<String>>,
+    socks5_local_dns: Option<bool>,
+}
+
+impl ConfigsProxyNetworkUpstream {
+    /// `None` when no upstream proxy is configured, otherwise the lowercased kind
+    /// (`"http"` or `"socks5"`).
+    pub fn get_kind(&self) -> Option<String> {
+        let kind = self.kind.clone().unwrap_or_else(|| {
+            get_default_configs()
+                .proxy
+                .network
+                .upstream
+                .kind
+                .clone()
+                .unwrap()
+        });
+        match kind.trim() {
+            "" => None,
+            v => Some(v.to_ascii_lowercase()),
+        }
+    }
+
+    pub fn get_host(&self) -> Option<String> {
+        match self.host {
+            Some(ref v) if !v.trim().is_empty() => Some(v.trim().to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn get_port(&self) -> u16 {
+        self.port
+            .map(|v| v.clamp(0, 65535))
+            .unwrap_or_else(|| get_default_configs().proxy.network.upstream.port.unwrap())
+            as u16
+    }
+
+    pub fn get_username(&self) -> Option<String> {
+        match self.username {
+            Some(ref v) if !v.is_empty() => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn get_password(&self) -> Option<String> {
+        match self.password {
+            Some(ref v) if !v.is_empty() => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn get_bypass(&self) -> Vec<String> {
+        self.bypass
+            .clone()
+            .unwrap_or_else(|| {
+                get_default_configs()
+                    .proxy
+                    .network
+                    .upstream
+                    .bypass
+                    .clone()
+                    .unwrap()
+            })
+            .into_iter()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+
+    pub fn get_socks5_local_dns(&self) -> bool {
+        self.socks5_local_dns.unwrap_or_else(|| {
+            get_default_configs()
+                .proxy
+                .network
+                .upstream
+                .socks5_local_dns
+                .unwrap()
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -540,6 +880,10 @@ pub struct ConfigsAppAssetSync {
     asset_existing_keys_endpoint: Option<String>,
     finder_tag: Option<String>,
     pub retry: ConfigsAppAssetSyncRetry,
+    #[serde(default)]
+    pub schedule: ConfigsAppAssetSyncSchedule,
+    #[serde(default)]
+    pub chunked_upload: ConfigsAppAssetSyncChunkedUpload,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -608,6 +952,119 @@ impl ConfigsAppAssetSyncRetry {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigsAppAssetSyncSchedule {
+    offline_mode: Option<bool>,
+    max_bytes_per_second: Option<u64>,
+    priority_order: Option<Vec<String>>,
+    asset_upload_window: Option<String>,
+}
+
+impl ConfigsAppAssetSyncSchedule {
+    pub fn get_offline_mode(&self) -> bool {
+        self.offline_mode.unwrap_or_else(|| {
+            get_default_configs()
+                .app
+                .asset_sync
+                .schedule
+                .offline_mode
+                .unwrap()
+        })
+    }
+
+    /// 0 means unlimited.
+    pub fn get_max_bytes_per_second(&self) -> u64 {
+        self.max_bytes_per_second.unwrap_or_else(|| {
+            get_default_configs()
+                .app
+                .asset_sync
+                .schedule
+                .max_bytes_per_second
+                .unwrap()
+        })
+    }
+
+    pub fn get_priority_order(&self) -> Vec<String> {
+        self.priority_order
+            .as_ref()
+            .or(get_default_configs()
+                .app
+                .asset_sync
+                .schedule
+                .priority_order
+                .as_ref())
+            .map(|order| {
+                order
+                    .iter()
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// `"HH:MM-HH:MM"` in JST; `None` when asset uploads may run at any time.
+    pub fn get_asset_upload_window(&self) -> Option<String> {
+        self.asset_upload_window
+            .clone()
+            .or_else(|| {
+                get_default_configs()
+                    .app
+                    .asset_sync
+                    .schedule
+                    .asset_upload_window
+                    .clone()
+            })
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigsAppAssetSyncChunkedUpload {
+    threshold_bytes: Option<u64>,
+    part_size_bytes: Option<u64>,
+    max_part_attempts: Option<u32>,
+}
+
+impl ConfigsAppAssetSyncChunkedUpload {
+    /// 0 disables chunked uploads.
+    pub fn get_threshold_bytes(&self) -> u64 {
+        self.threshold_bytes.unwrap_or_else(|| {
+            get_default_configs()
+                .app
+                .asset_sync
+                .chunked_upload
+                .threshold_bytes
+                .unwrap()
+        })
+    }
+
+    pub fn get_part_size_bytes(&self) -> u64 {
+        match self.part_size_bytes {
+            Some(v) if v > 0 => v,
+            _ => get_default_configs()
+                .app
+                .asset_sync
+                .chunked_upload
+                .part_size_bytes
+                .unwrap(),
+        }
+    }
+
+    pub fn get_max_part_attempts(&self) -> u32 {
+        match self.max_part_attempts {
+            Some(v) if v > 0 => v,
+            _ => get_default_configs()
+                .app
+                .asset_sync
+                .chunked_upload
+                .max_part_attempts
+                .unwrap(),
+        }
+    }
+}
+
 impl ConfigsAppAssetSync {
     pub fn get_enable(&self) -> bool {
         // Backward-compatible wrapper
@@ -771,6 +1228,8 @@ pub struct ConfigsAppAuth {
     anonymous_sync_v2_challenge_endpoint: Option<String>,
     anonymous_sync_v2_refresh_endpoint: Option<String>,
     anonymous_sync_v2_revoke_endpoint: Option<String>,
+    anonymous_sync_v2_rotate_endpoint: Option<String>,
+    anonymous_sync_v2_devices_endpoint: Option<String>,
 }
 
 impl ConfigsAppAuth {
@@ -841,6 +1300,30 @@ impl ConfigsAppAuth {
                 .map(|s| s.trim().to_string()),
         }
     }
+
+    pub fn get_anonymous_sync_v2_rotate_endpoint(&self) -> Option<String> {
+        match &self.anonymous_sync_v2_rotate_endpoint {
+            Some(v) if !v.trim().is_empty() => Some(v.trim().to_string()),
+            _ => get_default_configs()
+                .app
+                .auth
+                .anonymous_sync_v2_rotate_endpoint
+                .as_ref()
+                .map(|s| s.trim().to_string()),
+        }
+    }
+
+    pub fn get_anonymous_sync_v2_devices_endpoint(&self) -> Option<String> {
+        match &self.anonymous_sync_v2_devices_endpoint {
+            Some(v) if !v.trim().is_empty() => Some(v.trim().to_string()),
+            _ => get_default_configs()
+                .app
+                .auth
+                .anonymous_sync_v2_devices_endpoint
+                .as_ref()
+                .map(|s| s.trim().to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -998,6 +1481,79 @@ impl ConfigsAppRemodelSender {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigsAppEventStream {
+    enable: Option<bool>,
+    port: Option<i64>,
+    token: Option<String>,
+    replay_buffer_size: Option<i64>,
+}
+
+impl ConfigsAppEventStream {
+    pub fn get_enable(&self) -> bool {
+        self.enable
+            .unwrap_or_else(|| get_default_configs().app.event_stream.enable.unwrap())
+    }
+
+    /// 0 picks an unused port.
+    pub fn get_port(&self) -> u16 {
+        self.port
+            .map(|v| v.clamp(0, 65535))
+            .unwrap_or_else(|| get_default_configs().app.event_stream.port.unwrap()) as u16
+    }
+
+    /// `None` means a random token is generated at startup.
+    pub fn get_token(&self) -> Option<String> {
+        match self.token {
+            Some(ref v) if !v.trim().is_empty() => Some(v.trim().to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn get_replay_buffer_size(&self) -> usize {
+        match self.replay_buffer_size {
+            Some(v) if v > 0 => v as usize,
+            _ => get_default_configs()
+                .app
+                .event_stream
+                .replay_buffer_size
+                .unwrap() as usize,
+        }
+    }
+}
+
+/// A named search of the ship or equipment inventory.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ConfigsAppInventorySavedQuery {
+    pub name: String,
+    /// `ships` or `slot_items`; ships when omitted.
+    pub target: Option<String>,
+    pub query: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigsAppInventory {
+    saved_queries: Option<Vec<ConfigsAppInventorySavedQuery>>,
+}
+
+impl ConfigsAppInventory {
+    /// Saved queries with a name, in file order.
+    pub fn get_saved_queries(&self) -> Vec<ConfigsAppInventorySavedQuery> {
+        self.saved_queries
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|query| !query.name.trim().is_empty())
+            .collect()
+    }
+
+    pub fn get_saved_query(&self, name: &str) -> Option<ConfigsAppInventorySavedQuery> {
+        self.get_saved_queries()
+            .into_iter()
+            .find(|query| query.name == name)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ConfigsApp {
     pub connect_kc_server: ConfigsAppConnectKcServer,
@@ -1014,6 +1570,10 @@ pub struct ConfigsApp {
     pub ship_growth_sender: ConfigsAppShipGrowthSender,
     pub soku_speed_sender: ConfigsAppSokuSpeedSender,
     pub remodel_sender: ConfigsAppRemodelSender,
+    #[serde(default)]
+    pub event_stream: ConfigsAppEventStream,
+    #[serde(default)]
+    pub inventory: ConfigsAppInventory,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -1027,6 +1587,12 @@ pub struct ConfigsProxy {
     pub certificates: ConfigsProxyCertificates,
     pub pac: ConfigsProxyPac,
     pub channel: ConfigsProxyChannel,
+    #[serde(default)]
+    pub streaming: ConfigsProxyStreaming,
+    #[serde(default)]
+    pub har: ConfigsProxyHar,
+    #[serde(default)]
+    pub telemetry: ConfigsProxyTelemetry,
 }
 
 impl ConfigsProxy {
@@ -1117,11 +1683,34 @@ impl ConfigEnv {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigsDaemon {
+    data_dir: Option<String>,
+    status_port: Option<i64>,
+}
+
+impl ConfigsDaemon {
+    pub fn get_data_dir(&self) -> Option<PathBuf> {
+        match self.data_dir {
+            Some(ref v) if !v.trim().is_empty() => Some(PathBuf::from(v.trim())),
+            _ => None,
+        }
+    }
+
+    pub fn get_status_port(&self) -> u16 {
+        self.status_port
+            .map(|v| v.clamp(0, 65535))
+            .unwrap_or_else(|| get_default_configs().daemon.status_port.unwrap()) as u16
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Configs {
     version: Option<String>,
     pub proxy: ConfigsProxy,
     pub app: ConfigsApp,
+    #[serde(default)]
+    pub daemon: ConfigsDaemon,
     pub env: ConfigEnv,
 }
 
@@ -1317,6 +1906,9 @@ mod tests {
             recv_buffer_size: None,
             send_buffer_size: None,
             proxy_server_port: None,
+            proxy_server_bind_address: None,
+            proxy_allowed_clients: None,
+            upstream: ConfigsProxyNetworkUpstream::default(),
         };
 
         assert_eq!(
@@ -1340,11 +1932,41 @@ mod tests {
             "proxy_server_port getter should return configs.toml default"
         );
 
+        // Test Proxy Network Upstream defaults
+        let empty_upstream = ConfigsProxyNetworkUpstream::default();
+        let default_upstream = &default_configs.proxy.network.upstream;
+
+        assert_eq!(
+            empty_upstream.get_kind(),
+            default_upstream.get_kind(),
+            "upstream kind getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_upstream.get_port(),
+            default_upstream.get_port(),
+            "upstream port getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_upstream.get_bypass(),
+            default_upstream.get_bypass(),
+            "upstream bypass getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_upstream.get_socks5_local_dns(),
+            default_upstream.get_socks5_local_dns(),
+            "upstream socks5_local_dns getter should return configs.toml default"
+        );
+
         // Test Proxy Certificates defaults
         let empty_certs = ConfigsProxyCertificates {
             use_generated_certs: None,
             cert_file: None,
             key_file: None,
+            ca_validity_days: None,
+            ca_expiry_warning_days: None,
+            ca_rotate_before_days: None,
+            ca_rotation_grace_days: None,
+            leaf_cert_cache_size: None,
         };
 
         assert_eq!(
@@ -1352,12 +1974,40 @@ mod tests {
             default_configs.proxy.certificates.get_use_generated_certs(),
             "use_generated_certs getter should return configs.toml default"
         );
+        assert_eq!(
+            empty_certs.get_ca_validity_days(),
+            default_configs.proxy.certificates.get_ca_validity_days(),
+            "ca_validity_days getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_certs.get_ca_expiry_warning_days(),
+            default_configs.proxy.certificates.get_ca_expiry_warning_days(),
+            "ca_expiry_warning_days getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_certs.get_ca_rotate_before_days(),
+            default_configs.proxy.certificates.get_ca_rotate_before_days(),
+            "ca_rotate_before_days getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_certs.get_ca_rotation_grace_days(),
+            default_configs.proxy.certificates.get_ca_rotation_grace_days(),
+            "ca_rotation_grace_days getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_certs.get_leaf_cert_cache_size(),
+            default_configs.proxy.certificates.get_leaf_cert_cache_size(),
+            "leaf_cert_cache_size getter should return configs.toml default"
+        );
 
         // Test Proxy PAC defaults
         let empty_pac = ConfigsProxyPac {
             use_custom_pac: None,
             pac_script: None,
             pac_server_port: None,
+            generate: None,
+            extra_proxy_hosts: None,
+            fallbacks: None,
         };
 
         assert_eq!(
@@ -1370,6 +2020,21 @@ mod tests {
             default_configs.proxy.pac.get_pac_server_port(),
             "pac_server_port getter should return configs.toml default"
         );
+        assert_eq!(
+            empty_pac.get_generate(),
+            default_configs.proxy.pac.get_generate(),
+            "generate getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_pac.get_extra_proxy_hosts(),
+            default_configs.proxy.pac.get_extra_proxy_hosts(),
+            "extra_proxy_hosts getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_pac.get_fallbacks(),
+            default_configs.proxy.pac.get_fallbacks(),
+            "fallbacks getter should return configs.toml default"
+        );
 
         // Test Proxy defaults
         let empty_proxy_fields = ConfigsProxy {
@@ -1382,6 +2047,9 @@ mod tests {
             certificates: default_configs.proxy.certificates.clone(),
             pac: default_configs.proxy.pac.clone(),
             channel: default_configs.proxy.channel.clone(),
+            streaming: default_configs.proxy.streaming.clone(),
+            har: default_configs.proxy.har.clone(),
+            telemetry: default_configs.proxy.telemetry.clone(),
         };
 
         assert_eq!(
@@ -1524,6 +2192,8 @@ mod tests {
             asset_existing_keys_endpoint: None,
             finder_tag: None,
             retry: default_configs.app.asset_sync.retry.clone(),
+            schedule: default_configs.app.asset_sync.schedule.clone(),
+            chunked_upload: default_configs.app.asset_sync.chunked_upload.clone(),
         };
 
         assert_eq!(
@@ -1576,6 +2246,58 @@ mod tests {
             "retry auth_backoff_seconds getter should return configs.toml default"
         );
 
+        // Test App Asset Sync Schedule defaults
+        let empty_schedule = ConfigsAppAssetSyncSchedule::default();
+
+        assert_eq!(
+            empty_schedule.get_offline_mode(),
+            default_configs.app.asset_sync.schedule.get_offline_mode(),
+            "schedule offline_mode getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_schedule.get_max_bytes_per_second(),
+            default_configs
+                .app
+                .asset_sync
+                .schedule
+                .get_max_bytes_per_second(),
+            "schedule max_bytes_per_second getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_schedule.get_priority_order(),
+            default_configs.app.asset_sync.schedule.get_priority_order(),
+            "schedule priority_order getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_schedule.get_asset_upload_window(),
+            default_configs
+                .app
+                .asset_sync
+                .schedule
+                .get_asset_upload_window(),
+            "schedule asset_upload_window getter should return configs.toml default"
+        );
+
+        // Test App Asset Sync Chunked Upload defaults
+        let empty_chunked = ConfigsAppAssetSyncChunkedUpload::default();
+        let default_chunked = &default_configs.app.asset_sync.chunked_upload;
+
+        assert_eq!(
+            empty_chunked.get_threshold_bytes(),
+            default_chunked.get_threshold_bytes(),
+            "chunked_upload threshold_bytes getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_chunked.get_part_size_bytes(),
+            default_chunked.get_part_size_bytes(),
+            "chunked_upload part_size_bytes getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_chunked.get_max_part_attempts(),
+            default_chunked.get_max_part_attempts(),
+            "chunked_upload max_part_attempts getter should return configs.toml default"
+        );
+
         // Test App Auth defaults
         let empty_auth = ConfigsAppAuth {
             deny_auth: None,
@@ -1584,6 +2306,8 @@ mod tests {
             anonymous_sync_v2_challenge_endpoint: None,
             anonymous_sync_v2_refresh_endpoint: None,
             anonymous_sync_v2_revoke_endpoint: None,
+            anonymous_sync_v2_rotate_endpoint: None,
+            anonymous_sync_v2_devices_endpoint: None,
         };
 
         assert_eq!(
@@ -1596,6 +2320,16 @@ mod tests {
             default_configs.app.auth.get_auth_page_url(),
             "auth auth_page_url getter should return configs.toml default"
         );
+        assert_eq!(
+            empty_auth.get_anonymous_sync_v2_rotate_endpoint(),
+            default_configs.app.auth.get_anonymous_sync_v2_rotate_endpoint(),
+            "auth anonymous_sync_v2_rotate_endpoint getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_auth.get_anonymous_sync_v2_devices_endpoint(),
+            default_configs.app.auth.get_anonymous_sync_v2_devices_endpoint(),
+            "auth anonymous_sync_v2_devices_endpoint getter should return configs.toml default"
+        );
 
         // Test App Window defaults
         let empty_window = ConfigsAppWindow {
@@ -1668,4 +2402,154 @@ mod tests {
             "All servers should come from configs.toml"
         );
     }
+
+    #[test]
+    fn test_event_stream_defaults_match_config_toml() {
+        let default_configs = get_default_configs();
+        let empty_event_stream = ConfigsAppEventStream::default();
+        let default_event_stream = &default_configs.app.event_stream;
+
+        assert_eq!(
+            empty_event_stream.get_enable(),
+            default_event_stream.get_enable(),
+            "event_stream enable getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_event_stream.get_port(),
+            default_event_stream.get_port(),
+            "event_stream port getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_event_stream.get_replay_buffer_size(),
+            default_event_stream.get_replay_buffer_size(),
+            "event_stream replay_buffer_size getter should return configs.toml default"
+        );
+        assert_eq!(empty_event_stream.get_token(), None);
+    }
+
+    #[test]
+    fn test_inventory_saved_queries() {
+        let default_configs = get_default_configs();
+        assert!(default_configs.app.inventory.get_saved_queries().is_empty());
+
+        let inventory: ConfigsAppInventory = toml::from_str(
+            r#"saved_queries = [
+                { name = "Modernize", target = "ships", query = "locked mod" },
+                { name = "", query = "locked" },
+                { name = "Spare guns", query = "-equipped" },
+            ]"#,
+        )
+        .unwrap();
+        let names: Vec<String> = inventory
+            .get_saved_queries()
+            .into_iter()
+            .map(|query| query.name)
+            .collect();
+        assert_eq!(names, vec!["Modernize", "Spare guns"]);
+        assert_eq!(
+            inventory.get_saved_query("Spare guns").unwrap().target,
+            None
+        );
+    }
+
+    #[test]
+    fn test_daemon_defaults_match_config_toml() {
+        let default_configs = get_default_configs();
+        let empty_daemon = ConfigsDaemon::default();
+
+        assert_eq!(
+            empty_daemon.get_status_port(),
+            default_configs.daemon.get_status_port(),
+            "daemon status_port getter should return configs.toml default"
+        );
+        assert_eq!(empty_daemon.get_data_dir(), None);
+        assert_eq!(default_configs.daemon.get_data_dir(), None);
+        assert_eq!(
+            ConfigsProxyNetwork {
+                backend_crate: None,
+                enforce_http: None,
+                set_nodelay: None,
+                connect_timeout: None,
+                keepalive_interval: None,
+                recv_buffer_size: None,
+                send_buffer_size: None,
+                proxy_server_port: None,
+                proxy_server_bind_address: Some("not an address".to_string()),
+                proxy_allowed_clients: None,
+                upstream: ConfigsProxyNetworkUpstream::default(),
+            }
+            .get_proxy_server_bind_address(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+        assert!(default_configs
+            .proxy
+            .network
+            .get_proxy_allowed_clients()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_streaming_defaults_match_config_toml() {
+        let default_configs = get_default_configs();
+        let empty_streaming = ConfigsProxyStreaming::default();
+
+        assert_eq!(
+            empty_streaming.get_enable(),
+            default_configs.proxy.streaming.get_enable(),
+            "streaming enable getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_streaming.get_memory_spool_limit(),
+            default_configs.proxy.streaming.get_memory_spool_limit(),
+            "memory_spool_limit getter should return configs.toml default"
+        );
+        assert_eq!(empty_streaming.get_spool_dir(), None);
+        assert_eq!(default_configs.proxy.streaming.get_spool_dir(), None);
+    }
+
+    #[test]
+    fn test_har_defaults_match_config_toml() {
+        let default_configs = get_default_configs();
+        let empty_har = ConfigsProxyHar::default();
+
+        assert_eq!(
+            empty_har.get_enable(),
+            default_configs.proxy.har.get_enable(),
+            "har enable getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_har.get_include_resources(),
+            default_configs.proxy.har.get_include_resources(),
+            "har include_resources getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_har.get_max_entries_per_file(),
+            default_configs.proxy.har.get_max_entries_per_file(),
+            "har max_entries_per_file getter should return configs.toml default"
+        );
+        assert_eq!(empty_har.get_output_dir(), None);
+    }
+
+    #[test]
+    fn test_telemetry_defaults_match_config_toml() {
+        let default_configs = get_default_configs();
+        let empty_telemetry = ConfigsProxyTelemetry::default();
+
+        assert_eq!(
+            empty_telemetry.get_enable(),
+            default_configs.proxy.telemetry.get_enable(),
+            "telemetry enable getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_telemetry.get_window_minutes(),
+            default_configs.proxy.telemetry.get_window_minutes(),
+            "telemetry window_minutes getter should return configs.toml default"
+        );
+        assert_eq!(
+            empty_telemetry.get_retry_window(),
+            default_configs.proxy.telemetry.get_retry_window(),
+            "telemetry retry_window_secs getter should return configs.toml default"
+        );
+        assert_eq!(empty_telemetry.get_output_dir(), None);
+    }
 }
@@ -16,8 +16,24 @@ pub fn get_user_configs_for_app() -> configs::ConfigsApp {
     configs::get_user_configs().app.clone()
 }
 
+pub fn get_user_configs_for_daemon() -> configs::ConfigsDaemon {
+    configs::get_user_configs().daemon.clone()
+}
+
 pub use configs::ConfigsAppAssetSync;
+pub use configs::ConfigsAppAssetSyncChunkedUpload;
+pub use configs::ConfigsAppAssetSyncSchedule;
+pub use configs::ConfigsAppConnectKcServer;
+pub use configs::ConfigsAppEventStream;
+pub use configs::ConfigsAppInventory;
+pub use configs::ConfigsAppInventorySavedQuery;
 pub use configs::ConfigsAppQuestTreeSender;
+pub use configs::ConfigsProxyCertificates;
+pub use configs::ConfigsProxyHar;
+pub use configs::ConfigsProxyNetworkUpstream;
+pub use configs::ConfigsProxyPac;
+pub use configs::ConfigsProxyStreaming;
+pub use configs::ConfigsProxyTelemetry;
 pub use configs::ChannelTransportKind;
 
 #[cfg(target_os = "linux")]
@@ -23,6 +23,17 @@
 //!
 //! `device_id` はサーバー発行なので初回起動時は `None`。`/v2/register` 成功後に
 //! `set_device_id()` で確定値を書き戻す。
+//!
+//! 鍵ローテーション (`/v2/rotate`) は 2 段階で行う:
+//!   1. `begin_rotation()` で新 keypair を生成し `device-key.json.rotating` に退避する
+//!   2. サーバーが旧鍵・新鍵双方の署名を受理したら `commit_rotation()` で本体ファイルを
+//!      tmp 書き出し + rename により原子的に差し替える。サーバーが新鍵を保持していないと
+//!      確定したときだけ `abort_rotation()` で退避ファイルを破棄する
+//!
+//! 通信断などで応答を受け取れなかった場合は退避ファイルを残し、次回の `begin_rotation()`
+//! で同じ新鍵を再利用する。サーバーが既に差し替え済みなら旧鍵の署名は拒否されるので、
+//! `AuthManager::rotate_device_key_v2` は新鍵で署名し直してサーバー側の鍵を確かめてから
+//! 確定または破棄する。
 
 use std::path::{Path, PathBuf};
 
@@ -47,6 +58,9 @@ pub struct DeviceKeyRecord {
     pub public_key: String,
     /// keypair 生成時刻。監査用。
     pub created_at: DateTime<Utc>,
+    /// 直近の鍵ローテーション完了時刻。ローテーション未実施なら `None`。
+    #[serde(default)]
+    pub rotated_at: Option<DateTime<Utc>>,
 }
 
 /// 公開 API 用に署名処理を集約した型。
@@ -125,6 +139,7 @@ impl DeviceKey {
             secret_key: B64.encode(signing_key.to_bytes()),
             public_key: B64.encode(verifying_key.to_bytes()),
             created_at: Utc::now(),
+            rotated_at: None,
         };
 
         let mut device_key = Self {
@@ -138,25 +153,7 @@ impl DeviceKey {
 
     async fn persist(&mut self) -> Result<(), AuthError> {
         let json = serde_json::to_string(&self.record)?;
-        if let Some(parent) = self.storage_path.parent() {
-            tokio::fs::create_dir_all(parent)
-                .await
-                .map_err(|e| AuthError::Other(e.to_string()))?;
-        }
-        tokio::fs::write(&self.storage_path, &json)
-            .await
-            .map_err(|e| AuthError::Other(e.to_string()))?;
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let _ = std::fs::set_permissions(
-                &self.storage_path,
-                std::fs::Permissions::from_mode(0o600),
-            );
-        }
-        // Windows ではユーザー専有領域 (%APPDATA%) 前提のためファイルパーミッションは付けない。
-        // Stronghold / OS keyring 統合時に併せて強化する。
-        Ok(())
+        write_record_atomic(&self.storage_path, json.as_bytes()).await
     }
 
     /// 公開鍵を base64 で取得する。サーバーへ送信する値。
@@ -194,6 +191,176 @@ impl DeviceKey {
         let sig = self.signing_key.sign(message);
         B64.encode(sig.to_bytes())
     }
+
+    /// keypair の保存先パス。
+    pub fn storage_path(&self) -> &Path {
+        &self.storage_path
+    }
+
+    /// 直近の鍵ローテーション完了時刻。未実施なら `None`。
+    pub fn rotated_at(&self) -> Option<DateTime<Utc>> {
+        self.record.rotated_at
+    }
+
+    /// ローテーション途中の退避ファイルが残っているかを返す。
+    pub async fn has_pending_rotation(&self) -> bool {
+        tokio::fs::try_exists(self.rotating_path())
+            .await
+            .unwrap_or(false)
+    }
+
+    /// 鍵ローテーションを開始する。新 keypair を生成して退避ファイルに書き出す。
+    ///
+    /// 既に同じ device_id 向けの退避ファイルがあれば (前回の通信断など) それを再利用する。
+    /// 本体ファイルはこの時点では変更しない。
+    pub async fn begin_rotation(&self) -> Result<PendingRotation, AuthError> {
+        let device_id = self.record.device_id.clone().ok_or_else(|| {
+            AuthError::Other("device_key: cannot rotate before device is registered".to_string())
+        })?;
+        let rotating_path = self.rotating_path();
+
+        match tokio::fs::read(&rotating_path).await {
+            Ok(bytes) => match serde_json::from_slice::<DeviceKeyRecord>(&bytes) {
+                Ok(record) if record.device_id.as_deref() == Some(device_id.as_str()) => {
+                    let signing_key = decode_signing_key(&record.secret_key)?;
+                    if B64.encode(signing_key.verifying_key().to_bytes()) == record.public_key {
+                        tracing::info!(
+                            device_id = %device_id,
+                            "device_key: resuming interrupted key rotation",
+                        );
+                        return Ok(PendingRotation {
+                            signing_key,
+                            record,
+                        });
+                    }
+                    tracing::warn!("device_key: staged rotation key is inconsistent; regenerating");
+                }
+                _ => {
+                    tracing::warn!("device_key: discarding stale staged rotation record");
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(AuthError::Other(err.to_string())),
+        }
+
+        let mut csprng = OsRng;
+        let signing_key = SigningKey::generate(&mut csprng);
+        let record = DeviceKeyRecord {
+            device_id: Some(device_id),
+            secret_key: B64.encode(signing_key.to_bytes()),
+            public_key: B64.encode(signing_key.verifying_key().to_bytes()),
+            created_at: self.record.created_at,
+            rotated_at: None,
+        };
+        let json = serde_json::to_string(&record)?;
+        write_record_atomic(&rotating_path, json.as_bytes()).await?;
+
+        Ok(PendingRotation {
+            signing_key,
+            record,
+        })
+    }
+
+    /// サーバーが新鍵を受理した後に呼ぶ。本体ファイルを原子的に差し替え、退避ファイルを消す。
+    pub async fn commit_rotation(&mut self, pending: PendingRotation) -> Result<(), AuthError> {
+        if pending.record.device_id != self.record.device_id {
+            return Err(AuthError::Other(
+                "device_key: staged rotation belongs to a different device_id".to_string(),
+            ));
+        }
+
+        let mut record = pending.record;
+        record.rotated_at = Some(Utc::now());
+        let json = serde_json::to_string(&record)?;
+        write_record_atomic(&self.storage_path, json.as_bytes()).await?;
+
+        self.signing_key = pending.signing_key;
+        self.record = record;
+
+        if let Err(err) = tokio::fs::remove_file(self.rotating_path()).await {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(
+                    error = %err,
+                    "device_key: rotation committed but staged record could not be removed",
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// サーバーが新鍵を保持していないと確定した場合に呼ぶ。退避ファイルを破棄し、旧鍵のまま継続する。
+    pub async fn abort_rotation(&self) -> Result<(), AuthError> {
+        match tokio::fs::remove_file(self.rotating_path()).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(AuthError::Other(err.to_string())),
+        }
+    }
+
+    fn rotating_path(&self) -> PathBuf {
+        sibling_path(&self.storage_path, "rotating")
+    }
+}
+
+/// `begin_rotation` で生成された、まだサーバーに受理されていない新 keypair。
+pub struct PendingRotation {
+    signing_key: SigningKey,
+    record: DeviceKeyRecord,
+}
+
+impl PendingRotation {
+    /// 新公開鍵の base64 表現。`/v2/rotate` に `new_device_pub` として送る。
+    pub fn public_key_b64(&self) -> &str {
+        &self.record.public_key
+    }
+
+    /// 新秘密鍵で署名する。新鍵の所持証明に使う。
+    pub fn sign_b64(&self, message: &[u8]) -> String {
+        let sig = self.signing_key.sign(message);
+        B64.encode(sig.to_bytes())
+    }
+}
+
+/// `path` に拡張子 `suffix` を付け足した隣接パスを返す (`device-key.json` -> `device-key.json.tmp`)。
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(|n| n.to_os_string())
+        .unwrap_or_default();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// tmp ファイルに書き出してから rename で置き換える。途中でプロセスが落ちても
+/// 本体ファイルは旧内容か新内容のどちらかになり、半端な JSON が残らない。
+async fn write_record_atomic(path: &Path, bytes: &[u8]) -> Result<(), AuthError> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| AuthError::Other(e.to_string()))?;
+    }
+    let tmp_path = sibling_path(path, "tmp");
+    tokio::fs::write(&tmp_path, bytes)
+        .await
+        .map_err(|e| AuthError::Other(e.to_string()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600));
+    }
+    // Windows ではユーザー専有領域 (%APPDATA%) 前提のためファイルパーミッションは付けない。
+    // Stronghold / OS keyring 統合時に併せて強化する。
+    let file = tokio::fs::File::open(&tmp_path)
+        .await
+        .map_err(|e| AuthError::Other(e.to_string()))?;
+    file.sync_all()
+        .await
+        .map_err(|e| AuthError::Other(e.to_string()))?;
+    drop(file);
+    tokio::fs::rename(&tmp_path, path)
+        .await
+        .map_err(|e| AuthError::Other(e.to_string()))
 }
 
 fn decode_signing_key(secret_b64: &str) -> Result<SigningKey, AuthError> {
@@ -294,4 +461,70 @@ mod tests {
 
         let _ = tokio::fs::remove_file(&path).await;
     }
+
+    #[tokio::test]
+    async fn rotation_commit_swaps_keypair_on_disk() {
+        let path = temp_path();
+        let mut key = DeviceKey::load_or_create(path.clone()).await.unwrap();
+        key.set_device_id("00000000-0000-4000-8000-000000000000".to_string())
+            .await
+            .unwrap();
+        let old_pub = key.public_key_b64().to_string();
+
+        let pending = key.begin_rotation().await.unwrap();
+        let new_pub = pending.public_key_b64().to_string();
+        assert_ne!(new_pub, old_pub);
+        assert!(key.has_pending_rotation().await);
+
+        // commit 前は本体ファイルが旧鍵のまま
+        let before = DeviceKey::load_or_create(path.clone()).await.unwrap();
+        assert_eq!(before.public_key_b64(), old_pub);
+
+        key.commit_rotation(pending).await.unwrap();
+        assert_eq!(key.public_key_b64(), new_pub);
+        assert!(key.rotated_at().is_some());
+        assert!(!key.has_pending_rotation().await);
+
+        let reloaded = DeviceKey::load_or_create(path.clone()).await.unwrap();
+        assert_eq!(reloaded.public_key_b64(), new_pub);
+        assert_eq!(
+            reloaded.device_id(),
+            Some("00000000-0000-4000-8000-000000000000")
+        );
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn interrupted_rotation_reuses_staged_key_and_abort_discards_it() {
+        let path = temp_path();
+        let mut key = DeviceKey::load_or_create(path.clone()).await.unwrap();
+        key.set_device_id("00000000-0000-4000-8000-000000000000".to_string())
+            .await
+            .unwrap();
+
+        let first = key.begin_rotation().await.unwrap();
+        let staged_pub = first.public_key_b64().to_string();
+        drop(first);
+
+        let resumed = key.begin_rotation().await.unwrap();
+        assert_eq!(resumed.public_key_b64(), staged_pub);
+
+        key.abort_rotation().await.unwrap();
+        assert!(!key.has_pending_rotation().await);
+        let fresh = key.begin_rotation().await.unwrap();
+        assert_ne!(fresh.public_key_b64(), staged_pub);
+
+        key.abort_rotation().await.unwrap();
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn rotation_requires_registered_device() {
+        let path = temp_path();
+        let key = DeviceKey::load_or_create(path.clone()).await.unwrap();
+        assert!(key.begin_rotation().await.is_err());
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
 }
@@ -4,7 +4,7 @@ pub mod manager;
 pub mod storage;
 pub mod types;
 
-pub use device_key::{DeviceKey, DeviceKeyRecord};
-pub use manager::AuthManager;
+pub use device_key::{DeviceKey, DeviceKeyRecord, PendingRotation};
+pub use manager::{AnonymousSyncV2Endpoints, AuthManager};
 pub use storage::{FileStorage, InMemoryStorage, Storage};
-pub use types::Session;
+pub use types::{DeviceInfo, Session};
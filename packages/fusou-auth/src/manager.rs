@@ -1,6 +1,6 @@
 use crate::error::AuthError;
 use crate::storage::Storage;
-use crate::types::{DatasetToken, DatasetTokenStore, Session};
+use crate::types::{DatasetToken, DatasetTokenStore, DeviceInfo, Session};
 use chrono::{DateTime, Duration, Utc};
 use reqwest::Client;
 use serde::Deserialize;
@@ -24,6 +24,47 @@ fn masked_error_payload(input: &str) -> String {
     }
 }
 
+/// v2 anonymous-sync のエンドポイント URL 群。
+/// 通常は `from_configs()` で configs.toml から解決し、テストやローカル検証時だけ
+/// `AuthManager::set_anonymous_sync_v2_endpoints` で差し替える。
+#[derive(Clone, Debug, Default)]
+pub struct AnonymousSyncV2Endpoints {
+    pub register: Option<String>,
+    pub challenge: Option<String>,
+    pub refresh: Option<String>,
+    pub revoke: Option<String>,
+    pub rotate: Option<String>,
+    pub devices: Option<String>,
+}
+
+impl AnonymousSyncV2Endpoints {
+    pub fn from_configs() -> Self {
+        let auth = configs::get_user_configs_for_app().auth;
+        Self {
+            register: auth.get_anonymous_sync_v2_register_endpoint(),
+            challenge: auth.get_anonymous_sync_v2_challenge_endpoint(),
+            refresh: auth.get_anonymous_sync_v2_refresh_endpoint(),
+            revoke: auth.get_anonymous_sync_v2_revoke_endpoint(),
+            rotate: auth.get_anonymous_sync_v2_rotate_endpoint(),
+            devices: auth.get_anonymous_sync_v2_devices_endpoint(),
+        }
+    }
+
+    /// `base` (例: `http://127.0.0.1:8787/api/auth`) 配下の標準パスで全エンドポイントを組み立てる。
+    pub fn with_base_url(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        let path = |suffix: &str| Some(format!("{base}/anonymous-sync/v2/{suffix}"));
+        Self {
+            register: path("register"),
+            challenge: path("challenge"),
+            refresh: path("refresh"),
+            revoke: path("revoke"),
+            rotate: path("rotate"),
+            devices: path("devices"),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AuthConfig {
     pub supabase_url: String,
@@ -44,6 +85,8 @@ pub struct AuthManager<S: Storage + 'static> {
     dataset_token_cache: Arc<Mutex<DatasetTokenStore>>,
     // file path for persistent dataset_token storage (optional)
     dataset_token_path: Arc<std::sync::Mutex<Option<std::path::PathBuf>>>,
+    // v2 anonymous-sync endpoint override (None => configs.toml)
+    v2_endpoints: Arc<std::sync::Mutex<Option<AnonymousSyncV2Endpoints>>>,
 }
 
 impl<S: Storage> Clone for AuthManager<S> {
@@ -55,6 +98,7 @@ impl<S: Storage> Clone for AuthManager<S> {
             refresh_lock: self.refresh_lock.clone(),
             dataset_token_cache: self.dataset_token_cache.clone(),
             dataset_token_path: self.dataset_token_path.clone(),
+            v2_endpoints: self.v2_endpoints.clone(),
         }
     }
 }
@@ -68,6 +112,7 @@ impl<S: Storage> AuthManager<S> {
             refresh_lock: Arc::new(Mutex::new(())),
             dataset_token_cache: Arc::new(Mutex::new(DatasetTokenStore::default())),
             dataset_token_path: Arc::new(std::sync::Mutex::new(None)),
+            v2_endpoints: Arc::new(std::sync::Mutex::new(None)),
         }
     }
 
@@ -84,6 +129,7 @@ impl<S: Storage> AuthManager<S> {
             refresh_lock: Arc::new(Mutex::new(())),
             dataset_token_cache: Arc::new(Mutex::new(DatasetTokenStore::default())),
             dataset_token_path: Arc::new(std::sync::Mutex::new(dataset_token_path)),
+            v2_endpoints: Arc::new(std::sync::Mutex::new(None)),
         }
     }
 
@@ -94,6 +140,26 @@ impl<S: Storage> AuthManager<S> {
         }
     }
 
+    /// Override the v2 anonymous-sync endpoints. `None` falls back to configs.toml.
+    pub fn set_anonymous_sync_v2_endpoints(&mut self, endpoints: Option<AnonymousSyncV2Endpoints>) {
+        if let Ok(mut guard) = self.v2_endpoints.lock() {
+            *guard = endpoints;
+        }
+    }
+
+    fn v2_endpoint<F>(&self, key: &str, pick: F) -> Result<String, AuthError>
+    where
+        F: Fn(&AnonymousSyncV2Endpoints) -> Option<String>,
+    {
+        let endpoints = self
+            .v2_endpoints
+            .lock()
+            .ok()
+            .and_then(|guard| guard.clone())
+            .unwrap_or_else(AnonymousSyncV2Endpoints::from_configs);
+        pick(&endpoints).ok_or_else(|| AuthError::Other(format!("{key} not configured")))
+    }
+
     async fn read_dataset_token_store_from_disk(&self) -> Result<DatasetTokenStore, AuthError> {
         let path = self
             .dataset_token_path
@@ -594,7 +660,7 @@ impl<S: Storage> AuthManager<S> {
 //
 // 現在は v2 固定運用。クライアント側は `ensure_dataset_token_v2` を利用する。
 
-use crate::device_key::DeviceKey;
+use crate::device_key::{DeviceKey, PendingRotation};
 
 /// /v2/register のレスポンス
 #[derive(Debug, Deserialize)]
@@ -613,6 +679,20 @@ struct ChallengeV2Response {
     expires_at: Option<i64>,
 }
 
+/// GET /v2/devices のレスポンス
+#[derive(Debug, Deserialize)]
+struct DevicesV2Response {
+    #[serde(default)]
+    devices: Vec<DeviceInfo>,
+}
+
+/// v2 エンドポイントのエラーレスポンス (`{"error": "..."}`)
+#[derive(Debug, Default, Deserialize)]
+struct ErrorV2Response {
+    #[serde(default)]
+    error: String,
+}
+
 /// /v2/refresh のレスポンス
 #[derive(Debug, Deserialize)]
 struct RefreshV2Response {
@@ -645,12 +725,9 @@ impl<S: Storage> AuthManager<S> {
         let attestation_message = format!("register|{}", api_member_id);
         let attestation_b64 = device_key.sign_b64(attestation_message.as_bytes());
 
-        let url = configs::get_user_configs_for_app()
-            .auth
-            .get_anonymous_sync_v2_register_endpoint()
-            .ok_or_else(|| {
-                AuthError::Other("anonymous_sync_v2_register_endpoint not configured".to_string())
-            })?;
+        let url = self.v2_endpoint("anonymous_sync_v2_register_endpoint", |e| {
+            e.register.clone()
+        })?;
 
         let body = serde_json::json!({
             "api_member_id": api_member_id,
@@ -703,12 +780,9 @@ impl<S: Storage> AuthManager<S> {
     /// nonce はサーバー側 HMAC で 5 分単位のバケットに紐づき、refresh / revoke の
     /// メッセージ署名にそのまま使う。
     async fn fetch_challenge_v2(&self, device_id: &str) -> Result<ChallengeV2Response, AuthError> {
-        let base = configs::get_user_configs_for_app()
-            .auth
-            .get_anonymous_sync_v2_challenge_endpoint()
-            .ok_or_else(|| {
-                AuthError::Other("anonymous_sync_v2_challenge_endpoint not configured".to_string())
-            })?;
+        let base = self.v2_endpoint("anonymous_sync_v2_challenge_endpoint", |e| {
+            e.challenge.clone()
+        })?;
 
         let resp = self
             .client
@@ -757,12 +831,7 @@ impl<S: Storage> AuthManager<S> {
         // refresh メッセージ = nonce そのもの。サーバーは保存済み公開鍵で署名を検証する。
         let sig_b64 = device_key.sign_b64(challenge.nonce.as_bytes());
 
-        let url = configs::get_user_configs_for_app()
-            .auth
-            .get_anonymous_sync_v2_refresh_endpoint()
-            .ok_or_else(|| {
-                AuthError::Other("anonymous_sync_v2_refresh_endpoint not configured".to_string())
-            })?;
+        let url = self.v2_endpoint("anonymous_sync_v2_refresh_endpoint", |e| e.refresh.clone())?;
 
         let body = serde_json::json!({
             "device_id": device_id,
@@ -836,12 +905,7 @@ impl<S: Storage> AuthManager<S> {
         );
         let sig_b64 = device_key.sign_b64(message.as_bytes());
 
-        let url = configs::get_user_configs_for_app()
-            .auth
-            .get_anonymous_sync_v2_revoke_endpoint()
-            .ok_or_else(|| {
-                AuthError::Other("anonymous_sync_v2_revoke_endpoint not configured".to_string())
-            })?;
+        let url = self.v2_endpoint("anonymous_sync_v2_revoke_endpoint", |e| e.revoke.clone())?;
 
         let body = serde_json::json!({
             "device_id": device_id,
@@ -876,6 +940,193 @@ impl<S: Storage> AuthManager<S> {
         Ok(())
     }
 
+    /// 端末鍵をローテーションする。
+    ///
+    /// 新 keypair を退避ファイルに生成し、challenge nonce に対して
+    /// `"rotate|" + device_id + "|" + new_device_pub + "|" + nonce` を現在の鍵 (`sig`) と
+    /// 新鍵 (`new_sig`) の両方で署名して /v2/rotate に送る。サーバーが受理した場合のみ
+    /// 本体ファイルを差し替える。
+    ///
+    /// 前回の rotate がサーバーでは確定したのに応答が失われていると、サーバーは既に新鍵を
+    /// 保持しているため旧鍵の署名は 4xx で拒否される。そこで 4xx のときは新鍵で `sig` を
+    /// 作り直して再送し、受理されれば差し替え済みとして確定する。新鍵の署名も
+    /// `signature_invalid` で拒否された (サーバーが新鍵を持っていないと確定した) ときだけ
+    /// 退避ファイルを破棄する。それ以外の失敗では次回同じ新鍵で再送できるよう残す。
+    pub async fn rotate_device_key_v2(&self, device_key: &mut DeviceKey) -> Result<(), AuthError> {
+        let device_id = device_key
+            .device_id()
+            .ok_or_else(|| {
+                AuthError::Other(
+                    "rotate_device_key_v2 called before device is registered".to_string(),
+                )
+            })?
+            .to_string();
+
+        let url = self.v2_endpoint("anonymous_sync_v2_rotate_endpoint", |e| e.rotate.clone())?;
+        let pending = device_key.begin_rotation().await?;
+
+        let (status, error) = self
+            .post_rotation_v2(&url, &device_id, &pending, |message| {
+                device_key.sign_b64(message)
+            })
+            .await?;
+        if status.is_success() {
+            device_key.commit_rotation(pending).await?;
+            tracing::info!(device_id = %device_id, "anonymous-sync v2 rotate completed");
+            return Ok(());
+        }
+        tracing::warn!(
+            status = %status,
+            error = %error,
+            "anonymous-sync v2 rotate failed"
+        );
+        if !status.is_client_error() {
+            return Err(AuthError::RefreshFailed(format!(
+                "anonymous-sync v2 rotate failed: status {}",
+                status
+            )));
+        }
+
+        // サーバーがどちらの鍵を保持しているかを新鍵の署名で確かめる
+        let (confirm_status, confirm_error) = self
+            .post_rotation_v2(&url, &device_id, &pending, |message| {
+                pending.sign_b64(message)
+            })
+            .await?;
+        if confirm_status.is_success() {
+            device_key.commit_rotation(pending).await?;
+            tracing::info!(
+                device_id = %device_id,
+                "anonymous-sync v2 rotate: server already holds the staged key"
+            );
+            return Ok(());
+        }
+        if confirm_status == reqwest::StatusCode::UNAUTHORIZED
+            && confirm_error == "signature_invalid"
+        {
+            device_key.abort_rotation().await?;
+        } else {
+            tracing::warn!(
+                status = %confirm_status,
+                error = %confirm_error,
+                "anonymous-sync v2 rotate: could not tell which key the server holds; keeping staged key"
+            );
+        }
+        Err(AuthError::RefreshFailed(format!(
+            "anonymous-sync v2 rotate failed: status {}",
+            status
+        )))
+    }
+
+    /// 新しい challenge を取得して /v2/rotate に送り、ステータスとエラーコードを返す。
+    /// `sign` は `sig` を作る鍵。`new_sig` は常に退避中の新鍵で署名する。
+    async fn post_rotation_v2(
+        &self,
+        url: &str,
+        device_id: &str,
+        pending: &PendingRotation,
+        sign: impl Fn(&[u8]) -> String,
+    ) -> Result<(reqwest::StatusCode, String), AuthError> {
+        let challenge = self.fetch_challenge_v2(device_id).await?;
+        let message = format!(
+            "rotate|{}|{}|{}",
+            device_id,
+            pending.public_key_b64(),
+            challenge.nonce
+        );
+        let body = serde_json::json!({
+            "device_id": device_id,
+            "new_device_pub": pending.public_key_b64(),
+            "nonce": challenge.nonce,
+            "sig": sign(message.as_bytes()),
+            "new_sig": pending.sign_b64(message.as_bytes()),
+        });
+
+        let resp = self
+            .client
+            .post(url)
+            .header("apikey", &self.config.api_key)
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        if status.is_success() {
+            return Ok((status, String::new()));
+        }
+        let text = resp.text().await.unwrap_or_default();
+        let error = serde_json::from_str::<ErrorV2Response>(&text)
+            .unwrap_or_default()
+            .error;
+        Ok((status, error))
+    }
+
+    /// サインイン中のアカウント (Supabase セッション) に登録されている端末一覧を
+    /// GET /v2/devices で取得する。`device_key` が登録済みなら該当端末に `is_current` を付ける。
+    pub async fn list_devices_v2(
+        &self,
+        device_key: &DeviceKey,
+        include_revoked: bool,
+    ) -> Result<Vec<DeviceInfo>, AuthError> {
+        let url = self.v2_endpoint("anonymous_sync_v2_devices_endpoint", |e| e.devices.clone())?;
+        let include_revoked = if include_revoked { "1" } else { "0" };
+
+        let resp = self
+            .request_with_refresh(|client| {
+                client
+                    .get(&url)
+                    .query(&[("include_revoked", include_revoked)])
+                    .header("apikey", &self.config.api_key)
+            })
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            tracing::warn!(
+                status = %status,
+                body = %masked_error_payload(&text),
+                "anonymous-sync v2 devices failed"
+            );
+            return Err(AuthError::RefreshFailed(format!(
+                "anonymous-sync v2 devices failed: status {}",
+                status
+            )));
+        }
+
+        let parsed: DevicesV2Response = resp.json().await?;
+        let current = device_key.device_id();
+        let devices = parsed
+            .devices
+            .into_iter()
+            .map(|mut device| {
+                device.is_current = current == Some(device.device_id.as_str());
+                device
+            })
+            .collect();
+        Ok(devices)
+    }
+
+    /// 自端末以外の有効な端末をすべて失効させ、失効させた device_id を返す。
+    /// 途中で失敗した場合はそこで止め、それまでに失効できた分は取り消さない。
+    pub async fn revoke_other_devices_v2(
+        &self,
+        device_key: &DeviceKey,
+        reason: Option<&str>,
+    ) -> Result<Vec<String>, AuthError> {
+        let devices = self.list_devices_v2(device_key, false).await?;
+        let mut revoked = Vec::new();
+        for device in devices
+            .iter()
+            .filter(|d| !d.is_current && d.revoked_at.is_none())
+        {
+            self.revoke_device_v2(&device.device_id, device_key, reason)
+                .await?;
+            revoked.push(device.device_id.clone());
+        }
+        Ok(revoked)
+    }
+
     /// v2 のメイン入口。
     /// - device_key.device_id() が未確定なら register、確定済みなら challenge + refresh。
     /// - 既存 dataset_token が有効期限 1 日以上残っていればそのまま返す (v1 と同方針)。
@@ -51,6 +51,26 @@ pub struct DatasetTokenStore {
     pub tokens: HashMap<String, DatasetToken>,
 }
 
+/// v2 anonymous-sync で同一アカウント配下に登録されている端末の情報。
+/// `AuthManager::list_devices_v2` が返す。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub device_id: String,
+    /// サーバー側でマスク済みの pid (表示用)
+    #[serde(default)]
+    pub pid_masked: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_seen_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub revoked_reason: Option<String>,
+    /// 問い合わせ元の端末自身かどうか (クライアント側で付与する)
+    #[serde(default)]
+    pub is_current: bool,
+}
+
 impl MultiSession {
     pub fn new() -> Self {
         Self {
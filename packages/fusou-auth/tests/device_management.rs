@@ -0,0 +1,220 @@
+//! v2 anonymous-sync の端末管理 (register / rotate / devices / revoke) を
+//! fusou-mock-server に対して通しで検証する。
+//!
+//! mock は FUSOU-WEB の `/anonymous-sync/v2/*` と同じ署名メッセージを検証し、
+//! devices は同じく Supabase セッションの bearer で認可するため、
+//! クライアント側の署名フォーマットや認可方式が崩れるとここで失敗する。
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use fusou_auth::manager::AuthConfig;
+use fusou_auth::{AnonymousSyncV2Endpoints, AuthManager, DeviceKey, InMemoryStorage, Session};
+use fusou_mock_server::{FaultRule, MockServer};
+use rand::RngCore;
+
+const ROTATE_PATH: &str = "/api/auth/anonymous-sync/v2/rotate";
+
+fn auth_manager(server: &MockServer) -> AuthManager<InMemoryStorage> {
+    let endpoints = server.endpoints();
+    let config = AuthConfig {
+        supabase_url: endpoints.supabase_url,
+        api_key: "test-api-key".to_string(),
+        refresh_path: "/auth/v1/token".to_string(),
+        refresh_margin_secs: 30,
+    };
+    let mut manager = AuthManager::new(config, Arc::new(InMemoryStorage::new()));
+    manager.set_anonymous_sync_v2_endpoints(Some(AnonymousSyncV2Endpoints::with_base_url(
+        &endpoints.anonymous_sync_base,
+    )));
+    manager
+}
+
+/// `device_id` が紐づく Supabase ユーザーのセッションを manager に保存する。
+async fn sign_in_as_owner(
+    server: &MockServer,
+    manager: &AuthManager<InMemoryStorage>,
+    device_id: &str,
+) {
+    let user_id = server
+        .state()
+        .device_user_id(device_id)
+        .unwrap()
+        .to_string();
+    let issued = server.issue_session(&user_id);
+    manager
+        .save_session(&Session {
+            access_token: issued.access_token,
+            refresh_token: issued.refresh_token,
+            expires_at: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+            token_type: Some("bearer".to_string()),
+        })
+        .await
+        .unwrap();
+}
+
+fn temp_key_path(label: &str) -> PathBuf {
+    let mut bytes = [0u8; 8];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let suffix: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    std::env::temp_dir().join(format!("fusou-auth-device-mgmt-{label}-{suffix}.json"))
+}
+
+async fn registered_key(manager: &AuthManager<InMemoryStorage>, label: &str) -> DeviceKey {
+    let mut key = DeviceKey::load_or_create(temp_key_path(label))
+        .await
+        .unwrap();
+    manager
+        .register_device_v2("1234567890", &mut key)
+        .await
+        .unwrap();
+    key
+}
+
+#[tokio::test]
+async fn rotate_list_and_revoke_other_devices() {
+    let server = MockServer::start().await.unwrap();
+    let manager = auth_manager(&server);
+
+    let mut laptop = registered_key(&manager, "laptop").await;
+    let desktop = registered_key(&manager, "desktop").await;
+    let laptop_id = laptop.device_id().unwrap().to_string();
+    let desktop_id = desktop.device_id().unwrap().to_string();
+    sign_in_as_owner(&server, &manager, &laptop_id).await;
+
+    let devices = manager.list_devices_v2(&laptop, false).await.unwrap();
+    assert_eq!(devices.len(), 2);
+    let current: Vec<_> = devices.iter().filter(|d| d.is_current).collect();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0].device_id, laptop_id);
+
+    let old_pub = laptop.public_key_b64().to_string();
+    manager.rotate_device_key_v2(&mut laptop).await.unwrap();
+    assert_ne!(laptop.public_key_b64(), old_pub);
+    assert_eq!(
+        server.state().device_public_key(&laptop_id).unwrap(),
+        laptop.public_key_b64()
+    );
+
+    // 差し替え後の鍵がディスクにも反映されており、refresh が通る
+    let laptop_path_key = DeviceKey::load_or_create(laptop.storage_path().to_path_buf())
+        .await
+        .unwrap();
+    assert_eq!(laptop_path_key.public_key_b64(), laptop.public_key_b64());
+    manager
+        .refresh_dataset_token_v2("1234567890", &laptop)
+        .await
+        .unwrap();
+
+    let revoked = manager
+        .revoke_other_devices_v2(&laptop, Some("lost"))
+        .await
+        .unwrap();
+    assert_eq!(revoked, vec![desktop_id.clone()]);
+
+    let remaining = manager.list_devices_v2(&laptop, false).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    let all = manager.list_devices_v2(&laptop, true).await.unwrap();
+    assert!(all
+        .iter()
+        .any(|d| d.device_id == desktop_id && d.revoked_at.is_some()));
+
+    assert!(manager
+        .refresh_dataset_token_v2("1234567890", &desktop)
+        .await
+        .is_err());
+
+    let _ = tokio::fs::remove_file(laptop.storage_path()).await;
+    let _ = tokio::fs::remove_file(desktop.storage_path()).await;
+}
+
+#[tokio::test]
+async fn rejected_rotation_keeps_current_key() {
+    let server = MockServer::start().await.unwrap();
+    let manager = auth_manager(&server);
+    let mut key = registered_key(&manager, "rejected").await;
+    let old_pub = key.public_key_b64().to_string();
+
+    // 409 の後、新鍵での確認も signature_invalid になる (サーバーは旧鍵のまま) ので破棄する
+    server.inject_fault(FaultRule::status(ROTATE_PATH, 409, 1));
+    assert!(manager.rotate_device_key_v2(&mut key).await.is_err());
+    assert_eq!(key.public_key_b64(), old_pub);
+    assert!(!key.has_pending_rotation().await);
+    assert_eq!(
+        server
+            .state()
+            .device_public_key(key.device_id().unwrap())
+            .unwrap(),
+        old_pub
+    );
+
+    manager
+        .refresh_dataset_token_v2("1234567890", &key)
+        .await
+        .unwrap();
+
+    let _ = tokio::fs::remove_file(key.storage_path()).await;
+}
+
+#[tokio::test]
+async fn rotation_after_server_error_resubmits_staged_key() {
+    let server = MockServer::start().await.unwrap();
+    let manager = auth_manager(&server);
+    let mut key = registered_key(&manager, "retry").await;
+    let old_pub = key.public_key_b64().to_string();
+
+    server.inject_fault(FaultRule::status(ROTATE_PATH, 503, 1));
+    assert!(manager.rotate_device_key_v2(&mut key).await.is_err());
+    assert_eq!(key.public_key_b64(), old_pub);
+    assert!(key.has_pending_rotation().await);
+
+    let staged_pub = key
+        .begin_rotation()
+        .await
+        .unwrap()
+        .public_key_b64()
+        .to_string();
+    manager.rotate_device_key_v2(&mut key).await.unwrap();
+    assert_eq!(key.public_key_b64(), staged_pub);
+    assert!(!key.has_pending_rotation().await);
+    let rotate_calls = server
+        .requests()
+        .iter()
+        .filter(|r| r.path == ROTATE_PATH)
+        .count();
+    assert_eq!(rotate_calls, 2);
+
+    let _ = tokio::fs::remove_file(key.storage_path()).await;
+}
+
+#[tokio::test]
+async fn rotation_whose_response_was_lost_commits_staged_key() {
+    let server = MockServer::start().await.unwrap();
+    let manager = auth_manager(&server);
+    let mut key = registered_key(&manager, "lost").await;
+    let device_id = key.device_id().unwrap().to_string();
+    let old_pub = key.public_key_b64().to_string();
+
+    // サーバーは新鍵を確定したが、クライアントには 503 しか届かない
+    server.inject_fault(FaultRule::lose_response(ROTATE_PATH, 503, 1));
+    assert!(manager.rotate_device_key_v2(&mut key).await.is_err());
+    assert_eq!(key.public_key_b64(), old_pub);
+    assert!(key.has_pending_rotation().await);
+    let staged_pub = server
+        .state()
+        .device_public_key(&device_id)
+        .unwrap()
+        .to_string();
+    assert_ne!(staged_pub, old_pub);
+
+    // 再試行は旧鍵の署名で拒否されるが、新鍵での確認が通り差し替えが確定する
+    manager.rotate_device_key_v2(&mut key).await.unwrap();
+    assert_eq!(key.public_key_b64(), staged_pub);
+    assert!(!key.has_pending_rotation().await);
+    manager
+        .refresh_dataset_token_v2("1234567890", &key)
+        .await
+        .unwrap();
+
+    let _ = tokio::fs::remove_file(key.storage_path()).await;
+}
@@ -0,0 +1,81 @@
+//! Supabase セッションの refresh 経路を fusou-mock-server に対して検証する。
+//!
+//! mock の refresh_token は GoTrue と同じく使い捨てなので、同じトークンを
+//! 二重に使うような実装になっているとここで失敗する。
+
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use fusou_auth::manager::AuthConfig;
+use fusou_auth::{AuthManager, InMemoryStorage, Session};
+use fusou_mock_server::MockServer;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+async fn manager_with_session(
+    server: &MockServer,
+    expires_at: chrono::DateTime<Utc>,
+) -> AuthManager<InMemoryStorage> {
+    let issued = server.issue_session("user-1");
+    let config = AuthConfig {
+        supabase_url: server.endpoints().supabase_url,
+        api_key: "test-api-key".to_string(),
+        refresh_path: "/auth/v1/token".to_string(),
+        refresh_margin_secs: 30,
+    };
+    let manager = AuthManager::new(config, Arc::new(InMemoryStorage::new()));
+    manager
+        .save_session(&Session {
+            access_token: issued.access_token,
+            refresh_token: issued.refresh_token,
+            expires_at: Some(expires_at),
+            token_type: Some("bearer".to_string()),
+        })
+        .await
+        .unwrap();
+    manager
+}
+
+#[tokio::test]
+async fn expired_session_is_refreshed_once() {
+    let server = MockServer::start().await.unwrap();
+    let manager = manager_with_session(&server, Utc::now() - Duration::minutes(1)).await;
+    let before = manager.peek_session().await.unwrap().unwrap();
+
+    let token = manager.get_access_token().await.unwrap();
+    assert_ne!(token, before.access_token);
+    let after = manager.peek_session().await.unwrap().unwrap();
+    assert_ne!(after.refresh_token, before.refresh_token);
+
+    // 2 回目は保存済みの新しいトークンをそのまま使う
+    assert_eq!(manager.get_access_token().await.unwrap(), token);
+    let refreshes = server
+        .requests()
+        .iter()
+        .filter(|r| r.path == "/auth/v1/token")
+        .count();
+    assert_eq!(refreshes, 1);
+}
+
+#[tokio::test]
+async fn request_with_refresh_retries_after_401() {
+    let server = MockServer::start().await.unwrap();
+    let manager = manager_with_session(&server, Utc::now() + Duration::hours(1)).await;
+    let url = server.endpoints().fleet_snapshot;
+    server.expire_access_tokens();
+
+    let body = json!({ "content_hash": hex::encode(Sha256::digest(b"snapshot")) });
+    let resp = manager
+        .request_with_refresh(|client| client.post(&url).json(&body))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let statuses: Vec<u16> = server
+        .requests()
+        .iter()
+        .filter(|r| r.path == "/api/fleet/snapshot")
+        .map(|r| r.status)
+        .collect();
+    assert_eq!(statuses, vec![401, 200]);
+}
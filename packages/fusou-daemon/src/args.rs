@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: fusou-daemon [OPTIONS]
+
+Options:
+  --config <PATH>     configs.toml to use (created from defaults when missing)
+                      [default: <data dir>/configs.toml]
+  --data-dir <PATH>   Directory for the session, pending uploads, CA and saved data
+                      [default: [daemon].data_dir, else the platform data directory]
+  -h, --help          Print help
+  -V, --version       Print version";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DaemonArgs {
+    pub config: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Run(DaemonArgs),
+    Help,
+    Version,
+}
+
+impl Command {
+    /// Parse arguments without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = DaemonArgs::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            let mut value = |flag: &str| {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .filter(|value| !value.is_empty())
+                    .map(PathBuf::from)
+                    .ok_or_else(|| format!("{flag} requires a value"))
+            };
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-V" | "--version" => return Ok(Command::Version),
+                "--config" => parsed.config = Some(value("--config")?),
+                "--data-dir" => parsed.data_dir = Some(value("--data-dir")?),
+                other => return Err(format!("unknown argument: {other}")),
+            }
+        }
+        Ok(Command::Run(parsed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Command::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_paths_in_both_forms() {
+        assert_eq!(
+            parse(&[
+                "--config",
+                "/etc/fusou/configs.toml",
+                "--data-dir=/var/lib/fusou"
+            ]),
+            Ok(Command::Run(DaemonArgs {
+                config: Some(PathBuf::from("/etc/fusou/configs.toml")),
+                data_dir: Some(PathBuf::from("/var/lib/fusou")),
+            }))
+        );
+        assert_eq!(parse(&[]), Ok(Command::Run(DaemonArgs::default())));
+        assert_eq!(parse(&["-V"]), Ok(Command::Version));
+        assert!(parse(&["--config"]).is_err());
+        assert!(parse(&["--port", "1"]).is_err());
+    }
+}
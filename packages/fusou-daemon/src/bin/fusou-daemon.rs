@@ -0,0 +1,39 @@
+use fusou_daemon::{args::USAGE, load_configs, run, Command};
+use tracing_subscriber::EnvFilter;
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    let args = match Command::parse(std::env::args().skip(1)) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return;
+        }
+        Ok(Command::Version) => {
+            println!("fusou-daemon {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    let result = match load_configs(&args) {
+        Ok(paths) => {
+            tracing::info!("data directory: {}", paths.root.display());
+            run(paths).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        tracing::error!("fusou-daemon failed: {}", e);
+        std::process::exit(1);
+    }
+}
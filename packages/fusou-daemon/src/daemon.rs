@@ -0,0 +1,221 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use fusou_auth::{AuthManager, FileStorage};
+use fusou_storage::StorageRetryHandler;
+use fusou_upload::{PendingStore, UploadRetryService};
+use kc_api::parser::quarantine::EndpointQuarantine;
+use proxy_https::bidirectional_channel::{request_shutdown, BidirectionalChannel, StatusInfo};
+use proxy_https::ca_manager::{CaEnsureOutcome, CaManager, CaPolicy};
+use proxy_https::proxy_hook::ProxyHooks;
+use proxy_https::proxy_server_https::serve_proxy;
+use tokio::sync::watch;
+
+use crate::args::DaemonArgs;
+use crate::parser::run_parser;
+use crate::paths::DaemonPaths;
+use crate::status::{serve_status, DaemonStatus};
+use crate::storage::StorageSubmitter;
+
+type DaemonResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Load the config file and resolve the data directory. `--data-dir` wins
+/// over `[daemon].data_dir`, which wins over the platform default.
+pub fn load_configs(args: &DaemonArgs) -> DaemonResult<DaemonPaths> {
+    let bootstrap_root = args
+        .data_dir
+        .clone()
+        .unwrap_or_else(DaemonPaths::default_root);
+    let config_path = args
+        .config
+        .clone()
+        .unwrap_or_else(|| bootstrap_root.join("configs.toml"));
+    if let Some(parent) = config_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    configs::set_user_config(&config_path.to_string_lossy())?;
+
+    let root = args
+        .data_dir
+        .clone()
+        .or_else(|| configs::get_user_configs_for_daemon().get_data_dir())
+        .unwrap_or(bootstrap_root);
+    std::fs::create_dir_all(&root)?;
+    Ok(DaemonPaths::new(root))
+}
+
+fn build_auth_manager(paths: &DaemonPaths) -> DaemonResult<AuthManager<FileStorage>> {
+    let storage = Arc::new(FileStorage::new(paths.session()));
+    let mut auth_manager = AuthManager::from_env(storage)?;
+    auth_manager.set_dataset_token_path(Some(paths.dataset_token()));
+
+    let auth_manager_for_hooks = Arc::new(auth_manager.clone());
+    if let Err(err) = fusou_storage::set_dataset_id_resolver(move || {
+        let auth_manager = auth_manager_for_hooks.clone();
+        async move {
+            auth_manager
+                .resolve_dataset_id_for_upload(None)
+                .await
+                .unwrap_or_default()
+        }
+    }) {
+        tracing::debug!(%err, "storage dataset_id resolver hook already initialized");
+    }
+    Ok(auth_manager)
+}
+
+fn ensure_ca(paths: &DaemonPaths) -> DaemonResult<()> {
+    if !configs::get_user_configs_for_proxy()
+        .certificates
+        .get_use_generated_certs()
+    {
+        return Ok(());
+    }
+    let ca_manager = CaManager::new(
+        paths.ca(),
+        CaPolicy::from_configs(&configs::get_user_configs_for_proxy().certificates),
+    );
+    let outcome = ca_manager.ensure(chrono::Utc::now())?;
+    if let CaEnsureOutcome::Staged { next } = &outcome {
+        tracing::warn!(
+            activate_after = %next.activate_after,
+            "next CA certificate is staged in {}; install it on clients before it is activated",
+            ca_manager.next_dir().display()
+        );
+    } else if outcome.needs_install() {
+        tracing::warn!(
+            ?outcome,
+            "CA certificate was created or rotated; clients must install it"
+        );
+    }
+    // Clients routed through the daemon must trust this certificate.
+    tracing::info!("CA certificate directory: {}", paths.ca().display());
+    Ok(())
+}
+
+fn spawn_retry_loop(retry_service: Arc<UploadRetryService>, mut shutdown: watch::Receiver<bool>) {
+    let retry_interval_seconds = configs::get_user_configs_for_app()
+        .asset_sync
+        .retry
+        .get_interval_seconds()
+        .max(1);
+    tokio::spawn(async move {
+        tracing::info!(
+            retry_interval_seconds,
+            "starting background pending upload retry loop"
+        );
+        retry_service.trigger_retry_force().await;
+
+        let mut ticker = tokio::time::interval(Duration::from_secs(retry_interval_seconds));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => retry_service.trigger_retry().await,
+                _ = shutdown.changed() => break,
+            }
+        }
+    });
+}
+
+/// Resolves on Ctrl+C, or SIGTERM on Unix.
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => tracing::info!("Received Ctrl+C, shutting down."),
+                    _ = terminate.recv() => tracing::info!("Received SIGTERM, shutting down."),
+                }
+                return;
+            }
+            Err(e) => tracing::warn!("failed to install SIGTERM handler: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+    tracing::info!("Received Ctrl+C, shutting down.");
+}
+
+/// Run the proxy, parser, storage and upload retry until a shutdown signal.
+/// Asset sync is started by [`serve_proxy`] when `[app.asset_sync].enable`
+/// is set.
+pub async fn run(paths: DaemonPaths) -> DaemonResult<()> {
+    let auth_manager = build_auth_manager(&paths)?;
+    let auth_manager_for_retry = Arc::new(auth_manager.clone());
+
+    let pending_store = Arc::new(PendingStore::new(paths.pending_uploads()));
+    let retry_handler = Arc::new(StorageRetryHandler::new(auth_manager_for_retry.clone()));
+    let retry_service = Arc::new(UploadRetryService::new(
+        pending_store.clone(),
+        auth_manager_for_retry,
+        Some(retry_handler),
+    ));
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    spawn_retry_loop(retry_service.clone(), shutdown_rx.clone());
+
+    ensure_ca(&paths)?;
+    let period_tag = fusou_storage::get_period_tag().await;
+    let local_id = paths.load_or_create_local_id()?;
+    let file_prefix = auth_manager
+        .resolve_dataset_id_for_upload(None)
+        .await
+        .filter(|id| !id.trim().is_empty())
+        .unwrap_or_else(|| local_id.clone());
+
+    let proxy_channel = BidirectionalChannel::<StatusInfo>::new(1);
+    let proxy_log_channel = BidirectionalChannel::<StatusInfo>::new(1);
+
+    let status = Arc::new(DaemonStatus::default());
+    let proxy_addr = serve_proxy(
+        0,
+        proxy_channel.clone_slave(),
+        proxy_log_channel.clone_master(),
+        paths.proxy_data(&period_tag).to_string_lossy().to_string(),
+        paths.asset_sync(&period_tag).to_string_lossy().to_string(),
+        paths.ca().to_string_lossy().to_string(),
+        file_prefix,
+        Arc::new(auth_manager),
+        ProxyHooks::new(),
+    )
+    .map_err(|e| format!("failed to start proxy server: {e}"))?;
+    status.set_proxy_addr(proxy_addr);
+    tracing::info!("Proxy listening on {}", proxy_addr);
+
+    let parser = tokio::spawn(run_parser(
+        proxy_log_channel.clone_slave(),
+        status.clone(),
+        StorageSubmitter::new(pending_store.clone(), retry_service.clone(), local_id),
+        paths.api_drift(),
+        EndpointQuarantine::new(paths.quarantine()),
+        shutdown_rx.clone(),
+    ));
+
+    let status_addr = {
+        let mut shutdown_rx = shutdown_rx.clone();
+        serve_status(
+            status,
+            pending_store,
+            retry_service,
+            Some(proxy_channel.clone_master()),
+            configs::get_user_configs_for_daemon().get_status_port(),
+            async move {
+                let _ = shutdown_rx.changed().await;
+            },
+        )
+        .map_err(|e| format!("failed to start status server: {e}"))?
+    };
+    tracing::info!("Status endpoint on http://{}/status", status_addr);
+
+    shutdown_signal().await;
+
+    if let Err(e) = request_shutdown(proxy_channel.clone_master()).await {
+        tracing::warn!("proxy did not confirm shutdown: {}", e);
+    }
+    let _ = shutdown_tx.send(true);
+    let _ = parser.await;
+    tracing::info!("fusou-daemon stopped");
+    Ok(())
+}
@@ -0,0 +1,18 @@
+//! Headless FUSOU: the HTTPS proxy, the API parser, storage providers and the
+//! upload retry service without the Tauri UI.
+//!
+//! Reads the same `configs.toml` as the desktop app (plus its `[daemon]`
+//! table) and serves `/health`, `/status` and `/metrics` on 127.0.0.1 for
+//! supervisors and monitoring. Stops gracefully on Ctrl+C or SIGTERM.
+
+pub mod args;
+pub mod daemon;
+pub mod parser;
+pub mod paths;
+pub mod status;
+pub mod storage;
+
+pub use args::{Command, DaemonArgs};
+pub use daemon::{load_configs, run};
+pub use paths::DaemonPaths;
+pub use status::{serve_status, DaemonStatus, StatusReport};
@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use kc_api::interface::cells::Cells;
+use kc_api::interface::interface::{Add, EmitData, Identifier, Set};
+use kc_api::interface::ship::Ships;
+use kc_api::parser::drift;
+use kc_api::parser::error::ParseError;
+use kc_api::parser::parser::{request_parser, response_parser};
+use kc_api::parser::quarantine::EndpointQuarantine;
+use proxy_https::bidirectional_channel::{Slave, StatusInfo};
+use proxy_https::game_server::GameServer;
+use tokio::sync::watch;
+
+use crate::status::DaemonStatus;
+use crate::storage::StorageSubmitter;
+
+pub fn parse_response(path: String, content: String) -> Result<Vec<EmitData>, ParseError> {
+    let content = content.replace('\u{feff}', "").replace("svdata=", "");
+    response_parser(path, content)
+}
+
+pub fn parse_request(path: String, content: String) -> Result<Vec<EmitData>, ParseError> {
+    request_parser(path, content.replace('\u{feff}', ""))
+}
+
+/// Apply parsed data to the in-memory game state and trigger table uploads,
+/// mirroring the desktop app's `json_parser::emit_data` without the UI
+/// events. `server` is the game server the parsed message came from.
+pub fn apply_emit_data(emit_data: EmitData, storage: &StorageSubmitter, server: &GameServer) {
+    match emit_data {
+        EmitData::Add(data) => match data {
+            Add::Ships(data) => data.add_or(),
+            Add::Battle(data) => data.add_or(),
+            Add::Cell(data) => data.add_or(),
+            Add::Materials(_) | Add::QuestEvent(_) | Add::Dammy(_) => {}
+        },
+        EmitData::Set(data) => match data {
+            Set::DeckPorts(data) => data.restore(),
+            Set::Basic(data) => data.restore(),
+            Set::UseItems(data) => data.restore(),
+            Set::Ships(data) => data.restore(),
+            Set::SlotItems(data) => data.restore(),
+            Set::AirBases(data) => data.restore(),
+            Set::MstShips(data) => data.restore(),
+            Set::MstSlotItems(data) => data.restore(),
+            Set::MstEquipExslotShips(data) => data.restore(),
+            Set::MstEquipShips(data) => data.restore(),
+            Set::MstStypes(data) => data.restore(),
+            Set::MstUseItems(data) => data.restore(),
+            Set::MstSlotItemEquipTypes(data) => data.restore(),
+            Set::Cells(data) => data.restore(),
+            Set::MstMapAreas(data) => data.restore(),
+            Set::MstMapInfos(data) => data.restore(),
+            Set::MstShipGraphs(data) => data.restore(),
+            Set::MstShipUpgrades(data) => data.restore(),
+            Set::MstEquipExslots(data) => data.restore(),
+            Set::MstEquipLimitExslots(data) => data.restore(),
+            Set::Quests(data) => data.restore(),
+            // Sender snapshots are only uploaded by the desktop app.
+            Set::Materials(_)
+            | Set::NDocks(_)
+            | Set::Logs(_)
+            | Set::ShipGrowthSnapshot(_)
+            | Set::SokuSpeedObservedSnapshot(_)
+            | Set::RemodelSlotList(_)
+            | Set::RemodelDetail(_)
+            | Set::ImprovementAttempt(_)
+            | Set::Dammy(_) => {}
+        },
+        EmitData::Identifier(data) => match data {
+            Identifier::Port(_) => storage.submit_port_table(server),
+            Identifier::RequireInfo(_) => {}
+            Identifier::GetData(_) => {
+                storage.submit_get_data_table();
+                Cells::reset();
+            }
+            Identifier::MapStart(_) => Ships::cashe_restore(),
+            Identifier::ApiError(error) => {
+                tracing::warn!(
+                    endpoint = %error.endpoint,
+                    api_result = error.api_result,
+                    class = ?error.class,
+                    "game API error: {}",
+                    error.api_result_msg
+                );
+                error.interrupt_sortie();
+            }
+        },
+    }
+}
+
+/// Rewrite the API drift report in `dir` when the last message changed it.
+fn write_api_drift(dir: &std::path::Path) {
+    let changes = drift::take_new_changes();
+    if changes.is_empty() {
+        return;
+    }
+    match drift::write_report(dir) {
+        Ok(path) => tracing::warn!(
+            changes = changes.len(),
+            "game API changed, see {}",
+            path.display()
+        ),
+        Err(e) => tracing::warn!("failed to write API drift report: {}", e),
+    }
+}
+
+/// Parse every API message the proxy forwards until `shutdown` turns true.
+/// Messages are handled one at a time so state updates keep their order.
+/// Game API changes found while parsing are reported in `drift_dir`, and
+/// calls to endpoints without a DTO are kept in `quarantine`.
+pub async fn run_parser(
+    mut proxy_log: Slave<StatusInfo>,
+    status: Arc<DaemonStatus>,
+    storage: StorageSubmitter,
+    drift_dir: PathBuf,
+    quarantine: EndpointQuarantine,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut last_server = None;
+    loop {
+        let message = tokio::select! {
+            message = proxy_log.recv() => message,
+            _ = shutdown.changed() => break,
+        };
+        let (parsed, server) = match message {
+            Some(StatusInfo::RESPONSE {
+                path,
+                content,
+                server,
+                ..
+            }) => {
+                server.track_switch(&mut last_server);
+                status.record_message(&path, false);
+                quarantine.record_response(&path, &content);
+                (parse_response(path, content), server)
+            }
+            Some(StatusInfo::REQUEST {
+                path,
+                content,
+                server,
+                ..
+            }) => {
+                server.track_switch(&mut last_server);
+                status.record_message(&path, true);
+                quarantine.record_request(&path, &content);
+                (parse_request(path, content), server)
+            }
+            Some(_) => continue,
+            None => {
+                tracing::warn!("proxy log channel closed");
+                break;
+            }
+        };
+        match parsed {
+            Ok(emit_data_list) => {
+                status.record_events(emit_data_list.len());
+                for emit_data in emit_data_list {
+                    apply_emit_data(emit_data, &storage, &server);
+                }
+            }
+            // Logged and counted by kind in the parser; see `/status`.
+            Err(_) => status.record_parse_error(),
+        }
+        write_api_drift(&drift_dir);
+    }
+    tracing::info!("Parser stopped");
+}
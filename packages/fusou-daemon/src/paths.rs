@@ -0,0 +1,82 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Files and directories used by the daemon, all below one data directory.
+/// Names match the desktop app's roaming directory so an existing session
+/// can be copied over.
+#[derive(Debug, Clone)]
+pub struct DaemonPaths {
+    pub root: PathBuf,
+}
+
+impl DaemonPaths {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Platform data directory, e.g. `~/.local/share/fusou-daemon`.
+    pub fn default_root() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("fusou-daemon")
+    }
+
+    pub fn session(&self) -> PathBuf {
+        self.root.join("fusou-auth-session.json")
+    }
+
+    pub fn dataset_token(&self) -> PathBuf {
+        self.root.join("fusou-auth-dataset-token.json")
+    }
+
+    pub fn pending_uploads(&self) -> PathBuf {
+        self.root.join("pending_uploads")
+    }
+
+    pub fn ca(&self) -> PathBuf {
+        self.root.join("ca")
+    }
+
+    /// Raw API captures for `proxy.allow_save_*`, per period.
+    pub fn proxy_data(&self, period_tag: &str) -> PathBuf {
+        self.root.join("FUSOU-PROXY-DATA").join(period_tag)
+    }
+
+    /// Report of game API changes found by the parser.
+    pub fn api_drift(&self) -> PathBuf {
+        self.root.join("api_drift")
+    }
+
+    /// Captures of API endpoints the parser has no DTO for.
+    pub fn quarantine(&self) -> PathBuf {
+        self.root.join("quarantine")
+    }
+
+    pub fn asset_sync(&self, period_tag: &str) -> PathBuf {
+        self.root.join("asset_sync").join(period_tag)
+    }
+
+    /// Random id used as dataset id while no account is linked, persisted
+    /// like the desktop app's `user/ENV_UNIQ_ID`.
+    pub fn load_or_create_local_id(&self) -> std::io::Result<String> {
+        let path = self.root.join("user").join("ENV_UNIQ_ID");
+        if let Ok(contents) = std::fs::read_to_string(&path) {
+            let id = contents.trim();
+            if !id.is_empty() {
+                return Ok(id.to_string());
+            }
+        }
+        create_parent(&path)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut file = std::fs::File::create(&path)?;
+        writeln!(file, "{id}")?;
+        Ok(id)
+    }
+}
+
+fn create_parent(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) => std::fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
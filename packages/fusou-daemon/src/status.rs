@@ -0,0 +1,245 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use fusou_upload::{PendingStore, UploadMetricsSnapshot, UploadRetryService};
+use kc_api::parser::drift::{self, DriftReport};
+use kc_api::parser::error::{self as parse_error, ParseErrorReport};
+use proxy_https::bidirectional_channel::{check_health, Master, StatusInfo};
+use proxy_https::leaf_cert_cache::{leaf_cert_cache_stats, LeafCacheStats};
+use proxy_https::telemetry::{telemetry_snapshot, TelemetrySnapshot};
+use serde::Serialize;
+use warp::http::StatusCode;
+use warp::Filter;
+
+/// Counters updated by the daemon's tasks and reported on `/status`.
+pub struct DaemonStatus {
+    started_at: chrono::DateTime<chrono::Utc>,
+    proxy_addr: Mutex<Option<SocketAddr>>,
+    requests: AtomicU64,
+    responses: AtomicU64,
+    parse_errors: AtomicU64,
+    events: AtomicU64,
+    last_api: Mutex<Option<LastApi>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LastApi {
+    pub path: String,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiCounters {
+    pub requests: u64,
+    pub responses: u64,
+    pub parse_errors: u64,
+    pub events: u64,
+    pub last: Option<LastApi>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub version: &'static str,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub uptime_secs: i64,
+    pub proxy_addr: Option<SocketAddr>,
+    pub api: ApiCounters,
+    pub retry_running: bool,
+    pub uploads: UploadMetricsSnapshot,
+    /// `None` until the proxy has started.
+    pub leaf_cert_cache: Option<LeafCacheStats>,
+    /// Latency and errors of the last minutes; `None` until the proxy has
+    /// started or when `[proxy.telemetry]` is disabled.
+    pub telemetry: Option<TelemetrySnapshot>,
+    /// Game API fields that differ from the DTOs, per endpoint.
+    pub api_drift: DriftReport,
+    /// Parse errors per endpoint and class, with the last error of each.
+    pub parse_errors: ParseErrorReport,
+}
+
+impl Default for DaemonStatus {
+    fn default() -> Self {
+        Self {
+            started_at: chrono::Utc::now(),
+            proxy_addr: Mutex::new(None),
+            requests: AtomicU64::new(0),
+            responses: AtomicU64::new(0),
+            parse_errors: AtomicU64::new(0),
+            events: AtomicU64::new(0),
+            last_api: Mutex::new(None),
+        }
+    }
+}
+
+impl DaemonStatus {
+    pub fn set_proxy_addr(&self, addr: SocketAddr) {
+        *self.proxy_addr.lock().unwrap() = Some(addr);
+    }
+
+    pub fn record_message(&self, path: &str, is_request: bool) {
+        let counter = if is_request {
+            &self.requests
+        } else {
+            &self.responses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        *self.last_api.lock().unwrap() = Some(LastApi {
+            path: path.to_string(),
+            at: chrono::Utc::now(),
+        });
+    }
+
+    pub fn record_parse_error(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_events(&self, count: usize) {
+        self.events.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn api_counters(&self) -> ApiCounters {
+        ApiCounters {
+            requests: self.requests.load(Ordering::Relaxed),
+            responses: self.responses.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            events: self.events.load(Ordering::Relaxed),
+            last: self.last_api.lock().unwrap().clone(),
+        }
+    }
+
+    pub fn report(&self, store: &PendingStore, retry_service: &UploadRetryService) -> StatusReport {
+        StatusReport {
+            version: env!("CARGO_PKG_VERSION"),
+            started_at: self.started_at,
+            uptime_secs: (chrono::Utc::now() - self.started_at).num_seconds(),
+            proxy_addr: *self.proxy_addr.lock().unwrap(),
+            api: self.api_counters(),
+            retry_running: retry_service.is_running(),
+            uploads: retry_service.metrics().snapshot(store),
+            leaf_cert_cache: leaf_cert_cache_stats(),
+            telemetry: telemetry_snapshot(),
+            api_drift: drift::report(),
+            parse_errors: parse_error::report(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct StatusState {
+    status: Arc<DaemonStatus>,
+    store: Arc<PendingStore>,
+    retry_service: Arc<UploadRetryService>,
+    /// `None` when the proxy is not checked (tests); health checks are
+    /// serialized because replies arrive on a shared channel.
+    proxy: Option<Arc<tokio::sync::Mutex<Master<StatusInfo>>>>,
+}
+
+async fn health(state: StatusState) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let proxy_ok = match &state.proxy {
+        Some(proxy) => {
+            let master = proxy.lock().await;
+            check_health(master.clone()).await.is_ok()
+        }
+        None => true,
+    };
+    let (status, code) = if proxy_ok {
+        ("ok", StatusCode::OK)
+    } else {
+        ("proxy unavailable", StatusCode::SERVICE_UNAVAILABLE)
+    };
+    Ok(Box::new(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({ "status": status })),
+        code,
+    )))
+}
+
+/// Serve `/health` and `/status` (JSON) and `/metrics` (Prometheus text) on
+/// `127.0.0.1:port` until `shutdown` resolves.
+pub fn serve_status(
+    status: Arc<DaemonStatus>,
+    store: Arc<PendingStore>,
+    retry_service: Arc<UploadRetryService>,
+    proxy: Option<Master<StatusInfo>>,
+    port: u16,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<SocketAddr, Box<dyn std::error::Error>> {
+    let state = StatusState {
+        status,
+        store,
+        retry_service,
+        proxy: proxy.map(|master| Arc::new(tokio::sync::Mutex::new(master))),
+    };
+    let with_state = warp::any().map(move || state.clone());
+
+    let health = warp::path!("health")
+        .and(warp::get())
+        .and(with_state.clone())
+        .and_then(health);
+
+    let status = warp::path!("status")
+        .and(warp::get())
+        .and(with_state.clone())
+        .map(|state: StatusState| {
+            warp::reply::json(&state.status.report(&state.store, &state.retry_service))
+        });
+
+    let metrics =
+        warp::path!("metrics")
+            .and(warp::get())
+            .and(with_state)
+            .map(|state: StatusState| {
+                let api = state.status.api_counters();
+                let mut body = state
+                    .retry_service
+                    .metrics()
+                    .snapshot(&state.store)
+                    .to_prometheus();
+                for (name, help, value) in [
+                    (
+                        "fusou_daemon_api_requests_total",
+                        "Parsed API requests",
+                        api.requests,
+                    ),
+                    (
+                        "fusou_daemon_api_responses_total",
+                        "Parsed API responses",
+                        api.responses,
+                    ),
+                    (
+                        "fusou_daemon_api_parse_errors_total",
+                        "API messages that failed to parse",
+                        api.parse_errors,
+                    ),
+                    (
+                        "fusou_daemon_events_total",
+                        "Parsed game events",
+                        api.events,
+                    ),
+                ] {
+                    body.push_str(&format!(
+                        "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n"
+                    ));
+                }
+                let name = "fusou_daemon_api_parse_errors_by_kind_total";
+                body.push_str(&format!(
+                    "# HELP {name} API messages that failed to parse, by error class\n# TYPE {name} counter\n"
+                ));
+                for (kind, count) in parse_error::report().by_kind {
+                    body.push_str(&format!(
+                        "{name}{{kind=\"{}\"}} {count}\n",
+                        kind.as_str()
+                    ));
+                }
+                warp::reply::with_header(body, "content-type", "text/plain; version=0.0.4")
+            });
+
+    let (addr, server) = warp::serve(health.or(status).or(metrics))
+        .try_bind_with_graceful_shutdown(([127, 0, 0, 1], port), shutdown)?;
+    tracing::info!("Status server addr: {}", addr);
+
+    tokio::task::spawn(server);
+
+    Ok(addr)
+}
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use fusou_storage::service::{acquire_port_table_guard, StorageService};
+use fusou_upload::{PendingStore, UploadRetryService};
+use kc_api::database::table::{GetDataTable, PortTable, PortTableEncode};
+use kc_api::interface::api_error::ApiError;
+use kc_api::interface::cells::Cells;
+use proxy_https::game_server::GameServer;
+
+/// Writes parsed tables to the configured storage providers, as
+/// `FUSOU-APP`'s `storage::submit_data` does for the desktop app.
+#[derive(Clone)]
+pub struct StorageSubmitter {
+    pending_store: Arc<PendingStore>,
+    retry_service: Arc<UploadRetryService>,
+    local_id: String,
+}
+
+impl StorageSubmitter {
+    pub fn new(
+        pending_store: Arc<PendingStore>,
+        retry_service: Arc<UploadRetryService>,
+        local_id: String,
+    ) -> Self {
+        Self {
+            pending_store,
+            retry_service,
+            local_id,
+        }
+    }
+
+    async fn service(&self) -> Option<&'static StorageService> {
+        StorageService::get_instance(self.pending_store.clone(), self.retry_service.clone()).await
+    }
+
+    pub fn submit_get_data_table(&self) {
+        let submitter = self.clone();
+        tokio::task::spawn(async move {
+            let Some(storage_service) = submitter.service().await else {
+                return;
+            };
+            match GetDataTable::new().encode() {
+                Ok(encoded) => {
+                    let period_tag = fusou_storage::get_period_tag().await;
+                    storage_service
+                        .write_get_data_table(&period_tag, encoded)
+                        .await;
+                }
+                Err(e) => tracing::error!("Failed to encode get data table: {}", e),
+            }
+        });
+    }
+
+    /// Upload the accumulated cells, tagged with the server of the `port`
+    /// response that ended the sortie.
+    pub fn submit_port_table(&self, server: &GameServer) {
+        if Cells::reset_flag() {
+            tracing::info!(
+                "submit_port_table: skipped (Cells reset_flag true, no accumulated data)"
+            );
+            return;
+        }
+        if let Some(error) = ApiError::take_sortie_interruption() {
+            tracing::warn!(
+                "submit_port_table: skipped (sortie interrupted by API error {} at {})",
+                error.api_result,
+                error.endpoint
+            );
+            return;
+        }
+        let cells = Cells::load();
+        let server_index = server.index;
+        let submitter = self.clone();
+        tokio::task::spawn(async move {
+            let Some(storage_service) = submitter.service().await else {
+                return;
+            };
+            let _guard = acquire_port_table_guard().await;
+
+            let maparea_id = cells.maparea_id;
+            let mapinfo_no = cells.mapinfo_no;
+            let user_env = submitter
+                .retry_service
+                .auth_manager()
+                .resolve_dataset_id_for_upload(None)
+                .await
+                .filter(|id| !id.trim().is_empty())
+                .unwrap_or_else(|| submitter.local_id.clone());
+            let port_table = PortTable::new(
+                cells,
+                user_env,
+                server_index,
+                chrono::Utc::now().timestamp(),
+            );
+
+            match port_table.encode_non_empty_tables() {
+                Ok(tables) if tables.is_empty() => {
+                    tracing::info!("submit_port_table: all tables empty — skipping upload");
+                }
+                Ok(tables) => {
+                    let encode: PortTableEncode = tables.into();
+                    let period_tag = fusou_storage::get_period_tag().await;
+                    if !storage_service
+                        .write_port_table(&period_tag, encode, maparea_id, mapinfo_no)
+                        .await
+                    {
+                        tracing::warn!(
+                            "submit_port_table: upload failed for all providers; retry pipeline will handle pending uploads"
+                        );
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to encode port table (non-empty): {}", e);
+                    return;
+                }
+            }
+            Cells::reset();
+        });
+    }
+}
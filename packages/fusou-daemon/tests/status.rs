@@ -0,0 +1,86 @@
+//! The status server reports parser counters and the upload queue.
+
+use std::sync::Arc;
+
+use fusou_auth::manager::AuthConfig;
+use fusou_auth::{AuthManager, FileStorage};
+use fusou_daemon::{serve_status, DaemonStatus};
+use fusou_upload::{PendingStore, UploadRetryService};
+use kc_api::parser::error::ParseErrorKind;
+
+#[tokio::test]
+async fn status_endpoints_report_counters() {
+    let dir = std::env::temp_dir().join(format!("fusou-daemon-status-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let auth_manager = Arc::new(AuthManager::new(
+        AuthConfig {
+            supabase_url: "http://127.0.0.1:9".to_string(),
+            api_key: "test-api-key".to_string(),
+            refresh_path: "/auth/v1/token".to_string(),
+            refresh_margin_secs: 30,
+        },
+        Arc::new(FileStorage::new(dir.join("session.json"))),
+    ));
+    let store = Arc::new(PendingStore::new(dir.join("pending")));
+    let retry_service = Arc::new(UploadRetryService::new(store.clone(), auth_manager, None));
+
+    let status = Arc::new(DaemonStatus::default());
+    status.record_message("/kcsapi/api_port/port", false);
+    status.record_events(3);
+    status.record_parse_error();
+    let error = fusou_daemon::parser::parse_response(
+        "/kcsapi/api_unknown/call".to_string(),
+        "{}".to_string(),
+    )
+    .unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::UnknownEndpoint);
+
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let addr = serve_status(status, store, retry_service, None, 0, async move {
+        let _ = stopped.await;
+    })
+    .unwrap();
+    let client = reqwest::Client::new();
+
+    let health = client
+        .get(format!("http://{addr}/health"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(health.status(), 200);
+
+    let report: serde_json::Value = client
+        .get(format!("http://{addr}/status"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(report["api"]["responses"], 1);
+    assert_eq!(report["api"]["events"], 3);
+    assert_eq!(report["api"]["parse_errors"], 1);
+    assert_eq!(report["api"]["last"]["path"], "/kcsapi/api_port/port");
+    assert_eq!(
+        report["parse_errors"]["endpoints"]["/kcsapi/api_unknown/call"]["last"]["kind"],
+        "unknown_endpoint"
+    );
+    assert_eq!(report["uploads"]["queued"], 0);
+
+    let metrics = client
+        .get(format!("http://{addr}/metrics"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(metrics.contains("fusou_daemon_events_total 3\n"));
+    assert!(metrics
+        .contains("fusou_daemon_api_parse_errors_by_kind_total{kind=\"unknown_endpoint\"} 1\n"));
+    assert!(metrics.contains("fusou_upload_queue_pending 0\n"));
+
+    let _ = stop.send(());
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,36 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use fusou_mock_server::{MockServer, MockServerConfig, RateLimit};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let bind_addr: SocketAddr = std::env::var("FUSOU_MOCK_BIND")
+        .unwrap_or_else(|_| "127.0.0.1:8787".to_string())
+        .parse()?;
+    // "<max_requests>/<window_secs>", e.g. "60/60"
+    let rate_limit = std::env::var("FUSOU_MOCK_RATE_LIMIT")
+        .ok()
+        .and_then(|value| {
+            let (max, window) = value.split_once('/')?;
+            Some(RateLimit {
+                max_requests: max.trim().parse().ok()?,
+                window: Duration::from_secs(window.trim().parse().ok()?),
+            })
+        });
+
+    let config = MockServerConfig {
+        bind_addr,
+        rate_limit,
+        require_bearer: std::env::var("FUSOU_MOCK_REQUIRE_BEARER").is_ok(),
+        ..MockServerConfig::default()
+    };
+    let server = MockServer::start_with(config).await?;
+    let session = server.issue_session("mock-user");
+    println!("fusou mock server listening on {}", server.base_url());
+    println!("access_token={}", session.access_token);
+    println!("refresh_token={}", session.refresh_token);
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
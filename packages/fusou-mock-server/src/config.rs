@@ -0,0 +1,36 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Fixed-window rate limit applied per client identity (bearer / dataset token / anonymous).
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub window: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockServerConfig {
+    /// Address to bind. Port `0` picks an ephemeral port.
+    pub bind_addr: SocketAddr,
+    /// `None` disables rate limiting.
+    pub rate_limit: Option<RateLimit>,
+    /// Value returned by the kc-period endpoint.
+    pub period_tag: Option<String>,
+    /// Lifetime reported as `expires_in` for issued access tokens.
+    pub access_token_ttl_secs: i64,
+    /// When true, upload handshakes require a valid bearer token even if a
+    /// dataset token is present (matches production FUSOU-WEB).
+    pub require_bearer: bool,
+}
+
+impl Default for MockServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            rate_limit: None,
+            period_tag: Some("2026-01-01".to_string()),
+            access_token_ttl_secs: 3600,
+            require_bearer: false,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// What to do with a request that matches a [`FaultRule`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FaultAction {
+    /// Answer with this status code and a JSON error body instead of the real handler.
+    Status { status: u16 },
+    /// Sleep before running the real handler (simulates a slow upstream).
+    Delay { millis: u64 },
+    /// Run the real handler, then answer with this status code instead of its response
+    /// (the server committed but the client never saw the reply).
+    LoseResponse { status: u16 },
+}
+
+/// Fault injected for requests whose path starts with `path_prefix`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaultRule {
+    pub path_prefix: String,
+    /// Restrict to one HTTP method (`"POST"`, `"GET"`, ...). `None` matches all.
+    #[serde(default)]
+    pub method: Option<String>,
+    pub action: FaultAction,
+    /// Remaining number of matches. `None` keeps the rule active until cleared.
+    #[serde(default)]
+    pub remaining: Option<usize>,
+    /// Matching requests to let through before the rule starts firing.
+    #[serde(default)]
+    pub skip: usize,
+}
+
+impl FaultRule {
+    /// Fail the next `times` matching requests with `status`.
+    pub fn status(path_prefix: impl Into<String>, status: u16, times: usize) -> Self {
+        Self {
+            path_prefix: path_prefix.into(),
+            method: None,
+            action: FaultAction::Status { status },
+            remaining: Some(times),
+            skip: 0,
+        }
+    }
+
+    /// Run the next `times` matching requests but answer them with `status`.
+    pub fn lose_response(path_prefix: impl Into<String>, status: u16, times: usize) -> Self {
+        Self {
+            action: FaultAction::LoseResponse { status },
+            ..Self::status(path_prefix, status, times)
+        }
+    }
+
+    /// Delay every matching request by `delay` until cleared.
+    pub fn delay(path_prefix: impl Into<String>, delay: Duration) -> Self {
+        Self {
+            path_prefix: path_prefix.into(),
+            method: None,
+            action: FaultAction::Delay {
+                millis: delay.as_millis() as u64,
+            },
+            remaining: None,
+            skip: 0,
+        }
+    }
+
+    pub fn with_method(mut self, method: &str) -> Self {
+        self.method = Some(method.to_ascii_uppercase());
+        self
+    }
+
+    /// Let the first `count` matching requests through untouched.
+    pub fn after(mut self, count: usize) -> Self {
+        self.skip = count;
+        self
+    }
+
+    fn matches(&self, method: &str, path: &str) -> bool {
+        if !path.starts_with(&self.path_prefix) {
+            return false;
+        }
+        if let Some(expected) = &self.method {
+            if !expected.eq_ignore_ascii_case(method) {
+                return false;
+            }
+        }
+        self.remaining != Some(0)
+    }
+}
+
+/// Consume the first matching rule and return its action.
+pub(crate) fn take_fault(
+    rules: &mut Vec<FaultRule>,
+    method: &str,
+    path: &str,
+) -> Option<FaultAction> {
+    let index = rules.iter().position(|rule| rule.matches(method, path))?;
+    let rule = &mut rules[index];
+    if rule.skip > 0 {
+        rule.skip -= 1;
+        return None;
+    }
+    let action = rule.action.clone();
+    if let Some(remaining) = rule.remaining.as_mut() {
+        *remaining -= 1;
+        if *remaining == 0 {
+            rules.remove(index);
+        }
+    }
+    Some(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counted_rule_is_removed_after_last_match() {
+        let mut rules = vec![FaultRule::status("/api/battle-data", 503, 2)];
+        assert!(take_fault(&mut rules, "POST", "/api/battle-data/upload").is_some());
+        assert!(take_fault(&mut rules, "POST", "/api/battle-data/upload").is_some());
+        assert!(take_fault(&mut rules, "POST", "/api/battle-data/upload").is_none());
+        assert!(rules.is_empty());
+    }
+
+    #[test]
+    fn skipped_matches_pass_through() {
+        let mut rules = vec![FaultRule::status("/api/asset-sync", 503, 1).after(2)];
+        assert!(take_fault(&mut rules, "POST", "/api/asset-sync/upload").is_none());
+        assert!(take_fault(&mut rules, "POST", "/api/asset-sync/upload").is_none());
+        assert!(take_fault(&mut rules, "POST", "/api/asset-sync/upload").is_some());
+        assert!(rules.is_empty());
+    }
+
+    #[test]
+    fn method_filter_is_respected() {
+        let mut rules = vec![FaultRule::status("/api/asset-sync", 500, 1).with_method("get")];
+        assert!(take_fault(&mut rules, "POST", "/api/asset-sync/upload").is_none());
+        assert!(take_fault(&mut rules, "GET", "/api/asset-sync/keys").is_some());
+    }
+}
@@ -0,0 +1,187 @@
+//! In-memory stand-in for the FUSOU backend (FUSOU-WEB + Supabase auth).
+//!
+//! Implements the HTTP contracts the desktop client talks to, so integration tests
+//! can drive `fusou-upload`, `fusou-storage` asset sync, the ingest senders and
+//! `fusou-auth` end to end without network access:
+//!
+//! - two-stage uploads (handshake -> `uploadUrl` + `X-Upload-Token` -> body), with
+//!   SHA-256 content hash verification and `409 Conflict` for already stored payloads
+//! - asset-sync existing keys and the kc-period endpoint
+//! - anonymous-sync v2 (register / challenge / refresh / revoke / rotate / devices)
+//! - Supabase `POST /auth/v1/token?grant_type=refresh_token` with one-time refresh tokens
+//! - optional per-client rate limiting (`429` + `Retry-After`)
+//! - fault injection by path prefix (forced status codes and delays)
+//!
+//! ```no_run
+//! # async fn run() {
+//! let server = fusou_mock_server::MockServer::start().await.unwrap();
+//! let session = server.issue_session("user-1");
+//! let upload_url = server.endpoints().battle_data_upload;
+//! # let _ = (session, upload_url);
+//! # }
+//! ```
+
+pub mod config;
+pub mod faults;
+mod routes;
+pub mod state;
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+use warp::Filter;
+
+pub use config::{MockServerConfig, RateLimit};
+pub use faults::{FaultAction, FaultRule};
+pub use state::{IssuedSession, MockState, RequestRecord, StoredUpload, UploadKind};
+
+/// Full URLs of every endpoint served by a running mock, laid out like `configs.toml`.
+#[derive(Debug, Clone)]
+pub struct MockEndpoints {
+    pub base_url: String,
+    pub supabase_url: String,
+    pub battle_data_upload: String,
+    pub master_data_upload: String,
+    pub asset_upload: String,
+    pub asset_existing_keys: String,
+    pub kc_period: String,
+    pub fleet_snapshot: String,
+    pub quest_tree_ingest: String,
+    pub ship_growth_ingest: String,
+    pub soku_speed_ingest: String,
+    pub remodel_ingest: String,
+    /// Base for `AnonymousSyncV2Endpoints::with_base_url` (`.../api/auth`).
+    pub anonymous_sync_base: String,
+}
+
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// Start on an ephemeral `127.0.0.1` port with the default configuration.
+    pub async fn start() -> Result<Self, String> {
+        Self::start_with(MockServerConfig::default()).await
+    }
+
+    pub async fn start_with(config: MockServerConfig) -> Result<Self, String> {
+        let bind_addr = config.bind_addr;
+        let state = Arc::new(Mutex::new(MockState::new(config)));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+
+        let filter_state = state.clone();
+        let route = warp::any()
+            .and(warp::method())
+            .and(warp::path::full())
+            .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+            .and(warp::header::headers_cloned())
+            .and(warp::body::bytes())
+            .and_then(
+                move |method, path: warp::path::FullPath, query, headers, body| {
+                    let state = filter_state.clone();
+                    async move {
+                        let request = routes::MockRequest::new(
+                            method,
+                            path.as_str().to_string(),
+                            query,
+                            headers,
+                            body,
+                        );
+                        Ok::<_, Infallible>(routes::dispatch(state, request).await)
+                    }
+                },
+            );
+
+        let (addr, server) = warp::serve(route)
+            .try_bind_with_graceful_shutdown(bind_addr, async {
+                let _ = shutdown_rx.await;
+            })
+            .map_err(|e| format!("failed to bind mock server on {bind_addr}: {e}"))?;
+        tokio::spawn(server);
+        tracing::info!(%addr, "fusou mock server listening");
+
+        Ok(Self {
+            addr,
+            state,
+            shutdown: Some(shutdown_tx),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// `http://127.0.0.1:<port>` (no trailing slash).
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn endpoints(&self) -> MockEndpoints {
+        let base = self.base_url();
+        let url = |kind: UploadKind| format!("{base}{}", kind.path());
+        MockEndpoints {
+            supabase_url: base.clone(),
+            battle_data_upload: url(UploadKind::BattleData),
+            master_data_upload: url(UploadKind::MasterData),
+            asset_upload: url(UploadKind::Asset),
+            asset_existing_keys: format!("{base}{}", routes::ASSET_EXISTING_KEYS_PATH),
+            kc_period: format!("{base}{}", routes::KC_PERIOD_PATH),
+            fleet_snapshot: url(UploadKind::FleetSnapshot),
+            quest_tree_ingest: url(UploadKind::QuestTree),
+            ship_growth_ingest: url(UploadKind::ShipGrowth),
+            soku_speed_ingest: url(UploadKind::SokuSpeed),
+            remodel_ingest: url(UploadKind::Remodel),
+            anonymous_sync_base: format!("{base}/api/auth"),
+            base_url: base,
+        }
+    }
+
+    /// Direct access to the in-memory state for assertions and seeding.
+    pub fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Issue a Supabase-style session for `user_id` (access + one-time refresh token).
+    pub fn issue_session(&self, user_id: &str) -> IssuedSession {
+        self.state().issue_session(user_id)
+    }
+
+    /// Issue a dataset token that the upload handshake accepts for `dataset_id`.
+    pub fn issue_dataset_token(&self, dataset_id: &str) -> String {
+        self.state().issue_dataset_token(dataset_id)
+    }
+
+    /// Invalidate every outstanding access token so the next request gets `401`
+    /// and the client has to go through the refresh path.
+    pub fn expire_access_tokens(&self) {
+        self.state().expire_access_tokens();
+    }
+
+    pub fn inject_fault(&self, rule: FaultRule) {
+        self.state().faults.push(rule);
+    }
+
+    pub fn clear_faults(&self) {
+        self.state().faults.clear();
+    }
+
+    pub fn uploads(&self, kind: UploadKind) -> Vec<StoredUpload> {
+        self.state().uploads_of(kind)
+    }
+
+    pub fn requests(&self) -> Vec<RequestRecord> {
+        self.state().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Control endpoints for driving the mock from outside the process
+//! (used when running the `fusou-mock-server` binary against the desktop app).
+//!
+//! - `GET /__mock/state`: recorded requests, stored uploads and active faults
+//! - `POST /__mock/faults`: add a [`FaultRule`] (JSON)
+//! - `DELETE /__mock/faults`: clear all faults
+//! - `POST /__mock/sessions`: `{"user_id": "..."}` -> issued session
+//! - `POST /__mock/dataset-tokens`: `{"dataset_id": "..."}` -> `{"token": "..."}`
+//! - `POST /__mock/expire-access-tokens`
+
+use serde_json::json;
+use warp::http::Method;
+
+use super::{empty_response, error_response, json_response, MockRequest, MockResponse};
+use crate::faults::FaultRule;
+use crate::state::{MockState, UploadKind};
+
+pub(super) fn handle(state: &mut MockState, request: &MockRequest) -> MockResponse {
+    let action = &request.path[super::ADMIN_PREFIX.len()..];
+    match (&request.method, action) {
+        (&Method::GET, "state") => {
+            let uploads: Vec<_> = UploadKind::ALL
+                .into_iter()
+                .flat_map(|kind| state.uploads_of(kind))
+                .collect();
+            json_response(
+                200,
+                json!({
+                    "requests": state.requests,
+                    "uploads": uploads,
+                    "faults": state.faults,
+                }),
+            )
+        }
+        (&Method::POST, "faults") => match serde_json::from_slice::<FaultRule>(&request.body) {
+            Ok(rule) => {
+                state.faults.push(rule);
+                empty_response(204)
+            }
+            Err(e) => error_response(400, &format!("invalid fault rule: {e}")),
+        },
+        (&Method::DELETE, "faults") => {
+            state.faults.clear();
+            empty_response(204)
+        }
+        (&Method::POST, "sessions") => {
+            let body = request.json();
+            let user_id = body["user_id"].as_str().unwrap_or("mock-user");
+            json_response(200, json!(state.issue_session(user_id)))
+        }
+        (&Method::POST, "dataset-tokens") => {
+            let body = request.json();
+            let Some(dataset_id) = body["dataset_id"].as_str() else {
+                return error_response(400, "dataset_id is required");
+            };
+            json_response(
+                200,
+                json!({ "token": state.issue_dataset_token(dataset_id) }),
+            )
+        }
+        (&Method::POST, "expire-access-tokens") => {
+            state.expire_access_tokens();
+            empty_response(204)
+        }
+        _ => error_response(404, "not_found"),
+    }
+}
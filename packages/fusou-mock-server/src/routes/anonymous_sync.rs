@@ -0,0 +1,235 @@
+//! anonymous-sync v2: Ed25519 device keys, single-use challenge nonces.
+//!
+//! Signed messages match FUSOU-WEB `anonymous-sync-v2.ts`:
+//! `register|{api_member_id}`, `{nonce}` (refresh), `revoke|{device_id}|{target}|{nonce}`
+//! and `rotate|{device_id}|{new_pub}|{nonce}` (current and new key). `GET devices` takes the
+//! Supabase session bearer instead of a device signature.
+
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde_json::{json, Value};
+use warp::http::Method;
+
+use super::{empty_response, error_response, json_response, MockRequest, MockResponse};
+use crate::state::{MockDevice, MockState};
+
+pub(super) fn handle(state: &mut MockState, request: &MockRequest, action: &str) -> MockResponse {
+    let body = request.json();
+    match (&request.method, action) {
+        (&Method::POST, "register") => register(state, &body),
+        (&Method::GET, "challenge") => challenge(state, request),
+        (&Method::POST, "refresh") => refresh(state, &body),
+        (&Method::POST, "rotate") => rotate(state, &body),
+        (&Method::GET, "devices") => list_devices(state, request),
+        (&Method::POST, "revoke") => revoke(state, &body),
+        _ => error_response(404, "not_found"),
+    }
+}
+
+fn field(body: &Value, name: &str) -> String {
+    body[name].as_str().unwrap_or_default().to_string()
+}
+
+fn dataset_token_response(state: &mut MockState, pid: &str, device_id: Option<&str>) -> Value {
+    let token = state.issue_dataset_token(pid);
+    let mut value = json!({
+        "dataset_token": token,
+        "pid": pid,
+        "expires_at": (Utc::now() + Duration::days(30)).to_rfc3339(),
+    });
+    if let Some(device_id) = device_id {
+        value["device_id"] = json!(device_id);
+    }
+    value
+}
+
+fn register(state: &mut MockState, body: &Value) -> MockResponse {
+    let member = field(body, "api_member_id");
+    let device_pub = field(body, "device_pub");
+    if member.is_empty() {
+        return error_response(400, "api_member_id is required");
+    }
+    if !verify(
+        &device_pub,
+        &format!("register|{member}"),
+        &field(body, "attestation"),
+    ) {
+        return error_response(401, "attestation_invalid");
+    }
+    let seq = state.next_sequence();
+    let device_id = format!("00000000-0000-4000-8000-{seq:012}");
+    let pid = format!("pid-{member}");
+    state.devices.insert(
+        device_id.clone(),
+        MockDevice {
+            device_pub,
+            pid: pid.clone(),
+            canonical_user_id: format!("user-{member}"),
+            created_at: Utc::now(),
+            last_seen_at: None,
+            revoked_at: None,
+            revoked_reason: None,
+            nonces: Vec::new(),
+        },
+    );
+    state.device_order.push(device_id.clone());
+    json_response(200, dataset_token_response(state, &pid, Some(&device_id)))
+}
+
+fn challenge(state: &mut MockState, request: &MockRequest) -> MockResponse {
+    let device_id = request.query_param("device_id").unwrap_or_default();
+    let Some(device) = active_device_mut(state, &device_id) else {
+        return error_response(404, "device_unknown_or_revoked");
+    };
+    let nonce = MockState::random_hex(32);
+    device.nonces.push(nonce.clone());
+    json_response(
+        200,
+        json!({
+            "nonce": nonce,
+            "expires_at": (Utc::now() + Duration::minutes(5)).timestamp_millis(),
+        }),
+    )
+}
+
+/// Look up an active device and consume `nonce` if it was issued to it.
+/// Errors are `(status, error code)` for [`error_response`].
+fn authenticate<'a>(
+    state: &'a mut MockState,
+    device_id: &str,
+    nonce: &str,
+) -> Result<&'a mut MockDevice, (u16, &'static str)> {
+    let device = active_device_mut(state, device_id).ok_or((404, "device_unknown_or_revoked"))?;
+    let Some(index) = device.nonces.iter().position(|issued| issued == nonce) else {
+        return Err((401, "nonce_invalid"));
+    };
+    device.nonces.remove(index);
+    device.last_seen_at = Some(Utc::now());
+    Ok(device)
+}
+
+fn refresh(state: &mut MockState, body: &Value) -> MockResponse {
+    let nonce = field(body, "nonce");
+    let pid = match authenticate(state, &field(body, "device_id"), &nonce) {
+        Ok(device) if verify(&device.device_pub, &nonce, &field(body, "sig")) => device.pid.clone(),
+        Ok(_) => return error_response(401, "signature_invalid"),
+        Err((status, error)) => return error_response(status, error),
+    };
+    json_response(200, dataset_token_response(state, &pid, None))
+}
+
+fn rotate(state: &mut MockState, body: &Value) -> MockResponse {
+    let device_id = field(body, "device_id");
+    let nonce = field(body, "nonce");
+    let new_pub = field(body, "new_device_pub");
+    let message = format!("rotate|{device_id}|{new_pub}|{nonce}");
+    let device = match authenticate(state, &device_id, &nonce) {
+        Ok(device) => device,
+        Err((status, error)) => return error_response(status, error),
+    };
+    if !verify(&device.device_pub, &message, &field(body, "sig")) {
+        return error_response(401, "signature_invalid");
+    }
+    if !verify(&new_pub, &message, &field(body, "new_sig")) {
+        return error_response(401, "new_signature_invalid");
+    }
+    let rotated = device.device_pub != new_pub;
+    device.device_pub = new_pub;
+    json_response(200, json!({ "device_id": device_id, "rotated": rotated }))
+}
+
+fn list_devices(state: &mut MockState, request: &MockRequest) -> MockResponse {
+    let Some(token) = request.bearer() else {
+        return error_response(401, "unauthorized");
+    };
+    let Some(user_id) = state.user_for_access_token(token).map(str::to_string) else {
+        return error_response(401, "invalid_token");
+    };
+    let include_revoked = request
+        .query_param("include_revoked")
+        .is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true"));
+    let devices: Vec<Value> = state
+        .device_order
+        .iter()
+        .rev()
+        .filter_map(|id| {
+            let device = &state.devices[id];
+            if device.canonical_user_id != user_id
+                || (!include_revoked && device.revoked_at.is_some())
+            {
+                return None;
+            }
+            Some(json!({
+                "device_id": id,
+                "pid_masked": mask(&device.pid),
+                "created_at": device.created_at.to_rfc3339(),
+                "last_seen_at": device.last_seen_at.map(|at| at.to_rfc3339()),
+                "revoked_at": device.revoked_at.map(|at| at.to_rfc3339()),
+                "revoked_reason": device.revoked_reason,
+            }))
+        })
+        .collect();
+    json_response(
+        200,
+        json!({ "devices": devices, "include_revoked": include_revoked }),
+    )
+}
+
+fn revoke(state: &mut MockState, body: &Value) -> MockResponse {
+    let device_id = field(body, "device_id");
+    let nonce = field(body, "nonce");
+    let target_id = field(body, "target_device_id");
+    let target_id = if target_id.is_empty() {
+        device_id.clone()
+    } else {
+        target_id
+    };
+    let message = format!("revoke|{device_id}|{target_id}|{nonce}");
+    let pid = match authenticate(state, &device_id, &nonce) {
+        Ok(device) if verify(&device.device_pub, &message, &field(body, "sig")) => {
+            device.pid.clone()
+        }
+        Ok(_) => return error_response(401, "signature_invalid"),
+        Err((status, error)) => return error_response(status, error),
+    };
+    match state.devices.get_mut(&target_id) {
+        Some(target) if target.pid == pid => {
+            target.revoked_at = Some(Utc::now());
+            target.revoked_reason = body["reason"].as_str().map(str::to_string);
+            empty_response(204)
+        }
+        _ => error_response(404, "target_unknown"),
+    }
+}
+
+fn active_device_mut<'a>(state: &'a mut MockState, device_id: &str) -> Option<&'a mut MockDevice> {
+    state
+        .devices
+        .get_mut(device_id)
+        .filter(|device| device.revoked_at.is_none())
+}
+
+fn mask(pid: &str) -> String {
+    let visible: String = pid.chars().take(4).collect();
+    format!("{visible}****")
+}
+
+fn verify(pub_b64: &str, message: &str, sig_b64: &str) -> bool {
+    let (Ok(pub_bytes), Ok(sig_bytes)) = (B64.decode(pub_b64), B64.decode(sig_b64)) else {
+        return false;
+    };
+    let (Ok(pub_arr), Ok(sig_arr)) = (
+        <[u8; 32]>::try_from(pub_bytes.as_slice()),
+        <[u8; 64]>::try_from(sig_bytes.as_slice()),
+    ) else {
+        return false;
+    };
+    let Ok(verifying) = VerifyingKey::from_bytes(&pub_arr) else {
+        return false;
+    };
+    verifying
+        .verify_strict(message.as_bytes(), &Signature::from_bytes(&sig_arr))
+        .is_ok()
+}
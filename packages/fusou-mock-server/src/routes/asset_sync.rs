@@ -0,0 +1,68 @@
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+
+use super::{error_response, json_response, MockRequest, MockResponse};
+use crate::state::{MockState, UploadKind};
+
+/// `GET /api/asset-sync/keys[?since=<ms>]`: keys of stored assets plus seeded keys.
+pub(super) fn existing_keys(state: &mut MockState, request: &MockRequest) -> MockResponse {
+    let authorized = request
+        .bearer()
+        .is_some_and(|token| state.user_for_access_token(token).is_some());
+    if !authorized {
+        return error_response(401, "unauthorized");
+    }
+
+    let since_ms = request
+        .query_param("since")
+        .and_then(|value| value.parse::<i64>().ok());
+    let now = Utc::now();
+
+    let mut items: Vec<Value> = Vec::new();
+    if since_ms.is_none() {
+        items.extend(
+            state
+                .seeded_asset_keys
+                .iter()
+                .map(|key| json!({ "key": key, "content_hash": null })),
+        );
+    }
+    for upload in state.uploads_of(UploadKind::Asset) {
+        if since_ms.is_some_and(|since| upload.stored_at.timestamp_millis() <= since) {
+            continue;
+        }
+        let Some(key) = upload.handshake["key"].as_str() else {
+            continue;
+        };
+        items.push(json!({
+            "key": key,
+            "content_hash": upload.content_hash,
+            "uploadedAt": upload.stored_at.timestamp_millis(),
+        }));
+    }
+    let keys: Vec<Value> = items.iter().map(|item| item["key"].clone()).collect();
+
+    json_response(
+        200,
+        json!({
+            "keys": keys,
+            "items": items,
+            "cacheExpiresAt": (now + Duration::hours(1)).to_rfc3339(),
+            "refreshedAt": now.to_rfc3339(),
+            "snapshotUpperAt": now.to_rfc3339(),
+            "snapshotUpperMs": now.timestamp_millis(),
+            "incremental": since_ms.is_some(),
+        }),
+    )
+}
+
+/// `GET /api/kc-period/latest`
+pub(super) fn kc_period(state: &MockState) -> MockResponse {
+    json_response(
+        200,
+        json!({
+            "tag": state.config.period_tag,
+            "cache_expires_at": (Utc::now() + Duration::hours(1)).to_rfc3339(),
+        }),
+    )
+}
@@ -0,0 +1,158 @@
+mod admin;
+mod anonymous_sync;
+mod asset_sync;
+mod supabase;
+mod upload;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use warp::http::{HeaderMap, Method, Response, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::hyper::Body;
+
+use crate::faults::{take_fault, FaultAction};
+use crate::state::{MockState, RequestRecord, UploadKind};
+
+pub(crate) const ASSET_EXISTING_KEYS_PATH: &str = "/api/asset-sync/keys";
+pub(crate) const KC_PERIOD_PATH: &str = "/api/kc-period/latest";
+pub(crate) const ANONYMOUS_SYNC_V2_PREFIX: &str = "/api/auth/anonymous-sync/v2/";
+pub(crate) const SUPABASE_TOKEN_PATH: &str = "/auth/v1/token";
+pub(crate) const ADMIN_PREFIX: &str = "/__mock/";
+
+pub(crate) type MockResponse = Response<Body>;
+
+pub(crate) struct MockRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: String,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl MockRequest {
+    pub fn new(
+        method: Method,
+        path: String,
+        query: String,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Self {
+        Self {
+            method,
+            path,
+            query,
+            headers,
+            body,
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn bearer(&self) -> Option<&str> {
+        self.header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == name).then(|| value.to_string())
+        })
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    /// Identity used for rate limiting.
+    fn client_key(&self) -> String {
+        self.bearer()
+            .or_else(|| self.header("x-dataset-token"))
+            .unwrap_or("anonymous")
+            .to_string()
+    }
+}
+
+pub(crate) fn json_response(status: u16, body: Value) -> MockResponse {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid mock response")
+}
+
+pub(crate) fn error_response(status: u16, error: &str) -> MockResponse {
+    json_response(status, json!({ "error": error }))
+}
+
+pub(crate) fn empty_response(status: u16) -> MockResponse {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("valid mock response")
+}
+
+/// Entry point for every request: fault injection, rate limiting, routing, recording.
+pub(crate) async fn dispatch(state: Arc<Mutex<MockState>>, request: MockRequest) -> MockResponse {
+    let lock = || state.lock().unwrap_or_else(|e| e.into_inner());
+    let method = request.method.as_str().to_string();
+
+    if request.path.starts_with(ADMIN_PREFIX) {
+        return admin::handle(&mut lock(), &request);
+    }
+
+    let fault = take_fault(&mut lock().faults, &method, &request.path);
+    if let Some(FaultAction::Delay { millis }) = fault {
+        tokio::time::sleep(Duration::from_millis(millis)).await;
+    }
+
+    let mut state = lock();
+    let response = if let Some(FaultAction::Status { status }) = fault {
+        error_response(status, "injected_fault")
+    } else if let Some(retry_after) = state.check_rate_limit(&request.client_key()) {
+        let mut response = error_response(429, "rate_limited");
+        response
+            .headers_mut()
+            .insert("retry-after", retry_after.into());
+        response
+    } else {
+        let response = route(&mut state, &request);
+        match fault {
+            Some(FaultAction::LoseResponse { status }) => error_response(status, "injected_fault"),
+            _ => response,
+        }
+    };
+
+    state.requests.push(RequestRecord {
+        method,
+        path: request.path.clone(),
+        status: response.status().as_u16(),
+    });
+    response
+}
+
+fn route(state: &mut MockState, request: &MockRequest) -> MockResponse {
+    let path = request.path.as_str();
+    match (&request.method, path) {
+        (&Method::GET, ASSET_EXISTING_KEYS_PATH) => asset_sync::existing_keys(state, request),
+        (&Method::GET, KC_PERIOD_PATH) => asset_sync::kc_period(state),
+        (&Method::POST, SUPABASE_TOKEN_PATH) => supabase::token(state, request),
+        (&Method::POST, _) if UploadKind::from_path(path).is_some() => {
+            let kind = UploadKind::from_path(path).expect("checked above");
+            upload::handle(state, request, kind)
+        }
+        _ if path.starts_with(ANONYMOUS_SYNC_V2_PREFIX) => {
+            anonymous_sync::handle(state, request, &path[ANONYMOUS_SYNC_V2_PREFIX.len()..])
+        }
+        _ => error_response(StatusCode::NOT_FOUND.as_u16(), "not_found"),
+    }
+}
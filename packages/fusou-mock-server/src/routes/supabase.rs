@@ -0,0 +1,34 @@
+use serde_json::json;
+
+use super::{error_response, json_response, MockRequest, MockResponse};
+use crate::state::MockState;
+
+/// `POST /auth/v1/token?grant_type=refresh_token` (GoTrue compatible subset).
+pub(super) fn token(state: &mut MockState, request: &MockRequest) -> MockResponse {
+    if request.query_param("grant_type").as_deref() != Some("refresh_token") {
+        return error_response(400, "unsupported_grant_type");
+    }
+    let body = request.json();
+    let Some(refresh_token) = body["refresh_token"].as_str() else {
+        return error_response(400, "refresh_token is required");
+    };
+    match state.refresh_session(refresh_token) {
+        Some(session) => json_response(
+            200,
+            json!({
+                "access_token": session.access_token,
+                "refresh_token": session.refresh_token,
+                "expires_in": session.expires_in,
+                "token_type": "bearer",
+                "user": { "id": session.user_id },
+            }),
+        ),
+        None => json_response(
+            400,
+            json!({
+                "error": "invalid_grant",
+                "error_description": "Invalid Refresh Token: Already Used",
+            }),
+        ),
+    }
+}
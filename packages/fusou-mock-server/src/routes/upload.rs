@@ -0,0 +1,183 @@
+//! Two-stage upload contract shared by every upload / ingest endpoint.
+//!
+//! Stage 1 (no `X-Upload-Token`): JSON handshake carrying `content_hash`.
+//! Stage 2 (`X-Upload-Token` from stage 1): raw body, hash checked against stage 1.
+//!
+//! Multipart variant: the handshake carries `multipart: {part_size, part_count,
+//! resume_upload_id?}`; stage 2 is then a series of `X-Upload-Part: n` requests
+//! (with `X-Part-Sha256`), optional `X-Upload-Action: status`, and a final
+//! `X-Upload-Action: complete` that assembles the parts.
+
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use super::{error_response, json_response, MockRequest, MockResponse};
+use crate::state::{MockState, UploadKind, UploadTicket};
+
+const MULTIPART_MAX_PARTS: u64 = 1000;
+
+pub(super) fn handle(
+    state: &mut MockState,
+    request: &MockRequest,
+    kind: UploadKind,
+) -> MockResponse {
+    match request.header("x-upload-token") {
+        Some(token) if state.multipart_for_token(token).is_some() => {
+            execute_multipart(state, request, token)
+        }
+        Some(token) => execute(state, request, token),
+        None => handshake(state, request, kind),
+    }
+}
+
+fn handshake(state: &mut MockState, request: &MockRequest, kind: UploadKind) -> MockResponse {
+    let bearer_ok = request
+        .bearer()
+        .is_some_and(|token| state.user_for_access_token(token).is_some());
+    let dataset_id = request
+        .header("x-dataset-token")
+        .and_then(|token| state.dataset_for_token(token))
+        .map(str::to_string);
+
+    let authorized = if state.config.require_bearer {
+        bearer_ok
+    } else {
+        bearer_ok || dataset_id.is_some()
+    };
+    if !authorized {
+        return error_response(401, "unauthorized");
+    }
+
+    let handshake = request.json();
+    let Some(content_hash) = handshake["content_hash"].as_str().map(str::to_string) else {
+        return error_response(400, "content_hash is required");
+    };
+    if state.has_upload(kind, &content_hash) {
+        return error_response(409, "already_exists");
+    }
+
+    let dataset_id = dataset_id.or_else(|| handshake["dataset_id"].as_str().map(str::to_string));
+    let multipart = handshake.get("multipart").cloned();
+    let ticket = UploadTicket {
+        kind,
+        content_hash,
+        dataset_id,
+        handshake,
+    };
+    let host = request.header("host").unwrap_or("127.0.0.1");
+    let upload_url = format!("http://{host}{}", kind.path());
+    let expires_at = (Utc::now() + Duration::minutes(5)).to_rfc3339();
+
+    if let Some(multipart) = multipart.filter(|value| !value.is_null()) {
+        let part_size = multipart["part_size"].as_u64().unwrap_or_default();
+        let part_count = multipart["part_count"].as_u64().unwrap_or_default();
+        if part_size == 0 || part_count == 0 || part_count > MULTIPART_MAX_PARTS {
+            return error_response(400, "multipart_invalid");
+        }
+        let (token, upload_id) = state.begin_multipart(
+            ticket,
+            part_size as usize,
+            part_count as u32,
+            multipart["resume_upload_id"].as_str(),
+        );
+        return json_response(
+            200,
+            json!({
+                "uploadUrl": upload_url,
+                "token": token,
+                "expiresAt": expires_at,
+                "multipart": {
+                    "uploadId": upload_id,
+                    "partSize": part_size,
+                    "partCount": part_count,
+                },
+            }),
+        );
+    }
+
+    let token = state.create_ticket(ticket);
+    json_response(
+        200,
+        json!({
+            "uploadUrl": upload_url,
+            "token": token,
+            "expiresAt": expires_at,
+        }),
+    )
+}
+
+fn execute(state: &mut MockState, request: &MockRequest, token: &str) -> MockResponse {
+    let Some(ticket) = state.take_ticket(token) else {
+        return error_response(401, "upload_token_invalid");
+    };
+    let actual = hex::encode(Sha256::digest(&request.body));
+    if !actual.eq_ignore_ascii_case(&ticket.content_hash) {
+        return error_response(400, "content_hash_mismatch");
+    }
+    if state.has_upload(ticket.kind, &ticket.content_hash) {
+        return error_response(409, "already_exists");
+    }
+    let content_hash = ticket.content_hash.clone();
+    state.store_upload(ticket, request.body.to_vec());
+    json_response(200, json!({ "ok": true, "content_hash": content_hash }))
+}
+
+fn execute_multipart(state: &mut MockState, request: &MockRequest, token: &str) -> MockResponse {
+    if let Some(part) = request.header("x-upload-part") {
+        let session = state
+            .multipart_for_token(token)
+            .expect("checked by handle()");
+        let Some(part_number) = part
+            .parse::<u32>()
+            .ok()
+            .filter(|n| (1..=session.part_count).contains(n))
+        else {
+            return error_response(400, "part_invalid");
+        };
+        if request.body.is_empty() || request.body.len() > session.part_size {
+            return error_response(400, "part_invalid");
+        }
+        let actual = hex::encode(Sha256::digest(&request.body));
+        if !request
+            .header("x-part-sha256")
+            .is_some_and(|expected| expected.eq_ignore_ascii_case(&actual))
+        {
+            return error_response(400, "part_hash_mismatch");
+        }
+        session.parts.insert(part_number, request.body.to_vec());
+        return json_response(200, json!({ "part": part_number, "sha256": actual }));
+    }
+
+    match request.header("x-upload-action") {
+        Some("status") => {
+            let session = state
+                .multipart_for_token(token)
+                .expect("checked by handle()");
+            let parts: Vec<u32> = session.parts.keys().copied().collect();
+            json_response(200, json!({ "parts": parts }))
+        }
+        Some("complete") => {
+            let session = state
+                .multipart_for_token(token)
+                .expect("checked by handle()");
+            if let Some(missing) = (1..=session.part_count).find(|n| !session.parts.contains_key(n))
+            {
+                return error_response(400, &format!("part_missing:{missing}"));
+            }
+            let session = state.finish_multipart(token).expect("session exists");
+            let data: Vec<u8> = session.parts.into_values().flatten().collect();
+            let actual = hex::encode(Sha256::digest(&data));
+            if !actual.eq_ignore_ascii_case(&session.ticket.content_hash) {
+                return error_response(400, "content_hash_mismatch");
+            }
+            if state.has_upload(session.ticket.kind, &session.ticket.content_hash) {
+                return error_response(409, "already_exists");
+            }
+            let content_hash = session.ticket.content_hash.clone();
+            state.store_upload(session.ticket, data);
+            json_response(200, json!({ "ok": true, "content_hash": content_hash }))
+        }
+        _ => error_response(400, "multipart_action_missing"),
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::Serialize;
+
+use crate::config::MockServerConfig;
+use crate::faults::FaultRule;
+
+/// Upload endpoints that share the two-stage handshake contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadKind {
+    BattleData,
+    MasterData,
+    Asset,
+    FleetSnapshot,
+    QuestTree,
+    ShipGrowth,
+    SokuSpeed,
+    Remodel,
+}
+
+impl UploadKind {
+    pub const ALL: [UploadKind; 8] = [
+        UploadKind::BattleData,
+        UploadKind::MasterData,
+        UploadKind::Asset,
+        UploadKind::FleetSnapshot,
+        UploadKind::QuestTree,
+        UploadKind::ShipGrowth,
+        UploadKind::SokuSpeed,
+        UploadKind::Remodel,
+    ];
+
+    /// Path as configured in `configs.toml` (`https://fusou.dev<path>`).
+    pub fn path(self) -> &'static str {
+        match self {
+            UploadKind::BattleData => "/api/battle-data/upload",
+            UploadKind::MasterData => "/api/master-data/upload",
+            UploadKind::Asset => "/api/asset-sync/upload",
+            UploadKind::FleetSnapshot => "/api/fleet/snapshot",
+            UploadKind::QuestTree => "/api/quest-tree/ingest",
+            UploadKind::ShipGrowth => "/api/ship-growth/ingest",
+            UploadKind::SokuSpeed => "/api/soku-speed-observed/ingest",
+            UploadKind::Remodel => "/api/remodel-data/ingest",
+        }
+    }
+
+    pub fn from_path(path: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.path() == path)
+    }
+}
+
+/// Payload accepted by stage 2 of an upload.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredUpload {
+    pub kind: UploadKind,
+    pub content_hash: String,
+    pub dataset_id: Option<String>,
+    /// Handshake body as sent by the client (including `content_hash`).
+    pub handshake: serde_json::Value,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+    pub stored_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct UploadTicket {
+    pub kind: UploadKind,
+    pub content_hash: String,
+    pub dataset_id: Option<String>,
+    pub handshake: serde_json::Value,
+}
+
+/// Multipart upload in progress. Outlives individual upload tokens so a client
+/// can re-handshake with `resume_upload_id` and keep its staged parts.
+#[derive(Debug, Clone)]
+pub(crate) struct MultipartSession {
+    pub ticket: UploadTicket,
+    pub part_size: usize,
+    pub part_count: u32,
+    pub parts: BTreeMap<u32, Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedSession {
+    pub user_id: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MockDevice {
+    pub device_pub: String,
+    pub pid: String,
+    /// Supabase user the device belongs to (`user-{api_member_id}` in the mock).
+    pub canonical_user_id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoked_reason: Option<String>,
+    /// Outstanding challenge nonces; each one is accepted once.
+    pub nonces: Vec<String>,
+}
+
+/// One handled request, in arrival order.
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestRecord {
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+}
+
+struct RateWindow {
+    started_at: Instant,
+    count: u32,
+}
+
+pub struct MockState {
+    pub config: MockServerConfig,
+    pub faults: Vec<FaultRule>,
+    pub requests: Vec<RequestRecord>,
+    /// Asset keys reported by the existing-keys endpoint in addition to uploaded ones.
+    pub seeded_asset_keys: Vec<String>,
+    uploads: HashMap<(UploadKind, String), StoredUpload>,
+    tickets: HashMap<String, UploadTicket>,
+    multipart_sessions: HashMap<String, MultipartSession>,
+    /// Upload token -> multipart upload id.
+    multipart_tokens: HashMap<String, String>,
+    access_tokens: HashMap<String, String>,
+    refresh_tokens: HashMap<String, String>,
+    dataset_tokens: HashMap<String, String>,
+    pub(crate) devices: HashMap<String, MockDevice>,
+    pub(crate) device_order: Vec<String>,
+    rate_windows: HashMap<String, RateWindow>,
+    sequence: u64,
+}
+
+impl MockState {
+    pub fn new(config: MockServerConfig) -> Self {
+        Self {
+            config,
+            faults: Vec::new(),
+            requests: Vec::new(),
+            seeded_asset_keys: Vec::new(),
+            uploads: HashMap::new(),
+            tickets: HashMap::new(),
+            multipart_sessions: HashMap::new(),
+            multipart_tokens: HashMap::new(),
+            access_tokens: HashMap::new(),
+            refresh_tokens: HashMap::new(),
+            dataset_tokens: HashMap::new(),
+            devices: HashMap::new(),
+            device_order: Vec::new(),
+            rate_windows: HashMap::new(),
+            sequence: 0,
+        }
+    }
+
+    pub(crate) fn next_sequence(&mut self) -> u64 {
+        self.sequence += 1;
+        self.sequence
+    }
+
+    pub(crate) fn random_hex(bytes: usize) -> String {
+        let mut buf = vec![0u8; bytes];
+        rand::rngs::OsRng.fill_bytes(&mut buf);
+        hex::encode(buf)
+    }
+
+    pub fn issue_session(&mut self, user_id: &str) -> IssuedSession {
+        let access_token = format!("mock-access-{}", Self::random_hex(16));
+        let refresh_token = format!("mock-refresh-{}", Self::random_hex(16));
+        self.access_tokens
+            .insert(access_token.clone(), user_id.to_string());
+        self.refresh_tokens
+            .insert(refresh_token.clone(), user_id.to_string());
+        IssuedSession {
+            user_id: user_id.to_string(),
+            access_token,
+            refresh_token,
+            expires_in: self.config.access_token_ttl_secs,
+        }
+    }
+
+    /// Exchange a refresh token. Each refresh token is single-use, like GoTrue.
+    pub(crate) fn refresh_session(&mut self, refresh_token: &str) -> Option<IssuedSession> {
+        let user_id = self.refresh_tokens.remove(refresh_token)?;
+        Some(self.issue_session(&user_id))
+    }
+
+    pub fn expire_access_tokens(&mut self) {
+        self.access_tokens.clear();
+    }
+
+    pub(crate) fn user_for_access_token(&self, token: &str) -> Option<&str> {
+        self.access_tokens.get(token).map(String::as_str)
+    }
+
+    /// Currently registered public key of an anonymous-sync v2 device.
+    pub fn device_public_key(&self, device_id: &str) -> Option<&str> {
+        self.devices
+            .get(device_id)
+            .map(|device| device.device_pub.as_str())
+    }
+
+    /// Supabase user id an anonymous-sync v2 device is linked to; pass it to
+    /// `issue_session` to list that account's devices.
+    pub fn device_user_id(&self, device_id: &str) -> Option<&str> {
+        self.devices
+            .get(device_id)
+            .map(|device| device.canonical_user_id.as_str())
+    }
+
+    pub fn issue_dataset_token(&mut self, dataset_id: &str) -> String {
+        let token = format!("mock-dataset-{}", Self::random_hex(16));
+        self.dataset_tokens
+            .insert(token.clone(), dataset_id.to_string());
+        token
+    }
+
+    pub(crate) fn dataset_for_token(&self, token: &str) -> Option<&str> {
+        self.dataset_tokens.get(token).map(String::as_str)
+    }
+
+    pub(crate) fn has_upload(&self, kind: UploadKind, content_hash: &str) -> bool {
+        self.uploads.contains_key(&(kind, content_hash.to_string()))
+    }
+
+    pub(crate) fn create_ticket(&mut self, ticket: UploadTicket) -> String {
+        let token = format!("mock-upload-{}", Self::random_hex(16));
+        self.tickets.insert(token.clone(), ticket);
+        token
+    }
+
+    pub(crate) fn take_ticket(&mut self, token: &str) -> Option<UploadTicket> {
+        self.tickets.remove(token)
+    }
+
+    /// Start (or resume, when `resume_upload_id` names a session for the same
+    /// content) a multipart upload. Returns `(upload token, upload id)`.
+    pub(crate) fn begin_multipart(
+        &mut self,
+        ticket: UploadTicket,
+        part_size: usize,
+        part_count: u32,
+        resume_upload_id: Option<&str>,
+    ) -> (String, String) {
+        let resumable = resume_upload_id.filter(|id| {
+            self.multipart_sessions.get(*id).is_some_and(|session| {
+                session.ticket.kind == ticket.kind
+                    && session.ticket.content_hash == ticket.content_hash
+                    && session.part_size == part_size
+                    && session.part_count == part_count
+            })
+        });
+        let upload_id = match resumable {
+            Some(id) => id.to_string(),
+            None => {
+                let hex = Self::random_hex(16);
+                let upload_id = format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                );
+                self.multipart_sessions.insert(
+                    upload_id.clone(),
+                    MultipartSession {
+                        ticket,
+                        part_size,
+                        part_count,
+                        parts: BTreeMap::new(),
+                    },
+                );
+                upload_id
+            }
+        };
+        let token = format!("mock-upload-{}", Self::random_hex(16));
+        self.multipart_tokens
+            .insert(token.clone(), upload_id.clone());
+        (token, upload_id)
+    }
+
+    pub(crate) fn multipart_for_token(&mut self, token: &str) -> Option<&mut MultipartSession> {
+        let upload_id = self.multipart_tokens.get(token)?;
+        self.multipart_sessions.get_mut(upload_id)
+    }
+
+    pub(crate) fn finish_multipart(&mut self, token: &str) -> Option<MultipartSession> {
+        let upload_id = self.multipart_tokens.remove(token)?;
+        self.multipart_tokens.retain(|_, id| *id != upload_id);
+        self.multipart_sessions.remove(&upload_id)
+    }
+
+    pub(crate) fn store_upload(&mut self, ticket: UploadTicket, data: Vec<u8>) {
+        let key = (ticket.kind, ticket.content_hash.clone());
+        self.uploads.insert(
+            key,
+            StoredUpload {
+                kind: ticket.kind,
+                content_hash: ticket.content_hash,
+                dataset_id: ticket.dataset_id,
+                handshake: ticket.handshake,
+                data,
+                stored_at: Utc::now(),
+            },
+        );
+    }
+
+    pub fn uploads_of(&self, kind: UploadKind) -> Vec<StoredUpload> {
+        let mut uploads: Vec<StoredUpload> = self
+            .uploads
+            .values()
+            .filter(|upload| upload.kind == kind)
+            .cloned()
+            .collect();
+        uploads.sort_by_key(|upload| upload.stored_at);
+        uploads
+    }
+
+    /// Fixed-window limiter. Returns the seconds to wait when the client is over the limit.
+    pub(crate) fn check_rate_limit(&mut self, client: &str) -> Option<u64> {
+        let limit = self.config.rate_limit?;
+        let now = Instant::now();
+        let window = self
+            .rate_windows
+            .entry(client.to_string())
+            .or_insert(RateWindow {
+                started_at: now,
+                count: 0,
+            });
+        if now.duration_since(window.started_at) >= limit.window {
+            window.started_at = now;
+            window.count = 0;
+        }
+        if window.count >= limit.max_requests {
+            let elapsed = now.duration_since(window.started_at);
+            return Some(limit.window.saturating_sub(elapsed).as_secs().max(1));
+        }
+        window.count += 1;
+        None
+    }
+}
@@ -0,0 +1,251 @@
+//! HTTP contract checks for the mock, driven with plain reqwest so a broken
+//! route shows up here rather than as a confusing client-side failure.
+
+use std::time::{Duration, Instant};
+
+use fusou_mock_server::{FaultRule, MockServer, MockServerConfig, RateLimit, UploadKind};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+async fn handshake(
+    client: &reqwest::Client,
+    url: &str,
+    bearer: &str,
+    data: &[u8],
+) -> reqwest::Response {
+    client
+        .post(url)
+        .bearer_auth(bearer)
+        .json(&json!({
+            "tag": "2026-01-01",
+            "dataset_id": "dataset-1",
+            "content_hash": hex::encode(Sha256::digest(data)),
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn two_stage_upload_stores_payload_and_rejects_duplicates() {
+    let server = MockServer::start().await.unwrap();
+    let session = server.issue_session("user-1");
+    let client = reqwest::Client::new();
+    let url = server.endpoints().fleet_snapshot;
+    let data = b"fleet-snapshot-bytes";
+
+    let resp = handshake(&client, &url, &session.access_token, data).await;
+    assert_eq!(resp.status(), 200);
+    let ticket: Value = resp.json().await.unwrap();
+    assert_eq!(ticket["uploadUrl"], url);
+
+    let resp = client
+        .post(ticket["uploadUrl"].as_str().unwrap())
+        .header("X-Upload-Token", ticket["token"].as_str().unwrap())
+        .body(data.to_vec())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let stored = server.uploads(UploadKind::FleetSnapshot);
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].data, data);
+    assert_eq!(stored[0].dataset_id.as_deref(), Some("dataset-1"));
+
+    let resp = handshake(&client, &url, &session.access_token, data).await;
+    assert_eq!(resp.status(), 409);
+}
+
+#[tokio::test]
+async fn upload_body_must_match_handshake_hash() {
+    let server = MockServer::start().await.unwrap();
+    let session = server.issue_session("user-1");
+    let client = reqwest::Client::new();
+    let url = server.endpoints().battle_data_upload;
+
+    let ticket: Value = handshake(&client, &url, &session.access_token, b"expected")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let resp = client
+        .post(&url)
+        .header("X-Upload-Token", ticket["token"].as_str().unwrap())
+        .body(b"tampered".to_vec())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    assert!(server.uploads(UploadKind::BattleData).is_empty());
+}
+
+#[tokio::test]
+async fn expired_access_token_recovers_through_refresh() {
+    let server = MockServer::start().await.unwrap();
+    let session = server.issue_session("user-1");
+    let client = reqwest::Client::new();
+    let endpoints = server.endpoints();
+
+    server.expire_access_tokens();
+    let resp = handshake(
+        &client,
+        &endpoints.master_data_upload,
+        &session.access_token,
+        b"m",
+    )
+    .await;
+    assert_eq!(resp.status(), 401);
+
+    let refresh_url = format!(
+        "{}/auth/v1/token?grant_type=refresh_token",
+        endpoints.supabase_url
+    );
+    let refreshed: Value = client
+        .post(&refresh_url)
+        .json(&json!({ "refresh_token": session.refresh_token }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let access_token = refreshed["access_token"].as_str().unwrap();
+    let resp = handshake(&client, &endpoints.master_data_upload, access_token, b"m").await;
+    assert_eq!(resp.status(), 200);
+
+    // Refresh tokens are single-use.
+    let reused = client
+        .post(&refresh_url)
+        .json(&json!({ "refresh_token": session.refresh_token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reused.status(), 400);
+}
+
+#[tokio::test]
+async fn dataset_token_authorizes_handshake_without_bearer() {
+    let server = MockServer::start().await.unwrap();
+    let token = server.issue_dataset_token("pid-1");
+    let client = reqwest::Client::new();
+    let url = server.endpoints().quest_tree_ingest;
+
+    let resp = client
+        .post(&url)
+        .header("X-Dataset-Token", &token)
+        .json(&json!({ "content_hash": hex::encode(Sha256::digest(b"q")) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let resp = client
+        .post(&url)
+        .json(&json!({ "content_hash": hex::encode(Sha256::digest(b"q")) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn rate_limit_returns_retry_after() {
+    let server = MockServer::start_with(MockServerConfig {
+        rate_limit: Some(RateLimit {
+            max_requests: 2,
+            window: Duration::from_secs(60),
+        }),
+        ..MockServerConfig::default()
+    })
+    .await
+    .unwrap();
+    let client = reqwest::Client::new();
+    let url = server.endpoints().kc_period;
+
+    for _ in 0..2 {
+        assert_eq!(client.get(&url).send().await.unwrap().status(), 200);
+    }
+    let limited = client.get(&url).send().await.unwrap();
+    assert_eq!(limited.status(), 429);
+    let retry_after: u64 = limited.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after));
+}
+
+#[tokio::test]
+async fn injected_faults_apply_then_expire() {
+    let server = MockServer::start().await.unwrap();
+    let client = reqwest::Client::new();
+    let url = server.endpoints().kc_period;
+
+    server.inject_fault(FaultRule::status("/api/kc-period", 503, 1));
+    assert_eq!(client.get(&url).send().await.unwrap().status(), 503);
+    assert_eq!(client.get(&url).send().await.unwrap().status(), 200);
+
+    server.inject_fault(FaultRule::delay(
+        "/api/kc-period",
+        Duration::from_millis(200),
+    ));
+    let started = Instant::now();
+    assert_eq!(client.get(&url).send().await.unwrap().status(), 200);
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    server.clear_faults();
+
+    let statuses: Vec<u16> = server.requests().iter().map(|r| r.status).collect();
+    assert_eq!(statuses, vec![503, 200, 200]);
+}
+
+#[tokio::test]
+async fn existing_keys_lists_uploaded_assets() {
+    let server = MockServer::start().await.unwrap();
+    server
+        .state()
+        .seeded_asset_keys
+        .push("kcs2/img/common/seed.png".to_string());
+    let session = server.issue_session("user-1");
+    let client = reqwest::Client::new();
+    let endpoints = server.endpoints();
+    let data = b"png-bytes";
+
+    let ticket: Value = client
+        .post(&endpoints.asset_upload)
+        .bearer_auth(&session.access_token)
+        .json(&json!({
+            "key": "kcs2/img/common/uploaded.png",
+            "relative_path": "kcs2/img/common/uploaded.png",
+            "file_size": data.len().to_string(),
+            "content_hash": hex::encode(Sha256::digest(data)),
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    client
+        .post(&endpoints.asset_upload)
+        .header("X-Upload-Token", ticket["token"].as_str().unwrap())
+        .body(data.to_vec())
+        .send()
+        .await
+        .unwrap();
+
+    let keys: Value = client
+        .get(&endpoints.asset_existing_keys)
+        .bearer_auth(&session.access_token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        keys["keys"],
+        json!(["kcs2/img/common/seed.png", "kcs2/img/common/uploaded.png"])
+    );
+    assert_eq!(keys["incremental"], false);
+}
@@ -842,7 +842,7 @@ pub async fn upload_via_api(
     let request = UploadRequest {
         endpoint: &settings.api_endpoint,
         handshake_body,
-        data: file_bytes,
+        data: file_bytes.into(),
         headers,
         context: UploadContext::Asset {
             relative_path: relative.to_string_lossy().to_string(),
@@ -873,6 +873,13 @@ pub async fn upload_via_api(
             register_remote_key(key, Some(file_hash));
             Ok(())
         }
+        Ok(UploadResult::Queued) => {
+            tracing::debug!(
+                key = %mask_sensitive(key),
+                "asset upload event deferred (queued by upload scheduler)"
+            );
+            Ok(())
+        }
         Err(e) => {
             // Improved error detection: Check if error contains "Authentication error" prefix
             // This is safer than pattern matching against fixed strings like "401" or "RequireReauth"
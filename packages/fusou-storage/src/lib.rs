@@ -2,12 +2,16 @@ pub mod asset_sync;
 pub mod cloud_provider_trait;
 pub mod common;
 mod constants;
+pub mod period;
 pub mod providers;
+pub mod retry_handler;
 pub mod root_validator;
 pub mod runtime_hooks;
 pub mod service;
 
 pub use cloud_provider_trait::CloudProviderFactory;
+pub use period::get_period_tag;
+pub use retry_handler::StorageRetryHandler;
 pub use runtime_hooks::{
     launch_auth_page, resolve_dataset_id, set_auth_page_launcher, set_dataset_id_resolver,
 };
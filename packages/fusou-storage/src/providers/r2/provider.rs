@@ -129,7 +129,7 @@ impl R2StorageProvider {
         let request = UploadRequest {
             endpoint: &endpoint,
             handshake_body,
-            data,
+            data: data.into(),
             headers,
             context: UploadContext::Custom(serde_json::json!({
                 "provider": "r2",
@@ -164,6 +164,10 @@ impl R2StorageProvider {
                 tracing::info!("R2 upload skipped (already exists): tag={}", path_tag);
                 Ok(())
             }
+            Ok(UploadResult::Queued) => {
+                tracing::info!("R2 upload queued by upload scheduler: tag={}", path_tag);
+                Ok(())
+            }
             Err(e) => {
                 tracing::debug!("R2 upload failed: tag={}", path_tag);
                 // Trigger retry processing for pending items saved by Uploader
@@ -711,7 +715,7 @@ impl R2StorageProvider {
         let request = UploadRequest {
             endpoint,
             handshake_body,
-            data: concatenated_data,
+            data: concatenated_data.into(),
             headers,
             context: UploadContext::Custom(serde_json::json!({
                 "provider": "r2",
@@ -747,6 +751,13 @@ impl R2StorageProvider {
                 );
                 Ok(())
             }
+            Ok(UploadResult::Queued) => {
+                tracing::info!(
+                    "Master data upload queued by upload scheduler for period={}",
+                    period_tag
+                );
+                Ok(())
+            }
             Err(e) => {
                 // Trigger retry processing for pending items saved by Uploader.
                 let retry = self._retry_service.clone();
@@ -1,18 +1,23 @@
+//! Retry handler for pending uploads whose context is not one of the built-in
+//! `fusou-upload` kinds: R2 battle tables, master data, ingest payloads of the
+//! senders, local file writes and cloud provider uploads.
+
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
-use crate::storage::root_validator;
+use crate::root_validator;
 use fusou_auth::{AuthManager, FileStorage};
 use fusou_upload::retry_service::RetryHandler;
-use fusou_upload::{UploadContext, UploadRequest, Uploader};
+use fusou_upload::{UploadContext, UploadData, UploadRequest, Uploader};
 use kc_api::database::DATABASE_TABLE_VERSION;
 
 #[cfg(feature = "gdrive")]
-use crate::storage::cloud_provider_trait::{CloudProviderFactory, GOOGLE_PROVIDER_KEY};
+use crate::cloud_provider_trait::{CloudProviderFactory, GOOGLE_PROVIDER_KEY};
 
 type RetryResult = Result<(), Box<dyn std::error::Error>>;
 
-pub struct AppUploadRetryHandler {
+pub struct StorageRetryHandler {
     auth_manager: Arc<AuthManager<FileStorage>>,
 }
 
@@ -22,7 +27,7 @@ enum PayloadHashMode {
     ComputedContentHash,
 }
 
-impl AppUploadRetryHandler {
+impl StorageRetryHandler {
     pub fn new(auth_manager: Arc<AuthManager<FileStorage>>) -> Self {
         Self { auth_manager }
     }
@@ -37,7 +42,9 @@ impl AppUploadRetryHandler {
 
     async fn upload_request(&self, request: UploadRequest<'_>) -> RetryResult {
         let client = Self::build_client();
-        Uploader::upload(&client, &self.auth_manager, request, None)
+        // Already admitted by the retry loop; the boxed `UploadError` keeps
+        // its class for the pending item.
+        Uploader::upload_admitted(&client, &self.auth_manager, request, None)
             .await
             .map(|_| ())
             .map_err(|e| e.into())
@@ -57,14 +64,14 @@ impl AppUploadRetryHandler {
         &self,
         endpoint: &'a str,
         handshake_body: serde_json::Value,
-        data: &'a [u8],
+        data: UploadData,
         headers: HashMap<String, String>,
         context: &'a serde_json::Value,
     ) -> UploadRequest<'a> {
         UploadRequest {
             endpoint,
             handshake_body,
-            data: data.to_vec(),
+            data,
             headers,
             context: UploadContext::Custom(context.clone()),
         }
@@ -72,7 +79,9 @@ impl AppUploadRetryHandler {
 
     async fn handle_context(&self, context: &serde_json::Value, data: &[u8]) -> RetryResult {
         if let Some(provider) = context.get("provider").and_then(|v| v.as_str()) {
-            return self.handle_provider_retry(provider, context, data).await;
+            return self
+                .handle_provider_retry(provider, context, data.to_vec().into())
+                .await;
         }
 
         if let Some(operation) = context.get("operation").and_then(|v| v.as_str()) {
@@ -86,7 +95,7 @@ impl AppUploadRetryHandler {
         &self,
         provider: &str,
         context: &serde_json::Value,
-        data: &[u8],
+        data: UploadData,
     ) -> RetryResult {
         match provider {
             "r2" => {
@@ -99,7 +108,7 @@ impl AppUploadRetryHandler {
         }
     }
 
-    async fn handle_r2_retry(&self, context: &serde_json::Value, data: &[u8]) -> RetryResult {
+    async fn handle_r2_retry(&self, context: &serde_json::Value, data: UploadData) -> RetryResult {
         let path_tag = context
             .get("tag")
             .and_then(|v| v.as_str())
@@ -146,7 +155,7 @@ impl AppUploadRetryHandler {
             path_tag,
             &dataset_id,
             table,
-            data.len() as u64,
+            data.len()?,
             table_offsets,
             table_version,
         );
@@ -170,7 +179,7 @@ impl AppUploadRetryHandler {
     async fn handle_master_data_bulk_retry(
         &self,
         context: &serde_json::Value,
-        data: &[u8],
+        data: UploadData,
     ) -> RetryResult {
         let endpoint = self.endpoint_from_context(context)?;
         let period_tag = context
@@ -190,7 +199,7 @@ impl AppUploadRetryHandler {
         let handshake_body = serde_json::json!({
             "kc_period_tag": period_tag,
             "dataset_id": dataset_id,
-            "file_size": data.len().to_string(),
+            "file_size": data.len()?.to_string(),
             "table_offsets": table_offsets,
             "table_version": table_version,
         });
@@ -306,7 +315,7 @@ impl AppUploadRetryHandler {
         let request = self.upload_request_with_custom_context(
             endpoint,
             handshake_body,
-            data,
+            data.to_vec().into(),
             headers,
             context,
         );
@@ -393,7 +402,7 @@ impl AppUploadRetryHandler {
     }
 }
 
-impl RetryHandler for AppUploadRetryHandler {
+impl RetryHandler for StorageRetryHandler {
     fn handle<'a>(
         &'a self,
         context: &'a serde_json::Value,
@@ -403,4 +412,24 @@ impl RetryHandler for AppUploadRetryHandler {
     > {
         Box::pin(async move { self.handle_context(context, data).await })
     }
+
+    fn handle_file<'a>(
+        &'a self,
+        context: &'a serde_json::Value,
+        path: &'a Path,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + 'a>,
+    > {
+        Box::pin(async move {
+            // R2 payloads are uploaded unchanged, so stream them from the
+            // pending file instead of reading them into memory.
+            if let Some(provider @ "r2") = context.get("provider").and_then(|v| v.as_str()) {
+                return self
+                    .handle_provider_retry(provider, context, UploadData::File(path.to_path_buf()))
+                    .await;
+            }
+            let data = tokio::fs::read(path).await?;
+            self.handle_context(context, &data).await
+        })
+    }
 }
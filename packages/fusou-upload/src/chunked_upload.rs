@@ -0,0 +1,412 @@
+//! Chunked, resumable uploads for payloads above
+//! `app.asset_sync.chunked_upload.threshold_bytes`.
+//!
+//! The handshake asks for `multipart: {part_size, part_count, resume_upload_id?}`.
+//! A server that grants it answers with `multipart: {uploadId, partSize, partCount}`;
+//! each part is then sent with `X-Upload-Part: n` and `X-Part-Sha256`, and the
+//! upload is finished with `X-Upload-Action: complete`. When resuming a known
+//! `uploadId`, `X-Upload-Action: status` returns the parts the server still
+//! holds so only the missing ones are sent again. Servers that ignore the
+//! `multipart` request get the whole body in a single stage-2 request.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::SeekFrom;
+use std::path::Path;
+use std::time::Duration;
+
+use configs::ConfigsAppAssetSyncChunkedUpload;
+use fusou_auth::{AuthManager, FileStorage};
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::upload_scheduler::UploadScheduler;
+use crate::uploader::{Handshake, UploadError, UploadResult, Uploader};
+
+const HASH_BLOCK_BYTES: usize = 1024 * 1024;
+const PART_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedUploadSettings {
+    /// Payloads strictly larger than this are chunked; 0 disables chunking.
+    pub threshold_bytes: u64,
+    pub part_size_bytes: u64,
+    pub max_part_attempts: u32,
+}
+
+impl ChunkedUploadSettings {
+    pub fn from_configs(chunked: &ConfigsAppAssetSyncChunkedUpload) -> Self {
+        Self {
+            threshold_bytes: chunked.get_threshold_bytes(),
+            part_size_bytes: chunked.get_part_size_bytes(),
+            max_part_attempts: chunked.get_max_part_attempts(),
+        }
+    }
+
+    pub fn from_user_configs() -> Self {
+        let configs = configs::get_user_configs();
+        Self::from_configs(&configs.app.asset_sync.chunked_upload)
+    }
+
+    pub fn applies_to(&self, total_bytes: u64) -> bool {
+        self.threshold_bytes > 0 && total_bytes > self.threshold_bytes
+    }
+
+    pub fn part_count(total_bytes: u64, part_size: u64) -> u32 {
+        total_bytes.div_ceil(part_size.max(1)).max(1) as u32
+    }
+}
+
+/// A part acknowledged by the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletedPart {
+    pub part_number: u32,
+    pub sha256: String,
+}
+
+/// Resume state of a chunked upload, persisted in the pending item's meta.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultipartProgress {
+    pub upload_id: String,
+    pub part_size: u64,
+    pub part_count: u32,
+    #[serde(default)]
+    pub completed_parts: Vec<CompletedPart>,
+}
+
+impl MultipartProgress {
+    fn is_completed(&self, part_number: u32) -> bool {
+        self.completed_parts
+            .iter()
+            .any(|part| part.part_number == part_number)
+    }
+}
+
+/// Multipart grant returned by the handshake.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct MultipartGrant {
+    #[serde(rename = "uploadId")]
+    upload_id: String,
+    #[serde(rename = "partSize")]
+    part_size: u64,
+    #[serde(rename = "partCount")]
+    part_count: u32,
+}
+
+#[derive(Deserialize)]
+struct StatusResponse {
+    #[serde(default)]
+    parts: Vec<u32>,
+}
+
+/// Where the part bytes come from. Files are read one part at a time so large
+/// payloads never have to be held in memory.
+pub(crate) enum PartSource<'a> {
+    File(&'a Path),
+    Memory(&'a [u8]),
+}
+
+impl PartSource<'_> {
+    async fn len(&self) -> std::io::Result<u64> {
+        match self {
+            PartSource::File(path) => Ok(tokio::fs::metadata(path).await?.len()),
+            PartSource::Memory(data) => Ok(data.len() as u64),
+        }
+    }
+
+    /// Hex SHA-256 of the whole payload, read in blocks.
+    pub(crate) async fn sha256(&self) -> std::io::Result<String> {
+        match self {
+            PartSource::File(path) => {
+                let mut file = tokio::fs::File::open(path).await?;
+                let mut hasher = Sha256::new();
+                let mut buf = vec![0u8; HASH_BLOCK_BYTES];
+                loop {
+                    let read = file.read(&mut buf).await?;
+                    if read == 0 {
+                        break;
+                    }
+                    hasher.update(&buf[..read]);
+                }
+                Ok(hex::encode(hasher.finalize()))
+            }
+            PartSource::Memory(data) => Ok(hex::encode(Sha256::digest(data))),
+        }
+    }
+
+    async fn read(&self, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+        match self {
+            PartSource::File(path) => {
+                let mut file = tokio::fs::File::open(path).await?;
+                file.seek(SeekFrom::Start(offset)).await?;
+                let mut buf = vec![0u8; len as usize];
+                file.read_exact(&mut buf).await?;
+                Ok(buf)
+            }
+            PartSource::Memory(data) => Ok(data[offset as usize..(offset + len) as usize].to_vec()),
+        }
+    }
+}
+
+pub(crate) struct ChunkedUpload<'a> {
+    pub(crate) endpoint: &'a str,
+    pub(crate) handshake_body: &'a serde_json::Value,
+    pub(crate) headers: &'a HashMap<String, String>,
+    pub(crate) content_type: &'a str,
+    pub(crate) content_hash: &'a str,
+    pub(crate) source: PartSource<'a>,
+}
+
+impl ChunkedUpload<'_> {
+    /// Run the upload, resuming from `progress` when it names an upload the
+    /// server still knows. `on_progress` is called whenever `progress`
+    /// changes so the caller can persist it; it is reset to `None` once the
+    /// upload is finished.
+    pub(crate) async fn run(
+        self,
+        client: &Client,
+        auth_manager: &AuthManager<FileStorage>,
+        settings: &ChunkedUploadSettings,
+        progress: &mut Option<MultipartProgress>,
+        mut on_progress: impl FnMut(&Option<MultipartProgress>),
+    ) -> Result<UploadResult, UploadError> {
+        let total =
+            self.source.len().await.map_err(|e| {
+                UploadError::TransportError(format!("Failed to read payload: {}", e))
+            })?;
+        // Keep the part layout of a previous attempt so the server can reuse its parts.
+        let part_size = progress
+            .as_ref()
+            .map(|p| p.part_size)
+            .unwrap_or(settings.part_size_bytes);
+        let part_count = ChunkedUploadSettings::part_count(total, part_size);
+
+        let mut handshake_body = self.handshake_body.clone();
+        if let Some(obj) = handshake_body.as_object_mut() {
+            obj.insert(
+                "content_hash".to_string(),
+                serde_json::Value::String(self.content_hash.to_string()),
+            );
+            obj.insert(
+                "multipart".to_string(),
+                serde_json::json!({
+                    "part_size": part_size,
+                    "part_count": part_count,
+                    "resume_upload_id": progress.as_ref().map(|p| p.upload_id.clone()),
+                }),
+            );
+        }
+
+        let Some(handshake) = Uploader::handshake(
+            client,
+            auth_manager,
+            self.endpoint,
+            &handshake_body,
+            self.headers,
+        )
+        .await?
+        else {
+            Self::finish(progress, &mut on_progress);
+            return Ok(UploadResult::Skipped);
+        };
+
+        let Some(grant) = handshake.multipart.clone() else {
+            tracing::info!("server did not grant a multipart upload; sending a single request");
+            return self.send_whole(client, &handshake, total).await;
+        };
+        if grant.part_count != ChunkedUploadSettings::part_count(total, grant.part_size) {
+            return Err(UploadError::TransportError(format!(
+                "Server multipart plan does not fit the payload ({} parts of {} bytes for {} bytes)",
+                grant.part_count, grant.part_size, total
+            )));
+        }
+
+        match progress.as_mut() {
+            Some(existing) if existing.upload_id == grant.upload_id => {
+                // The server is authoritative about which parts it still holds.
+                let held = self.fetch_status(client, &handshake).await?;
+                existing
+                    .completed_parts
+                    .retain(|part| held.contains(&part.part_number));
+                tracing::info!(
+                    upload_id = %grant.upload_id,
+                    completed = existing.completed_parts.len(),
+                    part_count = grant.part_count,
+                    "resuming chunked upload"
+                );
+            }
+            _ => {
+                *progress = Some(MultipartProgress {
+                    upload_id: grant.upload_id.clone(),
+                    part_size: grant.part_size,
+                    part_count: grant.part_count,
+                    completed_parts: Vec::new(),
+                });
+            }
+        }
+        on_progress(progress);
+
+        for part_number in 1..=grant.part_count {
+            if progress
+                .as_ref()
+                .is_some_and(|p| p.is_completed(part_number))
+            {
+                continue;
+            }
+            let offset = (part_number as u64 - 1) * grant.part_size;
+            let len = grant.part_size.min(total - offset);
+            let data = self.source.read(offset, len).await.map_err(|e| {
+                UploadError::TransportError(format!("Failed to read part {}: {}", part_number, e))
+            })?;
+            let sha256 = Uploader::compute_content_hash(&data);
+
+            self.send_part(client, &handshake, settings, part_number, &sha256, data)
+                .await?;
+            if let Some(p) = progress.as_mut() {
+                p.completed_parts.push(CompletedPart {
+                    part_number,
+                    sha256,
+                });
+            }
+            on_progress(progress);
+        }
+
+        let resp = Uploader::stage_two(client, &handshake, self.headers)
+            .header("X-Upload-Action", "complete")
+            .send()
+            .await
+            .map_err(|e| UploadError::TransportError(format!("Upload network error: {}", e)))?;
+        if resp.status() == StatusCode::CONFLICT {
+            Self::finish(progress, &mut on_progress);
+            return Ok(UploadResult::Skipped);
+        }
+        if !resp.status().is_success() {
+            return Err(Uploader::error_from_response(resp).await);
+        }
+        Self::finish(progress, &mut on_progress);
+        Ok(UploadResult::Success)
+    }
+
+    fn finish(
+        progress: &mut Option<MultipartProgress>,
+        on_progress: &mut impl FnMut(&Option<MultipartProgress>),
+    ) {
+        if progress.take().is_some() {
+            on_progress(progress);
+        }
+    }
+
+    async fn send_whole(
+        &self,
+        client: &Client,
+        handshake: &Handshake,
+        total: u64,
+    ) -> Result<UploadResult, UploadError> {
+        let data =
+            self.source.read(0, total).await.map_err(|e| {
+                UploadError::TransportError(format!("Failed to read payload: {}", e))
+            })?;
+        let resp = Uploader::stage_two(client, handshake, self.headers)
+            .header("Content-Type", self.content_type)
+            .header(CONTENT_LENGTH, data.len())
+            .body(UploadScheduler::global().throttled_body(data))
+            .send()
+            .await
+            .map_err(|e| UploadError::TransportError(format!("Upload network error: {}", e)))?;
+        if resp.status() == StatusCode::CONFLICT {
+            return Ok(UploadResult::Skipped);
+        }
+        if !resp.status().is_success() {
+            return Err(Uploader::error_from_response(resp).await);
+        }
+        Ok(UploadResult::Success)
+    }
+
+    async fn fetch_status(
+        &self,
+        client: &Client,
+        handshake: &Handshake,
+    ) -> Result<BTreeSet<u32>, UploadError> {
+        let resp = Uploader::stage_two(client, handshake, self.headers)
+            .header("X-Upload-Action", "status")
+            .send()
+            .await
+            .map_err(|e| UploadError::TransportError(format!("Upload network error: {}", e)))?;
+        if !resp.status().is_success() {
+            return Err(Uploader::error_from_response(resp).await);
+        }
+        let status: StatusResponse = resp.json().await.map_err(|e| {
+            UploadError::TransportError(format!("Invalid multipart status response: {}", e))
+        })?;
+        Ok(status.parts.into_iter().collect())
+    }
+
+    /// Send one part, retrying transport and server errors with a linear backoff.
+    async fn send_part(
+        &self,
+        client: &Client,
+        handshake: &Handshake,
+        settings: &ChunkedUploadSettings,
+        part_number: u32,
+        sha256: &str,
+        data: Vec<u8>,
+    ) -> Result<(), UploadError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = Uploader::stage_two(client, handshake, self.headers)
+                .header("Content-Type", "application/octet-stream")
+                .header("X-Upload-Part", part_number.to_string())
+                .header("X-Part-Sha256", sha256)
+                .header(CONTENT_LENGTH, data.len())
+                .body(UploadScheduler::global().throttled_body(data.clone()))
+                .send()
+                .await;
+            let error = match result {
+                Ok(resp) if resp.status().is_success() => return Ok(()),
+                Ok(resp) => Uploader::error_from_response(resp).await,
+                Err(e) => UploadError::TransportError(format!("Upload network error: {}", e)),
+            };
+            let retryable = matches!(
+                error,
+                UploadError::TransportError(_) | UploadError::ServerError { .. }
+            );
+            if !retryable || attempt >= settings.max_part_attempts {
+                return Err(error);
+            }
+            tracing::warn!(part_number, attempt, error = %error, "part upload failed, retrying");
+            tokio::time::sleep(PART_RETRY_BASE_DELAY * attempt).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_is_exclusive_and_zero_disables() {
+        let settings = ChunkedUploadSettings {
+            threshold_bytes: 100,
+            part_size_bytes: 40,
+            max_part_attempts: 3,
+        };
+        assert!(!settings.applies_to(100));
+        assert!(settings.applies_to(101));
+
+        let disabled = ChunkedUploadSettings {
+            threshold_bytes: 0,
+            ..settings
+        };
+        assert!(!disabled.applies_to(u64::MAX));
+    }
+
+    #[test]
+    fn part_count_rounds_up() {
+        assert_eq!(ChunkedUploadSettings::part_count(100, 40), 3);
+        assert_eq!(ChunkedUploadSettings::part_count(120, 40), 3);
+        assert_eq!(ChunkedUploadSettings::part_count(0, 40), 1);
+    }
+}
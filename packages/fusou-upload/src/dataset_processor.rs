@@ -1,12 +1,17 @@
 use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
 use std::sync::Arc;
 use chrono::{DateTime, Utc};
+use fusou_auth::{AuthManager, FileStorage};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
 use object_store::ObjectStore;
 use tracing::{debug, info, warn};
 
+use crate::uploader::{UploadContext, UploadData, UploadRequest, UploadResult, Uploader};
+
 /// Metadata for a dataset file stored in R2
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatasetFileMetadata {
@@ -77,36 +82,80 @@ async fn convert_avro_to_parquet(data: Vec<u8>) -> ProcessingResult<Vec<u8>> {
     Ok(data)
 }
 
-/// Concatenates multiple Parquet binaries into a single binary file
-/// Stores offset information for later extraction
-fn concatenate_parquet_files(
-    parquet_files: HashMap<String, Vec<u8>>,
-) -> ProcessingResult<(Vec<u8>, HashMap<String, (usize, usize)>)> {
-    let mut concatenated = Vec::new();
-    let mut offsets = HashMap::new();
+/// Where [`process_and_upload_batch`] sends the concatenated file.
+pub enum BatchUploadTarget<'a> {
+    /// Pre-signed R2 URL taking the whole file in one PUT.
+    PresignedUrl(&'a str),
+    /// Upload endpoint with the stage-1 handshake, as used by `Uploader`.
+    /// Files above `app.asset_sync.chunked_upload.threshold_bytes` go up in
+    /// resumable parts. The handshake body gets `file_key`, the key the
+    /// returned metadata points at.
+    Endpoint {
+        client: &'a Client,
+        auth_manager: &'a AuthManager<FileStorage>,
+        endpoint: &'a str,
+        handshake_body: serde_json::Value,
+        headers: HashMap<String, String>,
+    },
+}
 
-    for (table_name, parquet_bytes) in parquet_files {
-        let start_offset = concatenated.len();
-        let byte_length = parquet_bytes.len();
-        
-        concatenated.extend_from_slice(&parquet_bytes);
-        offsets.insert(table_name, (start_offset, byte_length));
+/// Temporary file holding a concatenated batch; removed when dropped.
+struct SpoolFile {
+    path: PathBuf,
+}
 
-        debug!(
-            "Concatenated table: start={}, length={}",
-            start_offset, byte_length
-        );
+impl SpoolFile {
+    fn create(file_id: Uuid) -> ProcessingResult<(Self, std::fs::File)> {
+        let path = std::env::temp_dir().join(format!("fusou-batch-{}.bin", file_id));
+        let file = std::fs::File::create(&path).map_err(|e| {
+            ProcessingError::IOException(format!("Failed to create {}: {}", path.display(), e))
+        })?;
+        Ok((Self { path }, file))
     }
+}
 
-    info!("Total concatenated size: {} bytes", concatenated.len());
-    Ok((concatenated, offsets))
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Failed to remove batch spool file {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Writes Parquet binaries back to back into `file`, one at a time
+/// Returns the total length and the offset of every table for later extraction
+fn spool_parquet_file(
+    file: &mut std::fs::File,
+    table_name: String,
+    parquet_bytes: Vec<u8>,
+    total_len: &mut usize,
+    offsets: &mut HashMap<String, (usize, usize)>,
+) -> ProcessingResult<()> {
+    let start_offset = *total_len;
+    let byte_length = parquet_bytes.len();
+    file.write_all(&parquet_bytes).map_err(|e| {
+        ProcessingError::IOException(format!("Failed to spool table '{}': {}", table_name, e))
+    })?;
+
+    *total_len += byte_length;
+    offsets.insert(table_name, (start_offset, byte_length));
+
+    debug!(
+        "Spooled table: start={}, length={}",
+        start_offset, byte_length
+    );
+    Ok(())
 }
 
 /// Main upload function: processes Avro data, converts to Parquet, concatenates, and uploads to R2
 ///
 /// # Arguments
 /// * `tables` - HashMap of table_name -> binary data (Avro or Parquet format)
-/// * `upload_url` - Pre-signed URL for R2 upload
+/// * `target` - Pre-signed URL or upload endpoint to send the file to
 /// * `uploader_id` - UUID of the user uploading
 /// * `is_public` - Whether the dataset is public
 /// * `dataset_id` - UUID of the dataset
@@ -116,12 +165,12 @@ fn concatenate_parquet_files(
 ///
 /// # Process Flow
 /// 1. Convert Avro to Parquet for each table
-/// 2. Concatenate all Parquet files into a single binary
-/// 3. Upload to R2 storage
+/// 2. Concatenate all Parquet files into a temporary file
+/// 3. Upload to R2 storage, streamed from the file
 /// 4. Generate metadata with offset information
 pub async fn process_and_upload_batch(
     tables: HashMap<String, Vec<u8>>,
-    upload_url: &str,
+    target: BatchUploadTarget<'_>,
     uploader_id: Uuid,
     is_public: bool,
     dataset_id: Uuid,
@@ -132,23 +181,74 @@ pub async fn process_and_upload_batch(
         tables.len()
     );
 
-    // Step 1: Convert Avro to Parquet for each table
-    let mut parquet_files = HashMap::new();
+    let file_id = Uuid::new_v4();
+    let file_key = format!("raw/{}/{}.bin", dataset_id, file_id);
+
+    // Steps 1 and 2: Convert each table and append it to the spool file, so
+    // only one converted table is held in memory at a time
+    let (spool, mut file) = SpoolFile::create(file_id)?;
+    let mut total_len = 0;
+    let mut offsets = HashMap::new();
     for (table_name, data) in tables {
         debug!("Converting table '{}' from Avro to Parquet", table_name);
         let parquet_bytes = convert_avro_to_parquet(data).await?;
-        parquet_files.insert(table_name, parquet_bytes);
+        spool_parquet_file(
+            &mut file,
+            table_name,
+            parquet_bytes,
+            &mut total_len,
+            &mut offsets,
+        )?;
     }
-
-    // Step 2: Concatenate all Parquet files
-    let (concatenated_data, offsets) = concatenate_parquet_files(parquet_files)?;
+    file.sync_all()
+        .map_err(|e| ProcessingError::IOException(format!("Failed to spool batch: {}", e)))?;
+    drop(file);
+    info!("Total concatenated size: {} bytes", total_len);
 
     // Step 3: Upload to R2
-    let file_id = Uuid::new_v4();
-    let file_key = format!("raw/{}/{}.bin", dataset_id, file_id);
-
     debug!("Uploading concatenated file to R2: {}", file_key);
-    upload_to_r2(upload_url, &concatenated_data).await?;
+    match target {
+        BatchUploadTarget::PresignedUrl(upload_url) => {
+            let file = tokio::fs::File::open(&spool.path).await.map_err(|e| {
+                ProcessingError::IOException(format!("Failed to open batch: {}", e))
+            })?;
+            upload_to_r2(upload_url, file, total_len).await?;
+        }
+        BatchUploadTarget::Endpoint {
+            client,
+            auth_manager,
+            endpoint,
+            mut handshake_body,
+            headers,
+        } => {
+            if let Some(obj) = handshake_body.as_object_mut() {
+                obj.insert(
+                    "file_key".to_string(),
+                    serde_json::Value::String(file_key.clone()),
+                );
+            }
+            let request = UploadRequest {
+                endpoint,
+                handshake_body,
+                data: UploadData::File(spool.path.clone()),
+                headers,
+                context: UploadContext::Custom(serde_json::json!({
+                    "dataset_id": dataset_id,
+                    "file_key": file_key,
+                })),
+            };
+            match Uploader::upload(client, auth_manager, request, None).await {
+                Ok(UploadResult::Success) | Ok(UploadResult::Skipped) => {}
+                Ok(UploadResult::Queued) => {
+                    return Err(ProcessingError::NetworkError(
+                        "Batch upload was queued without a pending store".to_string(),
+                    ));
+                }
+                Err(e) => return Err(ProcessingError::NetworkError(e)),
+            }
+        }
+    }
+    drop(spool);
 
     // Step 4: Generate metadata
     let mut metadata_vec = Vec::new();
@@ -186,12 +286,18 @@ pub async fn process_and_upload_batch(
 }
 
 /// Uploads binary data to R2 using a pre-signed URL
-async fn upload_to_r2(upload_url: &str, data: &[u8]) -> ProcessingResult<()> {
+/// `len` is sent as Content-Length so streamed bodies are not chunk-encoded
+async fn upload_to_r2(
+    upload_url: &str,
+    body: impl Into<reqwest::Body>,
+    len: usize,
+) -> ProcessingResult<()> {
     let client = Client::new();
 
     let response = client
         .put(upload_url)
-        .body(data.to_vec())
+        .header(reqwest::header::CONTENT_LENGTH, len)
+        .body(body)
         .send()
         .await
         .map_err(|e| ProcessingError::NetworkError(e.to_string()))?;
@@ -209,7 +315,7 @@ async fn upload_to_r2(upload_url: &str, data: &[u8]) -> ProcessingResult<()> {
         )));
     }
 
-    info!("Successfully uploaded to R2: {} bytes", data.len());
+    info!("Successfully uploaded to R2: {} bytes", len);
     Ok(())
 }
 
@@ -348,7 +454,8 @@ pub async fn compact_dataset_files(
 
         debug!("Uploading optimized file: {}", optimized_path);
         let full_upload_url = format!("{}{}", upload_base_url, optimized_path);
-        upload_to_r2(&full_upload_url, &merged_parquet).await?;
+        let merged_len = merged_parquet.len();
+        upload_to_r2(&full_upload_url, merged_parquet, merged_len).await?;
 
         // Step 4: Create new metadata for compacted file
         let now = Utc::now();
@@ -361,7 +468,7 @@ pub async fn compact_dataset_files(
             table_name: table_name.clone(),
             file_path: optimized_path,
             start_byte: 0,
-            byte_length: merged_parquet.len() as i64,
+            byte_length: merged_len as i64,
             is_public: first_metadata.is_public,
             is_compacted: true,
             created_at: now,
@@ -371,7 +478,7 @@ pub async fn compact_dataset_files(
         debug!(
             "Created compacted metadata for table '{}': size={}",
             table_name,
-            merged_parquet.len()
+            merged_len
         );
     }
 
@@ -476,6 +583,36 @@ mod tests {
         assert_eq!(extracted, vec![3, 4, 5]);
     }
 
+    #[test]
+    fn test_spool_parquet_files_back_to_back() {
+        let (spool, mut file) = SpoolFile::create(Uuid::new_v4()).expect("Spool failed");
+        let mut total_len = 0;
+        let mut offsets = HashMap::new();
+        for (table_name, data) in [("a", vec![1, 2, 3]), ("b", vec![4, 5])] {
+            spool_parquet_file(
+                &mut file,
+                table_name.to_string(),
+                data,
+                &mut total_len,
+                &mut offsets,
+            )
+            .expect("Spool failed");
+        }
+        drop(file);
+
+        let data = std::fs::read(&spool.path).expect("Read failed");
+        assert_eq!(total_len, 5);
+        assert_eq!(offsets["b"], (3, 2));
+        assert_eq!(
+            extract_parquet_from_binary(&data, 3, 2).unwrap(),
+            vec![4, 5]
+        );
+
+        let path = spool.path.clone();
+        drop(spool);
+        assert!(!path.exists());
+    }
+
     #[test]
     fn test_extract_parquet_invalid_offset() {
         let data = vec![1, 2, 3, 4, 5];
@@ -1,18 +1,28 @@
+pub mod chunked_upload;
 pub mod pending_store;
 pub mod retry_service;
 pub mod uploader;
 pub mod dataset_processor;
 pub mod request_suppression_cache;
+pub mod upload_history;
+pub mod upload_metrics;
+pub mod upload_scheduler;
 
-pub use pending_store::{PendingSaveOutcome, PendingStore};
+pub use chunked_upload::{ChunkedUploadSettings, CompletedPart, MultipartProgress};
+pub use pending_store::{DeadLetterMeta, DeadLetterReason, PendingSaveOutcome, PendingStore};
 pub use retry_service::UploadRetryService;
-pub use uploader::{Uploader, UploadRequest, UploadContext, UploadResult, UploadError};
+pub use uploader::{Uploader, UploadRequest, UploadData, UploadContext, UploadResult, UploadError};
 pub use request_suppression_cache::{
     LocalRequestSuppressionCache,
     SuppressionCacheEntryStatus,
     SuppressionCacheStatus,
 };
+pub use upload_history::{UploadErrorClass, UploadHistory, UploadHistoryEntry, UploadOutcome};
+pub use upload_metrics::{UploadMetrics, UploadMetricsSnapshot, UploadTotals};
+pub use upload_scheduler::{
+    Admission, TimeWindow, UploadPolicy, UploadScheduleStatus, UploadScheduler,
+};
 pub use dataset_processor::{
-    DatasetFileMetadata, ProcessingError, ProcessingResult,
+    BatchUploadTarget, DatasetFileMetadata, ProcessingError, ProcessingResult,
     process_and_upload_batch, compact_dataset_files,
 };
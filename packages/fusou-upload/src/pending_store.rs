@@ -2,13 +2,21 @@ use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+use crate::chunked_upload::MultipartProgress;
+use crate::upload_history::{UploadErrorClass, UploadHistory};
+use crate::upload_metrics::UploadMetrics;
+use crate::uploader::UploadContext;
+
+const DEAD_LETTER_DIR_NAME: &str = "dead_letter";
+const OBSERVABILITY_DIR_NAME: &str = "observability";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PendingMeta {
     pub id: String,
@@ -20,6 +28,13 @@ pub struct PendingMeta {
     pub last_attempt_at: Option<u64>,
     pub file_path: PathBuf,
     pub context: Option<String>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub last_error_class: Option<UploadErrorClass>,
+    /// Parts already acknowledged by the server for a chunked upload.
+    #[serde(default)]
+    pub multipart: Option<MultipartProgress>,
 }
 
 impl PendingMeta {
@@ -44,6 +59,9 @@ impl PendingMeta {
             last_attempt_at: None,
             file_path,
             context,
+            last_error: None,
+            last_error_class: None,
+            multipart: None,
         }
     }
 
@@ -51,6 +69,99 @@ impl PendingMeta {
         self.attempt_count += 1;
         self.last_attempt_at = Some(attempted_at);
     }
+
+    /// Count a failed attempt and remember why it failed.
+    pub fn record_failure(&mut self, attempted_at: u64, message: &str, class: UploadErrorClass) {
+        self.increment_attempt(attempted_at);
+        self.record_error(message, class);
+    }
+
+    /// Remember why the upload failed without counting a retry attempt, for
+    /// the first attempt made before the item was queued.
+    pub fn record_error(&mut self, message: &str, class: UploadErrorClass) {
+        self.last_error = Some(message.to_string());
+        self.last_error_class = Some(class);
+    }
+
+    /// Human-readable kind of pending item, derived from its context
+    /// (`asset_upload`, `snapshot_upload`, `r2_upload`, `localfs_write`, ...).
+    pub fn pending_type(&self) -> String {
+        let context = self
+            .context
+            .as_deref()
+            .and_then(|raw| serde_json::from_str::<UploadContext>(raw).ok());
+        Self::kind_of(&self.target_url, context.as_ref())
+    }
+
+    /// Same classification as [`PendingMeta::pending_type`], for uploads that
+    /// have not been queued yet.
+    pub fn kind_of(target_url: &str, context: Option<&UploadContext>) -> String {
+        let Some(context) = context else {
+            return Self::classify_by_target(target_url);
+        };
+
+        match context {
+            UploadContext::Asset { .. } => "asset_upload".to_string(),
+            UploadContext::Snapshot { .. } => "snapshot_upload".to_string(),
+            UploadContext::Custom(value) => {
+                let provider = value
+                    .get("provider")
+                    .and_then(|v| v.as_str())
+                    .filter(|v| !v.is_empty());
+                let operation = value
+                    .get("operation")
+                    .and_then(|v| v.as_str())
+                    .filter(|v| !v.is_empty());
+
+                match (provider, operation) {
+                    (Some(provider), Some(operation)) => format!("{}_{}", provider, operation),
+                    (Some(provider), None) => format!("{}_custom", provider),
+                    (None, Some(operation)) => {
+                        if operation == "upload" {
+                            let target = target_url.trim();
+                            if !target.is_empty() && target != "localfs" {
+                                return format!("cloud_upload:{}", target);
+                            }
+                        }
+                        operation.to_string()
+                    }
+                    (None, None) => Self::classify_by_target(target_url),
+                }
+            }
+        }
+    }
+
+    fn classify_by_target(target_url: &str) -> String {
+        let target = target_url.trim();
+        if target.is_empty() {
+            return "unknown".to_string();
+        }
+        if target == "localfs" {
+            return "localfs_write".to_string();
+        }
+        if target.starts_with("http://") || target.starts_with("https://") {
+            return "http_upload".to_string();
+        }
+        format!("cloud_upload:{}", target)
+    }
+}
+
+/// Why a pending item was parked in the dead-letter folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterReason {
+    MaxAttempts,
+    Expired,
+}
+
+/// Pending item moved out of the retry queue. Kept on disk until the user
+/// requeues or deletes it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeadLetterMeta {
+    #[serde(flatten)]
+    pub pending: PendingMeta,
+    pub reason: DeadLetterReason,
+    pub dead_lettered_at: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -71,10 +182,14 @@ impl PendingSaveOutcome {
     }
 }
 
+/// Clones share the save lock, metrics and history, so keep one store (and
+/// its clones) per directory.
 #[derive(Clone)]
 pub struct PendingStore {
     base_dir: PathBuf,
     save_lock: Arc<Mutex<()>>,
+    metrics: Arc<OnceLock<Arc<UploadMetrics>>>,
+    history: Arc<OnceLock<Arc<UploadHistory>>>,
 }
 
 impl PendingStore {
@@ -87,9 +202,26 @@ impl PendingStore {
         Self {
             base_dir,
             save_lock: Arc::new(Mutex::new(())),
+            metrics: Arc::new(OnceLock::new()),
+            history: Arc::new(OnceLock::new()),
         }
     }
 
+    /// Counters for uploads through this store, shared by the uploader
+    /// (first attempts) and the retry service.
+    pub fn metrics(&self) -> Arc<UploadMetrics> {
+        self.metrics
+            .get_or_init(|| Arc::new(UploadMetrics::for_store(self)))
+            .clone()
+    }
+
+    /// History of finished uploads through this store.
+    pub fn history(&self) -> Arc<UploadHistory> {
+        self.history
+            .get_or_init(|| Arc::new(UploadHistory::for_store(self)))
+            .clone()
+    }
+
     fn compute_content_hash(data: &[u8]) -> String {
         let mut hasher = Sha256::new();
         hasher.update(data);
@@ -260,19 +392,156 @@ impl PendingStore {
         Ok(())
     }
 
-    pub fn cleanup_expired(&self, ttl_seconds: u64) {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+    /// Move items older than `ttl_seconds` to the dead-letter folder and return them.
+    pub fn cleanup_expired(&self, ttl_seconds: u64) -> Vec<DeadLetterMeta> {
+        let now = Self::now_epoch_seconds();
 
+        let mut expired = Vec::new();
         let pending = self.list_pending();
         for meta in pending {
             if now > meta.created_at + ttl_seconds {
-                tracing::info!("Removing expired pending upload: {}", meta.id);
-                let _ = self.delete_pending(&meta.id);
+                tracing::info!("Moving expired pending upload to dead-letter: {}", meta.id);
+                match self.move_to_dead_letter(&meta, DeadLetterReason::Expired) {
+                    Ok(dead) => expired.push(dead),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to dead-letter expired pending upload {}: {}; deleting",
+                            meta.id,
+                            e
+                        );
+                        let _ = self.delete_pending(&meta.id);
+                    }
+                }
             }
         }
+        expired
+    }
+
+    pub fn dead_letter_dir(&self) -> PathBuf {
+        self.base_dir.join(DEAD_LETTER_DIR_NAME)
+    }
+
+    /// Directory for queue history / metrics files, next to the queue itself.
+    pub fn observability_dir(&self) -> PathBuf {
+        self.base_dir.join(OBSERVABILITY_DIR_NAME)
+    }
+
+    /// Move a pending item (payload + metadata) into the dead-letter folder.
+    pub fn move_to_dead_letter(
+        &self,
+        meta: &PendingMeta,
+        reason: DeadLetterReason,
+    ) -> Result<DeadLetterMeta, io::Error> {
+        let dead_dir = self.dead_letter_dir();
+        fs::create_dir_all(&dead_dir)?;
+
+        let bin_path = self.base_dir.join(format!("{}.bin", meta.id));
+        let dead_bin_path = dead_dir.join(format!("{}.bin", meta.id));
+        fs::rename(&bin_path, &dead_bin_path)?;
+
+        let mut pending = meta.clone();
+        pending.file_path = dead_bin_path.clone();
+        let dead = DeadLetterMeta {
+            pending,
+            reason,
+            dead_lettered_at: Self::now_epoch_seconds(),
+        };
+        let meta_json = serde_json::to_string_pretty(&dead)?;
+        if let Err(e) = fs::write(dead_dir.join(format!("{}.json", meta.id)), meta_json) {
+            // Put the payload back so the item is not lost from both places.
+            let _ = fs::rename(&dead_bin_path, &bin_path);
+            return Err(e);
+        }
+        let _ = fs::remove_file(self.base_dir.join(format!("{}.json", meta.id)));
+        Ok(dead)
+    }
+
+    pub fn list_dead_letter(&self) -> Vec<DeadLetterMeta> {
+        let dead_dir = self.dead_letter_dir();
+        let mut items = Vec::new();
+
+        if let Ok(entries) = fs::read_dir(&dead_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                    continue;
+                }
+                let Ok(content) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                let Ok(dead) = serde_json::from_str::<DeadLetterMeta>(&content) else {
+                    continue;
+                };
+                let expected = dead_dir.join(format!("{}.bin", dead.pending.id));
+                if dead.pending.file_path == expected && expected.exists() {
+                    items.push(dead);
+                }
+            }
+        }
+
+        items.sort_by_key(|item| item.dead_lettered_at);
+        items
+    }
+
+    /// Put a dead-lettered item back into the retry queue with a fresh attempt count.
+    pub fn requeue_dead_letter(&self, id: &str) -> Result<PendingMeta, io::Error> {
+        let dead = self
+            .list_dead_letter()
+            .into_iter()
+            .find(|item| item.pending.id == id)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("dead-letter item not found: {}", id),
+                )
+            })?;
+
+        let _guard = self
+            .save_lock
+            .lock()
+            .map_err(|_| io::Error::other("pending save lock poisoned"))?;
+
+        let bin_path = self.base_dir.join(format!("{}.bin", id));
+        fs::rename(&dead.pending.file_path, &bin_path)?;
+
+        let mut meta = dead.pending;
+        meta.file_path = bin_path;
+        meta.attempt_count = 0;
+        meta.last_attempt_at = None;
+        // Restart the TTL clock, otherwise expired items would be dead-lettered again immediately.
+        meta.created_at = Self::now_epoch_seconds();
+        self.update_meta(&meta)?;
+        let _ = fs::remove_file(self.dead_letter_dir().join(format!("{}.json", id)));
+        Ok(meta)
+    }
+
+    pub fn delete_dead_letter(&self, id: &str) -> Result<(), io::Error> {
+        // Only ids that are actually listed, so a crafted id cannot escape the folder.
+        if !self
+            .list_dead_letter()
+            .iter()
+            .any(|item| item.pending.id == id)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("dead-letter item not found: {}", id),
+            ));
+        }
+        let dead_dir = self.dead_letter_dir();
+        for ext in ["json", "bin"] {
+            let path = dead_dir.join(format!("{}.{}", id, ext));
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn now_epoch_seconds() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
     }
 
     pub fn read_data(&self, meta: &PendingMeta) -> Result<Vec<u8>, io::Error> {
@@ -283,3 +552,41 @@ impl PendingStore {
         &self.base_dir
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expired_items_move_to_dead_letter_and_can_be_requeued() {
+        let dir = std::env::temp_dir().join(format!("fusou-pending-dead-{}", Uuid::new_v4()));
+        let store = PendingStore::new(dir.clone());
+        let saved = store
+            .save_pending(
+                "https://example.invalid/upload",
+                &HashMap::new(),
+                b"payload",
+                None,
+            )
+            .unwrap();
+
+        // ttl 0 with created_at in the past expires everything
+        let mut meta = saved.meta().clone();
+        meta.created_at -= 10;
+        store.update_meta(&meta).unwrap();
+        let expired = store.cleanup_expired(0);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].reason, DeadLetterReason::Expired);
+        assert!(store.list_pending().is_empty());
+        assert_eq!(store.list_dead_letter().len(), 1);
+
+        let requeued = store.requeue_dead_letter(&meta.id).unwrap();
+        assert_eq!(requeued.attempt_count, 0);
+        assert_eq!(store.read_data(&requeued).unwrap(), b"payload");
+        assert_eq!(store.list_pending().len(), 1);
+        assert!(store.list_dead_letter().is_empty());
+        assert!(store.delete_dead_letter("../escape").is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
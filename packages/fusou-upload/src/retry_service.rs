@@ -7,17 +7,34 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::time::sleep;
 
-use crate::pending_store::{PendingMeta, PendingStore};
-use crate::uploader::{UploadContext, UploadRequest, Uploader};
+use crate::chunked_upload::ChunkedUploadSettings;
+use crate::pending_store::{DeadLetterMeta, DeadLetterReason, PendingMeta, PendingStore};
+use crate::upload_history::{UploadErrorClass, UploadHistory, UploadHistoryEntry, UploadOutcome};
+use crate::upload_metrics::UploadMetrics;
+use crate::upload_scheduler::{Admission, UploadScheduler};
+use crate::uploader::{UploadContext, UploadData, UploadRequest, Uploader};
 use configs::get_user_configs;
 use fusou_auth::{AuthManager, FileStorage};
 
+pub type RetryFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + 'a>>;
+
 pub trait RetryHandler: Send + Sync {
-    fn handle<'a>(
+    fn handle<'a>(&'a self, context: &'a serde_json::Value, data: &'a [u8]) -> RetryFuture<'a>;
+
+    /// Retry the payload stored at `path`. The default reads it for
+    /// [`Self::handle`]; handlers that upload the payload unchanged can pass
+    /// it on as [`UploadData::File`] so large payloads are streamed from disk.
+    fn handle_file<'a>(
         &'a self,
         context: &'a serde_json::Value,
-        data: &'a [u8],
-    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + 'a>>;
+        path: &'a Path,
+    ) -> RetryFuture<'a> {
+        Box::pin(async move {
+            let data = tokio::fs::read(path).await?;
+            self.handle(context, &data).await
+        })
+    }
 }
 
 pub struct UploadRetryService {
@@ -25,6 +42,8 @@ pub struct UploadRetryService {
     is_running: Arc<AtomicBool>,
     auth_manager: Arc<AuthManager<FileStorage>>,
     custom_handler: Option<Arc<dyn RetryHandler>>,
+    history: Arc<UploadHistory>,
+    metrics: Arc<UploadMetrics>,
 }
 
 struct RunningFlagGuard {
@@ -73,14 +92,27 @@ impl UploadRetryService {
         auth_manager: Arc<AuthManager<FileStorage>>,
         custom_handler: Option<Arc<dyn RetryHandler>>,
     ) -> Self {
+        let history = store.history();
+        let metrics = store.metrics();
         Self {
             store,
             is_running: Arc::new(AtomicBool::new(false)),
             auth_manager,
             custom_handler,
+            history,
+            metrics,
         }
     }
 
+    /// Finished items (succeeded / dead-lettered / expired / discarded), newest first on read.
+    pub fn history(&self) -> Arc<UploadHistory> {
+        self.history.clone()
+    }
+
+    pub fn metrics(&self) -> Arc<UploadMetrics> {
+        self.metrics.clone()
+    }
+
     /// Get a clone of the underlying AuthManager used for retries
     pub fn auth_manager(&self) -> Arc<AuthManager<FileStorage>> {
         self.auth_manager.clone()
@@ -90,6 +122,18 @@ impl UploadRetryService {
         self.is_running.load(Ordering::Acquire)
     }
 
+    /// Run `f` with retries held off: no retry starts until it returns.
+    /// `None` (without running `f`) when a retry is already running.
+    pub fn run_exclusive<T>(&self, f: impl FnOnce() -> T) -> Option<T> {
+        self.is_running
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()?;
+        let _running_guard = RunningFlagGuard {
+            is_running: self.is_running.clone(),
+        };
+        Some(f())
+    }
+
     pub async fn trigger_retry(&self) {
         self.trigger_retry_internal(false).await;
     }
@@ -98,7 +142,13 @@ impl UploadRetryService {
         self.trigger_retry_internal(true).await;
     }
 
+    /// Retry a single item immediately. Its time window is ignored since the
+    /// user asked for it explicitly, but offline mode still applies.
     pub async fn retry_pending_item_now(&self, id: &str) -> Result<(), String> {
+        if UploadScheduler::global().is_paused() {
+            return Err("uploads are paused (offline mode)".to_string());
+        }
+
         if self
             .is_running
             .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
@@ -114,7 +164,8 @@ impl UploadRetryService {
         let configs = get_user_configs();
         let retry_config = &configs.app.asset_sync.retry;
 
-        self.store.cleanup_expired(retry_config.get_ttl_seconds());
+        let expired = self.store.cleanup_expired(retry_config.get_ttl_seconds());
+        Self::record_expired(&self.history, &self.metrics, &expired);
 
         let mut meta = self
             .store
@@ -124,9 +175,9 @@ impl UploadRetryService {
             .ok_or_else(|| format!("pending item not found: {id}"))?;
 
         if meta.attempt_count >= retry_config.get_max_attempts() {
-            let _ = self.store.delete_pending(&meta.id);
+            Self::dead_letter_exhausted(&self.store, &self.history, &self.metrics, &meta);
             return Err(format!(
-                "max attempts ({}) reached; pending item removed",
+                "max attempts ({}) reached; pending item moved to dead-letter",
                 retry_config.get_max_attempts()
             ));
         }
@@ -137,29 +188,42 @@ impl UploadRetryService {
             .build()
             .map_err(|e| format!("failed to build retry client: {e}"))?;
 
-        let retry_result = Self::retry_one(
-            &self.store,
-            &mut meta,
-            &client,
-            &self.auth_manager,
-            self.custom_handler.as_deref(),
-        )
-        .await
-        .map_err(|e| e.to_string());
+        let bytes = Self::payload_len(&meta);
+        let retry_result = {
+            let _in_flight = self.metrics.begin_attempt();
+            Self::retry_one(
+                &self.store,
+                &mut meta,
+                &client,
+                &self.auth_manager,
+                self.custom_handler.as_deref(),
+            )
+            .await
+            .map_err(|e| (e.to_string(), UploadErrorClass::from_dyn(e.as_ref())))
+        };
 
         match retry_result {
             Ok(()) => {
                 self.store
                     .delete_pending(&meta.id)
                     .map_err(|e| format!("failed to remove succeeded pending item: {e}"))?;
+                self.metrics.record_success(bytes);
+                Self::record_finished(
+                    &self.history,
+                    &meta,
+                    UploadOutcome::Succeeded,
+                    meta.attempt_count + 1,
+                    bytes,
+                );
                 Ok(())
             }
-            Err(message) => {
+            Err((message, class)) => {
                 if Self::is_auth_related_error(&message) {
                     return Err(message);
                 }
 
-                meta.increment_attempt(Self::now_epoch_seconds());
+                meta.record_failure(Self::now_epoch_seconds(), &message, class);
+                self.metrics.record_failure();
                 self.store
                     .update_meta(&meta)
                     .map_err(|e| format!("failed to update pending retry metadata: {e}"))?;
@@ -182,6 +246,8 @@ impl UploadRetryService {
         let is_running = self.is_running.clone();
         let auth_manager = self.auth_manager.clone();
         let custom_handler = self.custom_handler.clone();
+        let history = self.history.clone();
+        let metrics = self.metrics.clone();
 
         tokio::spawn(async move {
             let _running_guard = RunningFlagGuard { is_running };
@@ -191,15 +257,26 @@ impl UploadRetryService {
             let retry_config = &configs.app.asset_sync.retry;
 
             {
-                // Cleanup expired first
-                store.cleanup_expired(retry_config.get_ttl_seconds());
+                // Move expired items to dead-letter first
+                let expired = store.cleanup_expired(retry_config.get_ttl_seconds());
+                Self::record_expired(&history, &metrics, &expired);
 
-                let pending_items = store.list_pending();
+                let mut pending_items = store.list_pending();
                 if pending_items.is_empty() {
                     tracing::info!("No pending uploads to retry");
                     return;
                 }
 
+                let scheduler = UploadScheduler::global();
+                if scheduler.is_paused() {
+                    tracing::info!(
+                        total_pending = pending_items.len(),
+                        "Offline mode is on; pending uploads stay queued"
+                    );
+                    return;
+                }
+                scheduler.sort_by_priority(&mut pending_items);
+
                 let mut stats = RetryBatchStats {
                     total_pending: pending_items.len(),
                     ..RetryBatchStats::default()
@@ -246,13 +323,25 @@ impl UploadRetryService {
                         continue;
                     }
 
+                    if let Admission::Deferred { until } =
+                        scheduler.admit(&meta.pending_type(), Self::now_epoch_seconds())
+                    {
+                        stats.note_deferred(until);
+                        tracing::debug!(
+                            pending_id = %meta.id,
+                            next_window_at = until,
+                            "outside upload window; deferring pending retry"
+                        );
+                        continue;
+                    }
+
                     if meta.attempt_count >= retry_config.get_max_attempts() {
                         tracing::warn!(
-                            "Max attempts ({}) reached for {}, deleting",
+                            "Max attempts ({}) reached for {}, moving to dead-letter",
                             retry_config.get_max_attempts(),
                             meta.id
                         );
-                        let _ = store.delete_pending(&meta.id);
+                        Self::dead_letter_exhausted(&store, &history, &metrics, &meta);
                         stats.exhausted_removed += 1;
                         continue;
                     }
@@ -277,6 +366,13 @@ impl UploadRetryService {
                                 "Removing redundant pending upload already covered in this batch"
                             );
                             let _ = store.delete_pending(&meta.id);
+                            Self::record_finished(
+                                &history,
+                                &meta,
+                                UploadOutcome::Discarded,
+                                meta.attempt_count,
+                                0,
+                            );
                             stats.duplicate_removed += 1;
                             continue;
                         }
@@ -293,17 +389,21 @@ impl UploadRetryService {
                         retry_config.get_max_attempts()
                     );
 
-                    let retry_result = Self::retry_one(
-                        &store,
-                        &mut meta,
-                        &client,
-                        &auth_manager,
-                        custom_handler.as_deref(),
-                    )
-                    .await
-                    .map_err(|e| e.to_string());
-
-                    if let Err(error_text) = retry_result {
+                    let bytes = Self::payload_len(&meta);
+                    let retry_result = {
+                        let _in_flight = metrics.begin_attempt();
+                        Self::retry_one(
+                            &store,
+                            &mut meta,
+                            &client,
+                            &auth_manager,
+                            custom_handler.as_deref(),
+                        )
+                        .await
+                        .map_err(|e| (e.to_string(), UploadErrorClass::from_dyn(e.as_ref())))
+                    };
+
+                    if let Err((error_text, error_class)) = retry_result {
                         let is_auth_error = Self::is_auth_related_error(&error_text);
 
                         if is_auth_error {
@@ -316,8 +416,13 @@ impl UploadRetryService {
                             stats.auth_blocked = true;
                         } else {
                             tracing::error!("Failed to retry upload {}: {}", meta.id, error_text);
-                            meta.increment_attempt(Self::now_epoch_seconds());
+                            meta.record_failure(
+                                Self::now_epoch_seconds(),
+                                &error_text,
+                                error_class,
+                            );
                             let _ = store.update_meta(&meta);
+                            metrics.record_failure();
                             stats.failed += 1;
                         }
 
@@ -329,6 +434,14 @@ impl UploadRetryService {
                     } else {
                         tracing::info!("Successfully retried upload {}", meta.id);
                         let _ = store.delete_pending(&meta.id);
+                        metrics.record_success(bytes);
+                        Self::record_finished(
+                            &history,
+                            &meta,
+                            UploadOutcome::Succeeded,
+                            meta.attempt_count + 1,
+                            bytes,
+                        );
                         stats.succeeded += 1;
                     }
 
@@ -406,6 +519,71 @@ impl UploadRetryService {
         reference.saturating_add(jittered_wait)
     }
 
+    fn payload_len(meta: &PendingMeta) -> u64 {
+        std::fs::metadata(&meta.file_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or_default()
+    }
+
+    fn record_finished(
+        history: &UploadHistory,
+        meta: &PendingMeta,
+        outcome: UploadOutcome,
+        attempts: u32,
+        bytes: u64,
+    ) {
+        let entry = UploadHistoryEntry::from_meta(meta, outcome, attempts, bytes);
+        if let Err(e) = history.record(&entry) {
+            tracing::warn!("Failed to record upload history for {}: {}", meta.id, e);
+        }
+    }
+
+    fn record_expired(
+        history: &UploadHistory,
+        metrics: &UploadMetrics,
+        expired: &[DeadLetterMeta],
+    ) {
+        for dead in expired {
+            metrics.record_expired();
+            Self::record_finished(
+                history,
+                &dead.pending,
+                UploadOutcome::Expired,
+                dead.pending.attempt_count,
+                Self::payload_len(&dead.pending),
+            );
+        }
+    }
+
+    fn dead_letter_exhausted(
+        store: &PendingStore,
+        history: &UploadHistory,
+        metrics: &UploadMetrics,
+        meta: &PendingMeta,
+    ) {
+        let bytes = Self::payload_len(meta);
+        match store.move_to_dead_letter(meta, DeadLetterReason::MaxAttempts) {
+            Ok(_) => {
+                metrics.record_dead_lettered();
+                Self::record_finished(
+                    history,
+                    meta,
+                    UploadOutcome::DeadLettered,
+                    meta.attempt_count,
+                    bytes,
+                );
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Failed to move {} to dead-letter: {}; deleting instead",
+                    meta.id,
+                    e
+                );
+                let _ = store.delete_pending(&meta.id);
+            }
+        }
+    }
+
     fn is_auth_related_error(message: &str) -> bool {
         let normalized = message.to_ascii_lowercase();
         // Use specific prefixes only; substring-matching "401"/"403" risks false-positive
@@ -461,8 +639,6 @@ impl UploadRetryService {
         auth_manager: &AuthManager<FileStorage>,
         custom_handler: Option<&dyn RetryHandler>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let data = store.read_data(meta)?;
-
         // Reconstruct context
         let context = if let Some(context_str) = &meta.context {
             if let Ok(ctx) = serde_json::from_str::<UploadContext>(context_str) {
@@ -514,21 +690,42 @@ impl UploadRetryService {
                     resolved_dataset_id.as_deref(),
                 );
 
+                let context = UploadContext::Asset {
+                    relative_path,
+                    key,
+                    file_size,
+                    dataset_id: resolved_dataset_id,
+                    content_type,
+                };
+
+                // Large assets are streamed from disk in parts and resume
+                // from the parts the server already acknowledged.
+                let chunking = ChunkedUploadSettings::from_user_configs();
+                let stored_size = std::fs::metadata(&meta.file_path)?.len();
+                if chunking.applies_to(stored_size) {
+                    return Uploader::upload_pending_chunked(
+                        client,
+                        auth_manager,
+                        store,
+                        meta,
+                        &handshake_body,
+                        &context,
+                        &chunking,
+                    )
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.into());
+                }
+
                 let request = UploadRequest {
                     endpoint: &meta.target_url,
                     handshake_body,
-                    data,
+                    data: UploadData::File(meta.file_path.clone()),
                     headers: meta.headers.clone(),
-                    context: UploadContext::Asset {
-                        relative_path,
-                        key,
-                        file_size,
-                        dataset_id: resolved_dataset_id,
-                        content_type,
-                    },
+                    context,
                 };
 
-                Uploader::upload(client, auth_manager, request, None)
+                Uploader::upload_admitted(client, auth_manager, request, None)
                     .await
                     .map(|_| ())
                     .map_err(|e| e.into())
@@ -536,24 +733,26 @@ impl UploadRetryService {
             UploadContext::Snapshot { is_snapshot: _ } => {
                 // Reconstruct Snapshot Handshake
                 // Data is the JSON body
-                let json_body: serde_json::Value = serde_json::from_slice(&data)?;
+                let file = std::fs::File::open(&meta.file_path)?;
+                let json_body: serde_json::Value =
+                    serde_json::from_reader(std::io::BufReader::new(file))?;
 
                 let request = UploadRequest {
                     endpoint: &meta.target_url,
                     handshake_body: json_body,
-                    data,
+                    data: UploadData::File(meta.file_path.clone()),
                     headers: meta.headers.clone(),
                     context: UploadContext::Snapshot { is_snapshot: true },
                 };
 
-                Uploader::upload(client, auth_manager, request, None)
+                Uploader::upload_admitted(client, auth_manager, request, None)
                     .await
                     .map(|_| ())
                     .map_err(|e| e.into())
             }
             UploadContext::Custom(value) => {
                 if let Some(handler) = custom_handler {
-                    handler.handle(&value, &data).await
+                    handler.handle_file(&value, &meta.file_path).await
                 } else {
                     Err("No custom handler registered".into())
                 }
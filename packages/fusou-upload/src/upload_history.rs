@@ -0,0 +1,295 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::pending_store::{PendingMeta, PendingStore};
+use crate::uploader::UploadError;
+
+const HISTORY_FILE_NAME: &str = "history.jsonl";
+const DEFAULT_MAX_ENTRIES: usize = 2000;
+
+/// Coarse failure category, derived from `UploadError`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadErrorClass {
+    Authentication,
+    Client,
+    Server,
+    Transport,
+    Conflict,
+    Other,
+}
+
+impl UploadErrorClass {
+    pub fn from_error(err: &UploadError) -> Self {
+        match err {
+            UploadError::AuthenticationError { .. } => Self::Authentication,
+            UploadError::ClientError { .. } => Self::Client,
+            UploadError::ServerError { .. } => Self::Server,
+            UploadError::TransportError(_) => Self::Transport,
+            UploadError::Conflict => Self::Conflict,
+        }
+    }
+
+    /// Classify an error returned through `Box<dyn Error>` by the retry
+    /// paths. Errors other than `UploadError` (missing context, local I/O,
+    /// provider errors) are `Other`.
+    pub fn from_dyn(err: &(dyn std::error::Error + 'static)) -> Self {
+        err.downcast_ref::<UploadError>()
+            .map(Self::from_error)
+            .unwrap_or(Self::Other)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Authentication => "authentication",
+            Self::Client => "client",
+            Self::Server => "server",
+            Self::Transport => "transport",
+            Self::Conflict => "conflict",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// How a pending upload left the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadOutcome {
+    Succeeded,
+    /// Max attempts reached; moved to the dead-letter folder.
+    DeadLettered,
+    /// TTL elapsed before it could be uploaded; moved to the dead-letter folder.
+    Expired,
+    /// Removed by the user or as a duplicate of another pending item.
+    Discarded,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadHistoryEntry {
+    pub id: String,
+    pub context_kind: String,
+    pub target_url: String,
+    pub attempts: u32,
+    pub outcome: UploadOutcome,
+    pub error_class: Option<UploadErrorClass>,
+    pub last_error: Option<String>,
+    pub bytes: u64,
+    pub finished_at: u64,
+}
+
+impl UploadHistoryEntry {
+    /// An upload that succeeded on its first attempt and never entered the
+    /// pending queue.
+    pub fn first_attempt(context_kind: &str, target_url: &str, bytes: u64) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            context_kind: context_kind.to_string(),
+            target_url: target_url.to_string(),
+            attempts: 1,
+            outcome: UploadOutcome::Succeeded,
+            error_class: None,
+            last_error: None,
+            bytes,
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    pub fn from_meta(
+        meta: &PendingMeta,
+        outcome: UploadOutcome,
+        attempts: u32,
+        bytes: u64,
+    ) -> Self {
+        Self {
+            id: meta.id.clone(),
+            context_kind: meta.pending_type(),
+            target_url: meta.target_url.clone(),
+            attempts,
+            outcome,
+            error_class: meta.last_error_class,
+            last_error: meta.last_error.clone(),
+            bytes,
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+/// Append-only JSON-lines log of finished pending uploads, trimmed to the newest entries.
+#[derive(Clone)]
+pub struct UploadHistory {
+    path: PathBuf,
+    max_entries: usize,
+    lock: Arc<Mutex<()>>,
+}
+
+impl UploadHistory {
+    pub fn new(path: PathBuf) -> Self {
+        Self::with_max_entries(path, DEFAULT_MAX_ENTRIES)
+    }
+
+    pub fn with_max_entries(path: PathBuf, max_entries: usize) -> Self {
+        if let Some(parent) = path.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                tracing::error!("Failed to create upload history directory: {}", e);
+            }
+        }
+        Self {
+            path,
+            max_entries: max_entries.max(1),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// History kept next to the pending queue (`<pending>/observability/history.jsonl`).
+    pub fn for_store(store: &PendingStore) -> Self {
+        Self::new(store.observability_dir().join(HISTORY_FILE_NAME))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, entry: &UploadHistoryEntry) -> Result<(), io::Error> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| io::Error::other("upload history lock poisoned"))?;
+
+        let line = serde_json::to_string(entry)?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        drop(file);
+
+        // Trim lazily: only rewrite once the file holds 25% more than the cap.
+        let entries = self.read_all()?;
+        if entries.len() > self.max_entries + self.max_entries / 4 {
+            let keep = &entries[entries.len() - self.max_entries..];
+            let tmp_path = self.path.with_extension("jsonl.tmp");
+            let mut tmp = fs::File::create(&tmp_path)?;
+            for entry in keep {
+                writeln!(tmp, "{}", serde_json::to_string(entry)?)?;
+            }
+            tmp.sync_all()?;
+            fs::rename(&tmp_path, &self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Newest first.
+    pub fn recent(&self, limit: usize) -> Vec<UploadHistoryEntry> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut tail = VecDeque::with_capacity(limit.min(self.max_entries));
+        for entry in self.read_all().unwrap_or_default() {
+            if tail.len() == limit {
+                tail.pop_front();
+            }
+            if limit > 0 {
+                tail.push_back(entry);
+            }
+        }
+        tail.into_iter().rev().collect()
+    }
+
+    fn read_all(&self) -> Result<Vec<UploadHistoryEntry>, io::Error> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            // Skip lines torn by a crash mid-append.
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, outcome: UploadOutcome) -> UploadHistoryEntry {
+        UploadHistoryEntry {
+            id: id.to_string(),
+            context_kind: "asset_upload".to_string(),
+            target_url: "https://example.invalid/upload".to_string(),
+            attempts: 1,
+            outcome,
+            error_class: None,
+            last_error: None,
+            bytes: 10,
+            finished_at: 0,
+        }
+    }
+
+    #[test]
+    fn history_is_trimmed_and_returned_newest_first() {
+        let dir =
+            std::env::temp_dir().join(format!("fusou-upload-history-{}", uuid::Uuid::new_v4()));
+        let history = UploadHistory::with_max_entries(dir.join(HISTORY_FILE_NAME), 4);
+        for i in 0..10 {
+            history
+                .record(&entry(&i.to_string(), UploadOutcome::Succeeded))
+                .unwrap();
+        }
+
+        let recent = history.recent(3);
+        let ids: Vec<_> = recent.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["9", "8", "7"]);
+        assert!(history.recent(100).len() <= 5);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn error_class_survives_boxing() {
+        let cases = [
+            (
+                UploadError::AuthenticationError {
+                    status_code: 401,
+                    message: String::new(),
+                },
+                UploadErrorClass::Authentication,
+            ),
+            (
+                UploadError::ServerError {
+                    status_code: 503,
+                    message: String::new(),
+                },
+                UploadErrorClass::Server,
+            ),
+            (
+                UploadError::TransportError("Upload network error: reset".to_string()),
+                UploadErrorClass::Transport,
+            ),
+            (UploadError::Conflict, UploadErrorClass::Conflict),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(UploadErrorClass::from_error(&err), expected);
+            let boxed: Box<dyn std::error::Error> = Box::new(err);
+            assert_eq!(UploadErrorClass::from_dyn(boxed.as_ref()), expected);
+        }
+        let other: Box<dyn std::error::Error> = "Server error (503): in a plain string".into();
+        assert_eq!(
+            UploadErrorClass::from_dyn(other.as_ref()),
+            UploadErrorClass::Other
+        );
+    }
+}
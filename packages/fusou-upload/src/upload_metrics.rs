@@ -0,0 +1,225 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pending_store::PendingStore;
+
+const TOTALS_FILE_NAME: &str = "metrics_totals.json";
+
+/// Monotonic counters, persisted so they survive restarts and can be read by the CLI.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UploadTotals {
+    pub succeeded: u64,
+    pub failed: u64,
+    pub dead_lettered: u64,
+    pub expired: u64,
+    pub bytes_uploaded: u64,
+}
+
+/// Point-in-time view of the upload queue.
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadMetricsSnapshot {
+    pub queued: usize,
+    pub queued_bytes: u64,
+    pub in_flight: usize,
+    pub dead_letter: usize,
+    pub succeeded_total: u64,
+    pub failed_total: u64,
+    pub dead_lettered_total: u64,
+    pub expired_total: u64,
+    pub bytes_uploaded_total: u64,
+    pub generated_at: u64,
+}
+
+impl UploadMetricsSnapshot {
+    /// Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let metrics: [(&str, &str, &str, u64); 9] = [
+            (
+                "fusou_upload_queue_pending",
+                "gauge",
+                "Uploads waiting in the pending queue",
+                self.queued as u64,
+            ),
+            (
+                "fusou_upload_queue_pending_bytes",
+                "gauge",
+                "Payload bytes waiting in the pending queue",
+                self.queued_bytes,
+            ),
+            (
+                "fusou_upload_in_flight",
+                "gauge",
+                "Pending uploads currently being retried",
+                self.in_flight as u64,
+            ),
+            (
+                "fusou_upload_dead_letter",
+                "gauge",
+                "Uploads parked in the dead-letter folder",
+                self.dead_letter as u64,
+            ),
+            (
+                "fusou_upload_succeeded_total",
+                "counter",
+                "Pending uploads that were retried successfully",
+                self.succeeded_total,
+            ),
+            (
+                "fusou_upload_failed_total",
+                "counter",
+                "Failed retry attempts",
+                self.failed_total,
+            ),
+            (
+                "fusou_upload_dead_lettered_total",
+                "counter",
+                "Uploads moved to dead-letter after exhausting retries",
+                self.dead_lettered_total,
+            ),
+            (
+                "fusou_upload_expired_total",
+                "counter",
+                "Uploads moved to dead-letter after their TTL elapsed",
+                self.expired_total,
+            ),
+            (
+                "fusou_upload_bytes_total",
+                "counter",
+                "Payload bytes uploaded by the retry queue",
+                self.bytes_uploaded_total,
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out
+    }
+}
+
+/// Counters for the pending upload queue. Gauges (`queued`, `dead_letter`) are
+/// read from `PendingStore` when a snapshot is taken.
+#[derive(Debug)]
+pub struct UploadMetrics {
+    path: PathBuf,
+    totals: Mutex<UploadTotals>,
+    in_flight: AtomicUsize,
+}
+
+pub struct InFlightGuard<'a> {
+    metrics: &'a UploadMetrics,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl UploadMetrics {
+    /// Load persisted totals from `path` (missing or unreadable file starts from zero).
+    pub fn load(path: PathBuf) -> Self {
+        let totals = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            totals: Mutex::new(totals),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    /// Totals kept next to the pending queue (`<pending>/observability/metrics_totals.json`).
+    pub fn for_store(store: &PendingStore) -> Self {
+        Self::load(store.observability_dir().join(TOTALS_FILE_NAME))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn begin_attempt(&self) -> InFlightGuard<'_> {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        InFlightGuard { metrics: self }
+    }
+
+    pub fn record_success(&self, bytes: u64) {
+        self.update(|totals| {
+            totals.succeeded += 1;
+            totals.bytes_uploaded = totals.bytes_uploaded.saturating_add(bytes);
+        });
+    }
+
+    pub fn record_failure(&self) {
+        self.update(|totals| totals.failed += 1);
+    }
+
+    pub fn record_dead_lettered(&self) {
+        self.update(|totals| totals.dead_lettered += 1);
+    }
+
+    pub fn record_expired(&self) {
+        self.update(|totals| totals.expired += 1);
+    }
+
+    pub fn totals(&self) -> UploadTotals {
+        self.totals
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn snapshot(&self, store: &PendingStore) -> UploadMetricsSnapshot {
+        let pending = store.list_pending();
+        let queued_bytes = pending
+            .iter()
+            .filter_map(|meta| fs::metadata(&meta.file_path).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        let totals = self.totals();
+        UploadMetricsSnapshot {
+            queued: pending.len(),
+            queued_bytes,
+            in_flight: self.in_flight.load(Ordering::Acquire),
+            dead_letter: store.list_dead_letter().len(),
+            succeeded_total: totals.succeeded,
+            failed_total: totals.failed,
+            dead_lettered_total: totals.dead_lettered,
+            expired_total: totals.expired,
+            bytes_uploaded_total: totals.bytes_uploaded,
+            generated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut UploadTotals)) {
+        let mut totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+        apply(&mut totals);
+        if let Err(e) = self.flush(&totals) {
+            tracing::warn!("Failed to persist upload metrics: {}", e);
+        }
+    }
+
+    fn flush(&self, totals: &UploadTotals) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create metrics directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(totals)
+            .map_err(|e| format!("failed to serialize upload metrics: {}", e))?;
+        fs::write(&self.path, json)
+            .map_err(|e| format!("failed to write upload metrics file: {}", e))
+    }
+}
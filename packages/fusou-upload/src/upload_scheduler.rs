@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use chrono::{FixedOffset, TimeZone, Timelike};
+use configs::ConfigsAppAssetSyncSchedule;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::pending_store::PendingMeta;
+
+/// Time windows are written in JST, matching the game's own clock.
+const JST_OFFSET_SECONDS: i32 = 9 * 3600;
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const ASSET_UPLOAD_KIND: &str = "asset_upload";
+/// Bytes sent per bandwidth reservation while streaming a throttled body.
+const THROTTLE_CHUNK_BYTES: usize = 16 * 1024;
+
+static GLOBAL_SCHEDULER: OnceLock<UploadScheduler> = OnceLock::new();
+
+/// Daily `HH:MM-HH:MM` window in JST. `start > end` wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    start_minute: u32,
+    end_minute: u32,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        if self.start_minute <= self.end_minute {
+            (self.start_minute..self.end_minute).contains(&minute_of_day)
+        } else {
+            minute_of_day >= self.start_minute || minute_of_day < self.end_minute
+        }
+    }
+
+    /// Minutes from `minute_of_day` until the window next opens (0 when already open).
+    pub fn minutes_until_open(&self, minute_of_day: u32) -> u32 {
+        if self.contains(minute_of_day) {
+            return 0;
+        }
+        (self.start_minute + MINUTES_PER_DAY - minute_of_day) % MINUTES_PER_DAY
+    }
+
+    fn parse_clock(value: &str) -> Option<u32> {
+        let (hours, minutes) = value.trim().split_once(':')?;
+        let hours: u32 = hours.parse().ok()?;
+        let minutes: u32 = minutes.parse().ok()?;
+        // "24:00" is accepted as the end of the day.
+        if minutes >= 60 || hours > 24 || (hours == 24 && minutes != 0) {
+            return None;
+        }
+        Some(hours * 60 + minutes)
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid time window {value:?}; expected \"HH:MM-HH:MM\"");
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let start_minute = Self::parse_clock(start).ok_or_else(invalid)? % MINUTES_PER_DAY;
+        let end_minute = Self::parse_clock(end).ok_or_else(invalid)?;
+        if start_minute == end_minute {
+            return Err(format!("time window {value:?} is empty"));
+        }
+        Ok(Self {
+            start_minute,
+            end_minute,
+        })
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start_minute / 60,
+            self.start_minute % 60,
+            self.end_minute / 60,
+            self.end_minute % 60
+        )
+    }
+}
+
+/// Snapshot of `[app.asset_sync.schedule]`.
+#[derive(Debug, Clone, Default)]
+pub struct UploadPolicy {
+    pub offline_mode: bool,
+    /// 0 means unlimited.
+    pub max_bytes_per_second: u64,
+    /// Pending kinds in retry order; a trailing `*` matches by prefix.
+    /// Only orders the retry queue: [`UploadScheduler::admit`] does not hold
+    /// back a new upload behind pending ones of a higher priority.
+    pub priority_order: Vec<String>,
+    /// Kinds that may only upload inside a daily window.
+    pub windows: HashMap<String, TimeWindow>,
+}
+
+impl UploadPolicy {
+    pub fn from_configs(schedule: &ConfigsAppAssetSyncSchedule) -> Self {
+        let mut windows = HashMap::new();
+        if let Some(raw) = schedule.get_asset_upload_window() {
+            match raw.parse::<TimeWindow>() {
+                Ok(window) => {
+                    windows.insert(ASSET_UPLOAD_KIND.to_string(), window);
+                }
+                Err(e) => {
+                    tracing::warn!("Ignoring asset_upload_window: {}", e);
+                }
+            }
+        }
+
+        Self {
+            offline_mode: schedule.get_offline_mode(),
+            max_bytes_per_second: schedule.get_max_bytes_per_second(),
+            priority_order: schedule.get_priority_order(),
+            windows,
+        }
+    }
+
+    /// Lower runs first; kinds missing from `priority_order` share the last rank.
+    pub fn priority_rank(&self, kind: &str) -> usize {
+        self.priority_order
+            .iter()
+            .position(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => kind.starts_with(prefix),
+                None => pattern == kind,
+            })
+            .unwrap_or(self.priority_order.len())
+    }
+}
+
+/// Whether an upload of a given kind may start now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Now,
+    /// Offline mode is on; nothing uploads until it is turned off.
+    Paused,
+    /// Outside the kind's time window; `until` is the epoch second it next opens.
+    Deferred {
+        until: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadScheduleStatus {
+    pub paused: bool,
+    /// Paused from the app (not persisted; cleared on restart).
+    pub runtime_paused: bool,
+    /// Paused via `offline_mode` in configs.toml.
+    pub offline_mode: bool,
+    pub max_bytes_per_second: u64,
+    pub priority_order: Vec<String>,
+    pub windows: HashMap<String, String>,
+}
+
+/// Token bucket holding at most one second worth of bytes.
+#[derive(Debug)]
+struct BandwidthLimiter {
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    available: f64,
+    refilled_at: Instant,
+}
+
+impl BandwidthLimiter {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(BucketState {
+                available: 0.0,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Reserve `bytes` and wait until the bucket has paid them back. Callers
+    /// that arrive while the bucket is in debt queue behind earlier reservations.
+    async fn acquire(&self, bytes: u64, bytes_per_second: u64) {
+        if bytes_per_second == 0 || bytes == 0 {
+            return;
+        }
+        let rate = bytes_per_second as f64;
+        let wait = {
+            let mut state = self.state.lock().await;
+            let now = Instant::now();
+            let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+            state.available = (state.available + elapsed * rate).min(rate);
+            state.refilled_at = now;
+            state.available -= bytes as f64;
+            if state.available < 0.0 {
+                Duration::from_secs_f64(-state.available / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            tracing::debug!(
+                bytes,
+                wait_ms = wait.as_millis() as u64,
+                "Throttling upload"
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Decides when uploads may run: offline mode, per-kind time windows,
+/// retry priority and a global byte-rate limit shared by every upload.
+#[derive(Debug)]
+pub struct UploadScheduler {
+    /// `None` reads `[app.asset_sync.schedule]` from the user configs on every
+    /// decision.
+    policy: Option<UploadPolicy>,
+    runtime_paused: AtomicBool,
+    limiter: Arc<BandwidthLimiter>,
+}
+
+impl UploadScheduler {
+    pub fn new(policy: UploadPolicy) -> Self {
+        Self::with_policy(Some(policy))
+    }
+
+    fn with_policy(policy: Option<UploadPolicy>) -> Self {
+        Self {
+            policy,
+            runtime_paused: AtomicBool::new(false),
+            limiter: Arc::new(BandwidthLimiter::new()),
+        }
+    }
+
+    /// Process-wide scheduler used by `Uploader` and `UploadRetryService`.
+    /// It follows the user configs, so it does not matter whether it is first
+    /// used before or after they are loaded.
+    pub fn global() -> &'static UploadScheduler {
+        GLOBAL_SCHEDULER.get_or_init(|| Self::with_policy(None))
+    }
+
+    pub fn policy(&self) -> UploadPolicy {
+        match &self.policy {
+            Some(policy) => policy.clone(),
+            None => {
+                UploadPolicy::from_configs(&configs::get_user_configs().app.asset_sync.schedule)
+            }
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.runtime_paused.load(Ordering::Acquire) || self.policy().offline_mode
+    }
+
+    /// Toggle offline mode from the app. `offline_mode` in configs.toml still
+    /// applies while this is off.
+    pub fn set_paused(&self, paused: bool) {
+        self.runtime_paused.store(paused, Ordering::Release);
+        tracing::info!(paused, "Upload offline mode changed");
+    }
+
+    /// Checks offline mode and the kind's time window. Priority is not
+    /// considered here; see [`UploadPolicy::priority_order`].
+    pub fn admit(&self, kind: &str, now_epoch_seconds: u64) -> Admission {
+        if self.is_paused() {
+            return Admission::Paused;
+        }
+        let Some(window) = self.policy().windows.get(kind).copied() else {
+            return Admission::Now;
+        };
+
+        let minute_of_day = Self::jst_minute_of_day(now_epoch_seconds);
+        match window.minutes_until_open(minute_of_day) {
+            0 => Admission::Now,
+            minutes => {
+                let seconds_into_minute = now_epoch_seconds % 60;
+                Admission::Deferred {
+                    until: now_epoch_seconds + u64::from(minutes) * 60 - seconds_into_minute,
+                }
+            }
+        }
+    }
+
+    /// Stable sort by configured priority, oldest first within the same priority.
+    pub fn sort_by_priority(&self, items: &mut [PendingMeta]) {
+        let policy = self.policy();
+        items.sort_by_cached_key(|meta| {
+            (policy.priority_rank(&meta.pending_type()), meta.created_at)
+        });
+    }
+
+    /// Wait until `bytes` fit under `max_bytes_per_second`.
+    pub async fn throttle(&self, bytes: u64) {
+        let rate = self.policy().max_bytes_per_second;
+        self.limiter.acquire(bytes, rate).await;
+    }
+
+    /// Request body that sends `data` in small pieces, each paced by
+    /// `max_bytes_per_second`, so a large upload does not go out as one burst.
+    /// Callers set `Content-Length` since the streamed body has no known size.
+    pub fn throttled_body(&self, data: impl Into<Bytes>) -> reqwest::Body {
+        let data = data.into();
+        let rate = self.policy().max_bytes_per_second;
+        if rate == 0 {
+            return reqwest::Body::from(data);
+        }
+        let limiter = self.limiter.clone();
+        reqwest::Body::wrap_stream(futures::stream::unfold(data, move |mut rest| {
+            let limiter = limiter.clone();
+            async move {
+                if rest.is_empty() {
+                    return None;
+                }
+                let piece = rest.split_to(rest.len().min(THROTTLE_CHUNK_BYTES));
+                limiter.acquire(piece.len() as u64, rate).await;
+                Some((Ok::<_, std::io::Error>(piece), rest))
+            }
+        }))
+    }
+
+    pub fn status(&self) -> UploadScheduleStatus {
+        let policy = self.policy();
+        let runtime_paused = self.runtime_paused.load(Ordering::Acquire);
+        UploadScheduleStatus {
+            paused: runtime_paused || policy.offline_mode,
+            runtime_paused,
+            offline_mode: policy.offline_mode,
+            max_bytes_per_second: policy.max_bytes_per_second,
+            priority_order: policy.priority_order,
+            windows: policy
+                .windows
+                .iter()
+                .map(|(kind, window)| (kind.clone(), window.to_string()))
+                .collect(),
+        }
+    }
+
+    fn jst_minute_of_day(epoch_seconds: u64) -> u32 {
+        let jst = FixedOffset::east_opt(JST_OFFSET_SECONDS).expect("valid JST offset");
+        let time = jst
+            .timestamp_opt(epoch_seconds as i64, 0)
+            .single()
+            .unwrap_or_else(|| jst.timestamp_opt(0, 0).unwrap());
+        time.hour() * 60 + time.minute()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-01 00:00:00 JST
+    const JST_MIDNIGHT: u64 = 1_704_034_800;
+
+    #[test]
+    fn windows_wrap_past_midnight() {
+        let night: TimeWindow = "23:00-02:30".parse().unwrap();
+        assert!(night.contains(23 * 60 + 30));
+        assert!(night.contains(60));
+        assert!(!night.contains(3 * 60));
+        assert_eq!(night.minutes_until_open(22 * 60), 60);
+        assert_eq!(night.to_string(), "23:00-02:30");
+
+        assert!("07:00".parse::<TimeWindow>().is_err());
+        assert!("03:00-03:00".parse::<TimeWindow>().is_err());
+        assert!("25:00-03:00".parse::<TimeWindow>().is_err());
+    }
+
+    #[test]
+    fn asset_window_defers_until_it_opens() {
+        let mut policy = UploadPolicy::default();
+        policy.windows.insert(
+            ASSET_UPLOAD_KIND.to_string(),
+            "03:00-07:00".parse().unwrap(),
+        );
+        let scheduler = UploadScheduler::new(policy);
+
+        let one_am = JST_MIDNIGHT + 3600 + 15;
+        assert_eq!(
+            scheduler.admit(ASSET_UPLOAD_KIND, one_am),
+            Admission::Deferred {
+                until: JST_MIDNIGHT + 3 * 3600
+            }
+        );
+        assert_eq!(scheduler.admit("snapshot_upload", one_am), Admission::Now);
+        assert_eq!(
+            scheduler.admit(ASSET_UPLOAD_KIND, JST_MIDNIGHT + 4 * 3600),
+            Admission::Now
+        );
+
+        scheduler.set_paused(true);
+        assert_eq!(
+            scheduler.admit("snapshot_upload", one_am),
+            Admission::Paused
+        );
+    }
+
+    #[test]
+    fn priority_rank_supports_prefix_patterns() {
+        let policy = UploadPolicy {
+            priority_order: vec![
+                "r2_custom".to_string(),
+                "cloud_upload:*".to_string(),
+                ASSET_UPLOAD_KIND.to_string(),
+            ],
+            ..UploadPolicy::default()
+        };
+        assert_eq!(policy.priority_rank("r2_custom"), 0);
+        assert_eq!(policy.priority_rank("cloud_upload:gdrive"), 1);
+        assert_eq!(policy.priority_rank(ASSET_UPLOAD_KIND), 2);
+        assert_eq!(policy.priority_rank("quest_ingest"), 3);
+    }
+
+    #[tokio::test]
+    async fn throttle_spreads_bytes_over_time() {
+        let scheduler = UploadScheduler::new(UploadPolicy {
+            max_bytes_per_second: 10_000,
+            ..UploadPolicy::default()
+        });
+        let started = Instant::now();
+        scheduler.throttle(1_000).await;
+        scheduler.throttle(1_000).await;
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn throttled_body_paces_each_piece() {
+        use futures::TryStreamExt;
+
+        let scheduler = UploadScheduler::new(UploadPolicy {
+            max_bytes_per_second: (THROTTLE_CHUNK_BYTES * 10) as u64,
+            ..UploadPolicy::default()
+        });
+        let data = vec![7u8; THROTTLE_CHUNK_BYTES * 3];
+        let started = Instant::now();
+        let sent: Vec<Bytes> =
+            http_body_util::BodyDataStream::new(scheduler.throttled_body(data.clone()))
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent.concat(), data);
+        assert!(started.elapsed() >= Duration::from_millis(250));
+    }
+}
@@ -1,15 +1,32 @@
-use crate::pending_store::{PendingSaveOutcome, PendingStore};
+use crate::chunked_upload::{ChunkedUpload, ChunkedUploadSettings, MultipartGrant, PartSource};
+use crate::pending_store::{PendingMeta, PendingSaveOutcome, PendingStore};
+use crate::upload_history::{UploadErrorClass, UploadHistoryEntry};
+use crate::upload_scheduler::{Admission, UploadScheduler};
 use fusou_auth::{AuthManager, FileStorage};
-use reqwest::{Client, StatusCode};
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 #[derive(Deserialize)]
 struct HandshakeResponse {
     #[serde(rename = "uploadUrl")]
     upload_url: String,
     token: String,
+    #[serde(default)]
+    multipart: Option<MultipartGrant>,
+}
+
+/// Accepted stage-1 handshake: where and with which credentials to send the body.
+pub(crate) struct Handshake {
+    pub(crate) upload_url: String,
+    pub(crate) token: String,
+    pub(crate) access_token: Option<String>,
+    /// Present when the handshake asked for a multipart upload and the server agreed.
+    pub(crate) multipart: Option<MultipartGrant>,
 }
 
 /// Structured error type for upload failures
@@ -61,6 +78,8 @@ impl std::fmt::Display for UploadError {
     }
 }
 
+impl std::error::Error for UploadError {}
+
 impl From<UploadError> for String {
     fn from(err: UploadError) -> Self {
         err.to_string()
@@ -85,14 +104,59 @@ pub enum UploadContext {
 pub struct UploadRequest<'a> {
     pub endpoint: &'a str,
     pub handshake_body: serde_json::Value,
-    pub data: Vec<u8>,
+    pub data: UploadData,
     pub headers: HashMap<String, String>,
     pub context: UploadContext,
 }
 
+/// Body of an upload. A file is streamed in parts when the upload is chunked
+/// and only read into memory for a single-request upload or when it has to
+/// be copied into the pending store.
+pub enum UploadData {
+    Memory(Vec<u8>),
+    File(PathBuf),
+}
+
+impl UploadData {
+    pub fn len(&self) -> std::io::Result<u64> {
+        match self {
+            UploadData::Memory(data) => Ok(data.len() as u64),
+            UploadData::File(path) => Ok(std::fs::metadata(path)?.len()),
+        }
+    }
+
+    pub fn is_empty(&self) -> std::io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// The whole payload in memory; reads a file.
+    pub fn bytes(&self) -> std::io::Result<Cow<'_, [u8]>> {
+        match self {
+            UploadData::Memory(data) => Ok(Cow::Borrowed(data)),
+            UploadData::File(path) => Ok(Cow::Owned(std::fs::read(path)?)),
+        }
+    }
+
+    fn part_source(&self) -> PartSource<'_> {
+        match self {
+            UploadData::Memory(data) => PartSource::Memory(data),
+            UploadData::File(path) => PartSource::File(path),
+        }
+    }
+}
+
+impl From<Vec<u8>> for UploadData {
+    fn from(data: Vec<u8>) -> Self {
+        UploadData::Memory(data)
+    }
+}
+
 pub enum UploadResult {
     Success,
     Skipped,
+    /// Held back by the upload scheduler (offline mode or outside the kind's
+    /// time window) and saved to the pending store for a later retry.
+    Queued,
 }
 
 pub struct Uploader;
@@ -122,7 +186,7 @@ impl Uploader {
         format!("{}****{}", head, tail)
     }
 
-    fn compute_content_hash(data: &[u8]) -> String {
+    pub(crate) fn compute_content_hash(data: &[u8]) -> String {
         let mut hasher = Sha256::new();
         hasher.update(data);
         let digest = hasher.finalize();
@@ -217,26 +281,77 @@ impl Uploader {
         pending_store: Option<&PendingStore>,
     ) -> Result<UploadResult, String> {
         tracing::info!("upload event started");
-        let content_hash = Self::compute_content_hash(&request.data);
+
+        let kind = PendingMeta::kind_of(request.endpoint, Some(&request.context));
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        let admission = UploadScheduler::global().admit(&kind, now);
+        if admission != Admission::Now {
+            return Self::defer_upload(&request, pending_store, &kind, admission);
+        }
+
+        let endpoint = request.endpoint;
+        let bytes = request.data.len().unwrap_or_default();
+        let result = Self::upload_admitted(client, auth_manager, request, pending_store).await;
+        if let Some(store) = pending_store {
+            Self::record_first_attempt(store, &kind, endpoint, &result, bytes);
+        }
+        result.map_err(String::from)
+    }
+
+    /// Count a first attempt in the store's metrics and history, as the retry
+    /// service does for later attempts. Failures are queued, so only their
+    /// count is recorded here; they reach the history when they finish.
+    fn record_first_attempt(
+        store: &PendingStore,
+        kind: &str,
+        endpoint: &str,
+        result: &Result<UploadResult, UploadError>,
+        bytes: u64,
+    ) {
+        match result {
+            Ok(UploadResult::Success) => {
+                store.metrics().record_success(bytes);
+                let entry = UploadHistoryEntry::first_attempt(kind, endpoint, bytes);
+                if let Err(e) = store.history().record(&entry) {
+                    tracing::warn!("Failed to record upload history: {}", e);
+                }
+            }
+            Ok(UploadResult::Skipped) | Ok(UploadResult::Queued) => {}
+            Err(_) => store.metrics().record_failure(),
+        }
+    }
+
+    /// [`Self::upload`] without asking the [`UploadScheduler`], for uploads
+    /// that were already admitted (the retry loop) or explicitly requested
+    /// by the user. Never deferred, so an error is always a real failure.
+    pub async fn upload_admitted(
+        client: &Client,
+        auth_manager: &AuthManager<FileStorage>,
+        request: UploadRequest<'_>,
+        pending_store: Option<&PendingStore>,
+    ) -> Result<UploadResult, UploadError> {
+        let size = request.data.len().map_err(|e| {
+            UploadError::TransportError(format!("Failed to read upload payload: {}", e))
+        })?;
+        let chunking = ChunkedUploadSettings::from_user_configs();
+        if chunking.applies_to(size) {
+            return Self::upload_chunked(client, auth_manager, request, pending_store, &chunking)
+                .await;
+        }
+
         let result = Self::perform_upload(client, auth_manager, &request).await;
 
-        if result.is_err() {
+        if let Err(error) = &result {
             let mut queued_pending = false;
             let mut pending_already_exists = false;
             if let Some(store) = pending_store {
-                let context_json = serde_json::to_string(&request.context).unwrap_or_default();
-                let mut pending_headers = request.headers.clone();
-                pending_headers
-                    .entry("content-hash".to_string())
-                    .or_insert(content_hash);
-                match store.save_pending(
-                    request.endpoint,
-                    &pending_headers,
-                    &request.data,
-                    Some(context_json),
-                ) {
-                    Ok(PendingSaveOutcome::Created(meta)) => {
+                match Self::save_to_pending(store, &request) {
+                    Ok(PendingSaveOutcome::Created(mut meta)) => {
                         tracing::warn!(pending_id = %meta.id, "Upload failed, saved to pending store");
+                        meta.record_error(&error.to_string(), UploadErrorClass::from_error(error));
+                        if let Err(e) = store.update_meta(&meta) {
+                            tracing::warn!(pending_id = %meta.id, "Failed to record upload error: {}", e);
+                        }
                         queued_pending = true;
                     }
                     Ok(PendingSaveOutcome::Existing(meta)) => {
@@ -269,10 +384,194 @@ impl Uploader {
                 UploadResult::Skipped => {
                     tracing::info!("upload event completed (already exists upstream)");
                 }
+                UploadResult::Queued => {}
+            }
+        }
+
+        result
+    }
+
+    /// Large payloads: park an in-memory body in the pending store first so
+    /// the parts are streamed from disk and an interrupted upload resumes from
+    /// the parts already acknowledged instead of starting over. A body that is
+    /// already a file is streamed from it directly.
+    async fn upload_chunked(
+        client: &Client,
+        auth_manager: &AuthManager<FileStorage>,
+        request: UploadRequest<'_>,
+        pending_store: Option<&PendingStore>,
+        settings: &ChunkedUploadSettings,
+    ) -> Result<UploadResult, UploadError> {
+        let content_type = Self::content_type_of(&request.context);
+
+        let staging_store = pending_store.filter(|_| matches!(request.data, UploadData::Memory(_)));
+        let Some(store) = staging_store else {
+            let source = request.data.part_source();
+            let content_hash = source.sha256().await.map_err(|e| {
+                UploadError::TransportError(format!("Failed to hash upload payload: {}", e))
+            })?;
+            let mut progress = None;
+            let result = ChunkedUpload {
+                endpoint: request.endpoint,
+                handshake_body: &request.handshake_body,
+                headers: &request.headers,
+                content_type: &content_type,
+                content_hash: &content_hash,
+                source,
+            }
+            .run(client, auth_manager, settings, &mut progress, |_| {})
+            .await;
+            Self::log_outcome(&result);
+            return result;
+        };
+
+        let mut meta = match Self::save_to_pending(store, &request) {
+            Ok(outcome) => outcome.meta().clone(),
+            Err(e) => {
+                tracing::error!("Failed to stage chunked upload: {}", e);
+                return Err(UploadError::TransportError(format!(
+                    "Failed to stage chunked upload: {}",
+                    e
+                )));
+            }
+        };
+        let UploadRequest {
+            handshake_body,
+            data,
+            context,
+            ..
+        } = request;
+        drop(data);
+
+        let result = Self::upload_pending_chunked(
+            client,
+            auth_manager,
+            store,
+            &mut meta,
+            &handshake_body,
+            &context,
+            settings,
+        )
+        .await;
+        match &result {
+            Ok(_) => {
+                if let Err(e) = store.delete_pending(&meta.id) {
+                    tracing::warn!(pending_id = %meta.id, "Failed to remove staged upload: {}", e);
+                }
+            }
+            Err(e) => {
+                tracing::warn!(pending_id = %meta.id, error = %e, "Chunked upload failed, kept in pending store");
+                meta.record_error(&e.to_string(), UploadErrorClass::from_error(e));
+                if let Err(e) = store.update_meta(&meta) {
+                    tracing::warn!(pending_id = %meta.id, "Failed to record upload error: {}", e);
+                }
             }
         }
+        result
+    }
 
-        result.map_err(|e| e.into())
+    /// Upload a pending item in parts, streaming from its file and recording
+    /// acknowledged parts in `meta.multipart` (persisted via `store`) so the
+    /// next attempt resumes. The item itself is left in the store.
+    pub async fn upload_pending_chunked(
+        client: &Client,
+        auth_manager: &AuthManager<FileStorage>,
+        store: &PendingStore,
+        meta: &mut PendingMeta,
+        handshake_body: &serde_json::Value,
+        context: &UploadContext,
+        settings: &ChunkedUploadSettings,
+    ) -> Result<UploadResult, UploadError> {
+        let file_path = meta.file_path.clone();
+        let source = PartSource::File(&file_path);
+        let content_hash = match meta.headers.get("content-hash") {
+            Some(hash) => hash.clone(),
+            None => source.sha256().await.map_err(|e| {
+                UploadError::TransportError(format!("Failed to hash pending upload: {}", e))
+            })?,
+        };
+        let headers = meta.headers.clone();
+        let endpoint = meta.target_url.clone();
+        let content_type = Self::content_type_of(context);
+
+        let mut progress = meta.multipart.clone();
+        let result = ChunkedUpload {
+            endpoint: &endpoint,
+            handshake_body,
+            headers: &headers,
+            content_type: &content_type,
+            content_hash: &content_hash,
+            source,
+        }
+        .run(client, auth_manager, settings, &mut progress, |progress| {
+            meta.multipart = progress.clone();
+            if let Err(e) = store.update_meta(meta) {
+                tracing::warn!(pending_id = %meta.id, "Failed to persist multipart progress: {}", e);
+            }
+        })
+        .await;
+        Self::log_outcome(&result);
+        result
+    }
+
+    fn log_outcome(result: &Result<UploadResult, UploadError>) {
+        match result {
+            Ok(UploadResult::Success) => tracing::info!("upload event completed successfully"),
+            Ok(UploadResult::Skipped) => {
+                tracing::info!("upload event completed (already exists upstream)")
+            }
+            Ok(UploadResult::Queued) => {}
+            Err(_) => tracing::info!("upload event completed (failed)"),
+        }
+    }
+
+    fn save_to_pending(
+        store: &PendingStore,
+        request: &UploadRequest<'_>,
+    ) -> Result<PendingSaveOutcome, std::io::Error> {
+        let context_json = serde_json::to_string(&request.context).unwrap_or_default();
+        let data = request.data.bytes()?;
+        let mut pending_headers = request.headers.clone();
+        pending_headers
+            .entry("content-hash".to_string())
+            .or_insert_with(|| Self::compute_content_hash(&data));
+        store.save_pending(
+            request.endpoint,
+            &pending_headers,
+            &data,
+            Some(context_json),
+        )
+    }
+
+    fn defer_upload(
+        request: &UploadRequest<'_>,
+        pending_store: Option<&PendingStore>,
+        kind: &str,
+        admission: Admission,
+    ) -> Result<UploadResult, String> {
+        let reason = match admission {
+            Admission::Paused => "offline mode".to_string(),
+            Admission::Deferred { until } => format!("outside upload window until {}", until),
+            Admission::Now => unreachable!("admitted uploads are not deferred"),
+        };
+        let Some(store) = pending_store else {
+            tracing::info!(kind, reason = %reason, "upload event deferred (no pending store configured)");
+            return Err(format!("Upload deferred by scheduler ({})", reason));
+        };
+
+        match Self::save_to_pending(store, request) {
+            Ok(outcome) => {
+                tracing::info!(pending_id = %outcome.meta().id, kind, reason = %reason, "upload event queued by scheduler");
+                Ok(UploadResult::Queued)
+            }
+            Err(e) => {
+                tracing::error!("Failed to save deferred upload: {}", e);
+                Err(format!(
+                    "Upload deferred by scheduler ({}) but could not be queued: {}",
+                    reason, e
+                ))
+            }
+        }
     }
 
     async fn perform_upload(
@@ -280,7 +579,11 @@ impl Uploader {
         auth_manager: &AuthManager<FileStorage>,
         request: &UploadRequest<'_>,
     ) -> Result<UploadResult, UploadError> {
-        let content_hash = Self::compute_content_hash(&request.data);
+        let data = request
+            .data
+            .bytes()
+            .map_err(|e| UploadError::TransportError(format!("Failed to read payload: {}", e)))?;
+        let content_hash = Self::compute_content_hash(&data);
 
         // Merge content_hash into handshake_body
         let mut handshake_body = request.handshake_body.clone();
@@ -292,28 +595,68 @@ impl Uploader {
         }
 
         // 1. Handshake (JSON body)
+        let Some(handshake) = Self::handshake(
+            client,
+            auth_manager,
+            request.endpoint,
+            &handshake_body,
+            &request.headers,
+        )
+        .await?
+        else {
+            return Ok(UploadResult::Skipped);
+        };
+
+        // 2. Upload (binary body), paced by the global bandwidth limit
+        let upload_resp = Self::stage_two(client, &handshake, &request.headers)
+            .header("Content-Type", Self::content_type_of(&request.context))
+            .header(CONTENT_LENGTH, data.len())
+            .body(UploadScheduler::global().throttled_body(data.into_owned()))
+            .send()
+            .await
+            .map_err(|e| UploadError::TransportError(format!("Upload network error: {}", e)))?;
+
+        if upload_resp.status() == StatusCode::CONFLICT {
+            return Ok(UploadResult::Skipped);
+        }
+
+        if !upload_resp.status().is_success() {
+            return Err(Self::error_from_response(upload_resp).await);
+        }
+
+        Ok(UploadResult::Success)
+    }
+
+    /// Stage 1: send the JSON handshake (which must already carry
+    /// `content_hash`). Returns `None` when the server reports the content as
+    /// already uploaded (409).
+    pub(crate) async fn handshake(
+        client: &Client,
+        auth_manager: &AuthManager<FileStorage>,
+        endpoint: &str,
+        handshake_body: &serde_json::Value,
+        headers: &HashMap<String, String>,
+    ) -> Result<Option<Handshake>, UploadError> {
         // Serialize JSON manually to avoid automatic Content-Type header from .json()
-        let handshake_json = serde_json::to_vec(&handshake_body).map_err(|e| {
+        let handshake_json = serde_json::to_vec(handshake_body).map_err(|e| {
             UploadError::TransportError(format!("Failed to serialize handshake: {}", e))
         })?;
 
         let mut handshake_req = client
-            .post(request.endpoint)
+            .post(endpoint)
             .body(handshake_json)
             .header("Content-Type", "application/json");
 
         // Add custom headers (excluding Content-Type to avoid duplicates)
-        for (k, v) in &request.headers {
+        for (k, v) in headers {
             if k.to_lowercase() != "content-type" {
                 handshake_req = handshake_req.header(k, v);
             }
         }
-
         // Add X-Dataset-Token header if available.
         // Uploader path is intentionally read-only for auth state in v2 mode.
         // It does not perform v1 anonymous-sync bootstrap or token refresh itself.
-        let dataset_token_opt = if let Some(dataset_id) = Self::extract_dataset_id(&handshake_body)
-        {
+        let dataset_token_opt = if let Some(dataset_id) = Self::extract_dataset_id(handshake_body) {
             let loaded = auth_manager
                 .load_dataset_token_for_dataset(dataset_id)
                 .await
@@ -374,88 +717,75 @@ impl Uploader {
             .map_err(|e| UploadError::TransportError(format!("Handshake network error: {}", e)))?;
 
         if resp.status() == StatusCode::CONFLICT {
-            return Ok(UploadResult::Skipped);
+            return Ok(None);
         }
 
         if !resp.status().is_success() {
-            let status_code = resp.status().as_u16();
-            let body = resp.text().await.unwrap_or_default();
-            return Err(match status_code {
-                401 | 403 => UploadError::AuthenticationError {
-                    status_code,
-                    message: body,
-                },
-                400..=499 => UploadError::ClientError {
-                    status_code,
-                    message: body,
-                },
-                _ => UploadError::ServerError {
-                    status_code,
-                    message: body,
-                },
-            });
+            return Err(Self::error_from_response(resp).await);
         }
 
         let handshake_res: HandshakeResponse = resp.json().await.map_err(|e| {
             UploadError::TransportError(format!("Invalid handshake response: {}", e))
         })?;
 
-        // 2. Upload (binary body)
-        let mut upload_req = client
-            .post(&handshake_res.upload_url)
-            .body(request.data.clone());
+        Ok(Some(Handshake {
+            upload_url: handshake_res.upload_url,
+            token: handshake_res.token,
+            access_token,
+            multipart: handshake_res.multipart,
+        }))
+    }
 
+    /// Stage 2 request skeleton: upload token, custom headers (excluding
+    /// Content-Type) and bearer auth. The caller adds the body.
+    pub(crate) fn stage_two(
+        client: &Client,
+        handshake: &Handshake,
+        headers: &HashMap<String, String>,
+    ) -> RequestBuilder {
         // Add X-Upload-Token header from handshake response
-        upload_req = upload_req.header("X-Upload-Token", &handshake_res.token);
+        let mut upload_req = client
+            .post(&handshake.upload_url)
+            .header("X-Upload-Token", &handshake.token);
 
-        // Add custom headers first (excluding Content-Type)
-        for (k, v) in &request.headers {
+        for (k, v) in headers {
             if k.to_lowercase() != "content-type" {
                 upload_req = upload_req.header(k, v);
             }
         }
 
-        // Determine Content-Type based on context or fall back to application/octet-stream
-        let content_type = match &request.context {
+        if let Some(access_token) = &handshake.access_token {
+            upload_req = upload_req.bearer_auth(access_token);
+        }
+        upload_req
+    }
+
+    /// Determine Content-Type based on context or fall back to application/octet-stream
+    pub(crate) fn content_type_of(context: &UploadContext) -> String {
+        match context {
             UploadContext::Asset { content_type, .. } => content_type
                 .clone()
                 .unwrap_or_else(|| "application/octet-stream".to_string()),
             _ => "application/octet-stream".to_string(),
-        };
-        upload_req = upload_req.header("Content-Type", &content_type);
-
-        if let Some(access_token) = access_token {
-            upload_req = upload_req.bearer_auth(access_token);
-        }
-
-        let upload_resp = upload_req
-            .send()
-            .await
-            .map_err(|e| UploadError::TransportError(format!("Upload network error: {}", e)))?;
-
-        if upload_resp.status() == StatusCode::CONFLICT {
-            return Ok(UploadResult::Skipped);
         }
+    }
 
-        if !upload_resp.status().is_success() {
-            let status_code = upload_resp.status().as_u16();
-            let body = upload_resp.text().await.unwrap_or_default();
-            return Err(match status_code {
-                401 | 403 => UploadError::AuthenticationError {
-                    status_code,
-                    message: body,
-                },
-                400..=499 => UploadError::ClientError {
-                    status_code,
-                    message: body,
-                },
-                _ => UploadError::ServerError {
-                    status_code,
-                    message: body,
-                },
-            });
+    pub(crate) async fn error_from_response(resp: Response) -> UploadError {
+        let status_code = resp.status().as_u16();
+        let body = resp.text().await.unwrap_or_default();
+        match status_code {
+            401 | 403 => UploadError::AuthenticationError {
+                status_code,
+                message: body,
+            },
+            400..=499 => UploadError::ClientError {
+                status_code,
+                message: body,
+            },
+            _ => UploadError::ServerError {
+                status_code,
+                message: body,
+            },
         }
-
-        Ok(UploadResult::Success)
     }
 }
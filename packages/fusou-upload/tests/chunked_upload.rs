@@ -0,0 +1,104 @@
+//! Chunked uploads against `fusou-mock-server`: an interrupted upload resumes
+//! from the parts the server already acknowledged.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use fusou_auth::manager::AuthConfig;
+use fusou_auth::{AuthManager, FileStorage, Session};
+use fusou_mock_server::{FaultRule, MockServer, UploadKind};
+use fusou_upload::{ChunkedUploadSettings, PendingStore, UploadContext, UploadResult, Uploader};
+
+#[tokio::test]
+async fn interrupted_chunked_upload_resumes_missing_parts() {
+    let server = MockServer::start().await.unwrap();
+    let dir = std::env::temp_dir().join(format!("fusou-upload-chunked-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let issued = server.issue_session("user-1");
+    let manager = AuthManager::new(
+        AuthConfig {
+            supabase_url: server.endpoints().supabase_url,
+            api_key: "test-api-key".to_string(),
+            refresh_path: "/auth/v1/token".to_string(),
+            refresh_margin_secs: 30,
+        },
+        Arc::new(FileStorage::new(dir.join("session.json"))),
+    );
+    manager
+        .save_session(&Session {
+            access_token: issued.access_token,
+            refresh_token: issued.refresh_token,
+            expires_at: Some(Utc::now() + Duration::hours(1)),
+            token_type: Some("bearer".to_string()),
+        })
+        .await
+        .unwrap();
+
+    let store = PendingStore::new(dir.join("pending"));
+    let client = reqwest::Client::new();
+    let endpoint = server.endpoints().fleet_snapshot;
+    let context = UploadContext::Snapshot { is_snapshot: true };
+    let handshake_body = Uploader::build_snapshot_handshake("latest", "dataset-1");
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    let settings = ChunkedUploadSettings {
+        threshold_bytes: 1,
+        part_size_bytes: 1024,
+        max_part_attempts: 1,
+    };
+
+    let mut meta = store
+        .save_pending(
+            &endpoint,
+            &HashMap::new(),
+            &data,
+            Some(serde_json::to_string(&context).unwrap()),
+        )
+        .unwrap()
+        .meta()
+        .clone();
+
+    // Handshake and parts 1-3 go through, part 4 fails.
+    server.inject_fault(FaultRule::status("/api/fleet/snapshot", 503, 1).after(4));
+    let interrupted = Uploader::upload_pending_chunked(
+        &client,
+        &manager,
+        &store,
+        &mut meta,
+        &handshake_body,
+        &context,
+        &settings,
+    )
+    .await;
+    assert!(interrupted.is_err());
+    let progress = meta.multipart.clone().expect("progress recorded");
+    assert_eq!(progress.part_count, 10);
+    assert_eq!(progress.completed_parts.len(), 3);
+    let persisted = store.list_pending();
+    assert_eq!(persisted.len(), 1);
+    assert_eq!(persisted[0].multipart, Some(progress));
+    assert!(server.uploads(UploadKind::FleetSnapshot).is_empty());
+
+    let resumed = Uploader::upload_pending_chunked(
+        &client,
+        &manager,
+        &store,
+        &mut meta,
+        &handshake_body,
+        &context,
+        &settings,
+    )
+    .await
+    .unwrap();
+    assert!(matches!(resumed, UploadResult::Success));
+    assert!(meta.multipart.is_none());
+
+    let stored = server.uploads(UploadKind::FleetSnapshot);
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].data, data);
+    // First run: handshake + 4 parts. Resume: handshake + status + 7 parts + complete.
+    assert_eq!(server.requests().len(), 5 + 10);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
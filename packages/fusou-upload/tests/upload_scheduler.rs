@@ -0,0 +1,75 @@
+//! Offline mode against `fusou-mock-server`. Kept in its own test binary
+//! because it toggles the process-wide `UploadScheduler`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use fusou_auth::manager::AuthConfig;
+use fusou_auth::{AuthManager, FileStorage, Session};
+use fusou_mock_server::{MockServer, UploadKind};
+use fusou_upload::{
+    PendingStore, UploadContext, UploadRequest, UploadResult, UploadScheduler, Uploader,
+};
+
+#[tokio::test]
+async fn offline_mode_queues_uploads_until_resumed() {
+    let server = MockServer::start().await.unwrap();
+    let dir = std::env::temp_dir().join(format!("fusou-upload-scheduler-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let issued = server.issue_session("user-1");
+    let manager = AuthManager::new(
+        AuthConfig {
+            supabase_url: server.endpoints().supabase_url,
+            api_key: "test-api-key".to_string(),
+            refresh_path: "/auth/v1/token".to_string(),
+            refresh_margin_secs: 30,
+        },
+        Arc::new(FileStorage::new(dir.join("session.json"))),
+    );
+    manager
+        .save_session(&Session {
+            access_token: issued.access_token,
+            refresh_token: issued.refresh_token,
+            expires_at: Some(Utc::now() + Duration::hours(1)),
+            token_type: Some("bearer".to_string()),
+        })
+        .await
+        .unwrap();
+
+    let store = PendingStore::new(dir.join("pending"));
+    let client = reqwest::Client::new();
+    let endpoint = server.endpoints().fleet_snapshot;
+    let request = || UploadRequest {
+        endpoint: &endpoint,
+        handshake_body: Uploader::build_snapshot_handshake("latest", "dataset-1"),
+        data: b"fleet".to_vec().into(),
+        headers: HashMap::new(),
+        context: UploadContext::Snapshot { is_snapshot: true },
+    };
+
+    let scheduler = UploadScheduler::global();
+    scheduler.set_paused(true);
+
+    let queued = Uploader::upload(&client, &manager, request(), Some(&store))
+        .await
+        .unwrap();
+    assert!(matches!(queued, UploadResult::Queued));
+    assert_eq!(store.list_pending().len(), 1);
+    assert!(server.requests().is_empty());
+
+    // Without a pending store there is nowhere to park the upload.
+    assert!(Uploader::upload(&client, &manager, request(), None)
+        .await
+        .is_err());
+
+    scheduler.set_paused(false);
+    let resumed = Uploader::upload(&client, &manager, request(), None)
+        .await
+        .unwrap();
+    assert!(matches!(resumed, UploadResult::Success));
+    assert_eq!(server.uploads(UploadKind::FleetSnapshot).len(), 1);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,156 @@
+//! Drives `Uploader::upload` end to end against `fusou-mock-server`.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use fusou_auth::manager::AuthConfig;
+use fusou_auth::{AuthManager, FileStorage, Session};
+use fusou_mock_server::{FaultRule, MockServer, UploadKind};
+use fusou_upload::{
+    PendingStore, UploadContext, UploadErrorClass, UploadRequest, UploadResult, Uploader,
+};
+
+fn temp_dir(label: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "fusou-upload-mock-{label}-{}",
+        uuid::Uuid::new_v4()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+async fn auth_manager(server: &MockServer, dir: &std::path::Path) -> AuthManager<FileStorage> {
+    let issued = server.issue_session("user-1");
+    let config = AuthConfig {
+        supabase_url: server.endpoints().supabase_url,
+        api_key: "test-api-key".to_string(),
+        refresh_path: "/auth/v1/token".to_string(),
+        refresh_margin_secs: 30,
+    };
+    let manager = AuthManager::new(config, Arc::new(FileStorage::new(dir.join("session.json"))));
+    manager
+        .save_session(&Session {
+            access_token: issued.access_token,
+            refresh_token: issued.refresh_token,
+            expires_at: Some(Utc::now() + Duration::hours(1)),
+            token_type: Some("bearer".to_string()),
+        })
+        .await
+        .unwrap();
+    manager
+}
+
+fn snapshot_request<'a>(endpoint: &'a str, data: &[u8]) -> UploadRequest<'a> {
+    UploadRequest {
+        endpoint,
+        handshake_body: Uploader::build_snapshot_handshake("latest", "dataset-1"),
+        data: data.to_vec().into(),
+        headers: HashMap::new(),
+        context: UploadContext::Snapshot { is_snapshot: true },
+    }
+}
+
+#[tokio::test]
+async fn upload_succeeds_then_duplicate_is_skipped() {
+    let server = MockServer::start().await.unwrap();
+    let dir = temp_dir("dup");
+    let manager = auth_manager(&server, &dir).await;
+    let client = reqwest::Client::new();
+    let endpoint = server.endpoints().fleet_snapshot;
+
+    let first = Uploader::upload(
+        &client,
+        &manager,
+        snapshot_request(&endpoint, b"fleet"),
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(matches!(first, UploadResult::Success));
+    let stored = server.uploads(UploadKind::FleetSnapshot);
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].data, b"fleet");
+    assert_eq!(stored[0].handshake["tag"], "latest");
+
+    let second = Uploader::upload(
+        &client,
+        &manager,
+        snapshot_request(&endpoint, b"fleet"),
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(matches!(second, UploadResult::Skipped));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn server_error_queues_pending_upload() {
+    let server = MockServer::start().await.unwrap();
+    let dir = temp_dir("pending");
+    let manager = auth_manager(&server, &dir).await;
+    let store = PendingStore::new(dir.join("pending"));
+    let client = reqwest::Client::new();
+    let endpoint = server.endpoints().fleet_snapshot;
+
+    server.inject_fault(FaultRule::status("/api/fleet/snapshot", 503, 1));
+    let result = Uploader::upload(
+        &client,
+        &manager,
+        snapshot_request(&endpoint, b"fleet"),
+        Some(&store),
+    )
+    .await;
+    assert!(result.is_err());
+    let pending = store.list_pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].attempt_count, 0);
+    assert_eq!(pending[0].last_error_class, Some(UploadErrorClass::Server));
+    assert_eq!(store.metrics().totals().failed, 1);
+    assert!(server.uploads(UploadKind::FleetSnapshot).is_empty());
+
+    let retried = Uploader::upload(
+        &client,
+        &manager,
+        snapshot_request(&endpoint, b"fleet"),
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(matches!(retried, UploadResult::Success));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn first_attempt_success_is_counted_in_store_metrics() {
+    let server = MockServer::start().await.unwrap();
+    let dir = temp_dir("metrics");
+    let manager = auth_manager(&server, &dir).await;
+    let store = PendingStore::new(dir.join("pending"));
+    let client = reqwest::Client::new();
+    let endpoint = server.endpoints().fleet_snapshot;
+
+    let result = Uploader::upload(
+        &client,
+        &manager,
+        snapshot_request(&endpoint, b"fleet"),
+        Some(&store),
+    )
+    .await
+    .unwrap();
+    assert!(matches!(result, UploadResult::Success));
+
+    let totals = store.metrics().totals();
+    assert_eq!(totals.succeeded, 1);
+    assert_eq!(totals.bytes_uploaded, 5);
+    let history = store.history().recent(10);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].attempts, 1);
+    assert_eq!(history[0].context_kind, "snapshot_upload");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -66,7 +66,11 @@ export type AirFire = { use_item: Array<number>, idx: number, };
 
 export type Battle = { battle_order: Array<BattleType> | null, timestamp: number | null, midnight_timestamp: number | null, cell_id: number, deck_id: number | null, formation: Array<number> | null, enemy_ship_id: Array<number> | null, e_lv: Array<number> | null, e_params: Array<Array<number>> | null, f_params: Array<Array<number>> | null, e_slot: Array<Array<number>> | null, e_hp_max: Array<number> | null, e_combined_flag: number | null, f_total_damages: Array<number> | null, e_total_damages: Array<number> | null, friend_total_damages: Array<number> | null, midnight_f_total_damages: Array<number> | null, midnight_e_total_damages: Array<number> | null, reconnaissance: Array<number> | null, escape_idx: Array<number> | null, smoke_type: number | null, combat_ration: Array<number> | null, balloon_flag: number | null, air_base_assault: AirBaseAssult | null, carrier_base_assault: CarrierBaseAssault | null, air_base_air_attacks: AirBaseAirAttacks | null, opening_air_attack: Array<OpeningAirAttack | null> | null, support_attack: SupportAttack | null, night_support_attack: NightSupportAttack | null, opening_taisen: OpeningTaisen | null, opening_raigeki: OpeningRaigeki | null, hougeki: Array<Hougeki | null> | null, closing_raigeki: ClosingRaigeki | null, friendly_force_attack: FriendlyForceAttack | null, midnight_flare_pos: Array<number> | null, midnight_touchplane: Array<number> | null, midnight_hougeki: MidnightHougeki | null, f_nowhps: Array<number> | null, e_nowhps: Array<number> | null, midnight_f_nowhps: Array<number> | null, midnight_e_nowhps: Array<number> | null, battle_result: BattleResult | null, };
 
-export type BattleResult = { win_rank: string, drop_ship_id: number | null, landing_hp_now: number | null, landing_hp_max: number | null, landing_sub_value: number | null, };
+export type BattleResult = { win_rank: string, drop_ship_id: number | null, landing_hp_now: number | null, landing_hp_max: number | null, landing_sub_value: number | null, 
+/**
+ * `api_get_base_exp`; used for levelling estimates, not stored in the database.
+ */
+base_exp?: number, };
 
 export type BattleType = { "AirBaseAssult": null } | { "CarrierBaseAssault": null } | { "AirBaseAirAttack": null } | { "OpeningAirAttack": number } | { "SupportAttack": null } | { "OpeningTaisen": null } | { "OpeningRaigeki": null } | { "Hougeki": number } | { "ClosingRaigeki": null } | { "FriendlyForceAttack": null } | { "NightSupportAttack": null } | { "MidnightHougeki": null };
 
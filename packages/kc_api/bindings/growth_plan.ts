@@ -0,0 +1,43 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ModernizationLeft } from "./inventory";
+
+export type FarmingNode = { maparea_id: number, mapinfo_no: number, cell_id: number, };
+
+export type GrowthPlan = { 
+/**
+ * `api_id` of the ship instance.
+ */
+id: number, ship_id: number, name: string, lv: number | null, modernization_left: ModernizationLeft | null, goal: ShipGoal | null, exp_current: number | null, 
+/**
+ * Exp to the goal level; `None` while the total exp of that level is
+ * unknown (no ship in the roster has been one level below it).
+ */
+exp_needed: number | null, 
+/**
+ * Average base exp observed at the goal node.
+ */
+node_base_exp: number | null, 
+/**
+ * Exp of an S rank at the goal node, with the flagship and MVP bonuses
+ * of the goal.
+ */
+exp_per_sortie: number | null, 
+/**
+ * Sorties with one S rank battle at the goal node each.
+ */
+sorties_needed: number | null, };
+
+export type NodeExp = { node: FarmingNode, battles: number, base_exp_total: number, };
+
+/**
+ * Levelling goal the user set for a ship.
+ */
+export type ShipGoal = { target_lv: number, node: FarmingNode | null, 
+/**
+ * The ship sorties as flagship (exp ×1.5).
+ */
+flagship: boolean, 
+/**
+ * The ship is expected to take MVP (exp ×2).
+ */
+mvp: boolean, };
@@ -0,0 +1,59 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ImprovementAttempt = { 
+/**
+ * Milliseconds since the epoch.
+ */
+timestamp: number, 
+/**
+ * 0 = Monday, in JST like `remodel_slotlist`.
+ */
+weekday_jst: number, secretary_ship_master_id: number | null, helper_ship_master_id: number | null, remodel_id: number, 
+/**
+ * Instance id of the improved item.
+ */
+slot_id: number, 
+/**
+ * `None` when the item was not in the slot item cache.
+ */
+slotitem_master_id: number | null, 
+/**
+ * Differs from `slotitem_master_id` when the item was upgraded.
+ */
+after_slotitem_master_id: number | null, level_before: number | null, level_after: number | null, success: boolean, certain: boolean, 
+/**
+ * `None` when the recipe was not seen before the attempt.
+ */
+consumed: ImprovementCost | null, 
+/**
+ * Master ids of the items used up, in `api_use_slot_id` order.
+ */
+consumed_slot_items: Array<number>, };
+
+/**
+ * Materials spent on one attempt. Taken from the `remodel_slotlist` and
+ * `remodel_slotlist_detail` the client loaded before the attempt, since the
+ * response only carries the materials left.
+ */
+export type ImprovementCost = { fuel: number, bull: number, steel: number, bauxite: number, 
+/**
+ * Development materials
+ */
+buildkit: number, 
+/**
+ * Improvement materials (screws)
+ */
+remodelkit: number, };
+
+export type ImprovementHelper = { helper_ship_master_id: number, weekday_jst: number, };
+
+/**
+ * Outcomes of the attempts on one item from one star level.
+ */
+export type ImprovementStats = { level_before: number | null, certain: boolean, attempts: number, successes: number, consumed: ImprovementCost, consumed_slot_items: number, };
+
+export type ImprovementSummary = { slotitem_master_id: number, helpers: Array<ImprovementHelper>, 
+/**
+ * Uncertain attempts by star level, then certain ones by star level.
+ */
+stats: Array<ImprovementStats>, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type InventoryResults = { "target": "ships", "results": Array<InventoryShip> } | { "target": "slot_items", "results": Array<InventorySlotItem> };
+
+export type InventoryShip = { id: number, ship_id: number | null, name: string | null, stype: number | null, stype_name: string | null, lv: number | null, cond: number | null, locked: boolean, sally_area: number, slot: Array<number>, slot_ex: number | null, modernization_left: ModernizationLeft | null, };
+
+export type InventorySlotItem = { id: number, slotitem_id: number, name: string | null, equip_type: number | null, equip_type_name: string | null, level: number, alv: number | null, locked: boolean, 
+/**
+ * Ship holding the item.
+ */
+ship: number | null, };
+
+export type InventoryTarget = "ships" | "slot_items";
+
+/**
+ * Modernization points still available per stat.
+ */
+export type ModernizationLeft = { karyoku: number, raisou: number, taiku: number, soukou: number, lucky: number, };
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type RemodelItem = { kind: RemodelItemKind, id: number, name: string | null, required: number, held: number, };
+
+export type RemodelItemKind = "use_item" | "slot_item";
+
+export type RemodelPlan = { 
+/**
+ * `api_id` of the ship instance.
+ */
+id: number, ship_id: number, name: string, lv: number | null, steps: Array<RemodelStep>, 
+/**
+ * The first step's level and items are met.
+ */
+can_remodel: boolean, };
+
+export type RemodelStep = { from_ship_id: number, from_name: string, to_ship_id: number, to_name: string | null, level: number, ammo: number, steel: number, items: Array<RemodelItem>, 
+/**
+ * The step goes back to a form already in the chain (convertible
+ * ships); the chain ends here.
+ */
+converts_back: boolean, };
@@ -12,7 +12,8 @@ pub type EnvInfoId = Uuid;
 
 pub type EnvInfoProps = (
     UserEnv,
-    i64, // timestamp
+    Option<i32>, // game server index
+    i64,         // timestamp
 );
 
 #[derive(
@@ -30,6 +31,9 @@ pub struct EnvInfo {
     pub uuid: EnvInfoId,
     pub user_env_unique: UserEnv,
     pub timestamp: i64,
+    /// Index of the game server the data came from; `None` when unknown.
+    #[cfg(schema_since = "0.5.2")]
+    pub server_index: Option<i32>,
 }
 
 impl EnvInfo {
@@ -44,7 +48,9 @@ impl EnvInfo {
             version: DATABASE_TABLE_VERSION.to_string(),
             uuid: new_uuid,
             user_env_unique: data.0,
-            timestamp: data.1,
+            timestamp: data.2,
+            #[cfg(schema_since = "0.5.2")]
+            server_index: data.1,
         };
 
         table.env_info.push(new_data);
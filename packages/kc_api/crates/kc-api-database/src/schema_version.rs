@@ -4,13 +4,17 @@ pub const DATABASE_TABLE_VERSION: &str = "0.4.0";
 #[cfg(all(schema_since = "0.5.0", schema_until = "0.5.1"))]
 pub const DATABASE_TABLE_VERSION: &str = "0.5.0";
 
-#[cfg(schema_since = "0.5.1")]
+#[cfg(all(schema_since = "0.5.1", schema_until = "0.5.2"))]
 pub const DATABASE_TABLE_VERSION: &str = "0.5.1";
 
+#[cfg(schema_since = "0.5.2")]
+pub const DATABASE_TABLE_VERSION: &str = "0.5.2";
+
 #[cfg(not(any(
     all(schema_since = "0.4.0", schema_until = "0.5.0"),
     all(schema_since = "0.5.0", schema_until = "0.5.1"),
-    schema_since = "0.5.1",
+    all(schema_since = "0.5.1", schema_until = "0.5.2"),
+    schema_since = "0.5.2",
 )))]
 compile_error!(
     "At least one schema version must be selected via schema_since/schema_until cfgs."
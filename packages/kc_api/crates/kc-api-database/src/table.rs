@@ -410,6 +410,7 @@ impl PortTable {
     pub fn new(
         interface_cells: kc_api_interface::cells::Cells,
         user_env: UserEnv,
+        server_index: Option<i32>,
         timestamp: i64,
     ) -> PortTable {
         let mut table = PortTable::default();
@@ -417,7 +418,7 @@ impl PortTable {
         let timestamp_context = uuid::ContextV7::new().with_additional_precision();
         let ts: uuid::Timestamp =
             uuid::Timestamp::from_unix(&timestamp_context, timestamp as u64, 0);
-        let env_uuid = EnvInfo::new_ret_uuid(ts, (user_env, timestamp), &mut table);
+        let env_uuid = EnvInfo::new_ret_uuid(ts, (user_env, server_index, timestamp), &mut table);
         {
             let uuid = Uuid::new_v7(ts);
             Cells::new_ret_option(
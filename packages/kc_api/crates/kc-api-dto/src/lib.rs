@@ -1,4 +1,5 @@
 pub mod common;
 pub mod endpoints;
+pub mod mask;
 
 pub mod test_utils;
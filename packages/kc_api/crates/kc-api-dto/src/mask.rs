@@ -0,0 +1,141 @@
+//! Masking of secrets in captured API data.
+//!
+//! `api_token` is always masked. Other values are masked when the dotted path
+//! of their keys, starting from `key_root` (e.g. `res.api_data.api_member_id`),
+//! matches one of the regex `mask_patterns`. Masked values keep their JSON
+//! type so the data still deserializes into the DTOs.
+
+use serde_json::Value;
+
+/// Mask `val`, a request or response body under `key_root` (`"req"` or
+/// `"res"`).
+pub fn mask_value(key_root: &str, val: Value, mask_patterns: &[&str]) -> Value {
+    mask_secrets(
+        key_root.to_string(),
+        val,
+        vec![key_root.to_string()],
+        mask_patterns,
+    )
+}
+
+/// Mask the values of a `key=value&...` request body, keeping the order of
+/// the pairs. Keys are matched as `req.<key>`.
+pub fn mask_query_string(query: &str, mask_patterns: &[&str]) -> String {
+    query
+        .split('&')
+        .map(|pair| {
+            let Some((key, value)) = pair.split_once('=') else {
+                return pair.to_string();
+            };
+            let val = match value.parse::<i64>() {
+                Ok(i) => Value::Number(i.into()),
+                Err(_) => Value::String(value.to_string()),
+            };
+            let masked = mask_secrets(
+                key.to_string(),
+                val,
+                vec!["req".to_string(), key.to_string()],
+                mask_patterns,
+            );
+            match masked {
+                Value::String(s) if s == value => pair.to_string(),
+                Value::String(s) => format!("{key}={s}"),
+                masked => format!("{key}={masked}"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn mask_secrets(key: String, val: Value, keys: Vec<String>, mask_patterns: &[&str]) -> Value {
+    let matched_mask = mask_patterns.iter().find_map(|mask| {
+        let joined_keys = keys.join(".");
+        if regex::Regex::new(mask).unwrap().is_match(&joined_keys) {
+            match val {
+                Value::Number(_) => Some(Value::Number(i64::MIN.into())),
+                Value::String(_) => {
+                    Some(Value::String(format!("__MASKED_{}__", key.to_uppercase())))
+                }
+                Value::Bool(_) => Some(Value::Bool(false)),
+                Value::Null => Some(Value::Null),
+                Value::Array(_) => Some(Value::Array(vec![])),
+                Value::Object(_) => Some(Value::Object(serde_json::Map::new())),
+            }
+        } else {
+            None
+        }
+    });
+    if let Some(masked) = matched_mask {
+        return masked;
+    }
+    match val {
+        Value::Number(n) => Value::Number(n),
+
+        Value::String(s) => {
+            if key.eq("api_token") {
+                Value::String("__MASKED_API_TOKEN__".to_string())
+            } else {
+                Value::String(s)
+            }
+        }
+
+        Value::Array(arr) => {
+            let normalized_arr = arr
+                .into_iter()
+                .map(|v| mask_secrets(key.clone(), v, keys.clone(), mask_patterns))
+                .collect();
+            Value::Array(normalized_arr)
+        }
+
+        Value::Object(map) => {
+            let normalized_map = map
+                .into_iter()
+                .map(|(k, v)| {
+                    let mut new_keys = keys.clone();
+                    new_keys.push(k.clone());
+                    let masked = mask_secrets(k.clone(), v, new_keys, mask_patterns);
+                    (k, masked)
+                })
+                .collect();
+            Value::Object(normalized_map)
+        }
+        Value::Bool(b) => Value::Bool(b),
+        Value::Null => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_value_and_query_string() {
+        let val = serde_json::json!({
+            "api_token": "abc",
+            "api_data": {"api_member_id": 123, "api_nickname": "admiral", "api_lv": 99}
+        });
+        let masked = mask_value("res", val, &[r"\.api_member_id$", r"\.api_nickname$"]);
+        assert_eq!(
+            masked,
+            serde_json::json!({
+                "api_token": "__MASKED_API_TOKEN__",
+                "api_data": {
+                    "api_member_id": i64::MIN,
+                    "api_nickname": "__MASKED_API_NICKNAME__",
+                    "api_lv": 99
+                }
+            })
+        );
+
+        assert_eq!(
+            mask_query_string(
+                "api_token=abc&api_verno=1&api_port=42",
+                &[r"^req\.api_port$"]
+            ),
+            format!(
+                "api_token=__MASKED_API_TOKEN__&api_verno=1&api_port={}",
+                i64::MIN
+            )
+        );
+    }
+}
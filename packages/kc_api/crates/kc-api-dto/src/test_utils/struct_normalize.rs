@@ -11,6 +11,8 @@ use serde_json::Value;
 use serde_qs;
 use std::io::Write;
 
+use crate::mask::mask_value;
+
 #[derive(Clone)]
 pub enum FormatType {
     Json,
@@ -25,79 +27,6 @@ fn remove_metadata(data: String) -> String {
     data_removed_metadata
 }
 
-fn normalize_for_mask_seacret(
-    key: String,
-    val: Value,
-    keys: Vec<String>,
-    mask_patterns: Vec<&str>,
-) -> Value {
-    let matched_mask = mask_patterns.iter().find_map(|mask| {
-        let joined_keys = keys.join(".");
-        if regex::Regex::new(mask).unwrap().is_match(&joined_keys) {
-            match val {
-                Value::Number(_) => Some(Value::Number(i64::MIN.into())),
-                Value::String(_) => {
-                    Some(Value::String(format!("__MASKED_{}__", key.to_uppercase())))
-                }
-                Value::Bool(_) => Some(Value::Bool(false)),
-                Value::Null => Some(Value::Null),
-                Value::Array(_) => Some(Value::Array(vec![])),
-                Value::Object(_) => Some(Value::Object(serde_json::Map::new())),
-            }
-        } else {
-            None
-        }
-    });
-    if let Some(masked) = matched_mask {
-        return masked;
-    }
-    match val {
-        Value::Number(n) => Value::Number(n),
-
-        Value::String(s) => {
-            if key.eq("api_token") {
-                Value::String("__MASKED_API_TOKEN__".to_string())
-            } else {
-                Value::String(s)
-            }
-        }
-
-        Value::Array(arr) => {
-            let normalized_arr = arr
-                .into_iter()
-                .map(|v| {
-                    normalize_for_mask_seacret(key.clone(), v, keys.clone(), mask_patterns.clone())
-                })
-                .collect();
-            Value::Array(normalized_arr)
-        }
-
-        Value::Object(map) => {
-            let normalized_map = map
-                .into_iter()
-                .map(|(k, v)| {
-                    let key_for_map = k.clone();
-                    let key_for_call = k.clone();
-                    let mut new_keys = keys.clone();
-                    new_keys.push(key_for_call.clone());
-                    (
-                        key_for_map,
-                        normalize_for_mask_seacret(
-                            key_for_call,
-                            v,
-                            new_keys,
-                            mask_patterns.clone(),
-                        ),
-                    )
-                })
-                .collect();
-            Value::Object(normalized_map)
-        }
-        Value::Bool(b) => Value::Bool(b),
-        Value::Null => Value::Null,
-    }
-}
-
 fn keep_test_data(
     val: Value,
     another_val: Value,
@@ -115,18 +44,12 @@ fn keep_test_data(
         FormatType::Json => "req".to_string(),
         FormatType::QueryString => "res".to_string(),
     };
-    let val_masked =
-        normalize_for_mask_seacret(key_root.clone(), val, vec![key_root], mask_patterns.clone());
+    let val_masked = mask_value(&key_root, val, &mask_patterns);
     let serialized = match format_type {
         FormatType::Json => serde_json::to_string_pretty(&val_masked).unwrap(),
         FormatType::QueryString => serde_qs::to_string(&val_masked).unwrap(),
     };
-    let another_val_masked = normalize_for_mask_seacret(
-        another_key_root.clone(),
-        another_val,
-        vec![another_key_root],
-        mask_patterns,
-    );
+    let another_val_masked = mask_value(&another_key_root, another_val, &mask_patterns);
     let another_serialized = match format_type {
         FormatType::QueryString => serde_json::to_string_pretty(&another_val_masked).unwrap(),
         FormatType::Json => serde_qs::to_string(&another_val_masked).unwrap(),
@@ -104,6 +104,7 @@ fn emit_data(emit_data: EmitData) -> Option<ReturnType> {
             Set::SokuSpeedObservedSnapshot(_) => {}
             Set::RemodelSlotList(_) => {}
             Set::RemodelDetail(_) => {}
+            Set::ImprovementAttempt(_) => {}
             Set::Dammy(_) => {}
         },
         EmitData::Identifier(data) => match data {
@@ -122,7 +123,7 @@ fn emit_data(emit_data: EmitData) -> Option<ReturnType> {
                 let timestamp = DateTime::parse_from_rfc3339("1970-01-01T00:00:01Z")
                     .expect("failed to parse rfc3339")
                     .timestamp();
-                let port_table = PortTable::new(cells, user_env, timestamp);
+                let port_table = PortTable::new(cells, user_env, None, timestamp);
                 return Some(ReturnType::PortTable(port_table));
             }
             Identifier::RequireInfo(_) => {}
@@ -131,6 +132,7 @@ fn emit_data(emit_data: EmitData) -> Option<ReturnType> {
                 return Some(ReturnType::GetDataTable(get_data_table));
             }
             Identifier::MapStart(_) => {}
+            Identifier::ApiError(_) => {}
         },
     }
     None
@@ -207,7 +209,7 @@ pub fn check_database_field_size() {
         }
     }
 
-    let re_metadata = Regex::new(r"---\r?\n.*\r?\n.*\r?\n.*\r?\n.*\s*---\r?\n").unwrap();
+    let re_metadata = Regex::new(r"---\r?\n(?:.*\r?\n)*?---\r?\n").unwrap();
 
     let mut log_map_port_table: register_trait::LogMapNumberSize = std::collections::HashMap::new();
     let mut log_map_get_data_table: register_trait::LogMapNumberSize =
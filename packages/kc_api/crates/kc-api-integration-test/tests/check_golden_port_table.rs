@@ -0,0 +1,342 @@
+//! Golden-file regression check of capture → parser → `EmitData` →
+//! `PortTable`.
+//!
+//! Every directory under `tests/golden` (or `GOLDEN_DATA_PATH`) is a case:
+//!
+//! - `captures/`: capture files (`<timestamp>{Q|S}@<dir>@<name>`), replayed in
+//!   file name order. They are masked with [`kc_api_dto::mask`] and
+//!   [`GOLDEN_MASK_PATTERNS`] before parsing.
+//! - `port_table.json`: the rows of every `PortTable` built on `api_port/port`,
+//!   decoded back from the Avro encoding.
+//!
+//! With `UPDATE_GOLDEN=1` the golden files are rewritten from the current
+//! output and the captures are rewritten masked, so a new case can be added by
+//! copying raw captures into `captures/`.
+
+use chrono::DateTime;
+use kc_api_database::table::PortTable;
+use kc_api_dto::mask::{mask_query_string, mask_value};
+use kc_api_interface::{
+    api_error::ApiError,
+    cells::Cells,
+    interface::{Add, EmitData, Identifier, Set},
+    ship::Ships,
+};
+use kc_api_parser::parser;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::{fs, path::Path, path::PathBuf};
+use uuid::Uuid;
+
+use dotenvy::dotenv;
+
+/// Keys masked in golden captures besides `api_token`.
+pub const GOLDEN_MASK_PATTERNS: &[&str] = &[
+    r"\.api_member_id$",
+    r"\.api_nickname(_id)?$",
+    r"\.api_comment(_id)?$",
+];
+
+struct Capture {
+    path: PathBuf,
+    metadata: String,
+    body: String,
+}
+
+fn read_capture(file_path: PathBuf) -> Capture {
+    let re_metadata = regex::Regex::new(r"^---\r?\n(?:.*\r?\n)*?---\r?\n").unwrap();
+    let file_content = fs::read_to_string(&file_path)
+        .unwrap_or_else(|_| panic!("can not read the file({})", file_path.display()));
+    let file_content = file_content.replace('\u{feff}', "");
+    let metadata = re_metadata
+        .find(&file_content)
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_default();
+    let body = file_content[metadata.len()..].trim_end().to_string();
+    Capture {
+        path: file_path,
+        metadata,
+        body,
+    }
+}
+
+/// Masked body of a capture, in the form written to the capture file.
+fn mask_capture(capture: &Capture, is_response: bool) -> String {
+    if !is_response {
+        return mask_query_string(&capture.body, GOLDEN_MASK_PATTERNS);
+    }
+    let json = capture.body.trim_start_matches("svdata=");
+    let value: Value = serde_json::from_str(json)
+        .unwrap_or_else(|e| panic!("invalid JSON in {}: {e}", capture.path.display()));
+    let masked = mask_value("res", value, GOLDEN_MASK_PATTERNS);
+    format!("svdata={}", serde_json::to_string(&masked).unwrap())
+}
+
+fn apply_emit_data(emit_data: EmitData) -> Option<PortTable> {
+    match emit_data {
+        EmitData::Add(data) => match data {
+            Add::Ships(data) => data.add_or(),
+            Add::Battle(data) => data.add_or(),
+            Add::Cell(data) => data.add_or(),
+            Add::Materials(_) | Add::QuestEvent(_) | Add::Dammy(_) => {}
+        },
+        EmitData::Set(data) => match data {
+            Set::DeckPorts(data) => data.restore(),
+            Set::Basic(data) => data.restore(),
+            Set::UseItems(data) => data.restore(),
+            Set::Ships(data) => data.restore(),
+            Set::SlotItems(data) => data.restore(),
+            Set::AirBases(data) => data.restore(),
+            Set::MstShips(data) => data.restore(),
+            Set::MstSlotItems(data) => data.restore(),
+            Set::MstEquipExslotShips(data) => data.restore(),
+            Set::MstEquipShips(data) => data.restore(),
+            Set::MstStypes(data) => data.restore(),
+            Set::MstUseItems(data) => data.restore(),
+            Set::MstSlotItemEquipTypes(data) => data.restore(),
+            Set::Cells(data) => data.restore(),
+            Set::MstMapAreas(data) => data.restore(),
+            Set::MstMapInfos(data) => data.restore(),
+            Set::MstShipGraphs(data) => data.restore(),
+            Set::MstShipUpgrades(data) => data.restore(),
+            Set::MstEquipExslots(data) => data.restore(),
+            Set::MstEquipLimitExslots(data) => data.restore(),
+            Set::Quests(data) => data.restore(),
+            Set::Materials(_)
+            | Set::NDocks(_)
+            | Set::Logs(_)
+            | Set::ShipGrowthSnapshot(_)
+            | Set::SokuSpeedObservedSnapshot(_)
+            | Set::RemodelSlotList(_)
+            | Set::RemodelDetail(_)
+            | Set::ImprovementAttempt(_)
+            | Set::Dammy(_) => {}
+        },
+        EmitData::Identifier(data) => match data {
+            Identifier::Port(_) => {
+                if ApiError::take_sortie_interruption().is_some() {
+                    return None;
+                }
+                // 123e4567-e89b-12d3-a456-42661417400
+                let user_env = Uuid::from_fields(
+                    0x123e4567_u32,
+                    0xe89b_u16,
+                    0x12d3_u16,
+                    &[
+                        0x42_u8, 0x66_u8, 0x42_u8, 0x66_u8, 0x14_u8, 0x17_u8, 0x40_u8, 0x00_u8,
+                    ],
+                )
+                .to_string();
+                let timestamp = DateTime::parse_from_rfc3339("1970-01-01T00:00:01Z")
+                    .expect("failed to parse rfc3339")
+                    .timestamp();
+                return Some(PortTable::new(Cells::load(), user_env, None, timestamp));
+            }
+            Identifier::GetData(_) => Cells::reset(),
+            Identifier::RequireInfo(_) => {}
+            Identifier::MapStart(_) => Ships::cashe_restore(),
+            Identifier::ApiError(error) => {
+                error.interrupt_sortie();
+            }
+        },
+    }
+    None
+}
+
+/// Rows of the non-empty tables, keyed by table name.
+fn decode_port_table(port_table: &PortTable) -> Value {
+    let encoded = port_table
+        .encode()
+        .expect("failed to encode the port table");
+    let mut tables = serde_json::Map::new();
+    for (variant, bytes) in encoded.iter() {
+        let reader = apache_avro::Reader::new(bytes)
+            .unwrap_or_else(|e| panic!("failed to read {}: {e}", variant.table_name()));
+        let rows = reader
+            .map(|record| {
+                let record = record.expect("failed to read an avro record");
+                Value::try_from(record).expect("failed to convert an avro record to JSON")
+            })
+            .collect::<Vec<_>>();
+        if !rows.is_empty() {
+            tables.insert(variant.table_name().to_string(), Value::Array(rows));
+        }
+    }
+    Value::Object(tables)
+}
+
+/// Replace the values that change between runs: the random v7 uuids become
+/// `uuid#<n>`, numbered in order of appearance so references between rows
+/// are still visible, and the timestamps the adapter takes from the clock
+/// become `__TIMESTAMP__`.
+fn normalize_volatile(key: &str, value: Value, seen: &mut HashMap<String, usize>) -> Value {
+    match value {
+        Value::String(s) if Uuid::parse_str(&s).is_ok() => {
+            let next = seen.len() + 1;
+            let n = *seen.entry(s).or_insert(next);
+            Value::String(format!("uuid#{n}"))
+        }
+        Value::Number(_) if key.ends_with("timestamp") => {
+            Value::String("__TIMESTAMP__".to_string())
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| normalize_volatile(key, item, seen))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = normalize_volatile(&k, v, seen);
+                    (k, v)
+                })
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Path of the first difference between `expected` and `actual`.
+fn first_difference(path: String, expected: &Value, actual: &Value) -> Option<String> {
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            let mut keys = e.keys().chain(a.keys()).collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            keys.into_iter()
+                .find_map(|key| match (e.get(key), a.get(key)) {
+                    (Some(e), Some(a)) => first_difference(format!("{path}.{key}"), e, a),
+                    (Some(_), None) => Some(format!("{path}.{key} is missing")),
+                    (None, Some(_)) => Some(format!("{path}.{key} is unexpected")),
+                    (None, None) => None,
+                })
+        }
+        (Value::Array(e), Value::Array(a)) if e.len() != a.len() => Some(format!(
+            "{path} has {} entries, expected {}",
+            a.len(),
+            e.len()
+        )),
+        (Value::Array(e), Value::Array(a)) => e
+            .iter()
+            .zip(a)
+            .enumerate()
+            .find_map(|(i, (e, a))| first_difference(format!("{path}[{i}]"), e, a)),
+        (e, a) if e != a => Some(format!("{path}: expected {e}, got {a}")),
+        _ => None,
+    }
+}
+
+fn run_case(case_dir: &Path, update: bool) -> Value {
+    Cells::reset();
+
+    let mut files = fs::read_dir(case_dir.join("captures"))
+        .unwrap_or_else(|_| panic!("no captures in {}", case_dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut port_tables = Vec::new();
+    let mut uuids = HashMap::new();
+    for file_path in files {
+        let file_name = file_path
+            .file_name()
+            .expect("failed to get file name")
+            .to_str()
+            .expect("failed to convert to str")
+            .to_string();
+        let parse_path = file_name.split('@').collect::<Vec<&str>>();
+        if parse_path.len() < 3 {
+            panic!("file name format is invalid({})", file_path.display());
+        }
+        let path_name = format!("/kcsapi/{}/{}", parse_path[1], parse_path[2]);
+        let is_response = match parse_path[0] {
+            s if s.ends_with('S') => true,
+            s if s.ends_with('Q') => false,
+            _ => panic!("file name format is invalid({})", file_path.display()),
+        };
+
+        let capture = read_capture(file_path.clone());
+        let masked = mask_capture(&capture, is_response);
+        if update && masked != capture.body {
+            fs::write(&file_path, format!("{}{masked}\n", capture.metadata))
+                .unwrap_or_else(|_| panic!("can not write the file({})", file_path.display()));
+        }
+
+        let emit_data_list = if is_response {
+            parser::response_parser(path_name, masked.replacen("svdata=", "", 1))
+        } else {
+            parser::request_parser(path_name, masked.replace("%5B", "[").replace("%5D", "]"))
+        }
+        .unwrap_or_else(|e| panic!("failed to parse the file({}), e: {e}", file_path.display()));
+
+        for emit_data in emit_data_list {
+            if let Some(port_table) = apply_emit_data(emit_data) {
+                let tables = normalize_volatile("", decode_port_table(&port_table), &mut uuids);
+                port_tables.push(serde_json::json!({
+                    "capture": file_name,
+                    "tables": tables,
+                }));
+            }
+        }
+    }
+    Value::Array(port_tables)
+}
+
+pub fn check_golden_port_table() {
+    dotenv().ok();
+    let golden_root = std::env::var("GOLDEN_DATA_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("./tests/golden"));
+    let update = std::env::var("UPDATE_GOLDEN").is_ok_and(|v| !v.is_empty() && v != "0");
+
+    let mut case_dirs = fs::read_dir(&golden_root)
+        .unwrap_or_else(|_| panic!("can not read {}", golden_root.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join("captures").is_dir())
+        .collect::<Vec<_>>();
+    case_dirs.sort();
+    assert!(
+        !case_dirs.is_empty(),
+        "no golden cases in {}",
+        golden_root.display()
+    );
+
+    let mut failures = Vec::new();
+    for case_dir in case_dirs {
+        let actual = run_case(&case_dir, update);
+        let golden_path = case_dir.join("port_table.json");
+        let serialized = serde_json::to_string_pretty(&actual).unwrap() + "\n";
+        if update {
+            fs::write(&golden_path, serialized)
+                .unwrap_or_else(|_| panic!("can not write the file({})", golden_path.display()));
+            println!("updated {}", golden_path.display());
+            continue;
+        }
+
+        let expected: Value = fs::read_to_string(&golden_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_else(|| {
+                panic!(
+                    "missing or invalid {}, run with UPDATE_GOLDEN=1 to create it",
+                    golden_path.display()
+                )
+            });
+        if let Some(difference) = first_difference("$".to_string(), &expected, &actual) {
+            let case_name = case_dir.file_name().unwrap().to_string_lossy().to_string();
+            let actual_path =
+                PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{case_name}.actual.json"));
+            fs::write(&actual_path, serialized).expect("can not write the actual output");
+            failures.push(format!(
+                "{case_name}: {difference} (actual output: {})",
+                actual_path.display()
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "golden port tables differ, run with UPDATE_GOLDEN=1 if the change is intended:\n{}",
+        failures.join("\n")
+    );
+}
@@ -1,8 +1,21 @@
 #![cfg(test)]
 
 mod check_database_field_size;
+mod check_golden_port_table;
+
+use std::sync::Mutex;
+
+// Both checks replay captures into the process-wide interface state.
+static INTERFACE_STATE: Mutex<()> = Mutex::new(());
 
 #[test]
 fn test_database_number_size() {
+    let _guard = INTERFACE_STATE.lock().unwrap_or_else(|e| e.into_inner());
     check_database_field_size::check_database_field_size();
 }
+
+#[test]
+fn test_golden_port_table() {
+    let _guard = INTERFACE_STATE.lock().unwrap_or_else(|e| e.into_inner());
+    check_golden_port_table::check_golden_port_table();
+}
@@ -12,7 +12,7 @@ use kc_api_interface::soku_speed_observed::{
 use kc_api_dto::endpoints::api_get_member::*;
 use kc_api_interface::use_items::UseItems;
 
-use crate::{register_trait, InterfaceWrapper, TraitForConvert};
+use crate::{register_trait, ConvertError, InterfaceWrapper, TraitForConvert};
 
 fn build_slot_composition(slot_id: i64, slot_items: &SlotItems) -> Option<SlotComposition> {
     if slot_id <= 0 {
@@ -127,33 +127,33 @@ register_trait!(
 
 impl TraitForConvert for mapinfo::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let air_bases =
             InterfaceWrapper::<AirBases>::from(self.api_data.api_air_base.clone()).unwrap();
 
-        Some(vec![EmitData::Set(Set::AirBases(air_bases))])
+        Ok(Some(vec![EmitData::Set(Set::AirBases(air_bases))]))
     }
 }
 
 impl TraitForConvert for require_info::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let slot_item =
             InterfaceWrapper::<SlotItems>::from(self.api_data.api_slot_item.clone()).unwrap();
         let use_item =
             InterfaceWrapper::<UseItems>::from(self.api_data.api_useitem.clone()).unwrap();
 
-        Some(vec![
+        Ok(Some(vec![
             EmitData::Set(Set::SlotItems(slot_item)),
             EmitData::Set(Set::UseItems(use_item)),
             EmitData::Identifier(Identifier::RequireInfo(())),
-        ])
+        ]))
     }
 }
 
 impl TraitForConvert for chart_additional_info::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let mut deck_ports = DeckPorts::load();
         for (idx, deck_param) in self.api_data.api_deck_param.iter().enumerate() {
             let deck_id = (idx as i64) + 1;
@@ -163,41 +163,41 @@ impl TraitForConvert for chart_additional_info::Res {
             }
         }
 
-        Some(vec![EmitData::Set(Set::DeckPorts(deck_ports))])
+        Ok(Some(vec![EmitData::Set(Set::DeckPorts(deck_ports))]))
     }
 }
 
 impl TraitForConvert for slot_item::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let slot_item = InterfaceWrapper::<SlotItems>::from(self.api_data.clone()).unwrap();
 
-        Some(vec![EmitData::Set(Set::SlotItems(slot_item))])
+        Ok(Some(vec![EmitData::Set(Set::SlotItems(slot_item))]))
     }
 }
 
 impl TraitForConvert for questlist::Res {
     type Output = EmitData;
 
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let quests = InterfaceWrapper::<Quests>::from(self.clone()).unwrap();
-        Some(vec![EmitData::Set(Set::Quests(quests))])
+        Ok(Some(vec![EmitData::Set(Set::Quests(quests))]))
     }
 }
 
 impl TraitForConvert for questlist::Req {
     type Output = EmitData;
 
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         kc_api_interface::quest::Quests::set_current_page(self.api_tab_id);
-        None
+        Ok(None)
     }
 }
 
 impl TraitForConvert for ship2::Res {
     type Output = EmitData;
 
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let slot_items = SlotItems::load();
         let entries = self
             .api_data
@@ -282,14 +282,14 @@ impl TraitForConvert for ship2::Res {
                 },
             )));
         }
-        Some(emit_data)
+        Ok(Some(emit_data))
     }
 }
 
 impl TraitForConvert for ship3::Res {
     type Output = EmitData;
 
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let slot_items = SlotItems::load();
         let entries = self
             .api_data
@@ -379,6 +379,6 @@ impl TraitForConvert for ship3::Res {
                 },
             )));
         }
-        Some(emit_data)
+        Ok(Some(emit_data))
     }
 }
@@ -13,7 +13,7 @@ use kc_api_interface::soku_speed_observed::{
 
 use kc_api_dto::endpoints::api_port::*;
 
-use crate::{register_trait, InterfaceWrapper, TraitForConvert};
+use crate::{register_trait, ConvertError, InterfaceWrapper, TraitForConvert};
 
 fn build_slot_composition(slot_id: i64, slot_items: &SlotItems) -> Option<SlotComposition> {
     if slot_id <= 0 {
@@ -79,7 +79,7 @@ register_trait!(Res, (air_corps_cond_recovery_with_timer));
 
 impl TraitForConvert for port::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let materials =
             InterfaceWrapper::<Materials>::from(self.api_data.api_material.clone()).unwrap();
         let ships = InterfaceWrapper::<Ships>::from(self.api_data.api_ship.clone()).unwrap();
@@ -192,6 +192,6 @@ impl TraitForConvert for port::Res {
             EmitData::Set(Set::DeckPorts(deck_ports)),
             EmitData::Identifier(Identifier::Port(())),
         ]);
-        Some(emit_data)
+        Ok(Some(emit_data))
     }
 }
@@ -3,30 +3,30 @@ use kc_api_interface::interface::{Add, EmitData};
 
 use kc_api_dto::endpoints::api_req_battle_midnight::*;
 
-use crate::{register_trait, InterfaceWrapper, TraitForConvert};
+use crate::{register_trait, ConvertError, InterfaceWrapper, TraitForConvert};
 
 register_trait!(Req, (battle, sp_midnight));
 
 impl TraitForConvert for battle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         // let ships: Ships = self.api_data.clone().into();
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![
+        Ok(Some(vec![
             // EmitData::Add(Add::Ships(ships)),
             EmitData::Add(Add::Battle(battle)),
-        ])
+        ]))
     }
 }
 
 impl TraitForConvert for sp_midnight::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         // let ships: Ships = self.api_data.clone().into();
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![
+        Ok(Some(vec![
             // EmitData::Add(Add::Ships(ships)),
             EmitData::Add(Add::Battle(battle)),
-        ])
+        ]))
     }
 }
@@ -3,7 +3,7 @@ use kc_api_interface::interface::{Add, EmitData};
 
 use kc_api_dto::endpoints::api_req_combined_battle::*;
 
-use crate::{register_trait, InterfaceWrapper, TraitForConvert};
+use crate::{register_trait, ConvertError, InterfaceWrapper, TraitForConvert};
 
 register_trait!(
     Req,
@@ -25,83 +25,81 @@ register_trait!(Res, (goback_port));
 
 impl TraitForConvert for battleresult::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for ld_airbattle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for midnight_battle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for sp_midnight::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 
 impl TraitForConvert for each_battle_water::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for each_battle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for battle_water::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for battle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![
-            EmitData::Add(Add::Battle(battle)),
-        ])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for ec_battle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for ec_midnight_battle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
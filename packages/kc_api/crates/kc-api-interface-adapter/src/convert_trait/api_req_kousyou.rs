@@ -1,14 +1,17 @@
+use kc_api_interface::improvement::{ImprovementAttempt, ImprovementCost};
 use kc_api_interface::interface::{EmitData, Set};
 use kc_api_interface::remodel::{
     PENDING_DETAIL_REQ_CAP, RemodelDetail, RemodelSlotList, RemodelSlotListEntry,
-    PENDING_DETAIL_REQ,
+    PENDING_DETAIL_REQ, PENDING_REMODEL_SLOT_REQ, PENDING_REMODEL_SLOT_REQ_CAP, RemodelSlotReq,
+    LAST_DETAIL, LAST_SLOT_LIST,
 };
 use kc_api_interface::deck_port::DeckPorts;
 use kc_api_interface::ship::Ships;
+use kc_api_interface::slot_item::SlotItems;
 
 use kc_api_dto::endpoints::api_req_kousyou::*;
 
-use crate::{register_trait, TraitForConvert};
+use crate::{register_trait, ConvertError, TraitForConvert};
 
 register_trait!(
     Req,
@@ -18,7 +21,6 @@ register_trait!(
         destroyitem2,
         destroyship,
         getship,
-        remodel_slot,
         remodel_slotlist
     )
 );
@@ -29,22 +31,30 @@ register_trait!(
         createship,
         destroyitem2,
         destroyship,
-        getship,
-        remodel_slot
+        getship
     )
 );
 
 // --- ヘルパー: 秘書艦・曜日コンテキスト取得 ---
 
-fn get_secretary_ship_master_id() -> Option<i64> {
+fn get_first_fleet_ship_master_id(index: usize) -> Option<i64> {
     let deck_ports = DeckPorts::load();
     let first_fleet = deck_ports.deck_ports.get(&1)?;
-    let first_ship_instance_id = first_fleet.ship.as_ref()?.first()?;
+    let ship_instance_id = first_fleet.ship.as_ref()?.get(index)?;
     let ships = Ships::load();
-    let ship = ships.ships.get(first_ship_instance_id)?;
+    let ship = ships.ships.get(ship_instance_id)?;
     ship.ship_id
 }
 
+fn get_secretary_ship_master_id() -> Option<i64> {
+    get_first_fleet_ship_master_id(0)
+}
+
+/// 随伴艦（第1艦隊2番艦）
+fn get_helper_ship_master_id() -> Option<i64> {
+    get_first_fleet_ship_master_id(1)
+}
+
 fn weekday_jst() -> i64 {
     use chrono::{Datelike, FixedOffset, Utc};
     let jst = FixedOffset::east_opt(9 * 3600).unwrap();
@@ -60,18 +70,34 @@ fn req_slot_num_for_epoch(d: &remodel_slotlist::ApiData) -> i64 {
     d.api_req_slot_num.unwrap_or(0)
 }
 
+/// 変換されずに終わった応答 (エラー応答・パース失敗) に対応する Req コンテキストを捨てる。
+/// 残すと次の応答が古い Req と対応付けられる。
+pub(crate) fn discard_pending_req(name: &str) {
+    let discarded = match name.rsplit_once("/api_req_kousyou/").map(|(_, api)| api) {
+        Some("remodel_slotlist_detail") => PENDING_DETAIL_REQ.lock().unwrap().pop_front().is_some(),
+        Some("remodel_slot") => PENDING_REMODEL_SLOT_REQ
+            .lock()
+            .unwrap()
+            .pop_front()
+            .is_some(),
+        _ => return,
+    };
+    if discarded {
+        tracing::debug!(endpoint = name, "discarded pending request context");
+    }
+}
+
 // --- remodel_slotlist: 改修条件一覧 ---
 
 impl TraitForConvert for remodel_slotlist::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let secretary = match get_secretary_ship_master_id() {
             Some(v) => v,
             None => {
-                eprintln!(
-                    "remodel_slotlist: secretary ship not found; skip"
-                );
-                return Some(vec![]);
+                return Err(ConvertError::new(
+                    "remodel_slotlist: secretary ship not found",
+                ));
             }
         };
         let weekday = weekday_jst();
@@ -96,10 +122,12 @@ impl TraitForConvert for remodel_slotlist::Res {
             .collect();
         let data = RemodelSlotList {
             secretary_ship_master_id: secretary,
+            helper_ship_master_id: get_helper_ship_master_id(),
             weekday_jst: weekday,
             entries,
         };
-        Some(vec![EmitData::Set(Set::RemodelSlotList(data))])
+        *LAST_SLOT_LIST.lock().unwrap() = Some(data.clone());
+        Ok(Some(vec![EmitData::Set(Set::RemodelSlotList(data))]))
     }
 }
 
@@ -107,31 +135,30 @@ impl TraitForConvert for remodel_slotlist::Res {
 
 impl TraitForConvert for remodel_slotlist_detail::Req {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let mut q = PENDING_DETAIL_REQ.lock().unwrap();
         if q.len() >= PENDING_DETAIL_REQ_CAP {
-            eprintln!(
-                "PENDING_DETAIL_REQ overflow (cap={}); dropping oldest entry",
-                PENDING_DETAIL_REQ_CAP
+            tracing::warn!(
+                cap = PENDING_DETAIL_REQ_CAP,
+                "PENDING_DETAIL_REQ overflow; dropping oldest entry"
             );
             q.pop_front();
         }
         q.push_back((self.api_slot_id, self.api_id));
-        Some(vec![])
+        Ok(Some(vec![]))
     }
 }
 
 impl TraitForConvert for remodel_slotlist_detail::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let ctx = PENDING_DETAIL_REQ.lock().unwrap().pop_front();
         let (master_id, step_id) = match ctx {
             Some(v) => v,
             None => {
-                eprintln!(
-                    "remodel_slotlist_detail: Req context not found (spawn race); skip"
-                );
-                return Some(vec![]);
+                return Err(ConvertError::new(
+                    "remodel_slotlist_detail: Req context not found (spawn race)",
+                ));
             }
         };
         let d = &self.api_data;
@@ -140,6 +167,8 @@ impl TraitForConvert for remodel_slotlist_detail::Res {
             remodel_id: step_id,
             remodel_step_id: step_id,
             remodel_level: None,
+            req_buildkit: d.api_req_buildkit,
+            req_remodelkit: d.api_req_remodelkit,
             certain_buildkit: d.api_certain_buildkit,
             certain_remodelkit: d.api_certain_remodelkit,
             req_slot_id: d.api_req_slot_id,
@@ -150,6 +179,111 @@ impl TraitForConvert for remodel_slotlist_detail::Res {
             req_useitem_num: d.api_req_useitem_num,
             req_useitem_num2: d.api_req_useitem_num2,
         };
-        Some(vec![EmitData::Set(Set::RemodelDetail(detail))])
+        *LAST_DETAIL.lock().unwrap() = Some(detail.clone());
+        Ok(Some(vec![EmitData::Set(Set::RemodelDetail(detail))]))
+    }
+}
+
+// --- remodel_slot: 改修実行 ---
+
+impl TraitForConvert for remodel_slot::Req {
+    type Output = EmitData;
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
+        let mut q = PENDING_REMODEL_SLOT_REQ.lock().unwrap();
+        if q.len() >= PENDING_REMODEL_SLOT_REQ_CAP {
+            tracing::warn!(
+                cap = PENDING_REMODEL_SLOT_REQ_CAP,
+                "PENDING_REMODEL_SLOT_REQ overflow; dropping oldest entry"
+            );
+            q.pop_front();
+        }
+        q.push_back(RemodelSlotReq {
+            slot_id: self.api_slot_id,
+            remodel_id: self.api_id,
+            certain: self.api_certain_flag == 1,
+        });
+        Ok(Some(vec![]))
+    }
+}
+
+/// 直前に取得した slotlist / detail から 1 回分の消費資材を求める。
+/// 開発資材・改修資材は detail があればそちら（確実化はその値）を優先する。
+fn remodel_cost(remodel_id: i64, certain: bool) -> Option<ImprovementCost> {
+    let slot_list = LAST_SLOT_LIST.lock().unwrap();
+    let entry = slot_list
+        .as_ref()?
+        .entries
+        .iter()
+        .find(|entry| entry.remodel_id == remodel_id)?;
+    let detail = LAST_DETAIL.lock().unwrap();
+    let detail = detail
+        .as_ref()
+        .filter(|detail| detail.remodel_id == remodel_id);
+    let (buildkit, remodelkit) = match detail {
+        Some(detail) if certain => (detail.certain_buildkit, detail.certain_remodelkit),
+        Some(detail) => (detail.req_buildkit, detail.req_remodelkit),
+        None => (entry.req_buildkit, entry.req_remodelkit),
+    };
+    Some(ImprovementCost {
+        fuel: entry.req_fuel,
+        bull: entry.req_bull,
+        steel: entry.req_steel,
+        bauxite: entry.req_bauxite,
+        buildkit,
+        remodelkit,
+    })
+}
+
+impl TraitForConvert for remodel_slot::Res {
+    type Output = EmitData;
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
+        let req = match PENDING_REMODEL_SLOT_REQ.lock().unwrap().pop_front() {
+            Some(v) => v,
+            None => {
+                return Err(ConvertError::new(
+                    "remodel_slot: Req context not found (spawn race)",
+                ));
+            }
+        };
+        let d = &self.api_data;
+        let mut slot_items = SlotItems::load();
+        let before = slot_items.slot_items.get(&req.slot_id).cloned();
+        let consumed_slot_items = d
+            .api_use_slot_id
+            .iter()
+            .flatten()
+            .filter_map(|id| slot_items.slot_items.remove(id))
+            .map(|item| item.slotitem_id)
+            .collect();
+        let after = d.api_after_slot.as_ref();
+        if let (Some(after), Some(item)) = (after, slot_items.slot_items.get_mut(&req.slot_id)) {
+            item.slotitem_id = after.api_slotitem_id;
+            item.level = after.api_level;
+        }
+
+        let attempt = ImprovementAttempt {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            weekday_jst: weekday_jst(),
+            secretary_ship_master_id: get_secretary_ship_master_id(),
+            helper_ship_master_id: get_helper_ship_master_id(),
+            remodel_id: req.remodel_id,
+            slot_id: req.slot_id,
+            slotitem_master_id: before.as_ref().map(|item| item.slotitem_id),
+            after_slotitem_master_id: after
+                .map(|after| after.api_slotitem_id)
+                .or(before.as_ref().map(|item| item.slotitem_id)),
+            level_before: before.as_ref().map(|item| item.level),
+            level_after: after
+                .map(|after| after.api_level)
+                .or(before.as_ref().map(|item| item.level)),
+            success: d.api_remodel_flag == 1,
+            certain: req.certain,
+            consumed: remodel_cost(req.remodel_id, req.certain),
+            consumed_slot_items,
+        };
+        Ok(Some(vec![
+            EmitData::Set(Set::SlotItems(slot_items)),
+            EmitData::Set(Set::ImprovementAttempt(attempt)),
+        ]))
     }
 }
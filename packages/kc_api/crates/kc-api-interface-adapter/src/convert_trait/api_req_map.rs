@@ -3,26 +3,26 @@ use kc_api_interface::interface::{Add, EmitData, Identifier, Set};
 
 use kc_api_dto::endpoints::api_req_map::*;
 
-use crate::{register_trait, InterfaceWrapper, TraitForConvert};
+use crate::{register_trait, ConvertError, InterfaceWrapper, TraitForConvert};
 
 register_trait!(Req, (next, select_eventmap_rank, start_air_base, start));
 register_trait!(Res, (select_eventmap_rank, start_air_base));
 
 impl TraitForConvert for next::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let cell = InterfaceWrapper::<Cell>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Cell(cell))])
+        Ok(Some(vec![EmitData::Add(Add::Cell(cell))]))
     }
 }
 
 impl TraitForConvert for start::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let cells = InterfaceWrapper::<Cells>::from(self.api_data.clone()).unwrap();
-        Some(vec![
+        Ok(Some(vec![
             EmitData::Set(Set::Cells(cells)),
             EmitData::Identifier(Identifier::MapStart(())),
-        ])
+        ]))
     }
 }
@@ -4,7 +4,7 @@ use kc_api_interface::quest::QuestEvent;
 
 use kc_api_dto::endpoints::api_req_quest::*;
 
-use crate::{register_trait, TraitForConvert};
+use crate::{register_trait, ConvertError, TraitForConvert};
 
 register_trait!(Req, ());
 register_trait!(Res, (clearitemget, start, stop));
@@ -12,35 +12,35 @@ register_trait!(Res, (clearitemget, start, stop));
 impl TraitForConvert for start::Req {
 	type Output = EmitData;
 
-	fn convert(&self) -> Option<Vec<EmitData>> {
-		Some(vec![EmitData::Add(Add::QuestEvent(QuestEvent {
+	fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
+		Ok(Some(vec![EmitData::Add(Add::QuestEvent(QuestEvent {
 			timestamp: chrono::Utc::now().timestamp_millis(),
 			event_type: "start".to_string(),
 			quest_id: Some(self.api_quest_id),
-		}))])
+		}))]))
 	}
 }
 
 impl TraitForConvert for stop::Req {
 	type Output = EmitData;
 
-	fn convert(&self) -> Option<Vec<EmitData>> {
-		Some(vec![EmitData::Add(Add::QuestEvent(QuestEvent {
+	fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
+		Ok(Some(vec![EmitData::Add(Add::QuestEvent(QuestEvent {
 			timestamp: chrono::Utc::now().timestamp_millis(),
 			event_type: "stop".to_string(),
 			quest_id: Some(self.api_quest_id),
-		}))])
+		}))]))
 	}
 }
 
 impl TraitForConvert for clearitemget::Req {
 	type Output = EmitData;
 
-	fn convert(&self) -> Option<Vec<EmitData>> {
-		Some(vec![EmitData::Add(Add::QuestEvent(QuestEvent {
+	fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
+		Ok(Some(vec![EmitData::Add(Add::QuestEvent(QuestEvent {
 			timestamp: chrono::Utc::now().timestamp_millis(),
 			event_type: "complete".to_string(),
 			quest_id: Some(self.api_quest_id),
-		}))])
+		}))]))
 	}
 }
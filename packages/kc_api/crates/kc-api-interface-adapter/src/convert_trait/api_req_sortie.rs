@@ -3,7 +3,7 @@ use kc_api_interface::interface::{Add, EmitData};
 
 use kc_api_dto::endpoints::api_req_sortie::*;
 
-use crate::{register_trait, InterfaceWrapper, TraitForConvert};
+use crate::{register_trait, ConvertError, InterfaceWrapper, TraitForConvert};
 
 register_trait!(
     Req,
@@ -13,40 +13,40 @@ register_trait!(Res, (goback_port));
 
 impl TraitForConvert for battleresult::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for airbattle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![EmitData::Add(Add::Battle(battle))])
+        Ok(Some(vec![EmitData::Add(Add::Battle(battle))]))
     }
 }
 
 impl TraitForConvert for battle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         // let ships: Ships = self.api_data.clone().into();
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![
+        Ok(Some(vec![
             // EmitData::Add(Add::Ships(ships)),
             EmitData::Add(Add::Battle(battle)),
-        ])
+        ]))
     }
 }
 
 impl TraitForConvert for ld_airbattle::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         // let ships: Ships = self.api_data.clone().into();
         let battle = InterfaceWrapper::<Battle>::from(self.api_data.clone()).unwrap();
-        Some(vec![
+        Ok(Some(vec![
             // EmitData::Add(Add::Ships(ships)),
             EmitData::Add(Add::Battle(battle)),
-        ])
+        ]))
     }
 }
@@ -16,14 +16,14 @@ use kc_api_interface::mst_use_item::MstUseItems;
 
 use kc_api_dto::endpoints::api_start2::*;
 
-use crate::{register_trait, InterfaceWrapper, TraitForConvert};
+use crate::{register_trait, ConvertError, InterfaceWrapper, TraitForConvert};
 
 register_trait!(Req, (get_data, get_option_setting));
 register_trait!(Res, (get_option_setting));
 
 impl TraitForConvert for get_data::Res {
     type Output = EmitData;
-    fn convert(&self) -> Option<Vec<EmitData>> {
+    fn convert(&self) -> Result<Option<Vec<EmitData>>, ConvertError> {
         // need to add other fields
         let mst_ships =
             InterfaceWrapper::<MstShips>::from(self.api_data.api_mst_ship.clone()).unwrap();
@@ -86,6 +86,6 @@ impl TraitForConvert for get_data::Res {
             EmitData::Identifier(Identifier::GetData(())),
         ]);
 
-        Some(events)
+        Ok(Some(events))
     }
 }
@@ -836,6 +836,7 @@ impl From<kcapi_main::api_req_sortie::battleresult::ApiData> for InterfaceWrappe
             landing_hp_now,
             landing_hp_max,
             landing_sub_value,
+            base_exp: Some(battle_result.api_get_base_exp),
         })
     }
 }
@@ -52,6 +52,8 @@ impl From<kcapi_main::api_port::port::ApiShip> for InterfaceWrapper<Ship> {
             sakuteki: Some(ship.api_sakuteki),
             lucky: Some(ship.api_lucky),
             sally_area: ship.api_sally_area,
+            locked: Some(ship.api_locked),
+            kyouka: Some(ship.api_kyouka),
             sp_effect_items: ship
                 .api_sp_effect_items
                 .map(|items| InterfaceWrapper::<SpEffectItems>::from(items).unwrap()),
@@ -177,6 +179,8 @@ impl From<kcapi_main::api_req_hokyu::charge::ApiData> for InterfaceWrapper<Ships
                     sakuteki: None,
                     lucky: None,
                     sally_area: None,
+                    locked: None,
+                    kyouka: None,
                     sp_effect_items: None,
                 },
             );
@@ -8,10 +8,40 @@ impl<T> InterfaceWrapper<T> {
     }
 }
 
+/// A DTO that could not be converted into interface data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertError {
+    message: String,
+}
+
+impl ConvertError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+/// Drop the request context kept for the response of `name` (e.g.
+/// `/kcsapi/api_req_kousyou/remodel_slot`). Call when the response is not
+/// converted, so the next response is not matched with a stale request.
+pub fn discard_pending_request(name: &str) {
+    convert_trait::api_req_kousyou::discard_pending_req(name);
+}
+
 pub trait TraitForConvert {
     type Output;
-    fn convert(&self) -> Option<Vec<Self::Output>> {
-        None
+    /// `Ok(None)` when the message carries nothing to emit.
+    fn convert(&self) -> Result<Option<Vec<Self::Output>>, ConvertError> {
+        Ok(None)
     }
 }
 
@@ -19,8 +49,8 @@ pub trait TraitForConvert {
 macro_rules! register_trait {
     ($ident2:ident, ($($ident1:ident),*)) => {$(impl TraitForConvert for $ident1::$ident2 {
         type Output = EmitData;
-        fn convert(&self) -> Option<Vec<EmitData>> {
-            Some(vec![])
+        fn convert(&self) -> Result<Option<Vec<EmitData>>, $crate::ConvertError> {
+            Ok(Some(vec![]))
         }
     })*};
 }
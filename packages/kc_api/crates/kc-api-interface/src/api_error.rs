@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::cells::Cells;
+
+/// Error that interrupted the sortie in progress; its data is not submitted
+/// at the next port.
+static INTERRUPTED_SORTIE: Mutex<Option<ApiError>> = Mutex::new(None);
+
+/// `api_result` of a session the server no longer accepts (token expired or
+/// logged in elsewhere); the game shows the cat.
+pub const API_RESULT_SESSION_EXPIRED: i64 = 201;
+/// `api_result` of a request with invalid parameters.
+pub const API_RESULT_INVALID_REQUEST: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorClass {
+    SessionExpired,
+    Maintenance,
+    InvalidRequest,
+    Other,
+}
+
+impl ApiErrorClass {
+    pub fn classify(api_result: i64, api_result_msg: &str) -> Self {
+        if api_result_msg.contains("メンテナンス") || api_result_msg.contains("maintenance") {
+            return ApiErrorClass::Maintenance;
+        }
+        match api_result {
+            API_RESULT_SESSION_EXPIRED => ApiErrorClass::SessionExpired,
+            API_RESULT_INVALID_REQUEST => ApiErrorClass::InvalidRequest,
+            _ => ApiErrorClass::Other,
+        }
+    }
+}
+
+/// A response with an `api_result` other than 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiError {
+    pub timestamp: i64,
+    pub endpoint: String,
+    pub api_result: i64,
+    pub api_result_msg: String,
+    pub class: ApiErrorClass,
+}
+
+impl ApiError {
+    pub fn new(endpoint: &str, api_result: i64, api_result_msg: &str, timestamp: i64) -> Self {
+        Self {
+            timestamp,
+            endpoint: endpoint.to_string(),
+            api_result,
+            api_result_msg: api_result_msg.to_string(),
+            class: ApiErrorClass::classify(api_result, api_result_msg),
+        }
+    }
+
+    /// Mark the sortie in progress as interrupted, so its partial data is
+    /// dropped instead of submitted. Returns `false` outside a sortie.
+    pub fn interrupt_sortie(&self) -> bool {
+        if Cells::reset_flag() {
+            return false;
+        }
+        *INTERRUPTED_SORTIE.lock().unwrap() = Some(self.clone());
+        true
+    }
+
+    /// Take the error that interrupted the sortie ending now and drop the
+    /// sortie's cells. Called before the port table is submitted.
+    pub fn take_sortie_interruption() -> Option<ApiError> {
+        let error = INTERRUPTED_SORTIE.lock().unwrap().take()?;
+        Cells::reset();
+        Some(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            ApiErrorClass::classify(201, "不正なアクセス"),
+            ApiErrorClass::SessionExpired
+        );
+        assert_eq!(
+            ApiErrorClass::classify(100, ""),
+            ApiErrorClass::InvalidRequest
+        );
+        assert_eq!(
+            ApiErrorClass::classify(201, "只今メンテナンス中です"),
+            ApiErrorClass::Maintenance
+        );
+        assert_eq!(ApiErrorClass::classify(-1, ""), ApiErrorClass::Other);
+    }
+}
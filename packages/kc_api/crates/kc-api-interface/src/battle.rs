@@ -491,6 +491,9 @@ pub struct BattleResult {
     pub landing_hp_now: Option<i64>,
     pub landing_hp_max: Option<i64>,
     pub landing_sub_value: Option<i64>,
+    /// `api_get_base_exp`; used for levelling estimates, not stored in the database.
+    #[ts(optional)]
+    pub base_exp: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -0,0 +1,350 @@
+//! Modernization and levelling plans for ships: the modernization left per
+//! stat against the master caps, the exp needed to a target level, and the
+//! sorties that takes on a farming node, estimated from the base exp of the
+//! battles observed there.
+//!
+//! Goals, node observations and learned exp table entries live in a
+//! [`GrowthPlanStore`] that the caller persists with [`GrowthPlanStore::load`]
+//! and [`GrowthPlanStore::save`].
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::battle::Battle;
+use crate::inventory::ModernizationLeft;
+use crate::mst_ship::MstShips;
+use crate::ship::{Ship, Ships};
+
+/// Exp of an S rank relative to the base exp of the node, as a fraction.
+const S_RANK_FACTOR: (i64, i64) = (12, 10);
+const FLAGSHIP_FACTOR: (i64, i64) = (3, 2);
+const MVP_FACTOR: (i64, i64) = (2, 1);
+
+/// Total exp at `lv` where it follows from the game rules: `50·lv·(lv-1)` up
+/// to level 51, and 1,000,000 at level 99 and at level 100 (marriage).
+fn fixed_exp_at(lv: i64) -> Option<i64> {
+    match lv {
+        1..=51 => Some(50 * lv * (lv - 1)),
+        99 | 100 => Some(1_000_000),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "growth_plan.ts")]
+pub struct FarmingNode {
+    pub maparea_id: i64,
+    pub mapinfo_no: i64,
+    pub cell_id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "growth_plan.ts")]
+pub struct NodeExp {
+    pub node: FarmingNode,
+    pub battles: i64,
+    pub base_exp_total: i64,
+}
+
+impl NodeExp {
+    pub fn average_base_exp(&self) -> Option<i64> {
+        (self.battles > 0).then(|| self.base_exp_total / self.battles)
+    }
+}
+
+/// Levelling goal the user set for a ship.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "growth_plan.ts")]
+pub struct ShipGoal {
+    pub target_lv: i64,
+    pub node: Option<FarmingNode>,
+    /// The ship sorties as flagship (exp ×1.5).
+    #[serde(default)]
+    pub flagship: bool,
+    /// The ship is expected to take MVP (exp ×2).
+    #[serde(default)]
+    pub mvp: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrowthPlanStore {
+    /// Goals by ship `api_id`.
+    #[serde(default)]
+    pub goals: BTreeMap<i64, ShipGoal>,
+    #[serde(default)]
+    pub nodes: Vec<NodeExp>,
+    /// Total exp by level, learned from the roster for levels without a
+    /// fixed value.
+    #[serde(default)]
+    pub exp_table: BTreeMap<i64, i64>,
+}
+
+impl GrowthPlanStore {
+    /// Read the store at `path`; a missing file is an empty store.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, content)
+    }
+
+    /// Set the goal of ship `id`, or clear it with `None`.
+    pub fn set_goal(&mut self, id: i64, goal: Option<ShipGoal>) {
+        match goal {
+            Some(goal) => self.goals.insert(id, goal),
+            None => self.goals.remove(&id),
+        };
+    }
+
+    /// Count the base exp of a battle with a result at `maparea_id`-`mapinfo_no`.
+    /// Returns `false` when the battle has no result or no base exp.
+    pub fn record_battle(&mut self, maparea_id: i64, mapinfo_no: i64, battle: &Battle) -> bool {
+        let Some(base_exp) = battle
+            .battle_result
+            .as_ref()
+            .and_then(|result| result.base_exp)
+        else {
+            return false;
+        };
+        self.record_base_exp(
+            FarmingNode {
+                maparea_id,
+                mapinfo_no,
+                cell_id: battle.cell_id,
+            },
+            base_exp,
+        );
+        true
+    }
+
+    pub fn record_base_exp(&mut self, node: FarmingNode, base_exp: i64) {
+        match self.nodes.iter_mut().find(|observed| observed.node == node) {
+            Some(observed) => {
+                observed.battles += 1;
+                observed.base_exp_total += base_exp;
+            }
+            None => {
+                self.nodes.push(NodeExp {
+                    node,
+                    battles: 1,
+                    base_exp_total: base_exp,
+                });
+                self.nodes.sort_by_key(|observed| observed.node);
+            }
+        }
+    }
+
+    /// Learn the total exp of the next level of every ship: a ship at `lv`
+    /// with `E` exp and `N` to go puts `lv + 1` at `E + N`. Returns whether
+    /// the table changed.
+    pub fn learn_exp_table(&mut self, ships: &Ships) -> bool {
+        let mut changed = false;
+        for ship in ships.ships.values() {
+            let (Some(lv), Some(exp)) = (ship.lv, ship.exp.as_ref()) else {
+                continue;
+            };
+            let (Some(total), Some(to_next)) = (exp.first(), exp.get(1)) else {
+                continue;
+            };
+            if *to_next <= 0 || fixed_exp_at(lv + 1).is_some() {
+                continue;
+            }
+            changed |= self.exp_table.insert(lv + 1, total + to_next) != Some(total + to_next);
+        }
+        changed
+    }
+
+    /// Total exp at `lv`, when it is fixed or has been learned.
+    pub fn exp_at(&self, lv: i64) -> Option<i64> {
+        fixed_exp_at(lv).or_else(|| self.exp_table.get(&lv).copied())
+    }
+
+    pub fn node(&self, node: &FarmingNode) -> Option<&NodeExp> {
+        self.nodes.iter().find(|observed| observed.node == *node)
+    }
+
+    /// Plan of the ship `id`, or `None` when the ship or its master data is
+    /// unknown.
+    pub fn plan(&self, id: i64, ships: &Ships, mst_ships: &MstShips) -> Option<GrowthPlan> {
+        let ship = ships.ships.get(&id)?;
+        self.plan_ship(ship, mst_ships)
+    }
+
+    /// Plans of the ships with a goal, by ship id.
+    pub fn plans(&self, ships: &Ships, mst_ships: &MstShips) -> Vec<GrowthPlan> {
+        self.goals
+            .keys()
+            .filter_map(|id| self.plan(*id, ships, mst_ships))
+            .collect()
+    }
+
+    fn plan_ship(&self, ship: &Ship, mst_ships: &MstShips) -> Option<GrowthPlan> {
+        let mst_ship = mst_ships.mst_ships.get(&(ship.ship_id? as i32))?;
+        let goal = self.goals.get(&ship.id).cloned();
+        let exp_current = ship.exp.as_ref().and_then(|exp| exp.first().copied());
+
+        let exp_needed = goal.as_ref().and_then(|goal| {
+            if ship.lv? >= goal.target_lv {
+                return Some(0);
+            }
+            Some((self.exp_at(goal.target_lv)? - exp_current?).max(0))
+        });
+        let node_base_exp = goal
+            .as_ref()
+            .and_then(|goal| goal.node.as_ref())
+            .and_then(|node| self.node(node))
+            .and_then(NodeExp::average_base_exp);
+        let exp_per_sortie = goal.as_ref().zip(node_base_exp).map(|(goal, base_exp)| {
+            let mut exp = base_exp * S_RANK_FACTOR.0 / S_RANK_FACTOR.1;
+            if goal.flagship {
+                exp = exp * FLAGSHIP_FACTOR.0 / FLAGSHIP_FACTOR.1;
+            }
+            if goal.mvp {
+                exp = exp * MVP_FACTOR.0 / MVP_FACTOR.1;
+            }
+            exp
+        });
+        let sorties_needed = exp_needed
+            .zip(exp_per_sortie)
+            .filter(|(_, per_sortie)| *per_sortie > 0)
+            .map(|(needed, per_sortie)| (needed + per_sortie - 1) / per_sortie);
+
+        Some(GrowthPlan {
+            id: ship.id,
+            ship_id: mst_ship.id,
+            name: mst_ship.name.clone(),
+            lv: ship.lv,
+            modernization_left: ModernizationLeft::new(ship, mst_ship),
+            goal,
+            exp_current,
+            exp_needed,
+            node_base_exp,
+            exp_per_sortie,
+            sorties_needed,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "growth_plan.ts")]
+pub struct GrowthPlan {
+    /// `api_id` of the ship instance.
+    pub id: i64,
+    pub ship_id: i32,
+    pub name: String,
+    pub lv: Option<i64>,
+    pub modernization_left: Option<ModernizationLeft>,
+    pub goal: Option<ShipGoal>,
+    pub exp_current: Option<i64>,
+    /// Exp to the goal level; `None` while the total exp of that level is
+    /// unknown (no ship in the roster has been one level below it).
+    pub exp_needed: Option<i64>,
+    /// Average base exp observed at the goal node.
+    pub node_base_exp: Option<i64>,
+    /// Exp of an S rank at the goal node, with the flagship and MVP bonuses
+    /// of the goal.
+    pub exp_per_sortie: Option<i64>,
+    /// Sorties with one S rank battle at the goal node each.
+    pub sorties_needed: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures;
+
+    fn ships() -> Ships {
+        test_fixtures::ships([
+            Ship {
+                exp: Some(vec![43_500, 3_000, 0]),
+                kyouka: Some(vec![5, 40, 0, 15, 0, 0, 0]),
+                ..test_fixtures::ship(1, 10, 30)
+            },
+            Ship {
+                exp: Some(vec![200_000, 5_000, 20]),
+                ..test_fixtures::ship(2, 10, 60)
+            },
+        ])
+    }
+
+    fn mst_ships() -> MstShips {
+        test_fixtures::mst_ships([test_fixtures::mst_ship(10, "吹雪", 2)])
+    }
+
+    const NODE: FarmingNode = FarmingNode {
+        maparea_id: 3,
+        mapinfo_no: 2,
+        cell_id: 1,
+    };
+
+    #[test]
+    fn test_exp_table() {
+        let mut store = GrowthPlanStore::default();
+        assert_eq!(store.exp_at(31), Some(46_500));
+        assert_eq!(store.exp_at(61), None);
+        assert!(store.learn_exp_table(&ships()));
+        assert!(!store.learn_exp_table(&ships()));
+        assert_eq!(store.exp_at(61), Some(205_000));
+        assert_eq!(store.exp_table.len(), 1);
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut store = GrowthPlanStore::default();
+        store.record_base_exp(NODE, 400);
+        store.record_base_exp(NODE, 600);
+        store.set_goal(
+            1,
+            Some(ShipGoal {
+                target_lv: 50,
+                node: Some(NODE),
+                flagship: true,
+                mvp: true,
+            }),
+        );
+        let plan = store.plan(1, &ships(), &mst_ships()).unwrap();
+        assert_eq!(
+            plan.modernization_left,
+            Some(ModernizationLeft {
+                karyoku: 15,
+                raisou: 0,
+                taiku: 30,
+                soukou: 0,
+                lucky: 40,
+            })
+        );
+        // 122,500 exp at lv 50, 500 base exp × 1.2 × 1.5 × 2 = 1,800 per sortie.
+        assert_eq!(plan.exp_needed, Some(79_000));
+        assert_eq!(plan.node_base_exp, Some(500));
+        assert_eq!(plan.exp_per_sortie, Some(1_800));
+        assert_eq!(plan.sorties_needed, Some(44));
+
+        store.set_goal(
+            2,
+            Some(ShipGoal {
+                target_lv: 70,
+                node: Some(NODE),
+                flagship: false,
+                mvp: false,
+            }),
+        );
+        let plans = store.plans(&ships(), &mst_ships());
+        assert_eq!(plans.len(), 2);
+        assert_eq!(plans[1].exp_needed, None);
+        assert_eq!(plans[1].sorties_needed, None);
+
+        store.set_goal(1, None);
+        assert_eq!(store.plans(&ships(), &mst_ships()).len(), 1);
+    }
+}
@@ -0,0 +1,323 @@
+//! Equipment improvement (Akashi) log: every `remodel_slot` attempt with its
+//! stars, outcome and consumption, and a catalogue of the helper ship and
+//! weekday combinations `remodel_slotlist` offered each item under.
+//!
+//! The caller persists the [`ImprovementLog`] with [`ImprovementLog::load`]
+//! and [`ImprovementLog::save`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::remodel::RemodelSlotList;
+
+/// Materials spent on one attempt. Taken from the `remodel_slotlist` and
+/// `remodel_slotlist_detail` the client loaded before the attempt, since the
+/// response only carries the materials left.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "improvement.ts")]
+pub struct ImprovementCost {
+    pub fuel: i64,
+    pub bull: i64,
+    pub steel: i64,
+    pub bauxite: i64,
+    /// Development materials
+    pub buildkit: i64,
+    /// Improvement materials (screws)
+    pub remodelkit: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "improvement.ts")]
+pub struct ImprovementAttempt {
+    /// Milliseconds since the epoch.
+    pub timestamp: i64,
+    /// 0 = Monday, in JST like `remodel_slotlist`.
+    pub weekday_jst: i64,
+    pub secretary_ship_master_id: Option<i64>,
+    pub helper_ship_master_id: Option<i64>,
+    pub remodel_id: i64,
+    /// Instance id of the improved item.
+    pub slot_id: i64,
+    /// `None` when the item was not in the slot item cache.
+    pub slotitem_master_id: Option<i64>,
+    /// Differs from `slotitem_master_id` when the item was upgraded.
+    pub after_slotitem_master_id: Option<i64>,
+    pub level_before: Option<i64>,
+    pub level_after: Option<i64>,
+    pub success: bool,
+    pub certain: bool,
+    /// `None` when the recipe was not seen before the attempt.
+    pub consumed: Option<ImprovementCost>,
+    /// Master ids of the items used up, in `api_use_slot_id` order.
+    pub consumed_slot_items: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "improvement.ts")]
+pub struct ImprovementHelper {
+    pub helper_ship_master_id: i64,
+    pub weekday_jst: i64,
+}
+
+/// Outcomes of the attempts on one item from one star level.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "improvement.ts")]
+pub struct ImprovementStats {
+    pub level_before: Option<i64>,
+    pub certain: bool,
+    pub attempts: i64,
+    pub successes: i64,
+    pub consumed: ImprovementCost,
+    pub consumed_slot_items: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "improvement.ts")]
+pub struct ImprovementSummary {
+    pub slotitem_master_id: i64,
+    pub helpers: Vec<ImprovementHelper>,
+    /// Uncertain attempts by star level, then certain ones by star level.
+    pub stats: Vec<ImprovementStats>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImprovementLog {
+    #[serde(default)]
+    pub attempts: Vec<ImprovementAttempt>,
+    /// Helper and weekday combinations by item master id.
+    #[serde(default)]
+    pub helpers: BTreeMap<i64, BTreeSet<ImprovementHelper>>,
+}
+
+impl ImprovementLog {
+    /// Read the log at `path`; a missing file is an empty log.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, content)
+    }
+
+    pub fn record_attempt(&mut self, attempt: ImprovementAttempt) {
+        if let (Some(slotitem_master_id), Some(helper_ship_master_id)) =
+            (attempt.slotitem_master_id, attempt.helper_ship_master_id)
+        {
+            self.helpers
+                .entry(slotitem_master_id)
+                .or_default()
+                .insert(ImprovementHelper {
+                    helper_ship_master_id,
+                    weekday_jst: attempt.weekday_jst,
+                });
+        }
+        self.attempts.push(attempt);
+    }
+
+    /// Add the helper and weekday of a slot list to every item it offers.
+    /// Returns whether the catalogue changed.
+    pub fn record_slot_list(&mut self, slot_list: &RemodelSlotList) -> bool {
+        let Some(helper_ship_master_id) = slot_list.helper_ship_master_id else {
+            return false;
+        };
+        let helper = ImprovementHelper {
+            helper_ship_master_id,
+            weekday_jst: slot_list.weekday_jst,
+        };
+        let mut changed = false;
+        for entry in &slot_list.entries {
+            changed |= self
+                .helpers
+                .entry(entry.slotitem_master_id)
+                .or_default()
+                .insert(helper);
+        }
+        changed
+    }
+
+    /// Attempts on `slotitem_master_id`, or all attempts with `None`, newest
+    /// first.
+    pub fn attempts(&self, slotitem_master_id: Option<i64>) -> Vec<ImprovementAttempt> {
+        self.attempts
+            .iter()
+            .rev()
+            .filter(|attempt| {
+                slotitem_master_id.is_none() || attempt.slotitem_master_id == slotitem_master_id
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn summary(&self, slotitem_master_id: i64) -> ImprovementSummary {
+        let mut stats: BTreeMap<(bool, Option<i64>), ImprovementStats> = BTreeMap::new();
+        for attempt in &self.attempts {
+            if attempt.slotitem_master_id != Some(slotitem_master_id) {
+                continue;
+            }
+            let entry = stats
+                .entry((attempt.certain, attempt.level_before))
+                .or_insert_with(|| ImprovementStats {
+                    level_before: attempt.level_before,
+                    certain: attempt.certain,
+                    ..Default::default()
+                });
+            entry.attempts += 1;
+            if attempt.success {
+                entry.successes += 1;
+            }
+            if let Some(consumed) = &attempt.consumed {
+                entry.consumed.fuel += consumed.fuel;
+                entry.consumed.bull += consumed.bull;
+                entry.consumed.steel += consumed.steel;
+                entry.consumed.bauxite += consumed.bauxite;
+                entry.consumed.buildkit += consumed.buildkit;
+                entry.consumed.remodelkit += consumed.remodelkit;
+            }
+            entry.consumed_slot_items += attempt.consumed_slot_items.len() as i64;
+        }
+        ImprovementSummary {
+            slotitem_master_id,
+            helpers: self
+                .helpers
+                .get(&slotitem_master_id)
+                .map(|helpers| helpers.iter().copied().collect())
+                .unwrap_or_default(),
+            stats: stats.into_values().collect(),
+        }
+    }
+
+    /// Summaries of every item with an attempt or a known helper.
+    pub fn summaries(&self) -> Vec<ImprovementSummary> {
+        let ids: BTreeSet<i64> = self
+            .attempts
+            .iter()
+            .filter_map(|attempt| attempt.slotitem_master_id)
+            .chain(self.helpers.keys().copied())
+            .collect();
+        ids.into_iter().map(|id| self.summary(id)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remodel::RemodelSlotListEntry;
+
+    fn attempt(level_before: i64, success: bool, certain: bool) -> ImprovementAttempt {
+        ImprovementAttempt {
+            timestamp: 0,
+            weekday_jst: 2,
+            secretary_ship_master_id: Some(182),
+            helper_ship_master_id: Some(144),
+            remodel_id: 101,
+            slot_id: 500,
+            slotitem_master_id: Some(2),
+            after_slotitem_master_id: Some(2),
+            level_before: Some(level_before),
+            level_after: Some(if success {
+                level_before + 1
+            } else {
+                level_before
+            }),
+            success,
+            certain,
+            consumed: Some(ImprovementCost {
+                fuel: 10,
+                bull: 30,
+                steel: 60,
+                bauxite: 0,
+                buildkit: if certain { 2 } else { 1 },
+                remodelkit: if certain { 2 } else { 1 },
+            }),
+            consumed_slot_items: if success { vec![2] } else { vec![] },
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut log = ImprovementLog::default();
+        log.record_attempt(attempt(0, true, false));
+        log.record_attempt(attempt(1, false, false));
+        log.record_attempt(attempt(1, true, false));
+        log.record_attempt(attempt(2, true, true));
+
+        let summary = log.summary(2);
+        assert_eq!(
+            summary.helpers,
+            vec![ImprovementHelper {
+                helper_ship_master_id: 144,
+                weekday_jst: 2,
+            }]
+        );
+        let stats: Vec<_> = summary
+            .stats
+            .iter()
+            .map(|stats| {
+                (
+                    stats.level_before,
+                    stats.certain,
+                    stats.attempts,
+                    stats.successes,
+                )
+            })
+            .collect();
+        assert_eq!(
+            stats,
+            vec![
+                (Some(0), false, 1, 1),
+                (Some(1), false, 2, 1),
+                (Some(2), true, 1, 1),
+            ]
+        );
+        assert_eq!(summary.stats[1].consumed.remodelkit, 2);
+        assert_eq!(summary.stats[1].consumed_slot_items, 1);
+        assert_eq!(log.attempts(Some(2))[0].level_before, Some(2));
+        assert!(log.attempts(Some(3)).is_empty());
+    }
+
+    #[test]
+    fn test_record_slot_list() {
+        let entry = |slotitem_master_id: i64| RemodelSlotListEntry {
+            remodel_id: slotitem_master_id * 10,
+            remodel_step_id: slotitem_master_id * 10,
+            remodel_level: None,
+            slotitem_master_id,
+            sp_type: 0,
+            req_fuel: 10,
+            req_bull: 30,
+            req_steel: 60,
+            req_bauxite: 0,
+            req_buildkit: 1,
+            req_remodelkit: 1,
+            req_slot_id: 0,
+            req_slot_num: 0,
+        };
+        let mut slot_list = RemodelSlotList {
+            secretary_ship_master_id: 182,
+            helper_ship_master_id: Some(144),
+            weekday_jst: 4,
+            entries: vec![entry(2), entry(5)],
+        };
+        let mut log = ImprovementLog::default();
+        assert!(log.record_slot_list(&slot_list));
+        assert!(!log.record_slot_list(&slot_list));
+        slot_list.helper_ship_master_id = None;
+        assert!(!log.record_slot_list(&slot_list));
+
+        let summaries = log.summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[1].slotitem_master_id, 5);
+        assert_eq!(summaries[1].helpers.len(), 1);
+        assert!(summaries[1].stats.is_empty());
+    }
+}
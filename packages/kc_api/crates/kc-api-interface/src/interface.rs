@@ -1,7 +1,9 @@
 use crate::air_base::AirBases;
+use crate::api_error::ApiError;
 use crate::battle::Battle;
 use crate::cells::{Cell, Cells};
 use crate::deck_port::{Basic, DeckPorts};
+use crate::improvement::ImprovementAttempt;
 use crate::logs::Logs;
 use crate::material::Materials;
 use crate::mst_equip_exslot::MstEquipExslots;
@@ -83,6 +85,7 @@ pub enum Set {
     SokuSpeedObservedSnapshot(SokuSpeedObservedSnapshot),
     RemodelSlotList(RemodelSlotList),
     RemodelDetail(RemodelDetail),
+    ImprovementAttempt(ImprovementAttempt),
     Dammy(()),
 }
 
@@ -92,4 +95,5 @@ pub enum Identifier {
     GetData(()),
     RequireInfo(()),
     MapStart(()),
+    ApiError(ApiError),
 }
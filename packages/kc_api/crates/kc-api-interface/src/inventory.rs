@@ -0,0 +1,839 @@
+//! Search over the ships and equipment held in memory, so a large roster can
+//! be filtered and sorted without sending the full tables to the UI.
+//!
+//! A query is a list of terms separated by whitespace. Every term must match;
+//! a leading `-` negates it.
+//!
+//! | Term | Ships | Equipment |
+//! | --- | --- | --- |
+//! | `<text>`, `name:<text>` | name or reading contains the text | name contains the text |
+//! | `type:<t>,..` | ship type id, abbreviation (`DD`, `CV`, ...) or name | equipment type id or name |
+//! | `lv:<range>` | level | - |
+//! | `star:<range>` | - | improvement level |
+//! | `locked` | locked | locked |
+//! | `equipped` | holds any equipment | held by a ship |
+//! | `mod`, `mod:<stat>,..` | modernization left in firepower, torpedo, AA or armor, or in the listed stats (`fp`, `tp`, `aa`, `ar`, `luck`) | - |
+//! | `area:<n>,..` | sally area tag, 0 for none | - |
+//! | `sort:<key>,..` | `id`, `lv`, `type`, `name`, `cond` | `id`, `star`, `type`, `name` |
+//!
+//! Ranges are `50`, `50..`, `..20` or `50..99`, bounds included. Sort keys
+//! take a `-` prefix for descending order.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::mst_ship::{MstShip, MstShips};
+use crate::mst_slot_item::MstSlotItems;
+use crate::mst_slot_item_equip_type::MstSlotItemEquipTypes;
+use crate::mst_stype::MstStypes;
+use crate::ship::{Ship, Ships};
+use crate::slot_item::SlotItems;
+
+/// Ship type abbreviations by `api_stype`.
+const STYPE_ABBREVIATIONS: &[(&str, i32)] = &[
+    ("DE", 1),
+    ("DD", 2),
+    ("CL", 3),
+    ("CLT", 4),
+    ("CA", 5),
+    ("CAV", 6),
+    ("CVL", 7),
+    ("FBB", 8),
+    ("BB", 9),
+    ("BBV", 10),
+    ("CV", 11),
+    ("XBB", 12),
+    ("SS", 13),
+    ("SSV", 14),
+    ("AP", 15),
+    ("AV", 16),
+    ("LHA", 17),
+    ("CVB", 18),
+    ("AR", 19),
+    ("AS", 20),
+    ("CT", 21),
+    ("AO", 22),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "inventory.ts")]
+pub enum InventoryTarget {
+    Ships,
+    SlotItems,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryQueryError {
+    pub term: String,
+    pub message: String,
+}
+
+impl InventoryQueryError {
+    fn new(term: &str, message: impl Into<String>) -> Self {
+        Self {
+            term: term.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for InventoryQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`: {}", self.term, self.message)
+    }
+}
+
+impl std::error::Error for InventoryQueryError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Range {
+    min: Option<i64>,
+    max: Option<i64>,
+}
+
+impl Range {
+    fn parse(value: &str) -> Option<Self> {
+        let bound = |s: &str| -> Option<Option<i64>> {
+            if s.is_empty() {
+                Some(None)
+            } else {
+                s.parse().ok().map(Some)
+            }
+        };
+        let range = match value.split_once("..") {
+            Some((min, max)) => Range {
+                min: bound(min)?,
+                max: bound(max)?,
+            },
+            None => {
+                let value = value.parse().ok()?;
+                Range {
+                    min: Some(value),
+                    max: Some(value),
+                }
+            }
+        };
+        (range.min.is_some() || range.max.is_some()).then_some(range)
+    }
+
+    fn contains(&self, value: i64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+/// Stats that can be raised by modernization, in `api_kyouka` order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModStat {
+    Karyoku,
+    Raisou,
+    Taiku,
+    Soukou,
+    Lucky,
+}
+
+impl ModStat {
+    const DEFAULT: [ModStat; 4] = [
+        ModStat::Karyoku,
+        ModStat::Raisou,
+        ModStat::Taiku,
+        ModStat::Soukou,
+    ];
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fp" => Some(ModStat::Karyoku),
+            "tp" => Some(ModStat::Raisou),
+            "aa" => Some(ModStat::Taiku),
+            "ar" => Some(ModStat::Soukou),
+            "luck" => Some(ModStat::Lucky),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Name(String),
+    Type(Vec<String>),
+    Lv(Range),
+    Star(Range),
+    Locked,
+    Equipped,
+    Modernizable(Vec<ModStat>),
+    Area(Vec<i64>),
+}
+
+impl Term {
+    fn applies_to(&self, target: InventoryTarget) -> bool {
+        match self {
+            Term::Lv(_) | Term::Modernizable(_) | Term::Area(_) => target == InventoryTarget::Ships,
+            Term::Star(_) => target == InventoryTarget::SlotItems,
+            Term::Name(_) | Term::Type(_) | Term::Locked | Term::Equipped => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Filter {
+    source: String,
+    negated: bool,
+    term: Term,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortField {
+    Id,
+    Lv,
+    Type,
+    Name,
+    Cond,
+    Star,
+}
+
+impl SortField {
+    fn applies_to(self, target: InventoryTarget) -> bool {
+        match self {
+            SortField::Lv | SortField::Cond => target == InventoryTarget::Ships,
+            SortField::Star => target == InventoryTarget::SlotItems,
+            SortField::Id | SortField::Type | SortField::Name => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SortKey {
+    field: SortField,
+    descending: bool,
+}
+
+/// A parsed query, see the module documentation for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryQuery {
+    filters: Vec<Filter>,
+    sort: Vec<(String, SortKey)>,
+}
+
+impl InventoryQuery {
+    pub fn parse(query: &str) -> Result<Self, InventoryQueryError> {
+        let mut filters = Vec::new();
+        let mut sort = Vec::new();
+        for source in query.split_whitespace() {
+            let (negated, term) = match source.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, source),
+            };
+            let (key, value) = match term.split_once(':') {
+                Some((key, value)) => (key.to_ascii_lowercase(), Some(value)),
+                None => (term.to_ascii_lowercase(), None),
+            };
+            let term = match (key.as_str(), value) {
+                ("sort", Some(value)) if !negated => {
+                    for key in value.split(',').filter(|key| !key.is_empty()) {
+                        sort.push((source.to_string(), parse_sort_key(source, key)?));
+                    }
+                    continue;
+                }
+                ("name", Some(value)) if !value.is_empty() => Term::Name(value.to_lowercase()),
+                ("type", Some(value)) => Term::Type(list(source, value)?),
+                ("lv", Some(value)) => Term::Lv(range(source, value)?),
+                ("star", Some(value)) => Term::Star(range(source, value)?),
+                ("locked", None) => Term::Locked,
+                ("equipped", None) => Term::Equipped,
+                ("mod", None) => Term::Modernizable(ModStat::DEFAULT.to_vec()),
+                ("mod", Some(value)) => Term::Modernizable(
+                    list(source, value)?
+                        .iter()
+                        .map(|stat| {
+                            ModStat::parse(&stat.to_ascii_lowercase()).ok_or_else(|| {
+                                InventoryQueryError::new(
+                                    source,
+                                    format!(
+                                        "unknown stat `{stat}`, expected fp, tp, aa, ar or luck"
+                                    ),
+                                )
+                            })
+                        })
+                        .collect::<Result<_, _>>()?,
+                ),
+                ("area", Some(value)) => Term::Area(
+                    list(source, value)?
+                        .iter()
+                        .map(|area| {
+                            area.parse().map_err(|_| {
+                                InventoryQueryError::new(source, "sally area must be a number")
+                            })
+                        })
+                        .collect::<Result<_, _>>()?,
+                ),
+                (_, None) => Term::Name(term.to_lowercase()),
+                _ => return Err(InventoryQueryError::new(source, "unknown term")),
+            };
+            filters.push(Filter {
+                source: source.to_string(),
+                negated,
+                term,
+            });
+        }
+        Ok(Self { filters, sort })
+    }
+
+    fn check(&self, target: InventoryTarget) -> Result<(), InventoryQueryError> {
+        let target_name = match target {
+            InventoryTarget::Ships => "ships",
+            InventoryTarget::SlotItems => "equipment",
+        };
+        if let Some(filter) = self.filters.iter().find(|f| !f.term.applies_to(target)) {
+            return Err(InventoryQueryError::new(
+                &filter.source,
+                format!("does not apply to {target_name}"),
+            ));
+        }
+        if let Some((source, _)) = self.sort.iter().find(|(_, k)| !k.field.applies_to(target)) {
+            return Err(InventoryQueryError::new(
+                source,
+                format!("cannot sort {target_name} by this key"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl InventoryQuery {
+    /// Type ids of each `type:` filter, empty for the other filters.
+    fn resolve_types(
+        &self,
+        types: &[(i32, String)],
+        abbreviations: &[(&str, i32)],
+    ) -> Vec<HashSet<i32>> {
+        self.filters
+            .iter()
+            .map(|filter| match &filter.term {
+                Term::Type(names) => resolve_types(names, types, abbreviations),
+                _ => HashSet::new(),
+            })
+            .collect()
+    }
+}
+
+fn list(source: &str, value: &str) -> Result<Vec<String>, InventoryQueryError> {
+    let items: Vec<String> = value
+        .split(',')
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect();
+    if items.is_empty() {
+        return Err(InventoryQueryError::new(source, "missing value"));
+    }
+    Ok(items)
+}
+
+fn range(source: &str, value: &str) -> Result<Range, InventoryQueryError> {
+    Range::parse(value)
+        .ok_or_else(|| InventoryQueryError::new(source, "expected a number or a range like 50..99"))
+}
+
+fn parse_sort_key(source: &str, key: &str) -> Result<SortKey, InventoryQueryError> {
+    let (descending, key) = match key.strip_prefix('-') {
+        Some(key) => (true, key),
+        None => (false, key),
+    };
+    let field = match key.to_ascii_lowercase().as_str() {
+        "id" => SortField::Id,
+        "lv" => SortField::Lv,
+        "type" => SortField::Type,
+        "name" => SortField::Name,
+        "cond" => SortField::Cond,
+        "star" => SortField::Star,
+        _ => {
+            return Err(InventoryQueryError::new(
+                source,
+                format!("unknown sort key `{key}`"),
+            ))
+        }
+    };
+    Ok(SortKey { field, descending })
+}
+
+/// Modernization points still available per stat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "inventory.ts")]
+pub struct ModernizationLeft {
+    pub karyoku: i64,
+    pub raisou: i64,
+    pub taiku: i64,
+    pub soukou: i64,
+    pub lucky: i64,
+}
+
+impl ModernizationLeft {
+    pub(crate) fn new(ship: &Ship, mst_ship: &MstShip) -> Option<Self> {
+        let kyouka = ship.kyouka.as_ref()?;
+        let left = |index: usize, range: &Option<Vec<i32>>| -> Option<i64> {
+            let range = range.as_ref()?;
+            let (base, max) = (*range.first()? as i64, *range.get(1)? as i64);
+            Some((max - base - kyouka.get(index).copied().unwrap_or(0)).max(0))
+        };
+        Some(Self {
+            karyoku: left(0, &mst_ship.houg)?,
+            raisou: left(1, &mst_ship.raig)?,
+            taiku: left(2, &mst_ship.tyku)?,
+            soukou: left(3, &mst_ship.souk)?,
+            lucky: left(4, &mst_ship.luck)?,
+        })
+    }
+
+    fn get(&self, stat: ModStat) -> i64 {
+        match stat {
+            ModStat::Karyoku => self.karyoku,
+            ModStat::Raisou => self.raisou,
+            ModStat::Taiku => self.taiku,
+            ModStat::Soukou => self.soukou,
+            ModStat::Lucky => self.lucky,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "inventory.ts")]
+pub struct InventoryShip {
+    pub id: i64,
+    pub ship_id: Option<i64>,
+    pub name: Option<String>,
+    pub stype: Option<i32>,
+    pub stype_name: Option<String>,
+    pub lv: Option<i64>,
+    pub cond: Option<i64>,
+    pub locked: bool,
+    pub sally_area: i64,
+    pub slot: Vec<i64>,
+    pub slot_ex: Option<i64>,
+    pub modernization_left: Option<ModernizationLeft>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "inventory.ts")]
+pub struct InventorySlotItem {
+    pub id: i64,
+    pub slotitem_id: i64,
+    pub name: Option<String>,
+    pub equip_type: Option<i32>,
+    pub equip_type_name: Option<String>,
+    pub level: i64,
+    pub alv: Option<i64>,
+    pub locked: bool,
+    /// Ship holding the item.
+    pub ship: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "target", content = "results", rename_all = "snake_case")]
+#[ts(export, export_to = "inventory.ts")]
+pub enum InventoryResults {
+    Ships(Vec<InventoryShip>),
+    SlotItems(Vec<InventorySlotItem>),
+}
+
+/// The tables a query runs against.
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    pub ships: Ships,
+    pub slot_items: SlotItems,
+    pub mst_ships: MstShips,
+    pub mst_slot_items: MstSlotItems,
+    pub mst_stypes: MstStypes,
+    pub mst_slotitem_equip_types: MstSlotItemEquipTypes,
+}
+
+impl Inventory {
+    pub fn load() -> Self {
+        Self {
+            ships: Ships::load(),
+            slot_items: SlotItems::load(),
+            mst_ships: MstShips::load(),
+            mst_slot_items: MstSlotItems::load(),
+            mst_stypes: MstStypes::load(),
+            mst_slotitem_equip_types: MstSlotItemEquipTypes::load(),
+        }
+    }
+
+    pub fn search(
+        &self,
+        target: InventoryTarget,
+        query: &InventoryQuery,
+    ) -> Result<InventoryResults, InventoryQueryError> {
+        Ok(match target {
+            InventoryTarget::Ships => InventoryResults::Ships(self.search_ships(query)?),
+            InventoryTarget::SlotItems => {
+                InventoryResults::SlotItems(self.search_slot_items(query)?)
+            }
+        })
+    }
+
+    /// Matching ships, by level (highest first) unless the query sorts them.
+    pub fn search_ships(
+        &self,
+        query: &InventoryQuery,
+    ) -> Result<Vec<InventoryShip>, InventoryQueryError> {
+        query.check(InventoryTarget::Ships)?;
+        let types: Vec<(i32, String)> = self
+            .mst_stypes
+            .mst_stypes
+            .values()
+            .map(|stype| (stype.id, stype.name.clone()))
+            .collect();
+        let type_ids = query.resolve_types(&types, STYPE_ABBREVIATIONS);
+
+        let mut results = Vec::new();
+        for ship in self.ships.ships.values() {
+            let mst_ship = ship
+                .ship_id
+                .and_then(|id| self.mst_ships.mst_ships.get(&(id as i32)));
+            let modernization_left = mst_ship.and_then(|mst| ModernizationLeft::new(ship, mst));
+            let matched = query
+                .filters
+                .iter()
+                .zip(&type_ids)
+                .all(|(filter, type_ids)| {
+                    let matched = match &filter.term {
+                        Term::Name(text) => mst_ship.is_some_and(|mst| {
+                            mst.name.to_lowercase().contains(text)
+                                || mst.yomi.to_lowercase().contains(text)
+                        }),
+                        Term::Type(_) => mst_ship.is_some_and(|mst| type_ids.contains(&mst.stype)),
+                        Term::Lv(range) => ship.lv.is_some_and(|lv| range.contains(lv)),
+                        Term::Locked => ship.locked.unwrap_or(0) == 1,
+                        Term::Equipped => {
+                            ship.slot.iter().flatten().any(|id| *id > 0)
+                                || ship.slot_ex.unwrap_or(0) > 0
+                        }
+                        Term::Modernizable(stats) => modernization_left
+                            .as_ref()
+                            .is_some_and(|left| stats.iter().any(|stat| left.get(*stat) > 0)),
+                        Term::Area(areas) => areas.contains(&ship.sally_area.unwrap_or(0)),
+                        Term::Star(_) => false,
+                    };
+                    matched != filter.negated
+                });
+            if !matched {
+                continue;
+            }
+            let stype = mst_ship.map(|mst| mst.stype);
+            results.push(InventoryShip {
+                id: ship.id,
+                ship_id: ship.ship_id,
+                name: mst_ship.map(|mst| mst.name.clone()),
+                stype,
+                stype_name: stype
+                    .and_then(|stype| self.mst_stypes.mst_stypes.get(&stype))
+                    .map(|stype| stype.name.clone()),
+                lv: ship.lv,
+                cond: ship.cond,
+                locked: ship.locked.unwrap_or(0) == 1,
+                sally_area: ship.sally_area.unwrap_or(0),
+                slot: ship.slot.clone().unwrap_or_default(),
+                slot_ex: ship.slot_ex,
+                modernization_left,
+            });
+        }
+
+        let default_sort = [SortKey {
+            field: SortField::Lv,
+            descending: true,
+        }];
+        let keys = sort_keys(query, &default_sort);
+        results.sort_by(|a, b| {
+            keys.iter()
+                .map(|key| {
+                    let ordering = match key.field {
+                        SortField::Lv => a.lv.cmp(&b.lv),
+                        SortField::Type => a.stype.cmp(&b.stype),
+                        SortField::Name => a.name.cmp(&b.name),
+                        SortField::Cond => a.cond.cmp(&b.cond),
+                        SortField::Id | SortField::Star => a.id.cmp(&b.id),
+                    };
+                    if key.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.id.cmp(&b.id))
+        });
+        Ok(results)
+    }
+
+    /// Matching equipment, by type and then master id unless the query sorts
+    /// them.
+    pub fn search_slot_items(
+        &self,
+        query: &InventoryQuery,
+    ) -> Result<Vec<InventorySlotItem>, InventoryQueryError> {
+        query.check(InventoryTarget::SlotItems)?;
+        let types: Vec<(i32, String)> = self
+            .mst_slotitem_equip_types
+            .mst_slotitem_equip_types
+            .values()
+            .map(|equip_type| (equip_type.id, equip_type.name.clone()))
+            .collect();
+        let type_ids = query.resolve_types(&types, &[]);
+        let mut holders = HashMap::<i64, i64>::new();
+        for ship in self.ships.ships.values() {
+            for id in ship.slot.iter().flatten().chain(ship.slot_ex.iter()) {
+                if *id > 0 {
+                    holders.insert(*id, ship.id);
+                }
+            }
+        }
+
+        let mut results = Vec::new();
+        for slot_item in self.slot_items.slot_items.values() {
+            let mst_slot_item = self
+                .mst_slot_items
+                .mst_slot_items
+                .get(&(slot_item.slotitem_id as i32));
+            // api_type[2] is the equipment type of api_mst_slotitem_equip_type
+            let equip_type = mst_slot_item.and_then(|mst| mst.r#type.get(2).copied());
+            let ship = holders.get(&slot_item.id).copied();
+            let matched = query
+                .filters
+                .iter()
+                .zip(&type_ids)
+                .all(|(filter, type_ids)| {
+                    let matched = match &filter.term {
+                        Term::Name(text) => {
+                            mst_slot_item.is_some_and(|mst| mst.name.to_lowercase().contains(text))
+                        }
+                        Term::Type(_) => equip_type.is_some_and(|id| type_ids.contains(&id)),
+                        Term::Star(range) => range.contains(slot_item.level),
+                        Term::Locked => slot_item.locked == 1,
+                        Term::Equipped => ship.is_some(),
+                        Term::Lv(_) | Term::Modernizable(_) | Term::Area(_) => false,
+                    };
+                    matched != filter.negated
+                });
+            if !matched {
+                continue;
+            }
+            results.push(InventorySlotItem {
+                id: slot_item.id,
+                slotitem_id: slot_item.slotitem_id,
+                name: mst_slot_item.map(|mst| mst.name.clone()),
+                equip_type,
+                equip_type_name: equip_type
+                    .and_then(|id| {
+                        self.mst_slotitem_equip_types
+                            .mst_slotitem_equip_types
+                            .get(&id)
+                    })
+                    .map(|equip_type| equip_type.name.clone()),
+                level: slot_item.level,
+                alv: slot_item.alv,
+                locked: slot_item.locked == 1,
+                ship,
+            });
+        }
+
+        let default_sort = [
+            SortKey {
+                field: SortField::Type,
+                descending: false,
+            },
+            SortKey {
+                field: SortField::Star,
+                descending: true,
+            },
+        ];
+        let keys = sort_keys(query, &default_sort);
+        results.sort_by(|a, b| {
+            keys.iter()
+                .map(|key| {
+                    let ordering = match key.field {
+                        SortField::Type => a
+                            .equip_type
+                            .cmp(&b.equip_type)
+                            .then(a.slotitem_id.cmp(&b.slotitem_id)),
+                        SortField::Star => a.level.cmp(&b.level),
+                        SortField::Name => a.name.cmp(&b.name),
+                        SortField::Id | SortField::Lv | SortField::Cond => a.id.cmp(&b.id),
+                    };
+                    if key.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.id.cmp(&b.id))
+        });
+        Ok(results)
+    }
+}
+
+fn sort_keys(query: &InventoryQuery, default: &[SortKey]) -> Vec<SortKey> {
+    if query.sort.is_empty() {
+        default.to_vec()
+    } else {
+        query.sort.iter().map(|(_, key)| *key).collect()
+    }
+}
+
+/// Type ids named by `names`: an id, an abbreviation or part of a type name.
+fn resolve_types(
+    names: &[String],
+    types: &[(i32, String)],
+    abbreviations: &[(&str, i32)],
+) -> HashSet<i32> {
+    let mut ids = HashSet::new();
+    for name in names {
+        if let Ok(id) = name.parse::<i32>() {
+            ids.insert(id);
+        } else if let Some((_, id)) = abbreviations
+            .iter()
+            .find(|(abbreviation, _)| abbreviation.eq_ignore_ascii_case(name))
+        {
+            ids.insert(*id);
+        } else {
+            let name = name.to_lowercase();
+            ids.extend(
+                types
+                    .iter()
+                    .filter(|(_, type_name)| type_name.to_lowercase().contains(&name))
+                    .map(|(id, _)| *id),
+            );
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mst_slot_item_equip_type::MstSlotItemEquipType;
+    use crate::mst_stype::MstStype;
+    use crate::test_fixtures::{self, mst_ship, mst_slot_item, slot_item};
+
+    fn inventory() -> Inventory {
+        let ship =
+            |id: i64, ship_id: i64, lv: i64, locked: i64, kyouka: [i64; 7], slot: i64| Ship {
+                slot: Some(vec![slot, -1]),
+                slot_ex: Some(-1),
+                cond: Some(49),
+                sally_area: Some(if id == 2 { 3 } else { 0 }),
+                locked: Some(locked),
+                kyouka: Some(kyouka.to_vec()),
+                ..test_fixtures::ship(id, ship_id, lv)
+            };
+        let mst_stype = |id: i32, name: &str| {
+            (
+                id,
+                MstStype {
+                    id,
+                    sortno: id,
+                    name: name.to_string(),
+                    equip_type: HashMap::new(),
+                },
+            )
+        };
+        let equip_type = |id: i32, name: &str| {
+            (
+                id,
+                MstSlotItemEquipType {
+                    id,
+                    name: name.to_string(),
+                },
+            )
+        };
+        Inventory {
+            ships: test_fixtures::ships([
+                ship(1, 10, 99, 1, [20, 40, 30, 5, 0, 0, 0], 100),
+                ship(2, 11, 80, 1, [0, 0, 0, 0, 0, 0, 0], -1),
+                ship(3, 10, 1, 0, [0, 0, 0, 0, 0, 0, 0], -1),
+            ]),
+            slot_items: test_fixtures::slot_items([
+                slot_item(100, 2, 10),
+                slot_item(101, 2, 4),
+                slot_item(102, 5, 0),
+            ]),
+            mst_ships: test_fixtures::mst_ships([
+                mst_ship(10, "吹雪", 2),
+                mst_ship(11, "赤城", 11),
+            ]),
+            mst_slot_items: test_fixtures::mst_slot_items([
+                mst_slot_item(2, "12.7cm連装砲", 1),
+                mst_slot_item(5, "61cm三連装魚雷", 5),
+            ]),
+            mst_stypes: MstStypes {
+                mst_stypes: [mst_stype(2, "駆逐艦"), mst_stype(11, "正規空母")].into(),
+            },
+            mst_slotitem_equip_types: MstSlotItemEquipTypes {
+                mst_slotitem_equip_types: [equip_type(1, "小口径主砲"), equip_type(5, "魚雷")]
+                    .into(),
+            },
+        }
+    }
+
+    fn ship_ids(inventory: &Inventory, query: &str) -> Vec<i64> {
+        let query = InventoryQuery::parse(query).unwrap();
+        let ships = inventory.search_ships(&query).unwrap();
+        ships.iter().map(|ship| ship.id).collect()
+    }
+
+    fn slot_item_ids(inventory: &Inventory, query: &str) -> Vec<i64> {
+        let query = InventoryQuery::parse(query).unwrap();
+        let slot_items = inventory.search_slot_items(&query).unwrap();
+        slot_items.iter().map(|slot_item| slot_item.id).collect()
+    }
+
+    #[test]
+    fn filters_and_sorts_ships() {
+        let inventory = inventory();
+        assert_eq!(ship_ids(&inventory, ""), vec![1, 2, 3]);
+        assert_eq!(ship_ids(&inventory, "type:DD locked"), vec![1]);
+        assert_eq!(ship_ids(&inventory, "type:空母"), vec![2]);
+        assert_eq!(ship_ids(&inventory, "lv:..80 sort:id"), vec![2, 3]);
+        assert_eq!(ship_ids(&inventory, "-equipped area:0"), vec![3]);
+        // ship 1 has firepower, torpedo and AA maxed but 10 armor left
+        assert_eq!(ship_ids(&inventory, "mod:fp,tp,aa"), vec![2, 3]);
+        assert_eq!(ship_ids(&inventory, "mod sort:-id"), vec![3, 2, 1]);
+        assert_eq!(ship_ids(&inventory, "吹雪 sort:lv"), vec![3, 1]);
+    }
+
+    #[test]
+    fn filters_and_sorts_slot_items() {
+        let inventory = inventory();
+        assert_eq!(slot_item_ids(&inventory, ""), vec![100, 101, 102]);
+        assert_eq!(slot_item_ids(&inventory, "star:5.."), vec![100]);
+        assert_eq!(
+            slot_item_ids(&inventory, "-equipped type:1,5"),
+            vec![101, 102]
+        );
+        assert_eq!(slot_item_ids(&inventory, "name:魚雷"), vec![102]);
+        let query = InventoryQuery::parse("equipped").unwrap();
+        assert_eq!(
+            inventory.search_slot_items(&query).unwrap()[0].ship,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_and_misapplied_terms() {
+        assert_eq!(
+            InventoryQuery::parse("lv:high").unwrap_err().term,
+            "lv:high"
+        );
+        assert!(InventoryQuery::parse("mod:hp").is_err());
+        assert!(InventoryQuery::parse("sort:speed").is_err());
+        assert!(InventoryQuery::parse("color:red").is_err());
+
+        let inventory = inventory();
+        let query = InventoryQuery::parse("lv:50..").unwrap();
+        assert!(inventory.search_slot_items(&query).is_err());
+        let query = InventoryQuery::parse("sort:star").unwrap();
+        assert!(inventory.search_ships(&query).is_err());
+    }
+}
@@ -7,9 +7,13 @@ use crate::{
 };
 
 pub mod air_base;
+pub mod api_error;
 pub mod battle;
 pub mod cells;
 pub mod deck_port;
+pub mod growth_plan;
+pub mod improvement;
+pub mod inventory;
 pub mod logs;
 pub mod material;
 pub mod mission;
@@ -27,8 +31,11 @@ pub mod quest;
 pub mod remodel;
 pub mod ship;
 pub mod ship_growth;
+pub mod ship_remodel;
 pub mod slot_item;
 pub mod soku_speed_observed;
+#[cfg(test)]
+mod test_fixtures;
 pub mod use_items;
 
 pub mod mst_maparea;
@@ -13,6 +13,26 @@ pub static PENDING_DETAIL_REQ: Lazy<Mutex<VecDeque<(i64, i64)>>> =
 /// PENDING_DETAIL_REQ の最大長。超過時は古いエントリを破棄する。
 pub const PENDING_DETAIL_REQ_CAP: usize = 64;
 
+/// remodel_slot の Req コンテキスト。PENDING_DETAIL_REQ と同じく FIFO で Res と対応付ける。
+pub static PENDING_REMODEL_SLOT_REQ: Lazy<Mutex<VecDeque<RemodelSlotReq>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// PENDING_REMODEL_SLOT_REQ の最大長。超過時は古いエントリを破棄する。
+pub const PENDING_REMODEL_SLOT_REQ_CAP: usize = 16;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RemodelSlotReq {
+    /// 改修対象の装備固有 ID
+    pub slot_id: i64,
+    pub remodel_id: i64,
+    /// 確実化 (api_certain_flag == 1)
+    pub certain: bool,
+}
+
+/// 直近の remodel_slotlist / remodel_slotlist_detail。改修実行時の消費資材の算出に使う。
+pub static LAST_SLOT_LIST: Lazy<Mutex<Option<RemodelSlotList>>> = Lazy::new(|| Mutex::new(None));
+pub static LAST_DETAIL: Lazy<Mutex<Option<RemodelDetail>>> = Lazy::new(|| Mutex::new(None));
+
 // --- remodel_slotlist: 改修条件一覧（秘書艦×曜日 → 利用可能レシピ） ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +55,9 @@ pub struct RemodelSlotListEntry {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemodelSlotList {
     pub secretary_ship_master_id: i64,
+    /// 第1艦隊2番艦（随伴艦）。改修可能なレシピは随伴艦と曜日で決まる。
+    #[serde(default)]
+    pub helper_ship_master_id: Option<i64>,
     pub weekday_jst: i64,
     pub entries: Vec<RemodelSlotListEntry>,
 }
@@ -47,6 +70,10 @@ pub struct RemodelDetail {
     pub remodel_id: i64,
     pub remodel_step_id: i64,
     pub remodel_level: Option<i64>,
+    #[serde(default)]
+    pub req_buildkit: i64,
+    #[serde(default)]
+    pub req_remodelkit: i64,
     pub certain_buildkit: i64,
     pub certain_remodelkit: i64,
     pub req_slot_id: i64,
@@ -52,6 +52,10 @@ pub struct Ship {
     pub sakuteki: Option<Vec<i64>>, // 索敵
     pub lucky: Option<Vec<i64>>,    // 運
     pub sally_area: Option<i64>,
+    #[ts(optional)]
+    pub locked: Option<i64>,
+    #[ts(optional)]
+    pub kyouka: Option<Vec<i64>>,   // 近代化改修 [火力,雷装,対空,装甲,運,耐久,対潜]
     pub sp_effect_items: Option<SpEffectItems>,
 }
 
@@ -124,6 +128,8 @@ impl Ships {
                         sakuteki: ship.sakuteki.clone().or(v.sakuteki.clone()),
                         lucky: ship.lucky.clone().or(v.lucky.clone()),
                         sally_area: ship.sally_area.or(v.sally_area),
+                        locked: ship.locked.or(v.locked),
+                        kyouka: ship.kyouka.clone().or(v.kyouka.clone()),
                         sp_effect_items: ship.sp_effect_items.clone().or(v.sp_effect_items.clone()),
                     };
                     ship_map.ships.insert(*key, ship_or);
@@ -0,0 +1,392 @@
+//! Ship remodel (kaizou) planning from `api_mst_ship` and
+//! `api_mst_shipupgrade`: the chain of forms a ship can still reach, the level
+//! and items each step needs, and the ships that can remodel now.
+//!
+//! `MstShip.afterfuel` is the steel cost and `afterbull` the ammo cost of the
+//! step to `aftershipid`. Materials are not kept in memory, so only levels and
+//! items are checked against the player's inventory.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::mst_ship::{MstShip, MstShips};
+use crate::mst_ship_upgrade::{MstShipUpgrade, MstShipUpgrades};
+use crate::mst_slot_item::MstSlotItems;
+use crate::mst_use_item::MstUseItems;
+use crate::ship::{Ship, Ships};
+use crate::slot_item::SlotItems;
+use crate::use_items::UseItems;
+
+/// 改装設計図
+pub const USE_ITEM_BLUEPRINT: i64 = 58;
+/// 試製甲板カタパルト
+pub const USE_ITEM_CATAPULT: i64 = 65;
+/// 新型砲熕兵装資材
+pub const USE_ITEM_ARMS_MATERIAL: i64 = 75;
+/// 新型航空兵装資材
+pub const USE_ITEM_AVIATION_MATERIAL: i64 = 77;
+/// 戦闘詳報
+pub const USE_ITEM_ACTION_REPORT: i64 = 78;
+/// 新型兵装資材
+pub const USE_ITEM_TECH_MATERIAL: i64 = 94;
+/// 新型高温高圧缶, consumed from the unequipped equipment.
+pub const SLOT_ITEM_NEW_BOILER: i64 = 87;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "ship_remodel.ts")]
+pub enum RemodelItemKind {
+    UseItem,
+    SlotItem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "ship_remodel.ts")]
+pub struct RemodelItem {
+    pub kind: RemodelItemKind,
+    pub id: i64,
+    pub name: Option<String>,
+    pub required: i64,
+    pub held: i64,
+}
+
+impl RemodelItem {
+    pub fn is_met(&self) -> bool {
+        self.held >= self.required
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "ship_remodel.ts")]
+pub struct RemodelStep {
+    pub from_ship_id: i32,
+    pub from_name: String,
+    pub to_ship_id: i32,
+    pub to_name: Option<String>,
+    pub level: i64,
+    pub ammo: i64,
+    pub steel: i64,
+    pub items: Vec<RemodelItem>,
+    /// The step goes back to a form already in the chain (convertible
+    /// ships); the chain ends here.
+    pub converts_back: bool,
+}
+
+impl RemodelStep {
+    fn can_remodel(&self, lv: Option<i64>) -> bool {
+        lv.is_some_and(|lv| lv >= self.level) && self.items.iter().all(RemodelItem::is_met)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "ship_remodel.ts")]
+pub struct RemodelPlan {
+    /// `api_id` of the ship instance.
+    pub id: i64,
+    pub ship_id: i32,
+    pub name: String,
+    pub lv: Option<i64>,
+    pub steps: Vec<RemodelStep>,
+    /// The first step's level and items are met.
+    pub can_remodel: bool,
+}
+
+/// The tables a plan is computed from.
+#[derive(Debug, Clone)]
+pub struct RemodelPlanner {
+    pub ships: Ships,
+    pub slot_items: SlotItems,
+    pub use_items: UseItems,
+    pub mst_ships: MstShips,
+    pub mst_ship_upgrades: MstShipUpgrades,
+    pub mst_use_items: MstUseItems,
+    pub mst_slot_items: MstSlotItems,
+}
+
+impl RemodelPlanner {
+    pub fn load() -> Self {
+        Self {
+            ships: Ships::load(),
+            slot_items: SlotItems::load(),
+            use_items: UseItems::load(),
+            mst_ships: MstShips::load(),
+            mst_ship_upgrades: MstShipUpgrades::load(),
+            mst_use_items: MstUseItems::load(),
+            mst_slot_items: MstSlotItems::load(),
+        }
+    }
+
+    /// Remodel chain of the ship instance `id`, or `None` when the ship or
+    /// its master data is unknown.
+    pub fn plan(&self, id: i64) -> Option<RemodelPlan> {
+        let ship = self.ships.ships.get(&id)?;
+        self.plan_ship(ship, self.spare_boilers())
+    }
+
+    /// Plans of the ships whose next remodel can be done now, by level
+    /// (highest first).
+    pub fn ready(&self) -> Vec<RemodelPlan> {
+        let spare_boilers = self.spare_boilers();
+        let mut plans: Vec<RemodelPlan> = self
+            .ships
+            .ships
+            .values()
+            .filter_map(|ship| self.plan_ship(ship, spare_boilers))
+            .filter(|plan| plan.can_remodel)
+            .collect();
+        plans.sort_by(|a, b| b.lv.cmp(&a.lv).then(a.id.cmp(&b.id)));
+        plans
+    }
+
+    fn plan_ship(&self, ship: &Ship, spare_boilers: i64) -> Option<RemodelPlan> {
+        let mst_ship = self.mst_ship(ship.ship_id? as i32)?;
+        let mut steps = Vec::new();
+        let mut visited = HashSet::from([mst_ship.id]);
+        let mut current = mst_ship;
+        while let Some(to_ship_id) = after_ship_id(current) {
+            let to_ship = self.mst_ship(to_ship_id);
+            let converts_back = !visited.insert(to_ship_id);
+            steps.push(RemodelStep {
+                from_ship_id: current.id,
+                from_name: current.name.clone(),
+                to_ship_id,
+                to_name: to_ship.map(|to_ship| to_ship.name.clone()),
+                level: current.afterlv.unwrap_or(0) as i64,
+                ammo: current.afterbull.unwrap_or(0) as i64,
+                steel: current.afterfuel.unwrap_or(0) as i64,
+                items: self.items(current.id, to_ship_id, spare_boilers),
+                converts_back,
+            });
+            match to_ship {
+                Some(to_ship) if !converts_back => current = to_ship,
+                _ => break,
+            }
+        }
+        let can_remodel = steps.first().is_some_and(|step| step.can_remodel(ship.lv));
+        Some(RemodelPlan {
+            id: ship.id,
+            ship_id: mst_ship.id,
+            name: mst_ship.name.clone(),
+            lv: ship.lv,
+            steps,
+            can_remodel,
+        })
+    }
+
+    fn mst_ship(&self, ship_id: i32) -> Option<&MstShip> {
+        self.mst_ships.mst_ships.get(&ship_id)
+    }
+
+    /// Items the step from `from` to `to` consumes. The upgrade table is
+    /// keyed by the target form, so when several forms remodel into the same
+    /// ship only one of them has its items listed.
+    fn items(&self, from: i32, to: i32, spare_boilers: i64) -> Vec<RemodelItem> {
+        let Some(upgrade) = self
+            .mst_ship_upgrades
+            .mst_ship_upgrades
+            .values()
+            .find(|upgrade| upgrade.api_current_ship_id == from && upgrade.api_id == to)
+        else {
+            return Vec::new();
+        };
+        let mut items: Vec<RemodelItem> = use_item_counts(upgrade)
+            .into_iter()
+            .filter(|(_, required)| *required > 0)
+            .map(|(id, required)| RemodelItem {
+                kind: RemodelItemKind::UseItem,
+                id,
+                name: self
+                    .mst_use_items
+                    .mst_use_items
+                    .get(&(id as i32))
+                    .map(|item| item.name.clone()),
+                required,
+                held: self
+                    .use_items
+                    .use_items
+                    .get(&id)
+                    .map_or(0, |item| item.count),
+            })
+            .collect();
+        if let Some(required) = upgrade.api_boiler_count.filter(|count| *count > 0) {
+            items.push(RemodelItem {
+                kind: RemodelItemKind::SlotItem,
+                id: SLOT_ITEM_NEW_BOILER,
+                name: self
+                    .mst_slot_items
+                    .mst_slot_items
+                    .get(&(SLOT_ITEM_NEW_BOILER as i32))
+                    .map(|item| item.name.clone()),
+                required: required as i64,
+                held: spare_boilers,
+            });
+        }
+        items
+    }
+
+    /// New-model boilers not equipped on any ship.
+    fn spare_boilers(&self) -> i64 {
+        let equipped: HashSet<i64> = self
+            .ships
+            .ships
+            .values()
+            .flat_map(|ship| ship.slot.iter().flatten().chain(ship.slot_ex.iter()))
+            .copied()
+            .filter(|id| *id > 0)
+            .collect();
+        self.slot_items
+            .slot_items
+            .values()
+            .filter(|item| item.slotitem_id == SLOT_ITEM_NEW_BOILER && !equipped.contains(&item.id))
+            .count() as i64
+    }
+}
+
+fn after_ship_id(mst_ship: &MstShip) -> Option<i32> {
+    mst_ship
+        .aftershipid
+        .as_deref()
+        .and_then(|id| id.parse::<i32>().ok())
+        .filter(|id| *id > 0)
+}
+
+fn use_item_counts(upgrade: &MstShipUpgrade) -> [(i64, i64); 6] {
+    [
+        (USE_ITEM_BLUEPRINT, upgrade.api_drawing_count as i64),
+        (USE_ITEM_CATAPULT, upgrade.api_catapult_count as i64),
+        (USE_ITEM_ACTION_REPORT, upgrade.api_report_count as i64),
+        (
+            USE_ITEM_AVIATION_MATERIAL,
+            upgrade.api_aviation_mat_count as i64,
+        ),
+        (USE_ITEM_ARMS_MATERIAL, upgrade.api_arms_mat_count as i64),
+        (USE_ITEM_TECH_MATERIAL, upgrade.api_tech_count as i64),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mst_use_item::MstUseItem;
+    use crate::slot_item::SlotItem;
+    use crate::test_fixtures;
+    use crate::use_items::UseItem;
+
+    fn planner() -> RemodelPlanner {
+        let mst_ship = |id: i32, name: &str, afterlv: i32, aftershipid: &str| MstShip {
+            afterlv: Some(afterlv),
+            aftershipid: Some(aftershipid.to_string()),
+            afterfuel: Some(100),
+            afterbull: Some(50),
+            ..test_fixtures::mst_ship(id, name, 3)
+        };
+        let ship = |id: i64, ship_id: i64, lv: i64, slot: i64| Ship {
+            slot: Some(vec![slot]),
+            slot_ex: Some(-1),
+            ..test_fixtures::ship(id, ship_id, lv)
+        };
+        let upgrade = |from: i32, to: i32, drawing: i32, boiler: i32| {
+            (
+                to,
+                MstShipUpgrade {
+                    api_id: to,
+                    api_current_ship_id: from,
+                    api_original_ship_id: from,
+                    api_upgrade_type: 1,
+                    api_upgrade_level: 0,
+                    api_drawing_count: drawing,
+                    api_catapult_count: 0,
+                    api_report_count: 0,
+                    api_aviation_mat_count: 0,
+                    api_arms_mat_count: 0,
+                    api_tech_count: 0,
+                    api_sortno: 0,
+                    api_boiler_count: Some(boiler),
+                },
+            )
+        };
+        RemodelPlanner {
+            ships: test_fixtures::ships([
+                ship(1, 10, 30, -1),
+                ship(2, 11, 70, 500),
+                ship(3, 12, 99, -1),
+            ]),
+            slot_items: test_fixtures::slot_items([
+                SlotItem {
+                    locked: 0,
+                    ..test_fixtures::slot_item(500, 87, 0)
+                },
+                SlotItem {
+                    locked: 0,
+                    ..test_fixtures::slot_item(501, 87, 0)
+                },
+            ]),
+            use_items: UseItems {
+                use_items: [(58, UseItem { id: 58, count: 1 })].into(),
+            },
+            // 10 -> 11 -> 12 <-> 13
+            mst_ships: test_fixtures::mst_ships([
+                mst_ship(10, "A", 20, "11"),
+                mst_ship(11, "A改", 65, "12"),
+                mst_ship(12, "A改二", 88, "13"),
+                mst_ship(13, "A改二乙", 88, "12"),
+            ]),
+            mst_ship_upgrades: MstShipUpgrades {
+                mst_ship_upgrades: [upgrade(11, 12, 1, 2), upgrade(12, 13, 2, 0)].into(),
+            },
+            mst_use_items: MstUseItems {
+                mst_use_items: [(
+                    58,
+                    MstUseItem {
+                        id: 58,
+                        name: "改装設計図".to_string(),
+                    },
+                )]
+                .into(),
+            },
+            mst_slot_items: test_fixtures::mst_slot_items([]),
+        }
+    }
+
+    #[test]
+    fn plans_chains_and_checks_items() {
+        let planner = planner();
+        let plan = planner.plan(1).unwrap();
+        let forms: Vec<i32> = plan.steps.iter().map(|step| step.to_ship_id).collect();
+        assert_eq!(forms, vec![11, 12, 13, 12]);
+        assert!(plan.steps[3].converts_back);
+        assert_eq!(plan.steps[0].level, 20);
+        assert_eq!(plan.steps[0].steel, 100);
+        assert!(plan.steps[0].items.is_empty());
+        assert!(plan.can_remodel);
+
+        // one boiler is equipped on ship 2, so only one is spare
+        let plan = planner.plan(2).unwrap();
+        assert_eq!(
+            plan.steps[0].items,
+            vec![
+                RemodelItem {
+                    kind: RemodelItemKind::UseItem,
+                    id: USE_ITEM_BLUEPRINT,
+                    name: Some("改装設計図".to_string()),
+                    required: 1,
+                    held: 1,
+                },
+                RemodelItem {
+                    kind: RemodelItemKind::SlotItem,
+                    id: SLOT_ITEM_NEW_BOILER,
+                    name: None,
+                    required: 2,
+                    held: 1,
+                },
+            ]
+        );
+        assert!(!plan.can_remodel);
+
+        let ready: Vec<i64> = planner.ready().iter().map(|plan| plan.id).collect();
+        assert_eq!(ready, vec![1]);
+        assert!(planner.plan(4).is_none());
+    }
+}
@@ -0,0 +1,160 @@
+//! Port and master data builders for the planner and inventory tests. Every
+//! field the game may omit is `None`; tests set the fields they exercise with
+//! struct update syntax.
+
+use crate::mst_ship::{MstShip, MstShips};
+use crate::mst_slot_item::{MstSlotItem, MstSlotItems};
+use crate::ship::{Ship, Ships};
+use crate::slot_item::{SlotItem, SlotItems};
+
+pub(crate) fn ship(id: i64, ship_id: i64, lv: i64) -> Ship {
+    Ship {
+        id,
+        ship_id: Some(ship_id),
+        lv: Some(lv),
+        exp: None,
+        nowhp: None,
+        maxhp: None,
+        soku: None,
+        leng: None,
+        slot: None,
+        onslot: None,
+        slot_ex: None,
+        fuel: None,
+        bull: None,
+        slotnum: None,
+        cond: None,
+        cond_state: None,
+        karyoku: None,
+        raisou: None,
+        taiku: None,
+        soukou: None,
+        kaihi: None,
+        taisen: None,
+        sakuteki: None,
+        lucky: None,
+        sally_area: None,
+        locked: None,
+        kyouka: None,
+        sp_effect_items: None,
+    }
+}
+
+pub(crate) fn ships(ships: impl IntoIterator<Item = Ship>) -> Ships {
+    Ships {
+        ships: ships.into_iter().map(|ship| (ship.id, ship)).collect(),
+    }
+}
+
+/// A master ship with firepower 10-30, torpedo 20-60, AA 10-40, armor 5-20
+/// and luck 10-50.
+pub(crate) fn mst_ship(id: i32, name: &str, stype: i32) -> MstShip {
+    MstShip {
+        id,
+        sortno: None,
+        sort_id: id,
+        name: name.to_string(),
+        yomi: String::new(),
+        stype,
+        ctype: 1,
+        afterlv: None,
+        aftershipid: None,
+        taik: None,
+        souk: Some(vec![5, 20]),
+        houg: Some(vec![10, 30]),
+        raig: Some(vec![20, 60]),
+        tyku: Some(vec![10, 40]),
+        luck: Some(vec![10, 50]),
+        soku: 10,
+        leng: None,
+        slot_num: 2,
+        maxeq: None,
+        buildtime: None,
+        broken: None,
+        powup: None,
+        backs: None,
+        getmes: None,
+        afterfuel: None,
+        afterbull: None,
+        fuel_max: None,
+        bull_max: None,
+        voicef: None,
+        tais: None,
+    }
+}
+
+pub(crate) fn mst_ships(mst_ships: impl IntoIterator<Item = MstShip>) -> MstShips {
+    MstShips {
+        mst_ships: mst_ships
+            .into_iter()
+            .map(|mst_ship| (mst_ship.id, mst_ship))
+            .collect(),
+    }
+}
+
+pub(crate) fn slot_item(id: i64, slotitem_id: i64, level: i64) -> SlotItem {
+    SlotItem {
+        id,
+        slotitem_id,
+        locked: 1,
+        level,
+        alv: None,
+    }
+}
+
+pub(crate) fn slot_items(slot_items: impl IntoIterator<Item = SlotItem>) -> SlotItems {
+    SlotItems {
+        slot_items: slot_items
+            .into_iter()
+            .map(|slot_item| (slot_item.id, slot_item))
+            .collect(),
+    }
+}
+
+/// A master slot item of `equip_type` (the third entry of `api_type`) with
+/// no stats.
+pub(crate) fn mst_slot_item(id: i32, name: &str, equip_type: i32) -> MstSlotItem {
+    MstSlotItem {
+        id,
+        sortno: id,
+        name: name.to_string(),
+        r#type: vec![1, 1, equip_type, 1, 0],
+        taik: 0,
+        souk: 0,
+        houg: 0,
+        raig: 0,
+        soku: 0,
+        baku: 0,
+        tyku: 0,
+        tais: 0,
+        atap: 0,
+        houm: 0,
+        raim: 0,
+        houk: 0,
+        raik: 0,
+        bakk: 0,
+        saku: 0,
+        sakb: 0,
+        luck: 0,
+        leng: 0,
+        rare: 0,
+        taibaku: 0,
+        geigeki: 0,
+        broken: vec![0, 0, 0, 0],
+        usebull: "0".to_string(),
+        version: None,
+        cost: None,
+        distance: None,
+    }
+}
+
+pub(crate) fn mst_slot_items(
+    mst_slot_items: impl IntoIterator<Item = MstSlotItem>,
+) -> MstSlotItems {
+    MstSlotItems {
+        mst_slot_items: mst_slot_items
+            .into_iter()
+            .map(|mst_slot_item| (mst_slot_item.id, mst_slot_item))
+            .collect(),
+    }
+}
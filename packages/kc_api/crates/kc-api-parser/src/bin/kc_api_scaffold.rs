@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use kc_api_parser::scaffold::{load_samples, scaffold_module};
+
+const USAGE: &str = "\
+Usage:
+  kc_api_scaffold <CAPTURE_DIR> </kcsapi/DIR/NAME> [--out <FILE>]
+      Infer a DTO module for the endpoint from its capture files in CAPTURE_DIR
+      (e.g. the parser's quarantine directory) and print it, or write it to FILE";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [input, endpoint] => scaffold(input, endpoint, None),
+        [input, endpoint, "--out", output] => scaffold(input, endpoint, Some(output)),
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("kc_api_scaffold: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn scaffold(
+    input: &str,
+    endpoint: &str,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let samples = load_samples(&PathBuf::from(input), endpoint)?;
+    let module = scaffold_module(endpoint, &samples)?;
+    let Some(output) = output else {
+        print!("{module}");
+        return Ok(());
+    };
+    std::fs::write(output, module)?;
+    let (api_dir, api_name) = endpoint
+        .trim_start_matches("/kcsapi/")
+        .split_once('/')
+        .unwrap_or_default();
+    eprintln!(
+        "wrote {output} from {} requests and {} responses",
+        samples.requests.len(),
+        samples.responses.len()
+    );
+    eprintln!("next: add `pub mod {api_name};` to endpoints/{api_dir}/mod.rs and regenerate tests/struct_dependency_svg/{api_dir}@{api_name}.svg");
+    Ok(())
+}
@@ -0,0 +1,521 @@
+//! Detection of game API changes in live messages.
+//!
+//! DTOs absorb unknown keys into their `extra` map (`#[add_field(extra_with_flatten)]`)
+//! and type fields that were never seen with a value as `serde_json::Value`,
+//! so a game update that adds or changes fields parses without an error. With
+//! the `drift` feature (on by default), the parser runs the `TraitForTest`
+//! probes on live responses and records per endpoint:
+//!
+//! - added fields: keys found in an `extra` map,
+//! - retyped fields: `Value` fields holding something other than `null`, and
+//!   values that no longer deserialize into the declared type,
+//! - removed fields: required fields missing from the response.
+//!
+//! Every response has its `extra` maps checked. The type probes format every
+//! `Value` field, so they only run on the first [`FULL_PROBE_FIRST`] responses
+//! of each endpoint and then on one in [`FULL_PROBE_EVERY`]. Deserialize
+//! errors are always recorded.
+//!
+//! [`take_new_changes`] returns the fields seen for the first time since the
+//! last call, [`report`] everything seen so far, and [`write_report`] writes
+//! the report as JSON and as a diff with sample payloads.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+#[cfg(any(test, feature = "drift"))]
+use register_trait::LogMapType;
+use serde::{Deserialize, Serialize};
+
+pub const REPORT_JSON_FILE_NAME: &str = "api_drift.json";
+pub const REPORT_DIFF_FILE_NAME: &str = "api_drift.diff";
+
+/// Longest sample kept per field.
+const SAMPLE_MAX_LEN: usize = 512;
+
+/// Responses per endpoint that get the full probe walk after startup.
+pub const FULL_PROBE_FIRST: u64 = 5;
+/// After the first responses, one in this many gets the full probe walk.
+pub const FULL_PROBE_EVERY: u64 = 50;
+
+static DRIFT_STATE: Mutex<DriftState> = Mutex::new(DriftState {
+    report: DriftReport {
+        endpoints: BTreeMap::new(),
+    },
+    new_changes: Vec::new(),
+    #[cfg(any(test, feature = "drift"))]
+    responses: BTreeMap::new(),
+});
+
+struct DriftState {
+    report: DriftReport,
+    new_changes: Vec<DriftChange>,
+    /// Responses inspected per endpoint in this session.
+    #[cfg(any(test, feature = "drift"))]
+    responses: BTreeMap<String, u64>,
+}
+
+#[cfg(any(test, feature = "drift"))]
+impl DriftState {
+    /// Count a response of `endpoint` and tell whether it gets the full probe
+    /// walk.
+    fn full_probe_due(&mut self, endpoint: &str) -> bool {
+        if !self.responses.contains_key(endpoint) {
+            self.responses.insert(endpoint.to_string(), 0);
+        }
+        let seen = self.responses.get_mut(endpoint).unwrap();
+        *seen += 1;
+        *seen <= FULL_PROBE_FIRST || (*seen - FULL_PROBE_FIRST).is_multiple_of(FULL_PROBE_EVERY)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftKind {
+    Added,
+    Removed,
+    Retyped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldDrift {
+    pub kind: DriftKind,
+    /// JSON type of an added or retyped field, or the deserialize error.
+    pub observed: String,
+    /// Value from the most recent response, truncated.
+    pub sample: String,
+    pub count: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointDrift {
+    /// Keyed by `<struct>.<field>`; removed fields only know the field name.
+    pub fields: BTreeMap<String, FieldDrift>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriftReport {
+    pub endpoints: BTreeMap<String, EndpointDrift>,
+}
+
+/// A field that drifted for the first time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriftChange {
+    pub endpoint: String,
+    pub field: String,
+    pub kind: DriftKind,
+    pub observed: String,
+    pub sample: String,
+}
+
+impl DriftReport {
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Unified-diff-like listing: `+` added, `-` removed, `~` retyped fields.
+    pub fn to_diff(&self) -> String {
+        let mut diff = String::new();
+        for (endpoint, drift) in &self.endpoints {
+            diff.push_str(&format!(
+                "--- {endpoint} (kc-api-dto)\n+++ {endpoint} (live)\n"
+            ));
+            for (field, change) in &drift.fields {
+                let marker = match change.kind {
+                    DriftKind::Added => '+',
+                    DriftKind::Removed => '-',
+                    DriftKind::Retyped => '~',
+                };
+                diff.push_str(&format!(
+                    "{marker} {field}: {} (seen {} times, first {}, last {})\n",
+                    change.observed,
+                    change.count,
+                    change.first_seen.to_rfc3339(),
+                    change.last_seen.to_rfc3339()
+                ));
+                if !change.sample.is_empty() {
+                    diff.push_str(&format!("    sample: {}\n", change.sample));
+                }
+            }
+            diff.push('\n');
+        }
+        diff
+    }
+
+    fn record(
+        &mut self,
+        change: DriftChange,
+        now: DateTime<Utc>,
+        new_changes: &mut Vec<DriftChange>,
+    ) {
+        let DriftChange {
+            endpoint,
+            field,
+            kind,
+            observed,
+            sample,
+        } = change;
+        let fields = &mut self.endpoints.entry(endpoint.clone()).or_default().fields;
+        let sample = truncate(sample);
+        match fields.get_mut(&field) {
+            Some(drift) => {
+                drift.kind = kind;
+                drift.observed = observed;
+                drift.sample = sample;
+                drift.count += 1;
+                drift.last_seen = now;
+            }
+            None => {
+                tracing::warn!(endpoint, field, ?kind, observed, "game API changed");
+                new_changes.push(DriftChange {
+                    endpoint,
+                    field: field.clone(),
+                    kind,
+                    observed: observed.clone(),
+                    sample: sample.clone(),
+                });
+                fields.insert(
+                    field,
+                    FieldDrift {
+                        kind,
+                        observed,
+                        sample,
+                        count: 1,
+                        first_seen: now,
+                        last_seen: now,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Record the probe results of one message.
+    #[cfg(any(test, feature = "drift"))]
+    fn record_log_map(
+        &mut self,
+        endpoint: &str,
+        log_map: &LogMapType,
+        now: DateTime<Utc>,
+        new_changes: &mut Vec<DriftChange>,
+    ) {
+        let mut entries: Vec<_> = log_map.iter().collect();
+        entries.sort();
+        for ((test_name, struct_name, field_name), logs) in entries {
+            match test_name.as_str() {
+                "field_extra" => {
+                    for log in logs {
+                        let Some((key, value)) = log.split_once('=') else {
+                            continue;
+                        };
+                        let observed = serde_json::from_str::<serde_json::Value>(value)
+                            .map_or_else(|_| "unknown".to_string(), |v| json_type(&v).to_string());
+                        let change = DriftChange {
+                            endpoint: endpoint.to_string(),
+                            field: format!("{struct_name}.{key}"),
+                            kind: DriftKind::Added,
+                            observed,
+                            sample: value.to_string(),
+                        };
+                        self.record(change, now, new_changes);
+                    }
+                }
+                "type_value" => {
+                    for log in logs.iter().filter(|log| *log != "null") {
+                        let (observed, sample) = log.split_once(':').unwrap_or((log, ""));
+                        let change = DriftChange {
+                            endpoint: endpoint.to_string(),
+                            field: format!("{struct_name}.{field_name}"),
+                            kind: DriftKind::Retyped,
+                            observed: observed.to_string(),
+                            sample: sample.to_string(),
+                        };
+                        self.record(change, now, new_changes);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Record a response that no longer deserializes into its DTO.
+    fn record_json_error(
+        &mut self,
+        endpoint: &str,
+        error: &serde_json::Error,
+        data: &str,
+        now: DateTime<Utc>,
+        new_changes: &mut Vec<DriftChange>,
+    ) {
+        if !error.is_data() {
+            return;
+        }
+        let message = error.to_string();
+        let message = message
+            .rsplit_once(" at line ")
+            .map_or(message.as_str(), |(message, _)| message)
+            .to_string();
+        let (field, kind) = match message
+            .strip_prefix("missing field `")
+            .and_then(|rest| rest.split_once('`'))
+        {
+            Some((field, _)) => (field.to_string(), DriftKind::Removed),
+            None => (
+                key_before(data, error.line(), error.column()).unwrap_or_else(|| "_".to_string()),
+                DriftKind::Retyped,
+            ),
+        };
+        let change = DriftChange {
+            endpoint: endpoint.to_string(),
+            field,
+            kind,
+            observed: message,
+            sample: context(data, error.line(), error.column()),
+        };
+        self.record(change, now, new_changes);
+    }
+}
+
+/// Check the `extra` maps of a parsed response, and run the type probes too
+/// when the response is due for the full walk.
+#[cfg(any(test, feature = "drift"))]
+pub fn inspect_response<T: register_trait::TraitForTest>(endpoint: &str, root: &T) {
+    let full_probe = DRIFT_STATE.lock().unwrap().full_probe_due(endpoint);
+    let mut log_map = LogMapType::new();
+    root.test_extra(&mut log_map);
+    if full_probe {
+        root.test_type_value(&mut log_map);
+        root.test_integration(&mut log_map);
+    } else {
+        root.test_integration_extra(&mut log_map);
+    }
+    if log_map.is_empty() {
+        return;
+    }
+    let mut state = DRIFT_STATE.lock().unwrap();
+    let DriftState {
+        report,
+        new_changes,
+        ..
+    } = &mut *state;
+    report.record_log_map(endpoint, &log_map, Utc::now(), new_changes);
+}
+
+/// Record a response that failed to deserialize. `data` is the JSON that was
+/// parsed.
+pub fn record_parse_error(endpoint: &str, error: &serde_json::Error, data: &str) {
+    let mut state = DRIFT_STATE.lock().unwrap();
+    let DriftState {
+        report,
+        new_changes,
+        ..
+    } = &mut *state;
+    report.record_json_error(endpoint, error, data, Utc::now(), new_changes);
+}
+
+/// Everything detected since the start.
+pub fn report() -> DriftReport {
+    DRIFT_STATE.lock().unwrap().report.clone()
+}
+
+/// Fields that drifted for the first time since the previous call.
+pub fn take_new_changes() -> Vec<DriftChange> {
+    std::mem::take(&mut DRIFT_STATE.lock().unwrap().new_changes)
+}
+
+/// Write [`report`] to `<dir>/api_drift.json` and `<dir>/api_drift.diff`;
+/// returns the path of the diff.
+pub fn write_report(dir: &Path) -> std::io::Result<PathBuf> {
+    let report = report();
+    std::fs::create_dir_all(dir)?;
+    let json = serde_json::to_vec_pretty(&report).map_err(std::io::Error::other)?;
+    std::fs::write(dir.join(REPORT_JSON_FILE_NAME), json)?;
+    let diff_path = dir.join(REPORT_DIFF_FILE_NAME);
+    std::fs::write(&diff_path, report.to_diff())?;
+    Ok(diff_path)
+}
+
+#[cfg(any(test, feature = "drift"))]
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+fn truncate(mut sample: String) -> String {
+    if sample.len() > SAMPLE_MAX_LEN {
+        let mut end = SAMPLE_MAX_LEN;
+        while !sample.is_char_boundary(end) {
+            end -= 1;
+        }
+        sample.truncate(end);
+        sample.push('…');
+    }
+    sample
+}
+
+/// Byte offset of a serde_json error position (1-based line and column).
+pub(crate) fn offset(data: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = data
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let mut offset = (line_start + column).min(data.len());
+    while !data.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Key of the value the error position points into.
+fn key_before(data: &str, line: usize, column: usize) -> Option<String> {
+    let before = &data[..offset(data, line, column)];
+    let colon = before.rfind("\":")?;
+    let start = before[..colon].rfind('"')? + 1;
+    Some(before[start..colon].to_string())
+}
+
+fn context(data: &str, line: usize, column: usize) -> String {
+    let at = offset(data, line, column);
+    let mut start = at.saturating_sub(SAMPLE_MAX_LEN / 4);
+    while !data.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (at + SAMPLE_MAX_LEN / 4).min(data.len());
+    while !data.is_char_boundary(end) {
+        end += 1;
+    }
+    data[start..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_added_retyped_and_removed_fields_once() {
+        let mut report = DriftReport::default();
+        let mut new_changes = Vec::new();
+        let now = Utc::now();
+        let endpoint = "/kcsapi/api_port/port";
+
+        let log_map: LogMapType = [
+            (
+                (
+                    "field_extra".to_string(),
+                    "ApiData".to_string(),
+                    "extra".to_string(),
+                ),
+                vec![
+                    "api_new_flag=1".to_string(),
+                    "api_new_list=[1,2]".to_string(),
+                ],
+            ),
+            (
+                (
+                    "type_value".to_string(),
+                    "ApiBasic".to_string(),
+                    "api_fleetname".to_string(),
+                ),
+                vec!["null".to_string(), "string".to_string()],
+            ),
+            (
+                (
+                    "type_value".to_string(),
+                    "ApiBasic".to_string(),
+                    "api_comment".to_string(),
+                ),
+                vec!["null".to_string()],
+            ),
+        ]
+        .into();
+        report.record_log_map(endpoint, &log_map, now, &mut new_changes);
+        report.record_log_map(endpoint, &log_map, now, &mut new_changes);
+
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Dto {
+            api_id: i64,
+            api_lv: i64,
+            api_exp: i64,
+        }
+        for data in [
+            "{\"api_id\":1,\"api_lv\":\"99\",\"api_exp\":0}",
+            "{\"api_id\":1,\"api_lv\":99}",
+            "{\"api_id\":1,\"api_lv\":99}",
+        ] {
+            let error = serde_json::from_str::<Dto>(data).unwrap_err();
+            report.record_json_error(endpoint, &error, data, now, &mut new_changes);
+        }
+
+        let fields = &report.endpoints[endpoint].fields;
+        assert_eq!(
+            fields.keys().collect::<Vec<_>>(),
+            vec![
+                "ApiBasic.api_fleetname",
+                "ApiData.api_new_flag",
+                "ApiData.api_new_list",
+                "api_exp",
+                "api_lv",
+            ]
+        );
+        assert_eq!(fields["ApiData.api_new_flag"].count, 2);
+        assert_eq!(fields["ApiData.api_new_list"].observed, "array");
+        assert_eq!(fields["ApiData.api_new_list"].sample, "[1,2]");
+        assert_eq!(fields["ApiBasic.api_fleetname"].observed, "string");
+        assert_eq!(fields["api_lv"].kind, DriftKind::Retyped);
+        assert!(fields["api_lv"]
+            .observed
+            .starts_with("invalid type: string \"99\""));
+        assert_eq!(fields["api_exp"].kind, DriftKind::Removed);
+        assert_eq!(fields["api_exp"].count, 2);
+        assert_eq!(
+            new_changes
+                .iter()
+                .map(|change| (change.field.as_str(), change.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("ApiData.api_new_flag", DriftKind::Added),
+                ("ApiData.api_new_list", DriftKind::Added),
+                ("ApiBasic.api_fleetname", DriftKind::Retyped),
+                ("api_lv", DriftKind::Retyped),
+                ("api_exp", DriftKind::Removed),
+            ]
+        );
+
+        let diff = report.to_diff();
+        assert!(diff.starts_with(
+            "--- /kcsapi/api_port/port (kc-api-dto)\n+++ /kcsapi/api_port/port (live)\n"
+        ));
+        assert!(diff.contains("+ ApiData.api_new_list: array (seen 2 times"));
+        assert!(diff.contains("- api_exp: missing field `api_exp` (seen 2 times"));
+    }
+
+    #[test]
+    fn full_probe_runs_on_first_responses_then_sampled() {
+        let mut state = DriftState {
+            report: DriftReport::default(),
+            new_changes: Vec::new(),
+            responses: BTreeMap::new(),
+        };
+        let due: Vec<u64> = (1..=FULL_PROBE_FIRST + 2 * FULL_PROBE_EVERY)
+            .filter(|_| state.full_probe_due("/kcsapi/api_port/port"))
+            .collect();
+        let mut expected: Vec<u64> = (1..=FULL_PROBE_FIRST).collect();
+        expected.extend([
+            FULL_PROBE_FIRST + FULL_PROBE_EVERY,
+            FULL_PROBE_FIRST + 2 * FULL_PROBE_EVERY,
+        ]);
+        assert_eq!(due, expected);
+        assert!(state.full_probe_due("/kcsapi/api_get_member/deck"));
+    }
+}
@@ -0,0 +1,386 @@
+//! Errors of [`response_parser`](crate::parser::response_parser) and
+//! [`request_parser`](crate::parser::request_parser).
+//!
+//! Every error is also counted per endpoint and class; [`report`] returns the
+//! counts with the last error of each endpoint, so messages that produced no
+//! data can be told apart from messages that carry none.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+static PARSE_ERRORS: Mutex<ParseErrorReport> = Mutex::new(ParseErrorReport {
+    total: 0,
+    by_kind: BTreeMap::new(),
+    endpoints: BTreeMap::new(),
+});
+
+/// Class of a [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseErrorKind {
+    UnknownEndpoint,
+    Syntax,
+    Shape,
+    Api,
+    Conversion,
+}
+
+impl ParseErrorKind {
+    /// Name used in serialized reports.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ParseErrorKind::UnknownEndpoint => "unknown_endpoint",
+            ParseErrorKind::Syntax => "syntax",
+            ParseErrorKind::Shape => "shape",
+            ParseErrorKind::Api => "api",
+            ParseErrorKind::Conversion => "conversion",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParseError {
+    /// No DTO module for the endpoint.
+    UnknownEndpoint { endpoint: String },
+    /// The body is not JSON (responses) or not a query string (requests).
+    Syntax { endpoint: String, message: String },
+    /// The body does not match the DTO. `path` is the failing field, e.g.
+    /// `api_data.api_ship[3].api_lv`, when it could be located.
+    Shape {
+        endpoint: String,
+        path: Option<String>,
+        message: String,
+    },
+    /// The game answered with an error code (`api_result` other than 1).
+    /// Counted like the other errors, but `response_parser` returns it as an
+    /// `Identifier::ApiError` event instead.
+    Api {
+        endpoint: String,
+        api_result: i64,
+        api_result_msg: String,
+    },
+    /// The DTO could not be converted into `EmitData`.
+    Conversion { endpoint: String, message: String },
+}
+
+impl ParseError {
+    pub fn endpoint(&self) -> &str {
+        match self {
+            ParseError::UnknownEndpoint { endpoint }
+            | ParseError::Syntax { endpoint, .. }
+            | ParseError::Shape { endpoint, .. }
+            | ParseError::Api { endpoint, .. }
+            | ParseError::Conversion { endpoint, .. } => endpoint,
+        }
+    }
+
+    pub fn kind(&self) -> ParseErrorKind {
+        match self {
+            ParseError::UnknownEndpoint { .. } => ParseErrorKind::UnknownEndpoint,
+            ParseError::Syntax { .. } => ParseErrorKind::Syntax,
+            ParseError::Shape { .. } => ParseErrorKind::Shape,
+            ParseError::Api { .. } => ParseErrorKind::Api,
+            ParseError::Conversion { .. } => ParseErrorKind::Conversion,
+        }
+    }
+
+    /// API error of a response body whose `api_result` is present and not 1,
+    /// read from the raw JSON whatever the shape of `api_data`.
+    pub fn from_api_result(endpoint: &str, data: &str) -> Option<Self> {
+        let header = serde_json::from_str::<ResultHeader>(data).ok()?;
+        let api_result = header.api_result.filter(|result| *result != 1)?;
+        Some(ParseError::Api {
+            endpoint: endpoint.to_string(),
+            api_result,
+            api_result_msg: header.api_result_msg.unwrap_or_default(),
+        })
+    }
+
+    /// Error of a response body that `serde_json` rejected. `data` is the
+    /// parsed body; a body with an `api_result` other than 1 is an API error
+    /// rather than a shape mismatch.
+    pub fn from_json(endpoint: &str, error: &serde_json::Error, data: &str) -> Self {
+        if let Some(error) = Self::from_api_result(endpoint, data) {
+            return error;
+        }
+        let endpoint = endpoint.to_string();
+        let message = error.to_string();
+        if !error.is_data() {
+            return ParseError::Syntax { endpoint, message };
+        }
+        let mut path = json_path_at(data, error.line(), error.column());
+        if let Some(field) = missing_field(&message) {
+            path = Some(match path {
+                Some(path) => format!("{path}.{field}"),
+                None => field.to_string(),
+            });
+        }
+        ParseError::Shape {
+            endpoint,
+            path,
+            message,
+        }
+    }
+
+    /// Error of a request body that `serde_qs` rejected.
+    pub fn from_query(endpoint: &str, error: &serde_qs::Error) -> Self {
+        let endpoint = endpoint.to_string();
+        let message = error.to_string();
+        match error {
+            serde_qs::Error::Parse(..)
+            | serde_qs::Error::Utf8(_)
+            | serde_qs::Error::FromUtf8(_) => ParseError::Syntax { endpoint, message },
+            _ => ParseError::Shape {
+                endpoint,
+                path: missing_field(&message).map(str::to_string),
+                message,
+            },
+        }
+    }
+
+    pub fn unknown_endpoint(endpoint: String) -> Self {
+        ParseError::UnknownEndpoint { endpoint }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownEndpoint { endpoint } => write!(f, "{endpoint}: no DTO module"),
+            ParseError::Syntax { endpoint, message } => {
+                write!(f, "{endpoint}: malformed body: {message}")
+            }
+            ParseError::Shape {
+                endpoint,
+                path,
+                message,
+            } => match path {
+                Some(path) => write!(f, "{endpoint}: {path} does not match the DTO: {message}"),
+                None => write!(f, "{endpoint}: does not match the DTO: {message}"),
+            },
+            ParseError::Api {
+                endpoint,
+                api_result,
+                api_result_msg,
+            } => write!(f, "{endpoint}: API error {api_result} ({api_result_msg})"),
+            ParseError::Conversion { endpoint, message } => {
+                write!(f, "{endpoint}: conversion failed: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Deserialize)]
+struct ResultHeader {
+    api_result: Option<i64>,
+    api_result_msg: Option<String>,
+}
+
+fn missing_field(message: &str) -> Option<&str> {
+    message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split_once('`'))
+        .map(|(field, _)| field)
+}
+
+/// Dotted path of the value at a `serde_json` error position (1-based line
+/// and column), or `None` at the top level.
+fn json_path_at(data: &str, line: usize, column: usize) -> Option<String> {
+    enum Frame {
+        Object { key: Option<String>, in_key: bool },
+        Array { index: usize },
+    }
+
+    let end = crate::drift::offset(data, line, column);
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut chars = data[..end].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => stack.push(Frame::Object {
+                key: None,
+                in_key: true,
+            }),
+            '[' => stack.push(Frame::Array { index: 0 }),
+            '}' | ']' => {
+                stack.pop();
+            }
+            ':' => {
+                if let Some(Frame::Object { in_key, .. }) = stack.last_mut() {
+                    *in_key = false;
+                }
+            }
+            ',' => match stack.last_mut() {
+                Some(Frame::Object { in_key, .. }) => *in_key = true,
+                Some(Frame::Array { index }) => *index += 1,
+                None => {}
+            },
+            '"' => {
+                let mut string = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            chars.next();
+                        }
+                        c => string.push(c),
+                    }
+                }
+                if let Some(Frame::Object { key, in_key: true }) = stack.last_mut() {
+                    *key = Some(string);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut path = String::new();
+    for frame in &stack {
+        match frame {
+            Frame::Object { key: Some(key), .. } => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+            }
+            Frame::Object { key: None, .. } => {}
+            Frame::Array { index } => path.push_str(&format!("[{index}]")),
+        }
+    }
+    (!path.is_empty()).then_some(path)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointParseErrors {
+    pub count: u64,
+    pub by_kind: BTreeMap<ParseErrorKind, u64>,
+    pub last: ParseError,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseErrorReport {
+    pub total: u64,
+    pub by_kind: BTreeMap<ParseErrorKind, u64>,
+    pub endpoints: BTreeMap<String, EndpointParseErrors>,
+}
+
+impl ParseErrorReport {
+    fn record(&mut self, error: &ParseError, now: DateTime<Utc>) {
+        self.total += 1;
+        *self.by_kind.entry(error.kind()).or_default() += 1;
+        let endpoint = self
+            .endpoints
+            .entry(error.endpoint().to_string())
+            .or_insert_with(|| EndpointParseErrors {
+                count: 0,
+                by_kind: BTreeMap::new(),
+                last: error.clone(),
+                last_seen: now,
+            });
+        endpoint.count += 1;
+        *endpoint.by_kind.entry(error.kind()).or_default() += 1;
+        endpoint.last = error.clone();
+        endpoint.last_seen = now;
+    }
+}
+
+/// Count `error` and log it; the parsers call this for every error they
+/// return.
+pub fn record(error: &ParseError) {
+    match error.kind() {
+        ParseErrorKind::UnknownEndpoint => tracing::debug!("{}", error),
+        ParseErrorKind::Api => tracing::warn!("{}", error),
+        _ => tracing::error!("{}", error),
+    }
+    PARSE_ERRORS.lock().unwrap().record(error, Utc::now());
+}
+
+/// Fallback of the endpoint match in the parsers.
+pub(crate) fn record_unknown_endpoint(endpoint: String) -> ParseError {
+    let error = ParseError::unknown_endpoint(endpoint);
+    record(&error);
+    error
+}
+
+/// Errors counted since the start.
+pub fn report() -> ParseErrorReport {
+    PARSE_ERRORS.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_error<T: serde::de::DeserializeOwned + fmt::Debug>(data: &str) -> ParseError {
+        let error = serde_json::from_str::<T>(data).unwrap_err();
+        ParseError::from_json("/kcsapi/api_test/test", &error, data)
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Res {
+        api_result: i64,
+        api_data: Data,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Data {
+        api_ship: Vec<Ship>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Ship {
+        api_id: i64,
+        api_lv: i64,
+    }
+
+    #[test]
+    fn classifies_json_errors_and_locates_fields() {
+        let retyped = json_error::<Res>(
+            r#"{"api_result":1,"api_data":{"api_ship":[{"api_id":1,"api_lv":2},{"api_id":2,"api_lv":"x"}]}}"#,
+        );
+        assert_eq!(retyped.kind(), ParseErrorKind::Shape);
+        assert!(matches!(
+            &retyped,
+            ParseError::Shape { path: Some(path), .. } if path == "api_data.api_ship[1].api_lv"
+        ));
+
+        let missing =
+            json_error::<Res>(r#"{"api_result":1,"api_data":{"api_ship":[{"api_id":1}]}}"#);
+        assert!(matches!(
+            &missing,
+            ParseError::Shape { path: Some(path), .. } if path == "api_data.api_ship[0].api_lv"
+        ));
+
+        assert_eq!(
+            json_error::<Res>(r#"{"api_result":201,"api_result_msg":"不正なリクエスト"}"#),
+            ParseError::Api {
+                endpoint: "/kcsapi/api_test/test".to_string(),
+                api_result: 201,
+                api_result_msg: "不正なリクエスト".to_string(),
+            }
+        );
+        assert_eq!(json_error::<Res>("<html>").kind(), ParseErrorKind::Syntax);
+
+        let mut report = ParseErrorReport::default();
+        let now = Utc::now();
+        report.record(&retyped, now);
+        report.record(&missing, now);
+        report.record(
+            &ParseError::unknown_endpoint("/kcsapi/api_x/y".to_string()),
+            now,
+        );
+        assert_eq!(report.total, 3);
+        assert_eq!(report.by_kind[&ParseErrorKind::Shape], 2);
+        assert_eq!(report.endpoints["/kcsapi/api_test/test"].last, missing);
+    }
+}
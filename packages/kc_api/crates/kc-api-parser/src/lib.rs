@@ -1 +1,5 @@
+pub mod drift;
+pub mod error;
 pub mod parser;
+pub mod quarantine;
+pub mod scaffold;
@@ -1,53 +1,184 @@
-use register_trait::expand_struct_selector;
-
-use std::error::Error;
+use register_trait::{endpoint_names, expand_struct_selector};
 
 use kc_api_dto;
-use kc_api_interface::interface::EmitData;
+use kc_api_interface::api_error::ApiError;
+use kc_api_interface::interface::{EmitData, Identifier};
 use kc_api_interface_adapter::TraitForConvert;
 
+use crate::error::{self, ParseError};
+
 #[expand_struct_selector(
     path = "../kc-api-dto/src/endpoints",
-    subpath = "./crates/kc-api-dto/src/endpoints"
+    subpath = "./crates/kc-api-dto/src/endpoints",
+    fallback = "crate::error::record_unknown_endpoint"
 )]
-pub fn response_parser(name: String, data: String) -> Result<Vec<EmitData>, Box<dyn Error>> {
+pub fn response_parser(name: String, data: String) -> Result<Vec<EmitData>, ParseError> {
+    // error responses may still fit DTOs whose api_data is optional, so the
+    // result code is checked before the body is converted
+    if let Some(error) = ParseError::from_api_result(&name, &data) {
+        error::record(&error);
+        kc_api_interface_adapter::discard_pending_request(&name);
+        if let ParseError::Api {
+            api_result,
+            api_result_msg,
+            ..
+        } = &error
+        {
+            return Ok(vec![EmitData::Identifier(Identifier::ApiError(
+                ApiError::new(
+                    &name,
+                    *api_result,
+                    api_result_msg,
+                    chrono::Utc::now().timestamp_millis(),
+                ),
+            ))]);
+        }
+    }
+
     let root_wrap: Result<kcsapi_lib::Res, serde_json::Error> = serde_json::from_str(&data);
 
     match root_wrap {
-        Ok(root) => match root.convert() {
-            Some(emit_data_list) => {
-                return Ok(emit_data_list);
-            }
-            None => {
-                return Ok(Vec::new());
-            }
-        },
+        Ok(root) => {
+            #[cfg(any(test, feature = "drift"))]
+            crate::drift::inspect_response(&name, &root);
+            return convert(&name, &root);
+        }
         Err(e) => {
-            tracing::error!("Failed to parse Res JSON({:?}): {}", name, e);
-            return Err(Box::new(e));
+            let error = ParseError::from_json(&name, &e, &data);
+            error::record(&error);
+            kc_api_interface_adapter::discard_pending_request(&name);
+            crate::drift::record_parse_error(&name, &e, &data);
+            return Err(error);
         }
     };
 }
 
 #[expand_struct_selector(
     path = "../kc-api-dto/src/endpoints",
-    subpath = "./crates/kc-api-dto/src/endpoints"
+    subpath = "./crates/kc-api-dto/src/endpoints",
+    fallback = "crate::error::record_unknown_endpoint"
 )]
-pub fn request_parser(name: String, data: String) -> Result<Vec<EmitData>, Box<dyn Error>> {
+pub fn request_parser(name: String, data: String) -> Result<Vec<EmitData>, ParseError> {
     let root_wrap: Result<kcsapi_lib::Req, serde_qs::Error> = serde_qs::from_str(&data);
 
     match root_wrap {
-        Ok(root) => match root.convert() {
-            Some(emit_data_list) => {
-                return Ok(emit_data_list);
-            }
-            None => {
-                return Ok(Vec::new());
-            }
-        },
+        Ok(root) => {
+            return convert(&name, &root);
+        }
         Err(e) => {
-            tracing::error!("Failed to parse Req JSON({:?}): {}", name, e);
-            return Err(Box::new(e));
+            let error = ParseError::from_query(&name, &e);
+            error::record(&error);
+            return Err(error);
         }
     };
 }
+
+/// Run the adapter of a parsed DTO. `None` from the adapter means the
+/// message carries nothing to emit.
+fn convert<T: TraitForConvert<Output = EmitData>>(
+    name: &str,
+    root: &T,
+) -> Result<Vec<EmitData>, ParseError> {
+    root.convert().map(Option::unwrap_or_default).map_err(|e| {
+        let error = ParseError::Conversion {
+            endpoint: name.to_string(),
+            message: e.to_string(),
+        };
+        error::record(&error);
+        error
+    })
+}
+
+/// Endpoints with a DTO module, e.g. `/kcsapi/api_port/port`.
+const KNOWN_ENDPOINTS: &[&str] = &endpoint_names!(
+    path = "../kc-api-dto/src/endpoints",
+    subpath = "./crates/kc-api-dto/src/endpoints"
+);
+
+/// Whether `name` (e.g. `/kcsapi/api_port/port`) has a DTO module and is
+/// handled by [`response_parser`] and [`request_parser`].
+pub fn is_known_endpoint(name: &str) -> bool {
+    KNOWN_ENDPOINTS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kc_api_interface::api_error::ApiErrorClass;
+
+    #[test]
+    fn error_results_are_emitted_as_api_errors() {
+        let emitted = response_parser(
+            "/kcsapi/api_port/port".to_string(),
+            r#"{"api_result":201,"api_result_msg":"申し訳ありませんがエラーが発生しました。"}"#
+                .to_string(),
+        )
+        .unwrap();
+        assert!(matches!(
+            emitted.as_slice(),
+            [EmitData::Identifier(Identifier::ApiError(error))]
+                if error.api_result == 201 && error.class == ApiErrorClass::SessionExpired
+        ));
+    }
+
+    #[test]
+    fn error_results_discard_the_pending_remodel_request() {
+        use kc_api_interface::remodel::PENDING_REMODEL_SLOT_REQ;
+
+        let endpoint = "/kcsapi/api_req_kousyou/remodel_slot";
+        let request = |slot_id: i64| {
+            request_parser(
+                endpoint.to_string(),
+                format!(
+                    "api_token=x&api_verno=1&api_certain_flag=0&api_id=101&api_slot_id={slot_id}"
+                ),
+            )
+            .unwrap()
+        };
+        PENDING_REMODEL_SLOT_REQ.lock().unwrap().clear();
+
+        request(1);
+        let emitted = response_parser(
+            endpoint.to_string(),
+            r#"{"api_result":100,"api_result_msg":"パラメータエラー"}"#.to_string(),
+        )
+        .unwrap();
+        assert!(matches!(
+            emitted.as_slice(),
+            [EmitData::Identifier(Identifier::ApiError(_))]
+        ));
+        assert!(PENDING_REMODEL_SLOT_REQ.lock().unwrap().is_empty());
+
+        request(2);
+        let pending: Vec<_> = PENDING_REMODEL_SLOT_REQ
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|req| req.slot_id)
+            .collect();
+        assert_eq!(pending, vec![2]);
+        PENDING_REMODEL_SLOT_REQ.lock().unwrap().clear();
+    }
+
+    #[test]
+    fn error_results_are_api_errors_even_when_the_dto_parses() {
+        // api_data is optional in api_req_hensei/change
+        let emitted = response_parser(
+            "/kcsapi/api_req_hensei/change".to_string(),
+            r#"{"api_result":100,"api_result_msg":"パラメータエラー"}"#.to_string(),
+        )
+        .unwrap();
+        assert!(matches!(
+            emitted.as_slice(),
+            [EmitData::Identifier(Identifier::ApiError(error))] if error.api_result == 100
+        ));
+    }
+
+    #[test]
+    fn known_endpoints_come_from_dto_modules() {
+        assert!(is_known_endpoint("/kcsapi/api_port/port"));
+        assert!(is_known_endpoint("/kcsapi/api_req_kousyou/remodel_slot"));
+        assert!(!is_known_endpoint("/kcsapi/api_port/unknown"));
+        assert!(!is_known_endpoint("api_port/port"));
+    }
+}
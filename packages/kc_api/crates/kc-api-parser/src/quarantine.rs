@@ -0,0 +1,275 @@
+//! Capture of API calls to endpoints without a DTO module.
+//!
+//! [`response_parser`](crate::parser::response_parser) only knows the
+//! endpoints under `kc-api-dto/src/endpoints`; messages of any other
+//! `/kcsapi/...` path are dropped. [`EndpointQuarantine`] keeps them as
+//! request/response capture files (`<timestamp>Q@api_x@y` and
+//! `<timestamp>S@api_x@y`, the naming of the proxy's captures), so they can be
+//! fed to [`crate::scaffold`] and later used as test data for the new DTO.
+//! Bodies are masked with [`kc_api_dto::mask`] before they are written, so
+//! `api_token` and the [`QUARANTINE_MASK_PATTERNS`] keys never reach the disk.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use kc_api_dto::mask::{mask_query_string, mask_value};
+
+use crate::parser::is_known_endpoint;
+
+/// Samples kept per endpoint; later calls are not written.
+pub const DEFAULT_MAX_SAMPLES_PER_ENDPOINT: usize = 20;
+
+/// Keys masked in quarantined bodies besides `api_token`.
+pub const QUARANTINE_MASK_PATTERNS: &[&str] = &[
+    r"\.api_member_id$",
+    r"\.api_nickname(_id)?$",
+    r"\.api_comment(_id)?$",
+];
+
+struct PendingRequest {
+    stamp: String,
+    content: String,
+}
+
+#[derive(Default)]
+struct QuarantineState {
+    /// Last request per endpoint, written together with its response.
+    pending: HashMap<String, PendingRequest>,
+    /// Samples written so far per endpoint, including earlier runs.
+    samples: HashMap<String, usize>,
+}
+
+pub struct EndpointQuarantine {
+    dir: PathBuf,
+    max_samples_per_endpoint: usize,
+    state: Mutex<QuarantineState>,
+}
+
+impl EndpointQuarantine {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_samples_per_endpoint: DEFAULT_MAX_SAMPLES_PER_ENDPOINT,
+            state: Mutex::new(QuarantineState::default()),
+        }
+    }
+
+    pub fn with_max_samples_per_endpoint(mut self, max: usize) -> Self {
+        self.max_samples_per_endpoint = max;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether messages of `path` are quarantined.
+    pub fn is_quarantined(path: &str) -> bool {
+        endpoint_suffix(path).is_some() && !is_known_endpoint(path)
+    }
+
+    /// Keep the request until its response arrives. Returns `false` for
+    /// endpoints that are not quarantined.
+    pub fn record_request(&self, path: &str, content: &str) -> bool {
+        self.record_request_at(path, content, Utc::now())
+    }
+
+    /// Write the response with the pending request, if any. Returns the
+    /// written files, or `None` for endpoints that are not quarantined or
+    /// already have enough samples.
+    pub fn record_response(&self, path: &str, content: &str) -> Option<Vec<PathBuf>> {
+        self.record_response_at(path, content, Utc::now())
+    }
+
+    pub fn record_request_at(&self, path: &str, content: &str, now: DateTime<Utc>) -> bool {
+        if !Self::is_quarantined(path) {
+            return false;
+        }
+        self.state.lock().unwrap().pending.insert(
+            path.to_string(),
+            PendingRequest {
+                stamp: stamp(now),
+                content: mask_query_string(content, QUARANTINE_MASK_PATTERNS),
+            },
+        );
+        true
+    }
+
+    pub fn record_response_at(
+        &self,
+        path: &str,
+        content: &str,
+        now: DateTime<Utc>,
+    ) -> Option<Vec<PathBuf>> {
+        if !Self::is_quarantined(path) {
+            return None;
+        }
+        let suffix = endpoint_suffix(path)?;
+        let mut state = self.state.lock().unwrap();
+        let request = state.pending.remove(path);
+        let samples = match state.samples.get(path) {
+            Some(samples) => *samples,
+            None => count_samples(&self.dir, &suffix),
+        };
+        if samples >= self.max_samples_per_endpoint {
+            state.samples.insert(path.to_string(), samples);
+            return None;
+        }
+        if samples == 0 {
+            tracing::warn!(
+                path,
+                "unknown API endpoint, capturing it to {}",
+                self.dir.display()
+            );
+        }
+
+        let stamp = request
+            .as_ref()
+            .map_or_else(|| stamp(now), |request| request.stamp.clone());
+        let content = mask_response(content);
+        let mut written = Vec::new();
+        let mut files = vec![('S', content.as_str())];
+        if let Some(request) = &request {
+            files.insert(0, ('Q', request.content.as_str()));
+        }
+        for (marker, content) in files {
+            let file = self.dir.join(format!("{stamp}{marker}{suffix}"));
+            let result = std::fs::create_dir_all(&self.dir)
+                .and_then(|_| std::fs::write(&file, metadata(path, now) + content));
+            match result {
+                Ok(()) => written.push(file),
+                Err(e) => tracing::warn!("failed to write {}: {}", file.display(), e),
+            }
+        }
+        state.samples.insert(path.to_string(), samples + 1);
+        Some(written)
+    }
+}
+
+/// `@api_x@y` for `/kcsapi/api_x/y`.
+fn endpoint_suffix(path: &str) -> Option<String> {
+    let rest = path.strip_prefix("/kcsapi/")?;
+    let valid = !rest.is_empty()
+        && rest.split('/').all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    valid.then(|| format!("@{}", rest.replace('/', "@")))
+}
+
+/// `svdata=<json>` with the JSON masked; other bodies are kept as they are.
+fn mask_response(content: &str) -> String {
+    let json = content.trim_start_matches('\u{feff}');
+    let Some(json) = json.strip_prefix("svdata=") else {
+        return content.to_string();
+    };
+    match serde_json::from_str(json) {
+        Ok(value) => {
+            let masked = mask_value("res", value, QUARANTINE_MASK_PATTERNS);
+            format!("svdata={}", masked)
+        }
+        Err(_) => content.to_string(),
+    }
+}
+
+fn stamp(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%d_%H%M%S%3f").to_string()
+}
+
+/// Four-line header, the layout the DTO tests read, so the files can be used
+/// as test data as they are.
+fn metadata(path: &str, time: DateTime<Utc>) -> String {
+    format!(
+        "---\nProxyApp: FUSOU\nTimestamp: {}\nEndpoint: {}\nQuarantine: no DTO module\n---\n",
+        time.timestamp(),
+        path
+    )
+}
+
+fn count_samples(dir: &Path, suffix: &str) -> usize {
+    let suffix = format!("S{suffix}");
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_name().to_string_lossy().ends_with(&suffix))
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_pairs_for_unknown_endpoints_only() {
+        let dir = std::env::temp_dir().join(format!(
+            "fusou-quarantine-test-{}",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        let quarantine = EndpointQuarantine::new(&dir).with_max_samples_per_endpoint(2);
+        let now = DateTime::parse_from_rfc3339("2026-05-01T03:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let path = "/kcsapi/api_req_event/unknown_call";
+
+        assert!(!quarantine.record_request_at("/kcsapi/api_port/port", "api_token=x", now));
+        assert!(quarantine
+            .record_response_at("/kcsapi/api_port/port", "svdata={}", now)
+            .is_none());
+        assert!(!quarantine.record_request_at("/kcs2/index.php", "", now));
+
+        assert!(quarantine.record_request_at(path, "api_token=x&api_id=3", now));
+        let written = quarantine
+            .record_response_at(path, "svdata={\"api_result\":1}", now)
+            .unwrap();
+        let names: Vec<_> = written
+            .iter()
+            .map(|file| file.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "20260501_030000000Q@api_req_event@unknown_call",
+                "20260501_030000000S@api_req_event@unknown_call"
+            ]
+        );
+        let request = std::fs::read_to_string(&written[0]).unwrap();
+        assert!(request.ends_with("---\napi_token=__MASKED_API_TOKEN__&api_id=3"));
+        let response = std::fs::read_to_string(&written[1]).unwrap();
+        assert!(response.starts_with("---\nProxyApp: FUSOU\nTimestamp: 1777604400\n"));
+        assert!(response.ends_with("---\nsvdata={\"api_result\":1}"));
+
+        // A response without a request is still kept; then the limit is hit.
+        let later = now + chrono::Duration::seconds(1);
+        assert_eq!(
+            quarantine
+                .record_response_at(path, "svdata={}", later)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(quarantine
+            .record_response_at(path, "svdata={}", later)
+            .is_none());
+
+        // The count includes files from earlier runs.
+        let restarted = EndpointQuarantine::new(&dir).with_max_samples_per_endpoint(2);
+        assert!(restarted
+            .record_response_at(path, "svdata={}", later)
+            .is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn masks_member_data_in_responses() {
+        assert_eq!(
+            mask_response("svdata={\"api_data\":{\"api_member_id\":\"8\",\"api_lv\":9}}"),
+            "svdata={\"api_data\":{\"api_lv\":9,\"api_member_id\":\"__MASKED_API_MEMBER_ID__\"}}"
+        );
+        assert_eq!(mask_response("not json"), "not json");
+    }
+}
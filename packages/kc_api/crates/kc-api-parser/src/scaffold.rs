@@ -0,0 +1,569 @@
+//! Generation of a DTO module from captured samples of an endpoint.
+//!
+//! The field types are inferred from every sample: a field missing from some
+//! samples or sometimes `null` becomes an `Option`, integers mixed with
+//! floats become `f64`, and fields seen with different kinds of values (or
+//! only `null`) become `Value`. Objects, also inside arrays, get their own
+//! struct named after the field. The output follows the layout of the files
+//! in `kc-api-dto/src/endpoints` and is meant as a starting point to review,
+//! not as a finished DTO.
+
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+/// Request and response bodies of one endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointSamples {
+    /// Query strings of the requests.
+    pub requests: Vec<String>,
+    /// Response JSON, without the `svdata=` prefix.
+    pub responses: Vec<Value>,
+}
+
+/// Read the captures of `endpoint` (e.g. `/kcsapi/api_x/y`) in `dir`, in the
+/// format written by the proxy and [`crate::quarantine`]. Responses that are
+/// not valid JSON are skipped.
+pub fn load_samples(dir: &Path, endpoint: &str) -> std::io::Result<EndpointSamples> {
+    let (api_dir, api_name) = split_endpoint(endpoint).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("not a /kcsapi/<dir>/<name> endpoint: {endpoint}"),
+        )
+    })?;
+    let suffix = format!("@{api_dir}@{api_name}");
+
+    let mut files = std::fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut samples = EndpointSamples::default();
+    for file in files {
+        let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+        let Some(stem) = file_name.strip_suffix(&suffix) else {
+            continue;
+        };
+        let content = std::fs::read_to_string(&file)?;
+        let body = strip_metadata(&content).replace('\u{feff}', "");
+        if stem.ends_with('Q') {
+            samples.requests.push(body);
+        } else if stem.ends_with('S') {
+            let body = body.trim_start().trim_start_matches("svdata=");
+            match serde_json::from_str(body) {
+                Ok(value) => samples.responses.push(value),
+                Err(e) => tracing::warn!("skipping {}: {}", file.display(), e),
+            }
+        }
+    }
+    Ok(samples)
+}
+
+/// Render a DTO module for `endpoint` from `samples`.
+pub fn scaffold_module(
+    endpoint: &str,
+    samples: &EndpointSamples,
+) -> Result<String, Box<dyn Error>> {
+    let (api_dir, api_name) = split_endpoint(endpoint)
+        .ok_or_else(|| format!("not a /kcsapi/<dir>/<name> endpoint: {endpoint}"))?;
+    if samples.responses.is_empty() {
+        return Err(format!("no response samples for {endpoint}").into());
+    }
+
+    let mut root = Observed::default();
+    for response in &samples.responses {
+        if !response.is_object() {
+            return Err(format!("a response of {endpoint} is not a JSON object").into());
+        }
+        root.observe(response);
+    }
+    let Shape::Object(root) = root.shape else {
+        unreachable!("every response is an object");
+    };
+
+    let mut structs = Vec::new();
+    let res_fields = render_fields(&root, &mut structs);
+    let req_fields = infer_request_fields(&samples.requests);
+    let uses_value = res_fields
+        .iter()
+        .chain(
+            structs
+                .iter()
+                .flat_map(|s: &RenderedStruct| s.fields.iter()),
+        )
+        .any(|field| field.ty.contains("Value"));
+
+    let mut out = String::new();
+    let name = format!("{api_dir}/{api_name}");
+    let svg = format!("{api_dir}@{api_name}");
+    out.push_str("#![doc = \"# kanColle API\"]\n");
+    out.push_str("#![doc = \"KC APIs are also dependent on kcapi::kcapi_common.\"]\n");
+    out.push_str("#![doc = \"The dependency graph of the APIs is shown below.\"]\n");
+    writeln!(
+        out,
+        "#![doc = register_trait::insert_svg!(path=\"../../tests/struct_dependency_svg/{svg}.svg\", id=\"kc-dependency-svg-embed\", style=\"border: 1px solid black; height:80vh; width:100%\", role=\"img\", aria_label=\"KC_API_dependency({name})\")]"
+    )?;
+    out.push_str("#![doc = include_str!(\"../../../../../js/svg_pan_zoom.html\")]\n\n");
+    out.push_str("use serde::{Deserialize, Serialize};\n");
+    if uses_value {
+        out.push_str("use serde_json::Value;\n");
+    }
+    out.push_str("\nuse register_trait::{add_field, register_struct};\n");
+    out.push_str(
+        "use register_trait::{FieldSizeChecker, QueryWithExtra, TraitForRoot, TraitForTest};\n\n",
+    );
+
+    out.push_str("#[derive(FieldSizeChecker, TraitForTest, TraitForRoot)]\n");
+    out.push_str("#[struct_test_case(field_extra, type_value, integration)]\n");
+    out.push_str("#[add_field(extra_for_qs)]\n");
+    out.push_str("#[derive(Debug, Clone, QueryWithExtra)]\n");
+    out.push_str("pub struct Req {\n");
+    for field in &req_fields {
+        writeln!(out, "    #[qs(rename = \"{}\")]", field.key)?;
+        writeln!(out, "    pub {}: {},", field.ident, field.ty)?;
+    }
+    out.push_str("}\n\n");
+
+    out.push_str("#[derive(FieldSizeChecker, TraitForTest, TraitForRoot)]\n");
+    out.push_str("#[struct_test_case(field_extra, type_value, integration)]\n");
+    out.push_str("#[add_field(extra_with_flatten)]\n");
+    writeln!(out, "#[register_struct(name = \"{name}\")]")?;
+    out.push_str("#[derive(Debug, Clone, Deserialize, Serialize)]\n");
+    out.push_str("#[serde(rename_all = \"camelCase\")]\n");
+    out.push_str("pub struct Res {\n");
+    render_serde_fields(&mut out, &res_fields)?;
+    out.push_str("}\n");
+
+    for rendered in &structs {
+        out.push('\n');
+        out.push_str("#[derive(FieldSizeChecker, TraitForTest)]\n");
+        out.push_str("#[struct_test_case(field_extra, type_value, integration)]\n");
+        out.push_str("#[add_field(extra_with_flatten)]\n");
+        out.push_str("#[derive(Debug, Clone, Deserialize, Serialize)]\n");
+        out.push_str("#[serde(rename_all = \"camelCase\")]\n");
+        writeln!(out, "pub struct {} {{", rendered.name)?;
+        render_serde_fields(&mut out, &rendered.fields)?;
+        out.push_str("}\n");
+    }
+
+    out.push('\n');
+    out.push_str(&render_tests(api_dir, api_name));
+    Ok(out)
+}
+
+/// `("api_x", "y")` for `/kcsapi/api_x/y`.
+fn split_endpoint(endpoint: &str) -> Option<(&str, &str)> {
+    let (api_dir, api_name) = endpoint.strip_prefix("/kcsapi/")?.split_once('/')?;
+    let valid = |part: &str| {
+        !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    (valid(api_dir) && valid(api_name)).then_some((api_dir, api_name))
+}
+
+/// Body of a capture file after its `---` metadata block, if any.
+fn strip_metadata(content: &str) -> &str {
+    let mut lines = content.split_inclusive('\n');
+    match lines.next() {
+        Some(first) if first.trim_end() == "---" => {}
+        _ => return content,
+    }
+    let mut offset = content.find('\n').map_or(content.len(), |i| i + 1);
+    for line in lines {
+        offset += line.len();
+        if line.trim_end() == "---" {
+            return &content[offset..];
+        }
+    }
+    content
+}
+
+#[derive(Debug, Default)]
+enum Shape {
+    /// Nothing but `null` so far.
+    #[default]
+    Unknown,
+    Bool,
+    Int,
+    Float,
+    String,
+    Array(Box<Observed>),
+    Object(ObjectShape),
+    /// Values of different kinds.
+    Mixed,
+}
+
+#[derive(Debug, Default)]
+struct Observed {
+    shape: Shape,
+    nulls: usize,
+}
+
+impl Observed {
+    fn observe(&mut self, value: &Value) {
+        let shape = std::mem::take(&mut self.shape);
+        self.shape = match (shape, value) {
+            (shape, Value::Null) => {
+                self.nulls += 1;
+                shape
+            }
+            (Shape::Mixed, _) => Shape::Mixed,
+            (Shape::Unknown | Shape::Bool, Value::Bool(_)) => Shape::Bool,
+            (Shape::Unknown | Shape::Int, Value::Number(n)) if n.is_i64() || n.is_u64() => {
+                Shape::Int
+            }
+            (Shape::Unknown | Shape::Int | Shape::Float, Value::Number(_)) => Shape::Float,
+            (Shape::Unknown | Shape::String, Value::String(_)) => Shape::String,
+            (Shape::Unknown, Value::Array(values)) => {
+                let mut element = Observed::default();
+                values.iter().for_each(|value| element.observe(value));
+                Shape::Array(Box::new(element))
+            }
+            (Shape::Array(mut element), Value::Array(values)) => {
+                values.iter().for_each(|value| element.observe(value));
+                Shape::Array(element)
+            }
+            (Shape::Unknown, Value::Object(map)) => {
+                let mut object = ObjectShape::default();
+                object.observe(map);
+                Shape::Object(object)
+            }
+            (Shape::Object(mut object), Value::Object(map)) => {
+                object.observe(map);
+                Shape::Object(object)
+            }
+            _ => Shape::Mixed,
+        };
+    }
+}
+
+#[derive(Debug, Default)]
+struct ObjectShape {
+    samples: usize,
+    /// Fields in the order they are first seen (serde_json sorts the keys),
+    /// with the number of samples containing them.
+    fields: Vec<(String, usize, Observed)>,
+}
+
+impl ObjectShape {
+    fn observe(&mut self, map: &Map<String, Value>) {
+        self.samples += 1;
+        for (key, value) in map {
+            let index = match self.fields.iter().position(|(k, _, _)| k == key) {
+                Some(index) => index,
+                None => {
+                    self.fields.push((key.clone(), 0, Observed::default()));
+                    self.fields.len() - 1
+                }
+            };
+            let (_, present, observed) = &mut self.fields[index];
+            *present += 1;
+            observed.observe(value);
+        }
+    }
+}
+
+struct RenderedField {
+    key: String,
+    ident: String,
+    ty: String,
+}
+
+struct RenderedStruct {
+    name: String,
+    fields: Vec<RenderedField>,
+}
+
+fn render_fields(object: &ObjectShape, structs: &mut Vec<RenderedStruct>) -> Vec<RenderedField> {
+    object
+        .fields
+        .iter()
+        .map(|(key, present, observed)| {
+            let mut ty = render_type(observed, key, structs);
+            let optional = *present < object.samples || observed.nulls > 0;
+            if optional && ty != "Value" {
+                ty = format!("Option<{ty}>");
+            }
+            RenderedField {
+                key: key.clone(),
+                ident: field_ident(key),
+                ty,
+            }
+        })
+        .collect()
+}
+
+fn render_type(observed: &Observed, key: &str, structs: &mut Vec<RenderedStruct>) -> String {
+    match &observed.shape {
+        Shape::Unknown | Shape::Mixed => "Value".to_string(),
+        Shape::Bool => "bool".to_string(),
+        Shape::Int => "i64".to_string(),
+        Shape::Float => "f64".to_string(),
+        Shape::String => "String".to_string(),
+        Shape::Array(element) => {
+            let ty = render_type(element, key, structs);
+            if element.nulls > 0 && ty != "Value" {
+                format!("Vec<Option<{ty}>>")
+            } else {
+                format!("Vec<{ty}>")
+            }
+        }
+        Shape::Object(object) => {
+            // Reserve the name before the children so the parent is listed
+            // first, like in the hand-written files.
+            let name = struct_name(key, structs);
+            let index = structs.len();
+            structs.push(RenderedStruct {
+                name: name.clone(),
+                fields: Vec::new(),
+            });
+            structs[index].fields = render_fields(object, structs);
+            name
+        }
+    }
+}
+
+/// `ApiUnsetList` for `api_unset_list`, with a number appended when the name
+/// is taken.
+fn struct_name(key: &str, structs: &[RenderedStruct]) -> String {
+    let mut base = key
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect::<String>();
+    if base.is_empty()
+        || base.starts_with(|c: char| c.is_ascii_digit())
+        || base == "Req"
+        || base == "Res"
+    {
+        base = format!("Api{base}");
+    }
+    let mut name = base.clone();
+    let mut n = 2;
+    while structs.iter().any(|s| s.name == name) {
+        name = format!("{base}{n}");
+        n += 1;
+    }
+    name
+}
+
+fn field_ident(key: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn",
+        "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+        "async", "await", "dyn",
+    ];
+    let mut ident = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.insert_str(0, "r#");
+    }
+    ident
+}
+
+/// Request fields from query strings: `i64` when every value is an integer,
+/// `String` otherwise. Without samples only the fields every request has are
+/// listed.
+fn infer_request_fields(requests: &[String]) -> Vec<RenderedField> {
+    let mut fields: Vec<(String, usize, bool)> = Vec::new();
+    for request in requests {
+        for pair in request.trim().split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let is_int = value.parse::<i64>().is_ok();
+            match fields.iter_mut().find(|(k, _, _)| k == key) {
+                Some((_, present, int)) => {
+                    *present += 1;
+                    *int &= is_int;
+                }
+                None => fields.push((key.to_string(), 1, is_int)),
+            }
+        }
+    }
+    if requests.is_empty() {
+        fields = vec![
+            ("api_token".to_string(), 1, false),
+            ("api_verno".to_string(), 1, true),
+        ];
+    }
+    let samples = requests.len().max(1);
+    fields
+        .into_iter()
+        .map(|(key, present, is_int)| {
+            // The token is not a number even when it happens to look like one.
+            let ty = if is_int && key != "api_token" {
+                "i64"
+            } else {
+                "String"
+            };
+            let ty = if present < samples {
+                format!("Option<{ty}>")
+            } else {
+                ty.to_string()
+            };
+            RenderedField {
+                ident: field_ident(&key),
+                key,
+                ty,
+            }
+        })
+        .collect()
+}
+
+fn render_serde_fields(out: &mut String, fields: &[RenderedField]) -> std::fmt::Result {
+    for field in fields {
+        writeln!(out, "    #[serde(rename = \"{}\")]", field.key)?;
+        writeln!(out, "    pub {}: {},", field.ident, field.ty)?;
+    }
+    Ok(())
+}
+
+fn render_tests(api_dir: &str, api_name: &str) -> String {
+    const TEMPLATE: &str = r#"#[cfg(test)]
+mod tests {
+    use crate::test_utils::struct_normalize::{glob_match_normalize, FormatType};
+    use dotenvy::dotenv;
+    use register_trait::simple_root_test;
+
+    use super::*;
+    #[test]
+    fn test_deserialize() {
+        dotenv().expect(".env file not found");
+        let target_path = std::env::var("TEST_DATA_PATH").expect("failed to get env data");
+
+        let pattern_str = "S@{dir}@{name}";
+        let log_path = "./src/endpoints/{dir}/{name}@S.log";
+        simple_root_test::<Res>(
+            target_path.clone(),
+            pattern_str.to_string(),
+            log_path.to_string(),
+        );
+
+        let pattern_str = "Q@{dir}@{name}";
+        let log_path = "./src/endpoints/{dir}/{name}@Q.log";
+        simple_root_test::<Req>(
+            target_path.clone(),
+            pattern_str.to_string(),
+            log_path.to_string(),
+        );
+    }
+    #[test]
+    fn test_organize_test_data() {
+        dotenv().expect(".env file not found");
+        let target_path = std::env::var("TEST_DATA_PATH").expect("failed to get env data");
+        let snap_file_path = std::env::var("TEST_DATA_REPO_PATH").expect("failed to get env data");
+
+        let req_and_res_pattern_str = "@{dir}@{name}";
+        let snap_path = format!("{snap_file_path}/kcsapi");
+        let log_path = "./src/endpoints/{dir}/{name}@snap_data@S.log";
+        glob_match_normalize::<Req, Res>(
+            target_path.clone(),
+            req_and_res_pattern_str.to_string(),
+            snap_path.to_string(),
+            FormatType::Json,
+            log_path.to_string(),
+            None,
+        );
+
+        let log_path = "./src/endpoints/{dir}/{name}@snap_data@Q.log";
+        glob_match_normalize::<Req, Res>(
+            target_path.clone(),
+            req_and_res_pattern_str.to_string(),
+            snap_path.to_string(),
+            FormatType::QueryString,
+            log_path.to_string(),
+            None,
+        );
+    }
+
+}
+"#;
+    TEMPLATE
+        .replace("{dir}", api_dir)
+        .replace("{name}", api_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infers_optional_fields_and_nested_structs() {
+        let samples = EndpointSamples {
+            requests: vec![
+                "api_token=abc&api_verno=1&api_event_id=3".to_string(),
+                "api_token=abc&api_verno=1&api_event_id=4&api_memo=x".to_string(),
+            ],
+            responses: vec![
+                serde_json::json!({
+                    "api_result": 1,
+                    "api_result_msg": "成功",
+                    "api_data": {
+                        "api_point": 10,
+                        "api_rate": 1,
+                        "api_reward_list": [{"api_id": 1, "api_count": 2}],
+                        "api_note": null,
+                        "api_flag": "1"
+                    }
+                }),
+                serde_json::json!({
+                    "api_result": 1,
+                    "api_result_msg": "成功",
+                    "api_data": {
+                        "api_point": 12,
+                        "api_rate": 1.5,
+                        "api_reward_list": [{"api_id": 2, "api_count": 1, "api_slot": [1, 2]}],
+                        "api_flag": 1
+                    }
+                }),
+            ],
+        };
+        let module = scaffold_module("/kcsapi/api_req_event/reward", &samples).unwrap();
+
+        assert!(module.contains("api_req_event@reward.svg"));
+        assert!(module.contains("#[register_struct(name = \"api_req_event/reward\")]"));
+        assert!(module.contains("use serde_json::Value;"));
+        assert!(module.contains("    pub api_token: String,\n"));
+        assert!(module.contains("    pub api_event_id: i64,\n"));
+        assert!(module.contains("    pub api_memo: Option<String>,\n"));
+        assert!(module.contains("    pub api_data: ApiData,\n"));
+        assert!(module.contains("    pub api_point: i64,\n"));
+        assert!(module.contains("    pub api_rate: f64,\n"));
+        assert!(module.contains("    pub api_reward_list: Vec<ApiRewardList>,\n"));
+        assert!(module.contains("    pub api_note: Value,\n"));
+        assert!(module.contains("    pub api_flag: Value,\n"));
+        assert!(module.contains("    pub api_slot: Option<Vec<i64>>,\n"));
+        assert!(
+            module.find("pub struct ApiData").unwrap()
+                < module.find("pub struct ApiRewardList").unwrap()
+        );
+        assert!(module.contains("let pattern_str = \"S@api_req_event@reward\";"));
+    }
+
+    #[test]
+    fn strips_capture_metadata() {
+        let content = "---\nProxyApp: FUSOU\nTimestamp: 1\n---\nsvdata={}";
+        assert_eq!(strip_metadata(content), "svdata={}");
+        assert_eq!(strip_metadata("svdata={}"), "svdata={}");
+    }
+}
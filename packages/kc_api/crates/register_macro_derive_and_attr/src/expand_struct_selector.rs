@@ -17,6 +17,10 @@ pub struct MacroArgs4ExpandStructSelector {
     path: path::PathBuf,
     #[darling(default)]
     subpath: path::PathBuf,
+    /// Function called with `name` to build the error for unknown names;
+    /// a boxed `std::io::Error` when omitted.
+    #[darling(default)]
+    fallback: Option<syn::Path>,
 }
 
 pub fn expand_struct_selector(
@@ -116,35 +120,82 @@ pub fn expand_struct_selector(
         body.push(quote! { #s});
     }
 
-    let mut file_list: Vec<((String, String), (String, String))> = Vec::new();
-    let if_arg_path_not_exists = !args.path.exists();
+    let file_list = collect_endpoints(&args.path, &args.subpath)
+        .map_err(|message| syn::Error::new_spanned(ast.sig.output.clone(), message))?;
+
+    let mut match_list = Vec::new();
+    for (s1, s2) in &file_list {
+        let lit = syn::LitStr::new(&format!("/kcsapi/{}/{}", s1.0, s1.1), Span::call_site());
+        // let use_ident = syn::Ident::new(&format!("kcapi_main::{}::{}::Root;",s1 ,s2), Span::call_site());
+        let ident_s2_0 = syn::Ident::new(&s2.0, Span::call_site());
+        let ident_s2_1 = syn::Ident::new(&s2.1, Span::call_site());
+        let idnet_crate = syn::Ident::new("kc_api_dto", Span::call_site());
+        match_list.push(quote! {
+            #lit => {
+                use #idnet_crate::endpoints::#ident_s2_0::#ident_s2_1 as kcsapi_lib;
+                #(#body)*
+            },
+        });
+    }
+
+    // what should I do to use name in function arguments?
+    let match_wrap = quote! {
+        match name.as_str() {
+            #(#match_list)*
+            _ => {}
+        }
+    };
+
+    let return_wrap = match &args.fallback {
+        Some(fallback) => quote! {
+            return Err(#fallback(name));
+        },
+        None => quote! {
+            return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "The name is not match any word")));
+        },
+    };
+
+    ast.block.stmts.clear();
+    ast.block.stmts.push(syn::parse_quote! {
+        #match_wrap
+    });
+    ast.block.stmts.push(syn::parse_quote! {
+        #return_wrap
+    });
+
+    let expanded = quote! {
+        #ast
+    };
+
+    Ok(TokenStream::from(expanded))
+}
+
+/// `((api dir, api name), (module dir, module file))`
+type EndpointModule = ((String, String), (String, String));
+
+/// Every DTO module under `path` (or `subpath` when `path` does not exist).
+fn collect_endpoints(
+    path: &path::Path,
+    subpath: &path::Path,
+) -> Result<Vec<EndpointModule>, &'static str> {
+    let mut file_list: Vec<EndpointModule> = Vec::new();
+    let if_arg_path_not_exists = !path.exists();
     // let if_sub_arg_path_not_exists = match &args.subpath {
     //     Some(p) => !p.exists(),
     //     None => false,
     // };
-    let if_sub_arg_path_not_exists = !args.subpath.exists();
+    let if_sub_arg_path_not_exists = !subpath.exists();
     if if_arg_path_not_exists && if_sub_arg_path_not_exists {
-        return Err(syn::Error::new_spanned(
-            ast.sig.output.clone(),
-            "The path is not exist.",
-        ));
+        return Err("The path is not exist.");
     }
     let paths = match if_arg_path_not_exists {
-        false => fs::read_dir(args.path.clone()),
+        false => fs::read_dir(path),
         // true if !if_sub_arg_path_not_exists => fs::read_dir(args.subpath.clone().unwrap()),
-        true if !if_sub_arg_path_not_exists => fs::read_dir(args.subpath.clone()),
-        _ => {
-            return Err(syn::Error::new_spanned(
-                ast.sig.output.clone(),
-                "The path(& subpath) is not exist.",
-            ))
-        }
+        true if !if_sub_arg_path_not_exists => fs::read_dir(subpath),
+        _ => return Err("The path(& subpath) is not exist."),
     };
     if paths.is_err() {
-        return Err(syn::Error::new_spanned(
-            ast.sig.output.clone(),
-            "The path is not a directory",
-        ));
+        return Err("The path is not a directory");
     }
     let paths = paths.unwrap();
 
@@ -211,44 +262,19 @@ pub fn expand_struct_selector(
         }
     }
 
-    let mut match_list = Vec::new();
-    for (s1, s2) in &file_list {
-        let lit = syn::LitStr::new(&format!("/kcsapi/{}/{}", s1.0, s1.1), Span::call_site());
-        // let use_ident = syn::Ident::new(&format!("kcapi_main::{}::{}::Root;",s1 ,s2), Span::call_site());
-        let ident_s2_0 = syn::Ident::new(&s2.0, Span::call_site());
-        let ident_s2_1 = syn::Ident::new(&s2.1, Span::call_site());
-        let idnet_crate = syn::Ident::new("kc_api_dto", Span::call_site());
-        match_list.push(quote! {
-            #lit => {
-                use #idnet_crate::endpoints::#ident_s2_0::#ident_s2_1 as kcsapi_lib;
-                #(#body)*
-            },
-        });
-    }
-
-    // what should I do to use name in function arguments?
-    let match_wrap = quote! {
-        match name.as_str() {
-            #(#match_list)*
-            _ => {}
-        }
-    };
-
-    let return_wrap = quote! {
-        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "The name is not match any word")));
-    };
-
-    ast.block.stmts.clear();
-    ast.block.stmts.push(syn::parse_quote! {
-        #match_wrap
-    });
-    ast.block.stmts.push(syn::parse_quote! {
-        #return_wrap
-    });
+    Ok(file_list)
+}
 
-    let expanded = quote! {
-        #ast
-    };
+pub fn endpoint_names(attr: TokenStream) -> Result<TokenStream, syn::Error> {
+    let attr_args = NestedMeta::parse_meta_list(attr.into())?;
+    let args = MacroArgs4ExpandStructSelector::from_list(&attr_args)?;
+    let file_list = collect_endpoints(&args.path, &args.subpath)
+        .map_err(|message| syn::Error::new(Span::call_site(), message))?;
 
-    Ok(TokenStream::from(expanded))
+    let names = file_list
+        .iter()
+        .map(|(s1, _)| syn::LitStr::new(&format!("/kcsapi/{}/{}", s1.0, s1.1), Span::call_site()));
+    Ok(TokenStream::from(quote! {
+        [#(#names),*]
+    }))
 }
@@ -83,18 +83,26 @@ pub fn generate_test_struct(ast: &mut DeriveInput) -> Result<TokenStream, syn::E
 
             if args.field_extra.is_some() {
                 test_implementation.push(quote! {
-                    #[cfg(test)]
+                    #[cfg(any(test, feature = "drift"))]
                     fn test_extra(&self, log_map: &mut register_trait::LogMapType) {
                         let extra_field = self.extra.clone();
                         // assert!(extra_field.is_empty(), "\x1b[38;5;{}m extra field is not empty: {:?}\x1b[m ", 8, extra_field);
                         if !extra_field.is_empty() {
+                            #[cfg(test)]
                             println!("\x1b[38;5;{}m extra field is not empty: {:?}\x1b[m ", 8, extra_field);
                             let key = ("field_extra".to_string(), stringify!(#struct_name).to_string(), "extra".to_string());
                             if !log_map.contains_key(&key) {
                                 log_map.insert(key.clone(), Vec::new());
                             }
                             let mut log_vec = log_map.get_mut(&key).unwrap();
-                            log_vec.push(format!("{:?}", format!("extra field is not empty: {:?}", extra_field)));
+                            // One "<key>=<json value>" entry per unknown key, so the
+                            // drift detector can tell the keys apart.
+                            let mut extra_entries = extra_field
+                                .iter()
+                                .map(|(key, value)| format!("{}={}", key, value))
+                                .collect::<Vec<String>>();
+                            extra_entries.sort();
+                            log_vec.extend(extra_entries);
                         }
                     }
                 });
@@ -162,7 +170,7 @@ pub fn generate_test_struct(ast: &mut DeriveInput) -> Result<TokenStream, syn::E
                     }
                 }
                 test_implementation.push(quote! {
-                    #[cfg(test)]
+                    #[cfg(any(test, feature = "drift"))]
                     fn test_type_value(&self, log_map: &mut register_trait::LogMapType) {
                         #(#assertions)*
                     }
@@ -171,6 +179,7 @@ pub fn generate_test_struct(ast: &mut DeriveInput) -> Result<TokenStream, syn::E
 
             if args.integration.is_some() {
                 let mut assertions = Vec::new();
+                let mut extra_walks = Vec::new();
                 for field in &struct_data.fields {
                     let ident = field.ident.as_ref().unwrap();
                     let ty = &field.ty;
@@ -180,7 +189,7 @@ pub fn generate_test_struct(ast: &mut DeriveInput) -> Result<TokenStream, syn::E
                         match result_type_str {
                             Ok(type_str) => {
                                 assertions.push(parse_type_path::expand_children_from_self(
-                                    type_str,
+                                    type_str.clone(),
                                     0,
                                     Ident::new(
                                         &format!("{}", ident.clone()),
@@ -196,6 +205,22 @@ pub fn generate_test_struct(ast: &mut DeriveInput) -> Result<TokenStream, syn::E
                                         }
                                     },
                                 ));
+                                extra_walks.push(parse_type_path::expand_children_from_self(
+                                    type_str,
+                                    0,
+                                    Ident::new(
+                                        &format!("{}", ident.clone()),
+                                        proc_macro2::Span::call_site(),
+                                    ),
+                                    &|x: &proc_macro2::Ident| {
+                                        quote! {
+                                            if !#x.is_value() {
+                                                #x.test_extra(log_map);
+                                                #x.test_integration_extra(log_map);
+                                            }
+                                        }
+                                    },
+                                ));
                             }
                             Err(err) => {
                                 return Err(err);
@@ -205,10 +230,15 @@ pub fn generate_test_struct(ast: &mut DeriveInput) -> Result<TokenStream, syn::E
                 }
 
                 test_implementation.push(quote! {
-                    #[cfg(test)]
+                    #[cfg(any(test, feature = "drift"))]
                     fn test_integration(&self, log_map: &mut register_trait::LogMapType) {
                         #(#assertions)*
                     }
+
+                    #[cfg(any(test, feature = "drift"))]
+                    fn test_integration_extra(&self, log_map: &mut register_trait::LogMapType) {
+                        #(#extra_walks)*
+                    }
                 });
             };
         }
@@ -219,8 +249,10 @@ pub fn generate_test_struct(ast: &mut DeriveInput) -> Result<TokenStream, syn::E
 
     let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
 
+    // Also compiled with the "drift" feature of the crate deriving it, so the
+    // parser can run the probes on live responses.
     let expanded = quote! {
-        #[cfg(test)]
+        #[cfg(any(test, feature = "drift"))]
         impl #impl_generics TraitForTest for #struct_name #ty_generics #where_clause {
             #(#test_implementation)*
         }
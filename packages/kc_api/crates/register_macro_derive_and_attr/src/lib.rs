@@ -36,6 +36,16 @@ pub fn expand_struct_selector(attr: TokenStream, item: TokenStream) -> TokenStre
     }
 }
 
+/// Array of the `/kcsapi/...` paths with a DTO module, scanned like
+/// [`macro@expand_struct_selector`].
+#[proc_macro]
+pub fn endpoint_names(attr: TokenStream) -> TokenStream {
+    match expand_struct_selector::endpoint_names(attr) {
+        Ok(generated) => generated,
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
 #[proc_macro_attribute]
 pub fn register_struct(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut ast = syn::parse_macro_input!(item as DeriveInput);
@@ -10,6 +10,7 @@ pub use register_macro_derive_and_attr::TraitForRoot;
 pub use register_macro_derive_and_attr::TraitForTest;
 
 pub use register_macro_derive_and_attr::add_field;
+pub use register_macro_derive_and_attr::endpoint_names;
 pub use register_macro_derive_and_attr::expand_struct_selector;
 pub use register_macro_derive_and_attr::insert_svg;
 pub use register_macro_derive_and_attr::register_struct;
@@ -31,6 +32,8 @@ pub trait TraitForTest {
     fn test_type_value(&self, _: &mut LogMapType) {}
     fn test_extra(&self, _: &mut LogMapType) {}
     fn test_integration(&self, _: &mut LogMapType) {}
+    /// `test_integration` limited to the `extra` maps, without the type probes.
+    fn test_integration_extra(&self, _: &mut LogMapType) {}
 
     // fn is_iterable(&self) -> bool { return false; }
     // fn is_result(&self) -> bool { return false; }